@@ -0,0 +1,218 @@
+//! Kitchen-sink MCP server exercising most of the SDK in one place:
+//! tools with progress and structured output, prompts with argument schemas
+//! and completion, files of a directory served as resources, and resource
+//! subscriptions driven by a polling file watcher.
+//!
+//!     cargo run --example kitchen_sink -- [directory]
+
+use async_trait::async_trait;
+use mcp_sdk::completion::CompletionProvider;
+use mcp_sdk::error::MCPError;
+use mcp_sdk::context::RequestContext;
+use mcp_sdk::notifications::ProgressSender;
+use mcp_sdk::server::{ServerBuilder, SystemMCPServer, ToolHandler};
+use mcp_sdk::tools::{
+    Completion, CompletionArgument, CompletionReference, Prompt, PromptArgument, PromptMessage,
+    PromptResponse, Resource, ResourceContent, Tool, ToolAnnotations, ToolInputSchema, ToolProperty,
+    ToolResponse,
+};
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+const FILE_SCHEME: &str = "file://";
+
+struct KitchenSink {
+    root: PathBuf,
+}
+
+impl KitchenSink {
+    fn files(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = std::fs::read_dir(&self.root)
+            .into_iter()
+            .flatten()
+            .flatten()
+            .map(|entry| entry.path())
+            .filter(|path| path.is_file())
+            .collect();
+        files.sort();
+        files
+    }
+
+    /// Resolve a `file://` URI, refusing anything outside the root
+    fn resolve(&self, uri: &str) -> Result<PathBuf, MCPError> {
+        let path = uri.strip_prefix(FILE_SCHEME).ok_or_else(|| MCPError::UnknownResource(uri.into()))?;
+        let path = Path::new(path).canonicalize().map_err(|_| MCPError::ResourceNotFound(uri.into()))?;
+        let root = self.root.canonicalize()?;
+        if !path.starts_with(&root) {
+            return Err(MCPError::Forbidden(format!("{} is outside the served directory", uri)));
+        }
+        Ok(path)
+    }
+
+    async fn count(&self, args: &Value, progress: &ProgressSender) -> Result<ToolResponse, MCPError> {
+        let to = args.get("to").and_then(Value::as_u64).unwrap_or(10).min(1000);
+        for i in 1..=to {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            let _ = progress.send_progress("count", i as f64 / to as f64, Some(format!("counted {}", i))).await;
+        }
+        Ok(ToolResponse::new(format!("Counted to {}", to), false).with_structured_content(json!({ "count": to })))
+    }
+
+    async fn stat(&self, args: &Value) -> Result<ToolResponse, MCPError> {
+        let uri = args.get("uri").and_then(Value::as_str).ok_or(MCPError::MissingParameters)?;
+        let path = self.resolve(uri)?;
+        let text = tokio::fs::read_to_string(&path).await?;
+        let stats = json!({
+            "bytes": text.len(),
+            "lines": text.lines().count(),
+            "words": text.split_whitespace().count(),
+        });
+        Ok(ToolResponse::new(format!("{}: {}", uri, stats), false).with_structured_content(stats))
+    }
+}
+
+#[async_trait]
+impl ToolHandler for KitchenSink {
+    async fn call_tool(&self, name: &str, args: &Value, ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+        match name {
+            "count" => self.count(args, ctx.progress()).await,
+            "stat" => self.stat(args).await,
+            _ => Err(MCPError::UnknownTool(name.into())),
+        }
+    }
+
+    async fn get_prompt(&self, name: &str, args: &Value, _ctx: &RequestContext) -> Result<PromptResponse, MCPError> {
+        if name != "review" {
+            return Err(MCPError::UnknownPrompt(name.into()));
+        }
+        let language = args.get("language").and_then(Value::as_str).unwrap_or("rust");
+        let mut messages = vec![PromptMessage::user(format!("Review the following {} code for bugs and style.", language))];
+        if let Some(uri) = args.get("file").and_then(Value::as_str) {
+            let text = tokio::fs::read_to_string(self.resolve(uri)?).await?;
+            messages.push(PromptMessage::user(text));
+        }
+        Ok(PromptResponse {
+            description: format!("Code review ({})", language),
+            messages,
+        })
+    }
+
+    async fn read_resource(&self, uri: &str, _ctx: &RequestContext) -> Result<ResourceContent, MCPError> {
+        let path = self.resolve(uri)?;
+        Ok(ResourceContent {
+            uri: uri.into(),
+            mime_type: "text/plain".into(),
+            text: tokio::fs::read_to_string(path).await?,
+            blob: None,
+        })
+    }
+}
+
+/// Completes `file` arguments with the URIs of served files
+struct FileCompletion {
+    root: PathBuf,
+}
+
+#[async_trait]
+impl CompletionProvider for FileCompletion {
+    async fn complete(&self, _reference: &CompletionReference, argument: &CompletionArgument, _ctx: &RequestContext) -> Result<Completion, MCPError> {
+        if argument.name != "file" {
+            return Ok(Completion::default());
+        }
+        let handler = KitchenSink { root: self.root.clone() };
+        let values: Vec<String> = handler.files().iter()
+            .map(|path| format!("{}{}", FILE_SCHEME, path.display()))
+            .filter(|uri| uri.contains(&argument.value))
+            .collect();
+        Ok(Completion {
+            total: Some(values.len() as u64),
+            has_more: Some(false),
+            values,
+        })
+    }
+}
+
+fn tools() -> Vec<Tool> {
+    let count = Tool::new("count", "Count slowly, reporting progress at every step", ToolInputSchema {
+        schema_type: "object".into(),
+        properties: BTreeMap::from([("to".to_string(), ToolProperty {
+            property_type: "integer".into(),
+            description: "Number to count to (max 1000)".into(),
+            items: None,
+            default: Some(json!(10)),
+        })]),
+        required: vec![],
+    });
+    let stat = Tool::new("stat", "Byte, line and word counts of a served file", ToolInputSchema {
+        schema_type: "object".into(),
+        properties: BTreeMap::from([("uri".to_string(), ToolProperty::string("file:// URI of the file"))]),
+        required: vec!["uri".into()],
+    });
+
+    let read_only = ToolAnnotations {
+        title: None,
+        read_only_hint: Some(true),
+        destructive_hint: Some(false),
+        idempotent_hint: Some(true),
+        open_world_hint: Some(false),
+    };
+    vec![count.with_annotations(read_only.clone()), stat.with_annotations(read_only)]
+}
+
+fn prompts() -> Vec<Prompt> {
+    vec![Prompt::new("review", "Ask for a code review").with_arguments(vec![
+        PromptArgument::new("language", "Language of the code", true)
+            .with_schema(json!({ "type": "string", "enum": ["rust", "python", "go", "typescript"] })),
+        PromptArgument::new("file", "file:// URI of the code to review", false),
+    ])]
+}
+
+/// Poll modification times and notify subscribers of changed files
+fn watch(server: Arc<SystemMCPServer<KitchenSink>>, root: PathBuf) {
+    tokio::spawn(async move {
+        let handler = KitchenSink { root };
+        let mut seen: HashMap<PathBuf, SystemTime> = HashMap::new();
+        loop {
+            for path in handler.files() {
+                let Ok(modified) = path.metadata().and_then(|m| m.modified()) else { continue };
+                if seen.insert(path.clone(), modified).is_some_and(|before| before != modified) {
+                    let uri = format!("{}{}", FILE_SCHEME, path.display());
+                    if server.notify_resource_updated(&uri) {
+                        eprintln!("[WATCH] {} changed", uri);
+                    }
+                }
+            }
+            tokio::time::sleep(Duration::from_millis(500)).await;
+        }
+    });
+}
+
+#[tokio::main]
+async fn main() {
+    let root = std::env::args().nth(1).map(PathBuf::from).unwrap_or_else(|| PathBuf::from("."));
+    let root = root.canonicalize().expect("directory does not exist");
+
+    let handler = KitchenSink { root: root.clone() };
+    let resources = handler.files().iter()
+        .map(|path| {
+            let name = path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            Resource::new(format!("{}{}", FILE_SCHEME, path.display()), name).with_mime_type("text/plain")
+        })
+        .collect();
+
+    let server = Arc::new(ServerBuilder::full()
+        .with_tools(tools())
+        .with_prompts(prompts())
+        .with_resources(resources)
+        .completion_provider(FileCompletion { root: root.clone() })
+        .build(handler));
+    watch(server.clone(), root);
+
+    server.signal_ready().expect("self-check failed");
+    if let Err(e) = server.run_stdio().await {
+        eprintln!("Server stopped: {}", e);
+    }
+}
@@ -0,0 +1,46 @@
+//! The smallest useful server: one tool, everything else defaulted.
+//!
+//!     cargo run --example minimal
+
+use async_trait::async_trait;
+use mcp_sdk::error::MCPError;
+use mcp_sdk::context::RequestContext;
+use mcp_sdk::server::{SystemMCPServer, ToolHandler};
+use mcp_sdk::tools::{Tool, ToolInputSchema, ToolProperty, ToolResponse};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+
+struct Echo;
+
+#[async_trait]
+impl ToolHandler for Echo {
+    async fn call_tool(&self, name: &str, args: &Value, _ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+        match name {
+            "echo" => Ok(ToolResponse::new(args["text"].as_str().unwrap_or_default().into(), false)),
+            other => Err(MCPError::UnknownTool(other.into())),
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() {
+    let echo = Tool {
+        name: "echo".into(),
+        description: "Repeat the given text".into(),
+        input_schema: ToolInputSchema {
+            schema_type: "object".into(),
+            properties: BTreeMap::from([(
+                "text".into(),
+                ToolProperty { property_type: "string".into(), description: "Text to repeat".into(), items: None, default: None },
+            )]),
+            required: vec!["text".into()],
+        },
+        annotations: None,
+        meta: None,
+    };
+    let server = Arc::new(SystemMCPServer::<Echo>::builder().with_tools(vec![echo]).build(Echo));
+    if let Err(e) = server.run_stdio().await {
+        eprintln!("Server stopped: {}", e);
+    }
+}
@@ -0,0 +1,299 @@
+//! Typed client bindings generated from a server manifest.
+//!
+//! [`generate_client`] turns the JSON produced by
+//! `SystemMCPServer::export_manifest()` into Rust source with one argument
+//! struct per tool and a wrapper exposing one async method per tool. Use
+//! [`write_client`] from a build script and `include!` the output.
+//!
+//! Names become Rust identifiers: keywords are raw (`r#type`), the ones
+//! that cannot be raw get a trailing underscore (`self_`), and names that
+//! map to an identifier already taken get a numeric suffix (`read_file_2`).
+
+use mcp_types::error::MCPError;
+use serde_json::{Map, Value};
+use std::collections::HashSet;
+use std::fmt::Write as _;
+use std::path::Path;
+
+/// Transport used by generated clients to perform `tools/call`
+#[allow(async_fn_in_trait)]
+pub trait CallTool {
+    async fn call_tool(&self, name: &str, arguments: Value) -> Result<Value, MCPError>;
+}
+
+const KEYWORDS: &[&str] = &[
+    "as", "async", "await", "box", "break", "const", "continue", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "static", "struct", "trait", "true", "type", "unsafe", "use", "where", "while",
+    "yield",
+];
+
+/// Keywords that cannot be raw identifiers
+const NOT_RAW: &[&str] = &["crate", "self", "super", "Self"];
+
+fn words(name: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut current = String::new();
+    let mut prev_lower = false;
+    for c in name.chars() {
+        if !c.is_ascii_alphanumeric() {
+            if !current.is_empty() {
+                words.push(std::mem::take(&mut current));
+            }
+            prev_lower = false;
+            continue;
+        }
+        if c.is_ascii_uppercase() && prev_lower {
+            words.push(std::mem::take(&mut current));
+        }
+        prev_lower = c.is_ascii_lowercase() || c.is_ascii_digit();
+        current.push(c.to_ascii_lowercase());
+    }
+    if !current.is_empty() {
+        words.push(current);
+    }
+    words
+}
+
+fn snake_ident(name: &str) -> String {
+    let mut ident = words(name).join("_");
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, '_');
+    }
+    if NOT_RAW.contains(&ident.as_str()) {
+        ident.push('_');
+    } else if KEYWORDS.contains(&ident.as_str()) {
+        ident.insert_str(0, "r#");
+    }
+    ident
+}
+
+fn pascal_ident(name: &str) -> String {
+    let mut ident: String = words(name)
+        .iter()
+        .map(|w| {
+            let mut chars = w.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect();
+    if ident.is_empty() || ident.starts_with(|c: char| c.is_ascii_digit()) {
+        ident.insert(0, 'T');
+    }
+    if NOT_RAW.contains(&ident.as_str()) {
+        ident.push('_');
+    }
+    ident
+}
+
+/// `ident`, or `ident` with the first free numeric suffix if it is taken
+fn unique(ident: String, taken: &mut HashSet<String>, separator: &str) -> String {
+    let ident = (1..)
+        .map(|n| if n == 1 { ident.clone() } else { format!("{}{}{}", ident, separator, n) })
+        .find(|candidate| !taken.contains(candidate))
+        .unwrap();
+    taken.insert(ident.clone());
+    ident
+}
+
+fn rust_type(schema: &Value) -> String {
+    match schema.get("type").and_then(Value::as_str) {
+        Some("string") => "String".into(),
+        Some("number") => "f64".into(),
+        Some("integer") => "i64".into(),
+        Some("boolean") => "bool".into(),
+        Some("array") => {
+            let item = schema.get("items").map(rust_type).unwrap_or_else(|| "serde_json::Value".into());
+            format!("Vec<{}>", item)
+        }
+        _ => "serde_json::Value".into(),
+    }
+}
+
+fn doc_comment(out: &mut String, indent: &str, text: &str) {
+    for line in text.lines() {
+        let _ = writeln!(out, "{}/// {}", indent, line.trim_end());
+    }
+}
+
+fn generate_args(out: &mut String, struct_name: &str, schema: &Value) {
+    let empty = Map::new();
+    let properties = schema.get("properties").and_then(Value::as_object).unwrap_or(&empty);
+    let required: Vec<&str> = schema
+        .get("required")
+        .and_then(Value::as_array)
+        .map(|r| r.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+
+    let _ = writeln!(out, "#[derive(Debug, Clone, Default, serde::Serialize)]");
+    let _ = writeln!(out, "pub struct {} {{", struct_name);
+
+    let mut names: Vec<&String> = properties.keys().collect();
+    names.sort();
+    let mut fields = HashSet::new();
+    for name in names {
+        let property = &properties[name.as_str()];
+        if let Some(description) = property.get("description").and_then(Value::as_str) {
+            doc_comment(out, "    ", description);
+        }
+
+        let field = unique(snake_ident(name), &mut fields, "_");
+        if field.trim_start_matches("r#") != name {
+            let _ = writeln!(out, "    #[serde(rename = {:?})]", name);
+        }
+
+        let ty = rust_type(property);
+        if required.contains(&name.as_str()) {
+            let _ = writeln!(out, "    pub {}: {},", field, ty);
+        } else {
+            let _ = writeln!(out, "    #[serde(skip_serializing_if = \"Option::is_none\")]");
+            let _ = writeln!(out, "    pub {}: Option<{}>,", field, ty);
+        }
+    }
+    let _ = writeln!(out, "}}\n");
+}
+
+/// Generate Rust source for a typed client of the server described by `manifest`
+pub fn generate_client(manifest: &Value, client_name: &str) -> Result<String, MCPError> {
+    let tools = manifest
+        .get("tools")
+        .and_then(Value::as_array)
+        .ok_or_else(|| MCPError::InvalidParams("manifest has no tools array".into()))?;
+
+    let server_name = manifest
+        .pointer("/serverInfo/name")
+        .and_then(Value::as_str)
+        .unwrap_or("unknown");
+
+    let mut out = String::new();
+    let _ = writeln!(out, "// @generated by mcp_client::codegen from the {} manifest. Do not edit.\n", server_name);
+
+    let mut methods = String::new();
+    let (mut method_names, mut type_names) = (HashSet::new(), HashSet::new());
+    for tool in tools {
+        let name = tool
+            .get("name")
+            .and_then(Value::as_str)
+            .ok_or_else(|| MCPError::InvalidParams("tool without a name".into()))?;
+        let args_name = format!("{}Args", unique(pascal_ident(name), &mut type_names, ""));
+        let method = unique(snake_ident(name), &mut method_names, "_");
+
+        generate_args(&mut out, &args_name, tool.get("inputSchema").unwrap_or(&Value::Null));
+
+        if let Some(description) = tool.get("description").and_then(Value::as_str) {
+            doc_comment(&mut methods, "    ", description);
+        }
+        let _ = writeln!(
+            methods,
+            "    pub async fn {}(&self, args: {}) -> Result<serde_json::Value, mcp_client::mcp_types::MCPError> {{",
+            method,
+            args_name
+        );
+        let _ = writeln!(methods, "        let arguments = serde_json::to_value(args)?;");
+        let _ = writeln!(methods, "        self.inner.call_tool({:?}, arguments).await", name);
+        let _ = writeln!(methods, "    }}\n");
+    }
+
+    let client = pascal_ident(client_name);
+    let _ = writeln!(out, "pub struct {}<C> {{\n    inner: C,\n}}\n", client);
+    let _ = writeln!(out, "impl<C: mcp_client::codegen::CallTool> {}<C> {{", client);
+    let _ = writeln!(out, "    pub fn new(inner: C) -> Self {{\n        Self {{ inner }}\n    }}\n");
+    out.push_str(methods.trim_end());
+    let _ = writeln!(out, "\n}}");
+
+    Ok(out)
+}
+
+/// Build-script helper: read a manifest file and write the generated client
+pub fn write_client(manifest_path: impl AsRef<Path>, out_path: impl AsRef<Path>, client_name: &str) -> Result<(), MCPError> {
+    let manifest: Value = serde_json::from_slice(&std::fs::read(manifest_path)?)?;
+    std::fs::write(out_path, generate_client(&manifest, client_name)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_generate_client() {
+        let manifest = json!({
+            "serverInfo": { "name": "demo" },
+            "tools": [{
+                "name": "bash",
+                "description": "Run a command",
+                "inputSchema": {
+                    "type": "object",
+                    "properties": {
+                        "command": { "type": "string", "description": "Command" },
+                        "timeout": { "type": "number", "description": "Seconds" },
+                        "workingDir": { "type": "string", "description": "Directory" }
+                    },
+                    "required": ["command"]
+                }
+            }]
+        });
+
+        let source = generate_client(&manifest, "demo client").unwrap();
+        assert!(source.contains("pub struct BashArgs {"));
+        assert!(source.contains("    pub command: String,"));
+        assert!(source.contains("    pub timeout: Option<f64>,"));
+        assert!(source.contains("#[serde(rename = \"workingDir\")]"));
+        assert!(source.contains("pub struct DemoClient<C>"));
+        assert!(source.contains("pub async fn bash(&self, args: BashArgs)"));
+    }
+
+    #[test]
+    fn test_identifiers() {
+        assert_eq!(snake_ident("fs/read-file"), "fs_read_file");
+        assert_eq!(snake_ident("type"), "r#type");
+        assert_eq!(snake_ident("Self"), "self_");
+        assert_eq!(pascal_ident("list_directory"), "ListDirectory");
+        assert_eq!(pascal_ident("self"), "Self_");
+    }
+
+    fn awkward_manifest() -> Value {
+        json!({
+            "serverInfo": { "name": "awkward" },
+            "tools": [
+                { "name": "read-file", "inputSchema": { "type": "object", "properties": {
+                    "self": { "type": "string" }, "crate": { "type": "string" }, "type": { "type": "string" },
+                    "a-b": { "type": "integer" }, "a_b": { "type": "integer" }
+                }, "required": ["self"] } },
+                { "name": "read_file", "inputSchema": { "type": "object" } },
+                { "name": "super", "inputSchema": { "type": "object" } }
+            ]
+        })
+    }
+
+    // The checked-in output of `awkward_manifest`, compiled as part of this test module
+    mod generated {
+        include!(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/awkward_client.rs"));
+    }
+
+    struct Echo;
+
+    impl CallTool for Echo {
+        async fn call_tool(&self, name: &str, arguments: Value) -> Result<Value, MCPError> {
+            Ok(json!({ "name": name, "arguments": arguments }))
+        }
+    }
+
+    #[test]
+    fn test_generated_code_compiles() {
+        let source = generate_client(&awkward_manifest(), "awkward client").unwrap();
+        assert_eq!(source, include_str!(concat!(env!("CARGO_MANIFEST_DIR"), "/testdata/awkward_client.rs")));
+
+        let client = generated::AwkwardClient::new(Echo);
+        let args = generated::ReadFileArgs { self_: "x".into(), a_b: Some(1), a_b_2: Some(2), ..Default::default() };
+        let call = std::pin::pin!(client.read_file(args));
+        let std::task::Poll::Ready(sent) = call.poll(&mut std::task::Context::from_waker(std::task::Waker::noop())) else {
+            panic!("pending")
+        };
+        assert_eq!(sent.unwrap(), json!({ "name": "read-file", "arguments": { "self": "x", "a-b": 1, "a_b": 2 } }));
+        let _ = (client.read_file_2(generated::ReadFile2Args {}), client.super_(generated::SuperArgs {}));
+    }
+}
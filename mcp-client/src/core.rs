@@ -0,0 +1,257 @@
+use mcp_types::error::{JsonRpcError, MCPError};
+use mcp_types::protocol::{self, PROTOCOL_VERSION, SUPPORTED_PROTOCOL_VERSIONS};
+use mcp_types::{MCPRequest, MCPResponse};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+
+/// An inbound message classified by the client core
+#[derive(Debug)]
+pub enum ClientEvent {
+    /// Response to a request previously created by this core
+    Response {
+        id: u64,
+        method: String,
+        result: Result<Value, JsonRpcError>,
+    },
+    /// Notification pushed by the server
+    Notification {
+        method: String,
+        params: Option<Value>,
+    },
+    /// Request initiated by the server (ping, sampling, roots)
+    Request(MCPRequest),
+}
+
+/// Sans-IO client state: id allocation, response correlation and the
+/// negotiated protocol revision
+#[derive(Debug, Default)]
+pub struct ClientCore {
+    next_id: u64,
+    pending: HashMap<u64, String>,
+    protocol_version: Option<String>,
+}
+
+impl ClientCore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a request with a fresh id and remember it for correlation
+    pub fn request(&mut self, method: &str, params: Option<Value>) -> (u64, MCPRequest) {
+        self.next_id += 1;
+        let id = self.next_id;
+        self.pending.insert(id, method.to_string());
+        (id, MCPRequest::new(Some(json!(id)), method, params))
+    }
+
+    /// Create a notification (no id, no response expected)
+    pub fn notification(&self, method: &str, params: Option<Value>) -> MCPRequest {
+        MCPRequest::new(None, method, params)
+    }
+
+    pub fn initialize(&mut self, client_name: &str, client_version: &str) -> (u64, MCPRequest) {
+        #[cfg(feature = "zstd")]
+        let capabilities = json!({ "experimental": { mcp_types::compression::CAPABILITY: {} } });
+        #[cfg(not(feature = "zstd"))]
+        let capabilities = json!({});
+        self.request("initialize", Some(json!({
+            "protocolVersion": PROTOCOL_VERSION,
+            "capabilities": capabilities,
+            "clientInfo": { "name": client_name, "version": client_version },
+        })))
+    }
+
+    /// Confirm the handshake once the initialize response arrived
+    pub fn initialized(&self) -> MCPRequest {
+        self.notification("notifications/initialized", None)
+    }
+
+    pub fn list_tools(&mut self) -> (u64, MCPRequest) {
+        self.request("tools/list", None)
+    }
+
+    pub fn call_tool(&mut self, name: &str, arguments: Value) -> (u64, MCPRequest) {
+        self.request("tools/call", Some(json!({ "name": name, "arguments": arguments })))
+    }
+
+    pub fn read_resource(&mut self, uri: &str) -> (u64, MCPRequest) {
+        self.request("resources/read", Some(json!({ "uri": uri })))
+    }
+
+    /// Serialize a request for the wire
+    pub fn encode(&self, request: &MCPRequest) -> Result<String, MCPError> {
+        serde_json::to_string(request).map_err(MCPError::from)
+    }
+
+    /// Classify one inbound JSON message
+    pub fn handle_message(&mut self, text: &str) -> Result<ClientEvent, MCPError> {
+        let value: Value = serde_json::from_str(text)?;
+
+        if value.get("method").is_some() {
+            let request: MCPRequest = serde_json::from_value(value)?;
+            if request.is_notification() {
+                return Ok(ClientEvent::Notification {
+                    method: request.method,
+                    params: request.params,
+                });
+            }
+            return Ok(ClientEvent::Request(request));
+        }
+
+        let response: MCPResponse = serde_json::from_value(value)?;
+        let id = response.id.as_ref().and_then(Value::as_u64)
+            .ok_or_else(|| MCPError::UnexpectedResponse(format!("{:?}", response.id)))?;
+        let method = self.pending.remove(&id)
+            .ok_or_else(|| MCPError::UnexpectedResponse(id.to_string()))?;
+
+        let result = match response.error {
+            Some(error) => Err(error),
+            None => Ok(response.result.unwrap_or(Value::Null)),
+        };
+        let result = match result {
+            Ok(value) if method == "initialize" => self.negotiate(value),
+            other => other,
+        };
+        // Compressed contents are expanded before digests are checked
+        #[cfg(feature = "zstd")]
+        let result = match result {
+            Ok(mut value) if matches!(method.as_str(), "resources/read" | "tools/call") => {
+                mcp_types::compression::decompress_result(&mut value).map(|_| value).map_err(|e| e.to_json_rpc_error())
+            }
+            other => other,
+        };
+        // Contents carrying `_meta.sha256` that does not match become an error
+        #[cfg(feature = "integrity")]
+        let result = match result {
+            Ok(value) if matches!(method.as_str(), "resources/read" | "tools/call") => {
+                mcp_types::integrity::verify_result(&value).map(|_| value).map_err(|e| e.to_json_rpc_error())
+            }
+            other => other,
+        };
+        Ok(ClientEvent::Response { id, method, result })
+    }
+
+    /// Number of requests still awaiting a response
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Revision the server answered `initialize` with, once it is one this
+    /// client supports
+    pub fn protocol_version(&self) -> Option<&str> {
+        self.protocol_version.as_deref()
+    }
+
+    /// Accept the revision of an `initialize` result, or turn the result into
+    /// the error the spec gives for an unsupported revision
+    fn negotiate(&mut self, result: Value) -> Result<Value, JsonRpcError> {
+        let version = result.get("protocolVersion").and_then(Value::as_str);
+        match version.filter(|version| protocol::is_supported(version)) {
+            Some(version) => {
+                self.protocol_version = Some(version.to_string());
+                Ok(result)
+            }
+            None => Err(JsonRpcError {
+                code: -32602,
+                message: "Unsupported protocol version".into(),
+                data: Some(json!({ "supported": SUPPORTED_PROTOCOL_VERSIONS, "requested": version })),
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_response_correlation() {
+        let mut core = ClientCore::new();
+        let (id, request) = core.call_tool("bash", json!({ "command": "ls" }));
+        assert_eq!(request.method, "tools/call");
+        assert_eq!(core.pending(), 1);
+
+        let event = core
+            .handle_message(&format!(r#"{{"jsonrpc":"2.0","id":{},"result":{{"ok":true}}}}"#, id))
+            .unwrap();
+        match event {
+            ClientEvent::Response { method, result, .. } => {
+                assert_eq!(method, "tools/call");
+                assert_eq!(result.unwrap(), json!({ "ok": true }));
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+        assert_eq!(core.pending(), 0);
+    }
+
+    #[test]
+    fn test_protocol_negotiation() {
+        let mut core = ClientCore::new();
+        let (id, init) = core.initialize("test", "1.0");
+        assert_eq!(init.params.unwrap()["protocolVersion"], PROTOCOL_VERSION);
+        let answer = |id: u64, version: &str| json!({ "jsonrpc": "2.0", "id": id, "result": { "protocolVersion": version } }).to_string();
+        assert!(matches!(core.handle_message(&answer(id, PROTOCOL_VERSION)).unwrap(), ClientEvent::Response { result: Ok(_), .. }));
+        assert_eq!(core.protocol_version(), Some(PROTOCOL_VERSION));
+
+        let mut core = ClientCore::new();
+        let (id, _) = core.initialize("test", "1.0");
+        match core.handle_message(&answer(id, "2024-11-05")).unwrap() {
+            ClientEvent::Response { result: Err(error), .. } => {
+                assert_eq!(error.code, -32602);
+                assert_eq!(error.data.unwrap()["requested"], "2024-11-05");
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+        assert_eq!(core.protocol_version(), None);
+    }
+
+    #[test]
+    fn test_notification_and_unknown_id() {
+        let mut core = ClientCore::new();
+        let event = core
+            .handle_message(r#"{"jsonrpc":"2.0","method":"notifications/progress","params":{}}"#)
+            .unwrap();
+        assert!(matches!(event, ClientEvent::Notification { .. }));
+
+        let err = core.handle_message(r#"{"jsonrpc":"2.0","id":42,"result":{}}"#);
+        assert!(matches!(err, Err(MCPError::UnexpectedResponse(_))));
+    }
+
+    #[cfg(feature = "integrity")]
+    #[test]
+    fn test_digest_verification() {
+        let mut core = ClientCore::new();
+        let mut contents = json!({ "uri": "file:///a", "mimeType": "text/plain", "text": "abc" });
+        mcp_types::integrity::attach(&mut contents);
+
+        let (id, _) = core.read_resource("file:///a");
+        let ok = json!({ "jsonrpc": "2.0", "id": id, "result": contents });
+        assert!(matches!(core.handle_message(&ok.to_string()).unwrap(), ClientEvent::Response { result: Ok(_), .. }));
+
+        let (id, _) = core.read_resource("file:///a");
+        contents["text"] = json!("abd");
+        let tampered = json!({ "jsonrpc": "2.0", "id": id, "result": contents });
+        match core.handle_message(&tampered.to_string()).unwrap() {
+            ClientEvent::Response { result: Err(error), .. } => assert!(error.message.contains("sha256")),
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_compressed_contents_are_expanded() {
+        let mut core = ClientCore::new();
+        let (_, init) = core.initialize("test", "1.0");
+        assert!(mcp_types::compression::supported(&init.params.unwrap()["capabilities"]));
+
+        let text = "log line\n".repeat(500);
+        let mut contents = json!({ "uri": "file:///log", "mimeType": "text/plain", "text": text });
+        assert!(mcp_types::compression::compress(&mut contents, 1024));
+        let (id, _) = core.read_resource("file:///log");
+        let response = json!({ "jsonrpc": "2.0", "id": id, "result": contents });
+        match core.handle_message(&response.to_string()).unwrap() {
+            ClientEvent::Response { result: Ok(value), .. } => assert_eq!(value["text"], text),
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+}
@@ -0,0 +1,19 @@
+//! MCP client core.
+//!
+//! [`ClientCore`] is transport-agnostic: it allocates request ids, encodes
+//! requests and correlates responses, leaving IO to the caller. It has no
+//! tokio dependency and compiles for `wasm32-unknown-unknown`; browser
+//! transports are available behind the `web` feature. [`codegen`] generates
+//! typed client wrappers from a server manifest.
+
+pub mod codegen;
+pub mod core;
+#[cfg(feature = "web")]
+pub mod web;
+
+pub use crate::core::{ClientCore, ClientEvent};
+pub use mcp_types;
+
+// Generated code names this crate, and tests compile some
+#[cfg(test)]
+extern crate self as mcp_client;
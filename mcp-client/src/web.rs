@@ -0,0 +1,137 @@
+//! Browser transports built on `web-sys`.
+
+use futures_channel::{mpsc, oneshot};
+use futures_util::StreamExt;
+use mcp_types::error::MCPError;
+use std::cell::RefCell;
+use std::rc::Rc;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+use web_sys::{CloseEvent, ErrorEvent, MessageEvent, Request, RequestInit, Response, WebSocket};
+
+fn js_error(value: JsValue) -> MCPError {
+    MCPError::StreamError(format!("{:?}", value))
+}
+
+/// Request/response transport over HTTP POST using `fetch`
+#[derive(Debug, Clone)]
+pub struct FetchTransport {
+    url: String,
+}
+
+impl FetchTransport {
+    pub fn new(url: impl Into<String>) -> Self {
+        FetchTransport { url: url.into() }
+    }
+
+    /// POST one JSON-RPC message and return the response body
+    pub async fn send(&self, body: &str) -> Result<String, MCPError> {
+        let init = RequestInit::new();
+        init.set_method("POST");
+        init.set_body(&JsValue::from_str(body));
+
+        let request = Request::new_with_str_and_init(&self.url, &init).map_err(js_error)?;
+        request.headers().set("Content-Type", "application/json").map_err(js_error)?;
+        request.headers().set("Accept", "application/json").map_err(js_error)?;
+
+        let window = web_sys::window()
+            .ok_or_else(|| MCPError::StreamError("fetch requires a window".into()))?;
+        let response: Response = JsFuture::from(window.fetch_with_request(&request))
+            .await
+            .map_err(js_error)?
+            .dyn_into()
+            .map_err(js_error)?;
+
+        if !response.ok() {
+            return Err(MCPError::StreamError(format!("HTTP {}", response.status())));
+        }
+
+        let text = JsFuture::from(response.text().map_err(js_error)?)
+            .await
+            .map_err(js_error)?;
+        Ok(text.as_string().unwrap_or_default())
+    }
+}
+
+/// Bidirectional transport over a browser WebSocket, one JSON message per frame
+pub struct WebSocketTransport {
+    socket: WebSocket,
+    incoming: mpsc::UnboundedReceiver<String>,
+    _on_message: Closure<dyn FnMut(MessageEvent)>,
+    _on_close: Closure<dyn FnMut(CloseEvent)>,
+}
+
+impl WebSocketTransport {
+    /// Open a socket and wait until the connection is established
+    pub async fn connect(url: &str) -> Result<Self, MCPError> {
+        let socket = WebSocket::new(url).map_err(js_error)?;
+
+        let (tx, incoming) = mpsc::unbounded::<String>();
+        let on_message_tx = tx.clone();
+        let on_message = Closure::<dyn FnMut(MessageEvent)>::new(move |event: MessageEvent| {
+            if let Some(text) = event.data().as_string() {
+                let _ = on_message_tx.unbounded_send(text);
+            }
+        });
+        socket.set_onmessage(Some(on_message.as_ref().unchecked_ref()));
+
+        let on_close = Closure::<dyn FnMut(CloseEvent)>::new(move |_event: CloseEvent| {
+            tx.close_channel();
+        });
+        socket.set_onclose(Some(on_close.as_ref().unchecked_ref()));
+
+        let (open_tx, open_rx) = oneshot::channel::<Result<(), MCPError>>();
+        let open_tx = Rc::new(RefCell::new(Some(open_tx)));
+
+        let opened = open_tx.clone();
+        let on_open = Closure::<dyn FnMut(JsValue)>::new(move |_event: JsValue| {
+            if let Some(tx) = opened.borrow_mut().take() {
+                let _ = tx.send(Ok(()));
+            }
+        });
+        let failed = open_tx;
+        let on_error = Closure::<dyn FnMut(ErrorEvent)>::new(move |event: ErrorEvent| {
+            if let Some(tx) = failed.borrow_mut().take() {
+                let _ = tx.send(Err(MCPError::StreamError(event.message())));
+            }
+        });
+        socket.set_onopen(Some(on_open.as_ref().unchecked_ref()));
+        socket.set_onerror(Some(on_error.as_ref().unchecked_ref()));
+
+        let result = open_rx
+            .await
+            .unwrap_or_else(|_| Err(MCPError::StreamError("socket closed before open".into())));
+
+        socket.set_onopen(None);
+        socket.set_onerror(None);
+        result?;
+
+        Ok(WebSocketTransport {
+            socket,
+            incoming,
+            _on_message: on_message,
+            _on_close: on_close,
+        })
+    }
+
+    pub fn send(&self, text: &str) -> Result<(), MCPError> {
+        self.socket.send_with_str(text).map_err(js_error)
+    }
+
+    /// Next inbound message, or `None` once the socket has closed
+    pub async fn recv(&mut self) -> Option<String> {
+        self.incoming.next().await
+    }
+
+    pub fn close(&self) -> Result<(), MCPError> {
+        self.socket.close().map_err(js_error)
+    }
+}
+
+impl Drop for WebSocketTransport {
+    fn drop(&mut self) {
+        self.socket.set_onmessage(None);
+        self.socket.set_onclose(None);
+    }
+}
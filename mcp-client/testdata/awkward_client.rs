@@ -0,0 +1,51 @@
+// @generated by mcp_client::codegen from the awkward manifest. Do not edit.
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct ReadFileArgs {
+    #[serde(rename = "a-b")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub a_b: Option<i64>,
+    #[serde(rename = "a_b")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub a_b_2: Option<i64>,
+    #[serde(rename = "crate")]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crate_: Option<String>,
+    #[serde(rename = "self")]
+    pub self_: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub r#type: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct ReadFile2Args {
+}
+
+#[derive(Debug, Clone, Default, serde::Serialize)]
+pub struct SuperArgs {
+}
+
+pub struct AwkwardClient<C> {
+    inner: C,
+}
+
+impl<C: mcp_client::codegen::CallTool> AwkwardClient<C> {
+    pub fn new(inner: C) -> Self {
+        Self { inner }
+    }
+
+    pub async fn read_file(&self, args: ReadFileArgs) -> Result<serde_json::Value, mcp_client::mcp_types::MCPError> {
+        let arguments = serde_json::to_value(args)?;
+        self.inner.call_tool("read-file", arguments).await
+    }
+
+    pub async fn read_file_2(&self, args: ReadFile2Args) -> Result<serde_json::Value, mcp_client::mcp_types::MCPError> {
+        let arguments = serde_json::to_value(args)?;
+        self.inner.call_tool("read_file", arguments).await
+    }
+
+    pub async fn super_(&self, args: SuperArgs) -> Result<serde_json::Value, mcp_client::mcp_types::MCPError> {
+        let arguments = serde_json::to_value(args)?;
+        self.inner.call_tool("super", arguments).await
+    }
+}
@@ -1,21 +1,8 @@
-pub mod error;
-pub mod macros;
-pub mod notifications;
-pub mod prelude;
-pub mod request;
-pub mod response;
-pub mod server;
-pub mod tools;
+//! Facade crate re-exporting [`mcp_types`] and [`mcp_server`].
+//!
+//! Existing code can keep using `mcp_sdk::...` paths; consumers that only need
+//! the protocol types can depend on `mcp-types` directly.
 
-pub use error::MCPError;
-pub use notifications::{ProgressSender, ServerNotification};
-pub use request::MCPRequest;
-pub use response::MCPResponse;
-pub use server::{JsonRpcVersion, ServerBuilder, SystemMCPServer, ToolHandler};
-pub use tools::{
-    CancellationNotification, CancellationNotificationMessage, CancellationParams,
-    InitializeResponse, ProgressNotification, ProgressNotificationMessage, ProgressParams, Prompt,
-    PromptArgument, PromptContent, PromptMessage, PromptResponse, Resource, ResourceContent,
-    ServerCapabilities, ServerInfo, StreamChunk, Tool, ToolContent, ToolInputSchema, ToolProperty,
-    ToolResponse,
-};
+pub use mcp_server::*;
+pub use mcp_server;
+pub use mcp_types;
@@ -0,0 +1,71 @@
+//! Throughput of request parsing on representative MCP payloads.
+//!
+//! Compares plain serde_json with `mcp_server::json::from_slice`, which uses
+//! simd-json when built with the feature:
+//!
+//!     cargo bench -p mcp-server --bench json_parse
+//!     cargo bench -p mcp-server --bench json_parse --features simd-json
+
+use mcp_server::request::MCPRequest;
+use serde_json::json;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const MIN_RUN: Duration = Duration::from_millis(500);
+
+fn payloads() -> Vec<(&'static str, Vec<u8>)> {
+    let small = json!({
+        "jsonrpc": "2.0", "id": 1, "method": "tools/call",
+        "params": { "name": "bash", "arguments": { "command": "ls -la", "timeout": 30 } }
+    });
+    let script: String = (0..2000).map(|i| format!("echo \"line {i}\" | tee -a /tmp/out.log\n")).collect();
+    let large_args = json!({
+        "jsonrpc": "2.0", "id": 2, "method": "tools/call",
+        "params": { "name": "bash", "arguments": { "command": script, "env": { "A": "1", "B": "2" } } }
+    });
+    let image: String = (0..2_000_000).map(|i| b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/"[i % 64] as char).collect();
+    let image_args = json!({
+        "jsonrpc": "2.0", "id": 3, "method": "tools/call",
+        "params": { "name": "ocr", "arguments": { "image": { "data": image, "mimeType": "image/png" } } }
+    });
+    let many_items = json!({
+        "jsonrpc": "2.0", "id": 4, "method": "tools/call",
+        "params": { "name": "batch", "arguments": {
+            "items": (0..5000).map(|i| json!({ "id": i, "path": format!("/srv/data/{i}.txt"), "size": i * 17, "tags": ["a", "b"] })).collect::<Vec<_>>()
+        } }
+    });
+
+    [("small", small), ("large_args", large_args), ("base64_image", image_args), ("many_items", many_items)]
+        .into_iter()
+        .map(|(name, value)| (name, serde_json::to_vec(&value).unwrap()))
+        .collect()
+}
+
+/// Bytes per second parsed by `parse`
+fn throughput(payload: &[u8], parse: impl Fn(&[u8]) -> MCPRequest) -> f64 {
+    let start = Instant::now();
+    let mut iterations = 0u64;
+    while start.elapsed() < MIN_RUN {
+        black_box(parse(black_box(payload)));
+        iterations += 1;
+    }
+    (payload.len() as u64 * iterations) as f64 / start.elapsed().as_secs_f64()
+}
+
+fn main() {
+    let backend = if cfg!(feature = "simd-json") { "simd-json" } else { "serde_json" };
+    println!("{:<14} {:>10} {:>14} {:>14} {:>8}", "payload", "bytes", "serde_json", backend, "ratio");
+
+    for (name, payload) in payloads() {
+        let baseline = throughput(&payload, |raw| serde_json::from_slice(raw).unwrap());
+        let candidate = throughput(&payload, |raw| mcp_server::json::from_slice(raw).unwrap());
+        println!(
+            "{:<14} {:>10} {:>10.1} MB/s {:>10.1} MB/s {:>7.2}x",
+            name,
+            payload.len(),
+            baseline / 1e6,
+            candidate / 1e6,
+            candidate / baseline,
+        );
+    }
+}
@@ -0,0 +1,108 @@
+//! Typed params and tool arguments with located errors.
+//!
+//! serde reports failures as prose ("missing field `command`"), which models
+//! struggle to act on. [`from_value`] reports them as
+//! [`MCPError::InvalidParamAt`] instead: the JSON pointer of the failing
+//! field, the expected type and a snippet of what was received, all of which
+//! end up in the JSON-RPC error's `data`.
+
+use crate::error::MCPError;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use serde_path_to_error::Segment;
+
+/// Longest `received` snippet, in characters
+const SNIPPET_LEN: usize = 80;
+
+/// Deserialize `value`, locating any failure by JSON pointer
+pub fn from_value<T: DeserializeOwned>(value: &Value) -> Result<T, MCPError> {
+    serde_path_to_error::deserialize(value).map_err(|err| {
+        let mut pointer = String::new();
+        for segment in err.path().iter() {
+            match segment {
+                Segment::Seq { index } => push_token(&mut pointer, &index.to_string()),
+                Segment::Map { key } => push_token(&mut pointer, key),
+                Segment::Enum { variant } => push_token(&mut pointer, variant),
+                Segment::Unknown => {}
+            }
+        }
+        located(value, pointer, err.into_inner().to_string())
+    })
+}
+
+fn located(value: &Value, mut pointer: String, message: String) -> MCPError {
+    // The path of a missing field ends at the object that lacks it
+    let missing = message.strip_prefix("missing field `").and_then(|rest| rest.split('`').next());
+    let expected = match missing {
+        Some(field) => {
+            push_token(&mut pointer, field);
+            Some("a value".to_string())
+        }
+        None if message.starts_with("unknown field") && !message.contains(", expected ") => {
+            Some("no such field".to_string())
+        }
+        None => message.split_once(", expected ").map(|(_, expected)| expected.to_string()),
+    };
+    let received = value.pointer(&pointer).map(snippet);
+    MCPError::InvalidParamAt { pointer, message, expected, received }
+}
+
+/// Append one reference token, escaped per RFC 6901
+fn push_token(pointer: &mut String, token: &str) {
+    pointer.push('/');
+    pointer.push_str(&token.replace('~', "~0").replace('/', "~1"));
+}
+
+fn snippet(value: &Value) -> String {
+    let text = value.to_string();
+    match text.char_indices().nth(SNIPPET_LEN) {
+        Some((end, _)) => format!("{}…", &text[..end]),
+        None => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+    use serde_json::json;
+
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    #[allow(dead_code)]
+    struct Step {
+        command: String,
+        timeout: Option<u64>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[allow(dead_code)]
+    struct Plan {
+        steps: Vec<Step>,
+    }
+
+    fn error(value: Value) -> (String, Option<String>, Option<String>) {
+        match from_value::<Plan>(&value).unwrap_err() {
+            MCPError::InvalidParamAt { pointer, expected, received, .. } => (pointer, expected, received),
+            other => panic!("unexpected error: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_errors_point_at_the_field() {
+        let (pointer, expected, received) = error(json!({ "steps": [{ "command": "ls" }, { "timeout": 5 }] }));
+        assert_eq!((pointer.as_str(), expected.as_deref(), received), ("/steps/1/command", Some("a value"), None));
+
+        let (pointer, expected, received) = error(json!({ "steps": [{ "command": "ls", "timeout": "soon" }] }));
+        assert_eq!(pointer, "/steps/0/timeout");
+        assert_eq!(expected.as_deref(), Some("u64"));
+        assert_eq!(received.as_deref(), Some("\"soon\""));
+
+        let (pointer, _, received) = error(json!({ "steps": [{ "command": "ls", "a/b": "x".repeat(200) }] }));
+        assert_eq!(pointer, "/steps/0/a~1b");
+        assert!(received.unwrap().ends_with('…'));
+
+        let data = from_value::<Plan>(&json!({})).unwrap_err().to_json_rpc_error();
+        assert_eq!((data.code, data.data.unwrap()["pointer"].clone()), (-32602, json!("/steps")));
+    }
+}
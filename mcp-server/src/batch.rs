@@ -0,0 +1,198 @@
+//! `x-mcp/batch-call`: several tool calls with all-or-nothing semantics.
+//!
+//! Steps run in order. When one fails (an error or `isError: true`) the
+//! steps that already succeeded are rolled back in reverse order through
+//! [`ToolHandler::rollback_tool`]. Every step but the last must therefore
+//! use a tool that [supports rollback](ToolHandler::supports_rollback); the
+//! batch is refused up front otherwise.
+//!
+//! ```json
+//! { "steps": [ { "name": "write_file", "arguments": { ... } }, ... ] }
+//! ```
+//!
+//! [`ToolHandler::rollback_tool`]: crate::server::ToolHandler::rollback_tool
+//! [`ToolHandler::supports_rollback`]: crate::server::ToolHandler::supports_rollback
+
+use crate::args;
+use crate::custom::{MethodHandler, ToolCaller};
+use crate::error::MCPError;
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+pub const BATCH_CALL_METHOD: &str = "x-mcp/batch-call";
+
+#[derive(Debug, Deserialize)]
+struct Step {
+    name: String,
+    #[serde(default)]
+    arguments: Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct BatchParams {
+    steps: Vec<Step>,
+}
+
+#[derive(Debug, Default)]
+pub struct BatchCall;
+
+#[async_trait]
+impl MethodHandler for BatchCall {
+    async fn handle(&self, tools: &dyn ToolCaller, params: Option<&Value>) -> Result<Value, MCPError> {
+        let params: BatchParams = args::from_value(params.ok_or(MCPError::MissingParameters)?)?;
+        let Some((_, init)) = params.steps.split_last() else {
+            return Err(MCPError::InvalidParams("batch has no steps".into()));
+        };
+        if let Some(step) = init.iter().find(|step| !tools.supports_rollback(&step.name)) {
+            return Err(MCPError::InvalidParams(format!("tool {} does not support rollback", step.name)));
+        }
+
+        let mut results = Vec::with_capacity(params.steps.len());
+        let mut failed = None;
+        for (index, step) in params.steps.iter().enumerate() {
+            match tools.call_tool(&step.name, step.arguments.clone()).await {
+                Ok(result) => {
+                    let is_error = result.get("isError").and_then(Value::as_bool).unwrap_or(false);
+                    results.push(json!({ "name": step.name, "result": result }));
+                    if is_error {
+                        failed = Some(index);
+                        break;
+                    }
+                }
+                Err(e) => {
+                    let error = match e {
+                        MCPError::PeerError(error) => error,
+                        e => e.to_json_rpc_error(),
+                    };
+                    results.push(json!({ "name": step.name, "error": error }));
+                    failed = Some(index);
+                    break;
+                }
+            }
+        }
+
+        if let Some(failed) = failed {
+            for (index, step) in params.steps[..failed].iter().enumerate().rev() {
+                let rolled_back = match tools.rollback_tool(&step.name, &step.arguments).await {
+                    Ok(()) => json!(true),
+                    Err(e) => {
+                        eprintln!("[BATCH] Rollback of step {} ({}) failed: {}", index, step.name, e);
+                        json!({ "error": e.to_string() })
+                    }
+                };
+                results[index]["rolledBack"] = rolled_back;
+            }
+        }
+
+        let mut response = json!({ "committed": failed.is_none(), "steps": results });
+        if let Some(failed) = failed {
+            response["failedStep"] = json!(failed);
+        }
+        Ok(response)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::concurrency::ConcurrencyLimits;
+    use crate::context::RequestContext;
+    use crate::request::MCPRequest;
+    use crate::server::{SystemMCPServer, ToolHandler};
+    use crate::testing::fixtures;
+    use crate::testing::mock::{MockToolHandler, Reply};
+    use crate::tools::ToolResponse;
+    use std::sync::{Arc, Mutex};
+
+    /// `append` pushes onto a list and can be undone; `fail` always errors
+    struct Appender {
+        items: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[async_trait]
+    impl ToolHandler for Appender {
+        async fn call_tool(&self, name: &str, args: &Value, _ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+            match name {
+                "append" => {
+                    self.items.lock().unwrap().push(args["item"].as_str().unwrap_or_default().into());
+                    Ok(ToolResponse::new("ok".into(), false))
+                }
+                _ => Ok(ToolResponse::new("failed".into(), true)),
+            }
+        }
+
+        fn supports_rollback(&self, name: &str) -> bool {
+            name == "append"
+        }
+
+        async fn rollback_tool(&self, _name: &str, _args: &Value, _ctx: &RequestContext) -> Result<(), MCPError> {
+            self.items.lock().unwrap().pop();
+            Ok(())
+        }
+    }
+
+    async fn batch(server: &SystemMCPServer<Appender>, steps: Value) -> Value {
+        let req = MCPRequest::new(Some(json!(1)), BATCH_CALL_METHOD, Some(json!({ "steps": steps })));
+        serde_json::to_value(server.handle(req).await.unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_all_or_nothing() {
+        let items = Arc::new(Mutex::new(Vec::new()));
+        let server = SystemMCPServer::<Appender>::builder().relaxed_lifecycle().batch_calls().build(Appender { items: items.clone() });
+        let append = |item: &str| json!({ "name": "append", "arguments": { "item": item } });
+
+        let reply = batch(&server, json!([append("a"), append("b")])).await;
+        assert_eq!(reply["result"]["committed"], true);
+
+        let reply = batch(&server, json!([append("c"), { "name": "fail" }])).await;
+        assert_eq!(reply["result"]["committed"], false);
+        assert_eq!(reply["result"]["failedStep"], 1);
+        assert_eq!(reply["result"]["steps"][0]["rolledBack"], true);
+        assert_eq!(*items.lock().unwrap(), ["a", "b"]);
+
+        let reply = batch(&server, json!([{ "name": "fail" }, append("d")])).await;
+        assert_eq!(reply["error"]["code"], -32602);
+    }
+
+    #[tokio::test]
+    async fn test_steps_are_requests_of_the_batch() {
+        let mock = MockToolHandler::new().tool("fast", Reply::text("ok")).tool("slow", Reply::hang());
+        let server = Arc::new(SystemMCPServer::<MockToolHandler>::builder()
+            .relaxed_lifecycle()
+            .batch_calls()
+            .concurrency_limits(ConcurrencyLimits::new().max_in_flight(1))
+            .build(mock));
+        let steps = |name: &str| fixtures::request(BATCH_CALL_METHOD).id(7).param("steps", json!([{ "name": name }]));
+
+        // The step runs in the batch's slot, with an id under the batch's
+        let reply = server.handle(steps("fast").build()).await.unwrap().result.unwrap();
+        assert_eq!(reply["committed"], true);
+        assert_eq!(server.handler().calls()[0].request_id, "7/1");
+
+        // Cancelling the batch cancels its step
+        let batch = tokio::spawn({
+            let server = server.clone();
+            async move { server.handle(steps("slow").build()).await.unwrap() }
+        });
+        while server.handler().calls().len() < 2 {
+            tokio::task::yield_now().await;
+        }
+        server.handle(fixtures::cancelled(7, None)).await;
+        // Whichever notices first answers
+        let reply = serde_json::to_value(batch.await.unwrap()).unwrap();
+        let code = reply["error"]["code"].as_i64().or(reply["result"]["steps"][0]["error"]["code"].as_i64());
+        assert_eq!(code, Some(-32800));
+
+        // Steps go through the same checks as tools/call from the client
+        let server = SystemMCPServer::<MockToolHandler>::builder()
+            .relaxed_lifecycle()
+            .batch_calls()
+            .disable_methods(["tools/call"])
+            .build(MockToolHandler::new().tool("fast", Reply::text("ok")));
+        let reply = server.handle(steps("fast").build()).await.unwrap().result.unwrap();
+        assert_eq!(reply["steps"][0]["error"]["code"], -32601);
+        assert!(server.handler().calls().is_empty());
+    }
+}
@@ -0,0 +1,52 @@
+//! Play a request transcript against two server builds and print the
+//! differences as JSON. Exits 0 when both behave identically.
+//!
+//!     mcp-trace-diff <transcript> --left "<cmd>" --right "<cmd>" [--ignore <pointer>]... [--timeout <secs>]
+
+use mcp_server::trace_diff::{diff_servers, read_transcript, split_command, DEFAULT_RESPONSE_TIMEOUT};
+use std::time::Duration;
+
+fn usage() -> ! {
+    eprintln!("usage: mcp-trace-diff <transcript> --left <cmd> --right <cmd> [--ignore <pointer>]... [--timeout <secs>]");
+    std::process::exit(2);
+}
+
+#[tokio::main]
+async fn main() {
+    let mut args = std::env::args().skip(1);
+    let (mut transcript, mut left, mut right) = (None, None, None);
+    let mut ignore = Vec::new();
+    let mut timeout = DEFAULT_RESPONSE_TIMEOUT;
+
+    while let Some(arg) = args.next() {
+        let mut value = || args.next().unwrap_or_else(|| usage());
+        match arg.as_str() {
+            "--left" => left = Some(value()),
+            "--right" => right = Some(value()),
+            "--ignore" => ignore.push(value()),
+            "--timeout" => timeout = Duration::from_secs(value().parse().unwrap_or_else(|_| usage())),
+            _ if transcript.is_none() => transcript = Some(arg),
+            _ => usage(),
+        }
+    }
+    let (Some(transcript), Some(left), Some(right)) = (transcript, left, right) else { usage() };
+    let split = |cmd: String| split_command(&cmd).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(2);
+    });
+
+    let requests = read_transcript(&transcript).unwrap_or_else(|e| {
+        eprintln!("Failed to read {}: {}", transcript, e);
+        std::process::exit(2);
+    });
+    match diff_servers(&split(left), &split(right), &requests, &ignore, timeout).await {
+        Ok(report) => {
+            println!("{}", serde_json::to_string_pretty(&report).unwrap());
+            std::process::exit(if report.is_identical() { 0 } else { 1 });
+        }
+        Err(e) => {
+            eprintln!("Trace diff failed: {}", e);
+            std::process::exit(2);
+        }
+    }
+}
@@ -0,0 +1,179 @@
+//! Live logs of running tool calls.
+//!
+//! With [`ServerBuilder::call_logs`] every `tools/call` gets a transient
+//! resource `call://{request_id}/log` that accumulates the call's progress
+//! messages. Clients without notification support can poll it with
+//! `resources/read` while the call runs. Logs are dropped once the call has
+//! been finished for longer than the configured TTL. Each log keeps its last
+//! [`MAX_LOG_BYTES`], and past [`MAX_LOGS`] logs the oldest finished ones are
+//! dropped before their TTL.
+//!
+//! [`ServerBuilder::call_logs`]: crate::server::ServerBuilder::call_logs
+
+use crate::gc::GcReport;
+use crate::tools::ResourceContent;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+/// URI scheme of call logs
+pub const CALL_LOG_SCHEME: &str = "call://";
+
+/// Bytes of lines kept per log; older lines are dropped first
+pub const MAX_LOG_BYTES: usize = 1024 * 1024;
+
+/// Logs kept at once
+pub const MAX_LOGS: usize = 1024;
+
+/// Log URI for a request id
+pub fn log_uri(request_id: &Value) -> String {
+    match request_id {
+        Value::String(id) => format!("{}{}/log", CALL_LOG_SCHEME, id),
+        other => format!("{}{}/log", CALL_LOG_SCHEME, other),
+    }
+}
+
+#[derive(Debug, Default)]
+struct CallLog {
+    lines: VecDeque<String>,
+    bytes: usize,
+    finished_at: Option<Instant>,
+}
+
+#[derive(Debug)]
+pub struct CallLogs {
+    ttl: Duration,
+    logs: RwLock<HashMap<String, CallLog>>,
+}
+
+impl CallLogs {
+    /// Keep logs of finished calls readable for `ttl`
+    pub fn new(ttl: Duration) -> Self {
+        CallLogs {
+            ttl,
+            logs: RwLock::new(HashMap::new()),
+        }
+    }
+
+    pub fn start(&self, uri: &str) {
+        self.purge_expired();
+        let mut logs = self.logs.write().unwrap();
+        if logs.len() >= MAX_LOGS {
+            let mut finished: Vec<(Instant, String)> = logs.iter()
+                .filter_map(|(uri, log)| Some((log.finished_at?, uri.clone())))
+                .collect();
+            finished.sort();
+            for (_, uri) in finished.into_iter().take(logs.len() + 1 - MAX_LOGS) {
+                logs.remove(&uri);
+            }
+        }
+        logs.insert(uri.to_string(), CallLog::default());
+    }
+
+    pub fn append(&self, uri: &str, mut line: String) {
+        if let Some(log) = self.logs.write().unwrap().get_mut(uri) {
+            if line.len() > MAX_LOG_BYTES {
+                line.truncate(line.floor_char_boundary(MAX_LOG_BYTES));
+            }
+            log.bytes += line.len();
+            log.lines.push_back(line);
+            while log.bytes > MAX_LOG_BYTES {
+                let Some(dropped) = log.lines.pop_front() else { break };
+                log.bytes -= dropped.len();
+            }
+        }
+    }
+
+    /// Mark a call as done; its log expires after the TTL
+    pub fn finish(&self, uri: &str) {
+        if let Some(log) = self.logs.write().unwrap().get_mut(uri) {
+            log.finished_at = Some(Instant::now());
+        }
+    }
+
+    /// Contents for a `call://{request_id}/log` URI
+    pub fn read(&self, uri: &str) -> Option<ResourceContent> {
+        self.purge_expired();
+        let logs = self.logs.read().unwrap();
+        let log = logs.get(uri)?;
+        Some(ResourceContent {
+            uri: uri.to_string(),
+            mime_type: "text/plain".into(),
+            text: log.lines.iter().map(String::as_str).collect::<Vec<_>>().join("\n"),
+            blob: None,
+        })
+    }
+
+    /// Number of logs, running or expiring
+    pub fn len(&self) -> usize {
+        self.logs.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Drop logs of calls finished longer than the TTL ago
+    pub fn purge_expired(&self) -> GcReport {
+        let ttl = self.ttl;
+        let mut report = GcReport::default();
+        self.logs.write().unwrap().retain(|_, log| {
+            let keep = log.finished_at.is_none_or(|finished| finished.elapsed() < ttl);
+            if !keep {
+                report.removed += 1;
+                report.freed_bytes += log.bytes;
+            }
+            keep
+        });
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_log_lifecycle() {
+        let logs = CallLogs::new(Duration::ZERO);
+        let uri = log_uri(&json!(7));
+        assert_eq!(uri, "call://7/log");
+
+        logs.start(&uri);
+        logs.append(&uri, "starting".into());
+        logs.append(&uri, "done".into());
+        assert_eq!(logs.read(&uri).unwrap().text, "starting\ndone");
+
+        logs.finish(&uri);
+        assert!(logs.read(&uri).is_none());
+        assert_eq!(log_uri(&json!("abc")), "call://abc/log");
+    }
+
+    #[test]
+    fn test_logs_are_capped() {
+        let logs = CallLogs::new(Duration::from_secs(60));
+        let uri = log_uri(&json!(1));
+        logs.start(&uri);
+        logs.append(&uri, "a".repeat(MAX_LOG_BYTES / 2 + 1));
+        logs.append(&uri, "b".repeat(MAX_LOG_BYTES / 2));
+        logs.append(&uri, "c".repeat(MAX_LOG_BYTES + 1));
+        let text = logs.read(&uri).unwrap().text;
+        assert_eq!(text, "c".repeat(MAX_LOG_BYTES));
+
+        // Finished logs make way, oldest first; running ones stay
+        logs.finish(&uri);
+        for id in 2..=MAX_LOGS {
+            let uri = log_uri(&json!(id));
+            logs.start(&uri);
+            logs.finish(&uri);
+        }
+        let running = log_uri(&json!("running"));
+        logs.start(&running);
+        assert_eq!(logs.len(), MAX_LOGS);
+        assert!(logs.read(&uri).is_none());
+        assert!(logs.read(&log_uri(&json!(2))).is_some());
+        assert!(logs.read(&running).is_some());
+    }
+}
@@ -0,0 +1,331 @@
+//! Content-addressable store used to deduplicate large text results.
+//!
+//! The first time a large text block is returned it is sent inline and
+//! remembered under its SHA-256. Identical text returned later is replaced by
+//! a `resource_link` to `cas://{hash}`, which clients resolve with
+//! `resources/read`. Only sessions the text was returned to can read it, and
+//! each session's unread links are counted separately. Entries can be
+//! collected with [`crate::gc`]; regardless, the store never holds more than
+//! [`DEFAULT_MAX_BYTES`] (see [`ContentStore::with_max_bytes`]) and makes room
+//! for new text the way a size-capped collection would.
+
+use crate::gc::{GcPolicy, GcReport};
+use crate::memory::{MemoryAccountant, MemoryCategory};
+use crate::tools::{ContentBlock, ResourceContent, ResourceLink};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
+
+/// URI scheme of deduplicated content
+pub const CAS_SCHEME: &str = "cas://";
+
+/// Bytes of text a store holds unless [`ContentStore::with_max_bytes`] says
+/// otherwise
+pub const DEFAULT_MAX_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Debug)]
+struct Stored {
+    text: Arc<str>,
+    last_used: Instant,
+    // Sessions the text was returned to, with the links they have not read
+    sessions: HashMap<String, usize>,
+}
+
+impl Stored {
+    fn linked(&self) -> bool {
+        self.sessions.values().any(|&links| links > 0)
+    }
+}
+
+#[derive(Debug)]
+pub struct ContentStore {
+    min_size: usize,
+    max_bytes: usize,
+    entries: RwLock<HashMap<String, Stored>>,
+    // Bytes of stored text
+    bytes: AtomicUsize,
+    memory: Option<Arc<MemoryAccountant>>,
+}
+
+impl ContentStore {
+    /// Deduplicate text blocks of at least `min_size` bytes
+    pub fn new(min_size: usize) -> Self {
+        ContentStore {
+            min_size,
+            max_bytes: DEFAULT_MAX_BYTES,
+            entries: RwLock::new(HashMap::new()),
+            bytes: AtomicUsize::new(0),
+            memory: None,
+        }
+    }
+
+    /// Hold at most `bytes` of text; larger blocks are never stored
+    pub fn with_max_bytes(mut self, bytes: usize) -> Self {
+        self.max_bytes = bytes;
+        self
+    }
+
+    /// Account stored text against `memory`, evicting everything when full
+    pub fn with_memory(mut self, memory: Arc<MemoryAccountant>) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+
+    /// Drop all stored entries
+    pub fn clear(&self) {
+        let mut entries = self.entries.write().unwrap();
+        if let Some(memory) = &self.memory {
+            let bytes = entries.values().map(|stored| stored.text.len()).sum();
+            memory.release(MemoryCategory::Cache, bytes);
+            memory.record_eviction();
+        }
+        entries.clear();
+        self.bytes.store(0, Ordering::Relaxed);
+    }
+
+    fn admit(&self, bytes: usize) -> bool {
+        let Some(memory) = &self.memory else { return true };
+        if memory.try_reserve(MemoryCategory::Cache, bytes) {
+            return true;
+        }
+        self.clear();
+        memory.try_reserve(MemoryCategory::Cache, bytes)
+    }
+
+    pub fn hash(text: &str) -> String {
+        Sha256::digest(text.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Replace text blocks already in the store with links for `session`,
+    /// storing new ones
+    pub fn dedupe(&self, session: &str, content: &mut [ContentBlock]) {
+        for block in content.iter_mut() {
+            let Some(text) = block.as_text() else { continue };
+            if text.len() < self.min_size {
+                continue;
+            }
+
+            let hash = Self::hash(text);
+            let size = text.len() as u64;
+            if let Some(stored) = self.entries.write().unwrap().get_mut(&hash) {
+                *stored.sessions.entry(session.to_string()).or_default() += 1;
+                stored.last_used = Instant::now();
+                let annotations = block.annotations().cloned();
+                *block = ContentBlock::ResourceLink(ResourceLink {
+                    uri: format!("{}{}", CAS_SCHEME, hash),
+                    name: format!("cas-{}", &hash[..12]),
+                    description: Some("Identical to content returned earlier in this session".into()),
+                    mime_type: Some("text/plain".into()),
+                    size: Some(size),
+                    annotations,
+                });
+                continue;
+            }
+
+            if text.len() > self.max_bytes {
+                continue;
+            }
+            // Make room under the cap, unlinked and least recently used first
+            if self.bytes() + text.len() > self.max_bytes {
+                self.collect(&GcPolicy::new().max_bytes(self.max_bytes - text.len()));
+            }
+            // Reserve before taking the write lock; admitting may evict
+            if !self.admit(text.len()) {
+                continue;
+            }
+            match self.entries.write().unwrap().entry(hash) {
+                Entry::Occupied(mut entry) => {
+                    entry.get_mut().sessions.entry(session.to_string()).or_default();
+                    if let Some(memory) = &self.memory {
+                        memory.release(MemoryCategory::Cache, text.len());
+                    }
+                }
+                Entry::Vacant(entry) => {
+                    let sessions = HashMap::from([(session.to_string(), 0)]);
+                    entry.insert(Stored { text: Arc::from(text), last_used: Instant::now(), sessions });
+                    self.bytes.fetch_add(text.len(), Ordering::Relaxed);
+                }
+            }
+        }
+    }
+
+    /// Contents for a `cas://{hash}` URI, if the text was returned to `session`
+    pub fn read(&self, session: &str, uri: &str) -> Option<ResourceContent> {
+        let hash = uri.strip_prefix(CAS_SCHEME)?;
+        let mut entries = self.entries.write().unwrap();
+        let stored = entries.get_mut(hash)?;
+        let links = stored.sessions.get_mut(session)?;
+        *links = links.saturating_sub(1);
+        stored.last_used = Instant::now();
+        let text = stored.text.clone();
+        Some(ResourceContent {
+            uri: uri.to_string(),
+            mime_type: "text/plain".into(),
+            text: text.to_string(),
+            blob: None,
+        })
+    }
+
+    /// Number of stored entries
+    pub fn len(&self) -> usize {
+        self.entries.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Bytes of stored text
+    pub fn bytes(&self) -> usize {
+        self.bytes.load(Ordering::Relaxed)
+    }
+
+    /// Drop expired entries, then the least recently used until under the
+    /// size cap; under the cap, entries with unread links go last
+    pub fn collect(&self, policy: &GcPolicy) -> GcReport {
+        let mut entries = self.entries.write().unwrap();
+        let mut doomed: Vec<String> = match policy.ttl {
+            Some(ttl) => entries.iter()
+                .filter(|(_, stored)| stored.last_used.elapsed() >= ttl)
+                .map(|(hash, _)| hash.clone())
+                .collect(),
+            None => Vec::new(),
+        };
+        if let Some(max_bytes) = policy.max_bytes {
+            let mut bytes: usize = entries.iter()
+                .filter(|(hash, _)| !doomed.contains(hash))
+                .map(|(_, stored)| stored.text.len())
+                .sum();
+            let mut candidates: Vec<(&String, &Stored)> = entries.iter().filter(|(hash, _)| !doomed.contains(hash)).collect();
+            candidates.sort_by_key(|(_, stored)| (stored.linked(), stored.last_used));
+            for (hash, stored) in candidates {
+                if bytes <= max_bytes {
+                    break;
+                }
+                bytes -= stored.text.len();
+                doomed.push(hash.clone());
+            }
+        }
+
+        let mut report = GcReport::default();
+        for hash in doomed {
+            if let Some(stored) = entries.remove(&hash) {
+                report.removed += 1;
+                report.freed_bytes += stored.text.len();
+            }
+        }
+        self.bytes.fetch_sub(report.freed_bytes, Ordering::Relaxed);
+        if let Some(memory) = &self.memory {
+            memory.release(MemoryCategory::Cache, report.freed_bytes);
+        }
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::MCPError;
+    use crate::integrity;
+    use crate::context::RequestContext;
+    use crate::server::{SystemMCPServer, ToolHandler};
+    use crate::testing::fixtures;
+    use crate::tools::ToolResponse;
+    use serde_json::Value;
+    use std::time::Duration;
+
+    #[test]
+    fn test_duplicates_become_links() {
+        let store = ContentStore::new(8);
+        let text = "the same long output";
+
+        let mut first = vec![ContentBlock::text(text), ContentBlock::text("short")];
+        store.dedupe("s1", &mut first);
+        assert_eq!(first[0].as_text(), Some(text));
+        assert_eq!(store.len(), 1);
+
+        let mut second = vec![ContentBlock::text(text)];
+        store.dedupe("s2", &mut second);
+        let ContentBlock::ResourceLink(link) = &second[0] else {
+            panic!("expected a resource link");
+        };
+        assert_eq!(store.read("s2", &link.uri).unwrap().text, text);
+        assert_eq!(store.read("s1", &link.uri).unwrap().text, text);
+        assert!(store.read("s3", &link.uri).is_none());
+    }
+
+    #[test]
+    fn test_collect() {
+        let store = ContentStore::new(1);
+        let mut content = vec![ContentBlock::text("aaaa"), ContentBlock::text("bbbb"), ContentBlock::text("cccc")];
+        store.dedupe("s1", &mut content);
+        let mut linked = vec![ContentBlock::text("aaaa")];
+        store.dedupe("s2", &mut linked);
+
+        // Over the cap: the unlinked entries go first
+        let report = store.collect(&GcPolicy::new().max_bytes(5));
+        assert_eq!(report, GcReport { removed: 2, freed_bytes: 8 });
+
+        // Another session reading does not release the link
+        let uri = format!("{}{}", CAS_SCHEME, ContentStore::hash("aaaa"));
+        store.read("s1", &uri).unwrap();
+        assert!(store.entries.read().unwrap()[&ContentStore::hash("aaaa")].linked());
+        store.read("s2", &uri).unwrap();
+        assert!(!store.entries.read().unwrap()[&ContentStore::hash("aaaa")].linked());
+
+        // Unread links do not outlive the TTL
+        store.dedupe("s2", &mut [ContentBlock::text("aaaa")]);
+        assert_eq!(store.collect(&GcPolicy::new().ttl(Duration::ZERO)).removed, 1);
+        assert!(store.is_empty());
+    }
+
+    #[test]
+    fn test_store_stays_under_its_cap() {
+        let store = ContentStore::new(1).with_max_bytes(10);
+        store.dedupe("s1", &mut [ContentBlock::text("aaaa"), ContentBlock::text("bbbb")]);
+        // Linked text outlives text nobody was pointed to
+        store.dedupe("s2", &mut [ContentBlock::text("aaaa")]);
+        store.dedupe("s1", &mut [ContentBlock::text("cccc")]);
+        assert_eq!(store.bytes(), 8);
+        assert!(store.entries.read().unwrap().contains_key(&ContentStore::hash("aaaa")));
+        assert!(!store.entries.read().unwrap().contains_key(&ContentStore::hash("bbbb")));
+
+        let mut huge = [ContentBlock::text("x".repeat(11))];
+        store.dedupe("s1", &mut huge);
+        assert_eq!((store.len(), store.bytes()), (2, 8));
+        store.clear();
+        assert_eq!(store.bytes(), 0);
+    }
+
+    struct Repeat;
+
+    #[async_trait::async_trait]
+    impl ToolHandler for Repeat {
+        async fn call_tool(&self, _name: &str, _args: &Value, _ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+            Ok(ToolResponse::new("the same long output".into(), false))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_with_digest() {
+        let server = SystemMCPServer::<Repeat>::builder()
+            .relaxed_lifecycle()
+            .dedupe_content(8)
+            .content_digests()
+            .build(Repeat);
+        server.handle(fixtures::call_tool("repeat").build()).await;
+        let second = server.handle(fixtures::call_tool("repeat").build()).await.unwrap().result.unwrap();
+        let uri = second["content"][0]["uri"].as_str().unwrap();
+
+        let read = fixtures::request("resources/read").param("uri", uri).build();
+        let contents = server.handle(read).await.unwrap().result.unwrap();
+        assert_eq!(contents["_meta"]["sha256"], integrity::sha256_hex(b"the same long output"));
+        assert_eq!(integrity::verify_result(&contents).unwrap(), 1);
+    }
+}
@@ -0,0 +1,243 @@
+//! Audit trail of tool, prompt and resource list changes at runtime.
+//!
+//! Registries that change while the server runs (a tool file reloaded, a
+//! plugin unloaded) implement [`ListSource`] and are given to
+//! [`ServerBuilder::change_log`], which records their lists when the server
+//! is built. After a reload, call [`SystemMCPServer::reload_registries`]: it
+//! compares the source's lists with the previous snapshot, logs the
+//! difference as a `[CHANGES]` JSON line, and sends one `list_changed`
+//! notification per list that changed. The recent differences are readable
+//! as `mcp://server/changes`.
+//!
+//! A source lists what it serves to everyone, so the snapshots do not depend
+//! on any session or caller.
+//!
+//! [`SystemMCPServer::reload_registries`]: crate::server::SystemMCPServer::reload_registries
+//! [`ServerBuilder::change_log`]: crate::server::ServerBuilder::change_log
+
+use crate::notifications::ServerNotification;
+use crate::tools::{Prompt, Resource, ResourceContent, Tool};
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// URI of the change log resource
+pub const CHANGES_URI: &str = "mcp://server/changes";
+
+/// Change records kept for the resource
+const KEPT: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ListKind {
+    Tools,
+    Prompts,
+    Resources,
+}
+
+impl ListKind {
+    /// Member identifying an entry of this list
+    fn key(self) -> &'static str {
+        match self {
+            ListKind::Tools | ListKind::Prompts => "name",
+            ListKind::Resources => "uri",
+        }
+    }
+
+    pub fn notification(self) -> ServerNotification {
+        match self {
+            ListKind::Tools => ServerNotification::ToolListChanged,
+            ListKind::Prompts => ServerNotification::PromptListChanged,
+            ListKind::Resources => ServerNotification::ResourceListChanged,
+        }
+    }
+}
+
+/// Lists that may change at runtime, the same for every session
+pub trait ListSource: Send + Sync {
+    fn tools(&self) -> Vec<Tool> {
+        Vec::new()
+    }
+
+    fn prompts(&self) -> Vec<Prompt> {
+        Vec::new()
+    }
+
+    fn resources(&self) -> Vec<Resource> {
+        Vec::new()
+    }
+}
+
+/// Entries added, removed or redefined in one list
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ListDiff {
+    pub kind: ListKind,
+    pub added: Vec<String>,
+    pub removed: Vec<String>,
+    pub changed: Vec<String>,
+}
+
+impl ListDiff {
+    /// Compare two listings of `kind`, matching entries by name or URI
+    pub fn between(kind: ListKind, before: &[Value], after: &[Value]) -> Self {
+        let index = |list: &[Value]| -> BTreeMap<String, Value> {
+            list.iter()
+                .filter_map(|entry| Some((entry.get(kind.key())?.as_str()?.to_string(), entry.clone())))
+                .collect()
+        };
+        let (before, after) = (index(before), index(after));
+        ListDiff {
+            kind,
+            added: after.keys().filter(|key| !before.contains_key(*key)).cloned().collect(),
+            removed: before.keys().filter(|key| !after.contains_key(*key)).cloned().collect(),
+            changed: after.iter()
+                .filter(|(key, entry)| before.get(*key).is_some_and(|old| old != *entry))
+                .map(|(key, _)| key.clone())
+                .collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// One reload that changed something
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeRecord {
+    pub sequence: u64,
+    pub timestamp_ms: u64,
+    pub changes: Vec<ListDiff>,
+}
+
+pub struct ChangeLog {
+    source: Arc<dyn ListSource>,
+    snapshot: Mutex<BTreeMap<ListKind, Vec<Value>>>,
+    records: Mutex<VecDeque<ChangeRecord>>,
+}
+
+impl ChangeLog {
+    /// Track `source`, starting from what it lists now
+    pub fn new(source: Arc<dyn ListSource>) -> Self {
+        let snapshot = Mutex::new(snapshot(source.as_ref()));
+        ChangeLog { source, snapshot, records: Mutex::new(VecDeque::new()) }
+    }
+
+    /// List the source again and return the lists that differ from the
+    /// previous snapshot
+    pub fn reload(&self) -> Vec<ListDiff> {
+        // Held throughout, so concurrent reloads report a change once
+        let mut snapshot = self.snapshot.lock().unwrap();
+        let previous = std::mem::replace(&mut *snapshot, self::snapshot(self.source.as_ref()));
+        let diffs: Vec<ListDiff> = snapshot.iter()
+            .map(|(kind, after)| ListDiff::between(*kind, previous.get(kind).map_or(&[], Vec::as_slice), after))
+            .filter(|diff| !diff.is_empty())
+            .collect();
+        if !diffs.is_empty() {
+            self.record(diffs.clone());
+        }
+        diffs
+    }
+
+    fn record(&self, changes: Vec<ListDiff>) {
+        let mut records = self.records.lock().unwrap();
+        let record = ChangeRecord {
+            sequence: records.back().map_or(1, |last| last.sequence + 1),
+            timestamp_ms: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0),
+            changes,
+        };
+        eprintln!("[CHANGES] {}", json!(record));
+        if records.len() == KEPT {
+            records.pop_front();
+        }
+        records.push_back(record);
+    }
+
+    /// Recent records, oldest first
+    pub fn records(&self) -> Vec<ChangeRecord> {
+        self.records.lock().unwrap().iter().cloned().collect()
+    }
+
+    /// Listing entry for the change log resource
+    pub fn resource() -> Resource {
+        Resource::new(CHANGES_URI, "changes")
+            .with_description("Tools, prompts and resources added, removed or changed while the server runs")
+            .with_mime_type("application/json")
+    }
+
+    pub fn read(&self) -> ResourceContent {
+        ResourceContent {
+            uri: CHANGES_URI.into(),
+            mime_type: "application/json".into(),
+            text: json!({ "changes": self.records() }).to_string(),
+            blob: None,
+        }
+    }
+}
+
+fn snapshot(source: &dyn ListSource) -> BTreeMap<ListKind, Vec<Value>> {
+    fn values<T: Serialize>(entries: Vec<T>) -> Vec<Value> {
+        entries.iter().filter_map(|entry| serde_json::to_value(entry).ok()).collect()
+    }
+    BTreeMap::from([
+        (ListKind::Tools, values(source.tools())),
+        (ListKind::Prompts, values(source.prompts())),
+        (ListKind::Resources, values(source.resources())),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::SystemMCPServer;
+    use crate::testing::fixtures;
+    use crate::testing::mock::MockToolHandler;
+    use crate::tools::ToolInputSchema;
+
+    #[test]
+    fn test_diff() {
+        let before = [json!({ "name": "a" }), json!({ "name": "b", "description": "old" })];
+        let after = [json!({ "name": "b", "description": "new" }), json!({ "name": "c" })];
+        let diff = ListDiff::between(ListKind::Tools, &before, &after);
+        assert_eq!((diff.added, diff.removed, diff.changed), (vec!["c".to_string()], vec!["a".to_string()], vec!["b".to_string()]));
+        assert!(ListDiff::between(ListKind::Resources, &[json!({ "uri": "x" })], &[json!({ "uri": "x" })]).is_empty());
+    }
+
+    #[derive(Default)]
+    struct Plugins(Mutex<Vec<&'static str>>);
+
+    impl ListSource for Plugins {
+        fn tools(&self) -> Vec<Tool> {
+            let schema = || ToolInputSchema { schema_type: "object".into(), properties: Default::default(), required: vec![] };
+            self.0.lock().unwrap().iter().map(|name| Tool::new(*name, "plugin", schema())).collect()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reload_notifies_once() {
+        let plugins = Arc::new(Plugins(Mutex::new(vec!["old"])));
+        let mut server = SystemMCPServer::<MockToolHandler>::builder()
+            .relaxed_lifecycle()
+            .change_log(plugins.clone())
+            .build(MockToolHandler::new());
+        let mut notifications = server.take_notification_receiver().unwrap();
+        // The baseline was recorded at build
+        assert!(server.reload_registries().is_empty());
+
+        *plugins.0.lock().unwrap() = vec!["new"];
+        let diffs = server.reload_registries();
+        assert_eq!((diffs.len(), diffs[0].kind), (1, ListKind::Tools));
+        assert_eq!((diffs[0].added.as_slice(), diffs[0].removed.as_slice()), (&["new".to_string()][..], &["old".to_string()][..]));
+        assert!(server.reload_registries().is_empty());
+        assert!(matches!(notifications.try_recv(), Some(ServerNotification::ToolListChanged)));
+        assert!(notifications.try_recv().is_none());
+
+        let read = fixtures::request("resources/read").param("uri", CHANGES_URI).build();
+        let text = server.handle(read).await.unwrap().result.unwrap()["text"].as_str().unwrap().to_string();
+        let log: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(log["changes"][0]["changes"][0]["added"], json!(["new"]));
+    }
+}
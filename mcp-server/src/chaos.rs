@@ -0,0 +1,240 @@
+//! Fault injection for resilience testing (feature `chaos`).
+//!
+//! [`Chaos`] is both a [`Middleware`] and a [`Transport`] decorator: as
+//! middleware it delays requests and answers some with a spurious
+//! cancellation, either before dispatch or while the handler runs, whose
+//! work is then dropped; wrapping a transport with [`Chaos::wrap`] drops
+//! notifications and corrupts responses. Faults are drawn from a seeded
+//! generator, so a failing run can be reproduced with the same seed and
+//! request order.
+//!
+//! ```ignore
+//! let chaos = Chaos::new(42).delays(0.2, Duration::from_millis(500)).drop_notifications(0.1);
+//! let server = Arc::new(SystemMCPServer::builder().layer(chaos.clone()).build(handler));
+//! server.run_with_transport(chaos.wrap(StdioTransport::new())).await?;
+//! ```
+
+use crate::error::MCPError;
+use crate::middleware::{IncomingRequest, Middleware, Next};
+use crate::notifications::ServerNotification;
+use crate::response::MCPResponse;
+use crate::transport::Transport;
+use async_trait::async_trait;
+use serde_json::json;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// SplitMix64: tiny, seedable and good enough for picking faults
+#[derive(Debug)]
+struct Rng(u64);
+
+impl Rng {
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Uniform in `[0, 1)`
+    fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Faults {
+    delay: f64,
+    max_delay: Duration,
+    drop_notification: f64,
+    malformed_response: f64,
+    spurious_cancel: f64,
+    cancel_within: Duration,
+}
+
+/// Seeded fault injector; clones share one generator
+#[derive(Debug, Clone)]
+pub struct Chaos {
+    faults: Faults,
+    rng: Arc<Mutex<Rng>>,
+}
+
+impl Chaos {
+    /// No faults until configured
+    pub fn new(seed: u64) -> Self {
+        Chaos { faults: Faults::default(), rng: Arc::new(Mutex::new(Rng(seed))) }
+    }
+
+    /// Delay a `probability` share of requests by up to `max`
+    pub fn delays(mut self, probability: f64, max: Duration) -> Self {
+        self.faults.delay = probability;
+        self.faults.max_delay = max;
+        self
+    }
+
+    pub fn drop_notifications(mut self, probability: f64) -> Self {
+        self.faults.drop_notification = probability;
+        self
+    }
+
+    /// Send a response with a foreign id or with neither result nor error
+    pub fn malformed_responses(mut self, probability: f64) -> Self {
+        self.faults.malformed_response = probability;
+        self
+    }
+
+    /// Answer a `probability` share of requests with a cancellation error the
+    /// client never asked for: half before dispatch, half mid-call, up to
+    /// `within` after the handler started
+    pub fn spurious_cancellations(mut self, probability: f64, within: Duration) -> Self {
+        self.faults.spurious_cancel = probability;
+        self.faults.cancel_within = within;
+        self
+    }
+
+    /// Inject transport-level faults into `transport`
+    pub fn wrap<T: Transport>(&self, transport: T) -> ChaosTransport<T> {
+        ChaosTransport { inner: transport, chaos: self.clone() }
+    }
+
+    fn roll(&self, probability: f64) -> bool {
+        probability > 0.0 && self.rng.lock().unwrap().next_f64() < probability
+    }
+
+    /// Up to `max`, uniformly
+    fn delay(&self, max: Duration) -> Duration {
+        max.mul_f64(self.rng.lock().unwrap().next_f64())
+    }
+}
+
+#[async_trait]
+impl Middleware for Chaos {
+    async fn on_request(&self, request: &mut IncomingRequest) -> Result<(), MCPError> {
+        let req = &request.request;
+        if self.roll(self.faults.delay) {
+            let delay = self.delay(self.faults.max_delay);
+            eprintln!("[CHAOS] Delaying {} by {:?}", req.method, delay);
+            tokio::time::sleep(delay).await;
+        }
+        Ok(())
+    }
+
+    async fn handle(&self, request: &mut IncomingRequest, next: Next<'_>) -> Option<MCPResponse> {
+        if let Err(err) = self.on_request(request).await {
+            return next.reject(&request.request, err);
+        }
+        let Some(id) = request.request.id.clone().filter(|_| self.roll(self.faults.spurious_cancel)) else {
+            return next.run(request).await;
+        };
+        let cancelled = next.reject(&request.request, MCPError::RequestCancelled(id.to_string()));
+        if self.roll(0.5) {
+            eprintln!("[CHAOS] Cancelling request {} before dispatch", id);
+            return cancelled;
+        }
+        let after = self.delay(self.faults.cancel_within);
+        eprintln!("[CHAOS] Cancelling request {} after {:?}", id, after);
+        tokio::select! {
+            response = next.run(request) => response,
+            _ = tokio::time::sleep(after) => cancelled,
+        }
+    }
+}
+
+/// A transport that drops notifications and corrupts responses
+pub struct ChaosTransport<T> {
+    inner: T,
+    chaos: Chaos,
+}
+
+#[async_trait]
+impl<T: Transport> Transport for ChaosTransport<T> {
+    async fn recv(&mut self) -> Result<Option<IncomingRequest>, MCPError> {
+        self.inner.recv().await
+    }
+
+    async fn send(&mut self, mut response: MCPResponse) -> Result<(), MCPError> {
+        if self.chaos.roll(self.chaos.faults.malformed_response) {
+            eprintln!("[CHAOS] Corrupting response {:?}", response.id);
+            if self.chaos.roll(0.5) {
+                response.id = Some(json!("chaos"));
+            } else {
+                (response.result, response.error) = (None, None);
+            }
+        }
+        self.inner.send(response).await
+    }
+
+    async fn send_notification(&mut self, notification: ServerNotification) -> Result<(), MCPError> {
+        if self.chaos.roll(self.chaos.faults.drop_notification) {
+            eprintln!("[CHAOS] Dropping notification");
+            return Ok(());
+        }
+        self.inner.send_notification(notification).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::RequestContext;
+    use crate::server::{SystemMCPServer, ToolHandler};
+    use crate::testing::fixtures;
+    use crate::testing::mock::{MockToolHandler, Reply};
+    use crate::tools::ToolResponse;
+    use crate::transport::in_process;
+    use serde_json::Value;
+
+    #[test]
+    fn test_same_seed_same_faults() {
+        let rolls = |seed| {
+            let chaos = Chaos::new(seed);
+            (0..64).map(|_| chaos.roll(0.5)).collect::<Vec<_>>()
+        };
+        assert_eq!(rolls(7), rolls(7));
+        assert_ne!(rolls(7), rolls(8));
+        assert!(!Chaos::new(7).roll(0.0));
+    }
+
+    struct Progress;
+
+    #[async_trait]
+    impl ToolHandler for Progress {
+        async fn call_tool(&self, _name: &str, _args: &Value, ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+            let _ = ctx.progress().send_progress("1", 0.5, None).await;
+            Ok(ToolResponse::new("ok".into(), false))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_faults_reach_the_client() {
+        let chaos = Chaos::new(1).drop_notifications(1.0).malformed_responses(1.0);
+        let (mut client, transport) = in_process();
+        let wrapped = chaos.wrap(transport);
+        tokio::spawn(async move {
+            let server = std::sync::Arc::new(SystemMCPServer::<Progress>::builder().build(Progress));
+            server.runner().without_signals().run_with_transport(wrapped).await.unwrap();
+        });
+
+        client.send_raw(json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/call", "params": { "name": "p" } }).to_string()).unwrap();
+        // The progress notification is dropped, so the next message is the response
+        let response = client.next_notification().await.unwrap();
+        assert!(response["id"] == "chaos" || (response.get("result").is_none() && response.get("error").is_none()));
+    }
+
+    #[tokio::test]
+    async fn test_cancellations_also_hit_running_calls() {
+        let server = SystemMCPServer::<MockToolHandler>::builder()
+            .relaxed_lifecycle()
+            .layer(Chaos::new(3).spurious_cancellations(1.0, Duration::from_millis(5)))
+            .build(MockToolHandler::new().tool("slow", Reply::hang()));
+        for id in 0..16 {
+            let response = server.handle(fixtures::call_tool("slow").id(id).build()).await.unwrap();
+            assert_eq!(response.error.unwrap().code, -32800);
+        }
+        // Some calls were cancelled before reaching the handler, others while it ran
+        let started = server.handler().calls_to("slow").len();
+        assert!((1..16).contains(&started), "{} of 16 calls started", started);
+        assert_eq!(server.in_flight(), 0);
+    }
+}
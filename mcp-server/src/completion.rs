@@ -0,0 +1,143 @@
+//! Argument completion (`completion/complete`) and prompt argument validation.
+//!
+//! Prompt arguments may carry a JSON-schema fragment (`type`, `enum`,
+//! `pattern`). `prompts/get` arguments are validated against it, and `enum`
+//! values are offered as completions automatically; anything else is
+//! delegated to an optional [`CompletionProvider`].
+
+use crate::context::RequestContext;
+use crate::error::MCPError;
+use crate::tools::{Completion, CompletionArgument, CompletionReference, Prompt, PromptArgument};
+use async_trait::async_trait;
+use regex::Regex;
+use serde_json::Value;
+
+/// Maximum number of values returned in one completion
+pub const MAX_COMPLETION_VALUES: usize = 100;
+
+#[async_trait]
+pub trait CompletionProvider: Send + Sync {
+    async fn complete(&self, reference: &CompletionReference, argument: &CompletionArgument, ctx: &RequestContext) -> Result<Completion, MCPError>;
+}
+
+/// Parse `ref` and `argument` from `completion/complete` params
+pub fn parse_request(params: &Value) -> Result<(CompletionReference, CompletionArgument), MCPError> {
+    let reference = params.get("ref").ok_or(MCPError::MissingParameters)?;
+    let reference = match reference.get("type").and_then(Value::as_str) {
+        Some("ref/prompt") => CompletionReference::Prompt {
+            name: reference.get("name").and_then(Value::as_str).ok_or(MCPError::MissingParameters)?.into(),
+        },
+        Some("ref/resource") => CompletionReference::Resource {
+            uri: reference.get("uri").and_then(Value::as_str).ok_or(MCPError::MissingParameters)?.into(),
+        },
+        other => return Err(MCPError::InvalidParams(format!("unsupported completion ref type: {:?}", other))),
+    };
+
+    let argument = params.get("argument").ok_or(MCPError::MissingParameters)?;
+    let argument = CompletionArgument {
+        name: argument.get("name").and_then(Value::as_str).ok_or(MCPError::MissingParameters)?.into(),
+        value: argument.get("value").and_then(Value::as_str).unwrap_or_default().into(),
+    };
+    Ok((reference, argument))
+}
+
+/// Completion from the argument's declared `enum`, if it has one
+pub fn enum_completion(argument: &PromptArgument, prefix: &str) -> Option<Completion> {
+    let values = argument.enum_values();
+    if values.is_empty() {
+        return None;
+    }
+
+    let prefix = prefix.to_lowercase();
+    let matching: Vec<String> = values.into_iter()
+        .filter(|v| v.to_lowercase().starts_with(&prefix))
+        .map(str::to_owned)
+        .collect();
+    let total = matching.len();
+    Some(Completion {
+        values: matching.into_iter().take(MAX_COMPLETION_VALUES).collect(),
+        total: Some(total as u64),
+        has_more: Some(total > MAX_COMPLETION_VALUES),
+    })
+}
+
+fn validate_value(argument: &PromptArgument, schema: &Value, value: &Value) -> Result<(), MCPError> {
+    let invalid = |reason: String| MCPError::InvalidParams(format!("argument {}: {}", argument.name, reason));
+
+    // Prompt arguments are strings on the wire; typed schemas check the text parses
+    let text = match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    };
+
+    match schema.get("type").and_then(Value::as_str) {
+        Some("number") if text.parse::<f64>().is_err() => return Err(invalid("expected a number".into())),
+        Some("integer") if text.parse::<i64>().is_err() => return Err(invalid("expected an integer".into())),
+        Some("boolean") if text != "true" && text != "false" => return Err(invalid("expected true or false".into())),
+        _ => {}
+    }
+
+    let allowed = argument.enum_values();
+    if !allowed.is_empty() && !allowed.contains(&text.as_str()) {
+        return Err(invalid(format!("expected one of {}", allowed.join(", "))));
+    }
+
+    if let Some(pattern) = schema.get("pattern").and_then(Value::as_str) {
+        let regex = Regex::new(pattern).map_err(|e| invalid(format!("invalid pattern: {}", e)))?;
+        if !regex.is_match(&text) {
+            return Err(invalid(format!("does not match pattern {}", pattern)));
+        }
+    }
+    Ok(())
+}
+
+/// Check required arguments and schema fragments for `prompts/get`
+pub fn validate_prompt_arguments(prompt: &Prompt, args: &Value) -> Result<(), MCPError> {
+    for argument in prompt.arguments.iter().flatten() {
+        match args.get(&argument.name) {
+            None | Some(Value::Null) if argument.required => {
+                return Err(MCPError::InvalidParams(format!("missing required argument {}", argument.name)));
+            }
+            None | Some(Value::Null) => {}
+            Some(value) => {
+                if let Some(schema) = &argument.schema {
+                    validate_value(argument, schema, value)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn prompt() -> Prompt {
+        Prompt::new("review", "Review code").with_arguments(vec![
+            PromptArgument::new("language", "Language", true)
+                .with_schema(json!({ "type": "string", "enum": ["rust", "ruby", "go"] })),
+            PromptArgument::new("ticket", "Ticket id", false)
+                .with_schema(json!({ "pattern": "^[A-Z]+-[0-9]+$" })),
+        ])
+    }
+
+    #[test]
+    fn test_validation() {
+        let prompt = prompt();
+        assert!(validate_prompt_arguments(&prompt, &json!({ "language": "rust" })).is_ok());
+        assert!(validate_prompt_arguments(&prompt, &json!({})).is_err());
+        assert!(validate_prompt_arguments(&prompt, &json!({ "language": "cobol" })).is_err());
+        assert!(validate_prompt_arguments(&prompt, &json!({ "language": "go", "ticket": "MCP-12" })).is_ok());
+        assert!(validate_prompt_arguments(&prompt, &json!({ "language": "go", "ticket": "12" })).is_err());
+    }
+
+    #[test]
+    fn test_enum_completion() {
+        let prompt = prompt();
+        let language = &prompt.arguments.as_ref().unwrap()[0];
+        assert_eq!(enum_completion(language, "r").unwrap().values, vec!["rust", "ruby"]);
+        assert!(enum_completion(&prompt.arguments.as_ref().unwrap()[1], "").is_none());
+    }
+}
@@ -0,0 +1,170 @@
+//! Limits on requests in flight inside one server.
+//!
+//! [`ServerBuilder::max_concurrent_requests`] bounds what one runner handles
+//! at once, but a server shared by several transports, or one slow tool
+//! holding every slot, still starves the rest. [`ConcurrencyLimits`] caps the
+//! requests in flight across the whole server and, separately, the calls of
+//! individual tools. A request over a limit fails with a "server busy" error
+//! at once, or after waiting up to [`ConcurrencyLimits::queue_for`] for a
+//! slot. `initialize` and `ping` are never limited, and the tool calls a
+//! custom method makes only take their tool's slot, since the method already
+//! holds one in the global limit.
+//!
+//! A call takes its tool's slot before the global one, so calls queued
+//! behind a busy tool do not hold global slots other requests could use.
+//! Limits are looked up by the tool version a call resolves to, then by the
+//! tool's base name, so a limit on `search` covers `search@2` too.
+//!
+//! [`ServerBuilder::max_concurrent_requests`]: crate::server::ServerBuilder::max_concurrent_requests
+
+use crate::error::MCPError;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+#[derive(Debug, Clone, Default)]
+pub struct ConcurrencyLimits {
+    global: Option<Arc<Semaphore>>,
+    per_tool: HashMap<String, Arc<Semaphore>>,
+    queue_for: Option<Duration>,
+}
+
+/// Slots held by a request until it is answered
+#[derive(Debug)]
+pub struct Permits {
+    _held: Vec<OwnedSemaphorePermit>,
+}
+
+impl ConcurrencyLimits {
+    /// No limits
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests in flight across the server
+    pub fn max_in_flight(mut self, limit: usize) -> Self {
+        self.global = Some(Arc::new(Semaphore::new(limit.max(1))));
+        self
+    }
+
+    /// Concurrent calls of `tool`
+    pub fn tool(mut self, tool: impl Into<String>, limit: usize) -> Self {
+        self.per_tool.insert(tool.into(), Arc::new(Semaphore::new(limit.max(1))));
+        self
+    }
+
+    /// Wait up to `wait` for a slot instead of failing at once
+    pub fn queue_for(mut self, wait: Duration) -> Self {
+        self.queue_for = Some(wait);
+        self
+    }
+
+    /// Take the slots a request of `method` (calling `tool`) needs
+    pub async fn acquire(&self, method: &str, tool: Option<&str>) -> Result<Permits, MCPError> {
+        let mut permits = Vec::new();
+        if matches!(method, "initialize" | "ping") {
+            return Ok(Permits { _held: permits });
+        }
+        if let Some(tool) = tool {
+            permits.extend(self.acquire_tool(tool).await?._held);
+        }
+        if let Some(global) = &self.global {
+            permits.push(self.slot(global, || "too many requests in flight".to_string()).await?);
+        }
+        Ok(Permits { _held: permits })
+    }
+
+    /// Take only `tool`'s slot, for a call made inside a request already
+    /// counted against the global limit
+    pub async fn acquire_tool(&self, tool: &str) -> Result<Permits, MCPError> {
+        let mut permits = Vec::new();
+        let base = tool.rsplit_once('@').map_or(tool, |(base, _)| base);
+        if let Some(semaphore) = self.per_tool.get(tool).or_else(|| self.per_tool.get(base)) {
+            permits.push(self.slot(semaphore, || format!("tool {} is at its concurrency limit", tool)).await?);
+        }
+        Ok(Permits { _held: permits })
+    }
+
+    async fn slot(&self, semaphore: &Arc<Semaphore>, busy: impl Fn() -> String) -> Result<OwnedSemaphorePermit, MCPError> {
+        let semaphore = semaphore.clone();
+        let permit = match self.queue_for {
+            Some(wait) => tokio::time::timeout(wait, semaphore.acquire_owned()).await.ok().and_then(Result::ok),
+            None => semaphore.try_acquire_owned().ok(),
+        };
+        permit.ok_or_else(|| {
+            eprintln!("[BUSY] Rejected request: {}", busy());
+            MCPError::ServerBusy(busy())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::SystemMCPServer;
+    use crate::testing::fixtures;
+    use crate::testing::mock::{MockToolHandler, Reply};
+
+    #[tokio::test]
+    async fn test_limits() {
+        let limits = ConcurrencyLimits::new().max_in_flight(2).tool("slow", 1);
+        let slow = limits.acquire("tools/call", Some("slow")).await.unwrap();
+        assert!(matches!(limits.acquire("tools/call", Some("slow")).await, Err(MCPError::ServerBusy(_))));
+        let other = limits.acquire("tools/list", None).await.unwrap();
+        assert!(matches!(limits.acquire("resources/list", None).await, Err(MCPError::ServerBusy(_))));
+        assert!(limits.acquire("ping", None).await.is_ok());
+        drop((slow, other));
+        assert!(limits.acquire("tools/call", Some("slow")).await.is_ok());
+
+        // A limit on the base name covers every version
+        let _v2 = limits.acquire("tools/call", Some("slow@2")).await.unwrap();
+        assert!(matches!(limits.acquire("tools/call", Some("slow@3")).await, Err(MCPError::ServerBusy(_))));
+    }
+
+    #[tokio::test]
+    async fn test_queued_calls_hold_no_global_slot_and_can_be_cancelled() {
+        let mock = MockToolHandler::new().tool("slow", Reply::hang()).tool("fast", Reply::text("ok"));
+        let server = Arc::new(SystemMCPServer::<MockToolHandler>::builder()
+            .relaxed_lifecycle()
+            .concurrency_limits(ConcurrencyLimits::new().max_in_flight(2).tool("slow", 1).queue_for(Duration::from_secs(60)))
+            .build(mock));
+        tokio::spawn({
+            let server = server.clone();
+            async move { server.handle(fixtures::call_tool("slow").id("running").build()).await }
+        });
+        while server.handler().calls().is_empty() {
+            tokio::task::yield_now().await;
+        }
+        let queued = tokio::spawn({
+            let server = server.clone();
+            async move { server.handle(fixtures::call_tool("slow").id("queued").build()).await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        assert!(server.handle(fixtures::call_tool("fast").build()).await.unwrap().result.is_some());
+        server.handle(fixtures::cancelled("queued", None)).await;
+        let cancelled = tokio::time::timeout(Duration::from_secs(5), queued).await.unwrap().unwrap().unwrap();
+        assert_eq!(cancelled.error.unwrap().code, -32800);
+    }
+
+    #[tokio::test]
+    async fn test_busy_tool_does_not_starve_others() {
+        let mock = MockToolHandler::new().tool("slow", Reply::hang()).tool("fast", Reply::text("ok"));
+        let server = Arc::new(SystemMCPServer::<MockToolHandler>::builder()
+            .relaxed_lifecycle()
+            .concurrency_limits(ConcurrencyLimits::new().tool("slow", 1).queue_for(Duration::from_millis(20)))
+            .build(mock));
+        tokio::spawn({
+            let server = server.clone();
+            async move { server.handle(fixtures::call_tool("slow").build()).await }
+        });
+        while server.handler().calls().is_empty() {
+            tokio::task::yield_now().await;
+        }
+
+        let busy = server.handle(fixtures::call_tool("slow").build()).await.unwrap();
+        assert_eq!(busy.error.unwrap().code, -32005);
+        assert!(server.handle(fixtures::call_tool("fast").build()).await.unwrap().result.is_some());
+    }
+}
@@ -0,0 +1,382 @@
+//! Operator configuration that can change while the server runs.
+//!
+//! Builder settings are fixed once the server is built, and restarting a
+//! long-running HTTP server to raise a timeout drops every session with it.
+//! A [`ServerConfig`] holds the knobs operators tune in production: request
+//! and per-tool timeouts, a cap on requests in flight, the default log level
+//! and which tools are served. It is read from a JSON or TOML (feature
+//! `toml`) file, overlaid with `MCP_*` environment variables:
+//!
+//! ```toml
+//! request_timeout_secs = 30
+//! max_in_flight = 64
+//! log_level = "warning"
+//! disabled_tools = ["bash"]
+//!
+//! [tool_timeouts_secs]
+//! build = 600
+//! ```
+//!
+//! Hand a [`LiveConfig`] to [`ServerBuilder::config`] and call
+//! [`LiveConfig::reload`] (or [`LiveConfig::watch`]) to apply edits; every
+//! request reads the current values, so sessions carry on undisturbed, and
+//! an edit that changes which tools are served sends
+//! `notifications/tools/list_changed`. Values set here take precedence over
+//! the builder's.
+//!
+//! `log_level` is the minimum level of `notifications/message` for sessions
+//! that have not sent `logging/setLevel`, covering the server's own notices
+//! and what handlers send with [`ProgressSender::send_log`].
+//!
+//! [`ServerBuilder::config`]: crate::server::ServerBuilder::config
+//! [`ProgressSender::send_log`]: crate::notifications::ProgressSender::send_log
+
+use crate::error::MCPError;
+use crate::notifications::ServerNotification;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+
+/// Prefix of the environment variables overriding file values
+pub const ENV_PREFIX: &str = "MCP_";
+
+/// Severity order of `logging/setLevel` levels (RFC 5424), least severe first
+const LOG_LEVELS: [&str; 8] = ["debug", "info", "notice", "warning", "error", "critical", "alert", "emergency"];
+
+#[derive(Debug, Clone, Default, PartialEq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    /// Limit for any request without a more specific one
+    pub request_timeout_secs: Option<u64>,
+    /// Limits for individual tools
+    pub tool_timeouts_secs: BTreeMap<String, u64>,
+    /// Requests in flight across the server
+    pub max_in_flight: Option<usize>,
+    /// Log level of sessions that have not sent `logging/setLevel`
+    pub log_level: Option<String>,
+    /// Serve only these tools, if set
+    pub enabled_tools: Option<Vec<String>>,
+    /// Never serve these tools
+    pub disabled_tools: Vec<String>,
+}
+
+impl ServerConfig {
+    /// Parse a config file; `.toml` files need the `toml` feature
+    pub fn parse(path: &Path, text: &str) -> Result<Self, MCPError> {
+        let config: ServerConfig = if path.extension().is_some_and(|ext| ext == "toml") {
+            #[cfg(feature = "toml")]
+            {
+                toml::from_str(text).map_err(|e| MCPError::InvalidParams(format!("{}: {}", path.display(), e)))?
+            }
+            #[cfg(not(feature = "toml"))]
+            {
+                return Err(MCPError::InvalidParams(format!("{}: TOML config files need the `toml` feature", path.display())));
+            }
+        } else {
+            serde_json::from_str(text)?
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self, MCPError> {
+        let path = path.as_ref();
+        Self::parse(path, &std::fs::read_to_string(path)?)
+    }
+
+    /// Override values with the process's `MCP_*` environment variables
+    pub fn with_env(self) -> Result<Self, MCPError> {
+        self.with_vars(std::env::vars())
+    }
+
+    /// Override values with `MCP_REQUEST_TIMEOUT_SECS`, `MCP_MAX_IN_FLIGHT`,
+    /// `MCP_LOG_LEVEL`, `MCP_ENABLED_TOOLS` and `MCP_DISABLED_TOOLS` (tool
+    /// lists comma-separated) from `vars`
+    pub fn with_vars(mut self, vars: impl IntoIterator<Item = (String, String)>) -> Result<Self, MCPError> {
+        let number = |key: &str, value: &str| {
+            value.trim().parse().map_err(|_| MCPError::InvalidParams(format!("{}{} must be a number, got {:?}", ENV_PREFIX, key, value)))
+        };
+        let list = |value: &str| value.split(',').map(str::trim).filter(|name| !name.is_empty()).map(String::from).collect();
+        for (key, value) in vars {
+            let Some(key) = key.strip_prefix(ENV_PREFIX) else { continue };
+            match key {
+                "REQUEST_TIMEOUT_SECS" => self.request_timeout_secs = Some(number(key, &value)?),
+                "MAX_IN_FLIGHT" => self.max_in_flight = Some(number(key, &value)? as usize),
+                "LOG_LEVEL" => self.log_level = Some(value.trim().to_string()),
+                "ENABLED_TOOLS" => self.enabled_tools = Some(list(&value)),
+                "DISABLED_TOOLS" => self.disabled_tools = list(&value),
+                _ => {}
+            }
+        }
+        self.validate()?;
+        Ok(self)
+    }
+
+    fn validate(&self) -> Result<(), MCPError> {
+        match &self.log_level {
+            Some(level) if !LOG_LEVELS.contains(&level.as_str()) => {
+                Err(MCPError::InvalidParams(format!("unknown log level {:?}, expected one of {}", level, LOG_LEVELS.join(", "))))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Time limit for a request, calling `tool` if it is a tool call
+    pub fn timeout_for(&self, tool: Option<&str>) -> Option<Duration> {
+        tool.and_then(|tool| self.tool_timeouts_secs.get(tool))
+            .or(self.request_timeout_secs.as_ref())
+            .map(|secs| Duration::from_secs(*secs))
+    }
+
+    pub fn tool_enabled(&self, name: &str) -> bool {
+        self.enabled_tools.as_ref().is_none_or(|enabled| enabled.iter().any(|tool| tool == name))
+            && !self.disabled_tools.iter().any(|tool| tool == name)
+    }
+
+    /// Whether `other` serves different tools
+    fn tools_differ(&self, other: &ServerConfig) -> bool {
+        self.enabled_tools != other.enabled_tools || self.disabled_tools != other.disabled_tools
+    }
+}
+
+/// Whether a message at `level` passes a `minimum` level; unknown levels pass
+pub fn level_passes(level: &str, minimum: &str) -> bool {
+    let rank = |level: &str| LOG_LEVELS.iter().position(|known| *known == level);
+    match (rank(level), rank(minimum)) {
+        (Some(level), Some(minimum)) => level >= minimum,
+        _ => true,
+    }
+}
+
+/// A [`ServerConfig`] shared with the server and reloadable from its file
+#[derive(Debug, Default)]
+pub struct LiveConfig {
+    path: Option<PathBuf>,
+    current: RwLock<ServerConfig>,
+    modified: RwLock<Option<SystemTime>>,
+    in_flight: AtomicUsize,
+    // Advanced whenever the values change
+    generation: AtomicU64,
+    // Notification queues of the servers using the config
+    servers: Mutex<Vec<mpsc::UnboundedSender<ServerNotification>>>,
+}
+
+/// A request counted against [`ServerConfig::max_in_flight`]
+#[derive(Debug)]
+pub struct InFlight<'a> {
+    config: Option<&'a LiveConfig>,
+}
+
+impl Drop for InFlight<'_> {
+    fn drop(&mut self) {
+        if let Some(config) = self.config {
+            config.in_flight.fetch_sub(1, Ordering::AcqRel);
+        }
+    }
+}
+
+impl LiveConfig {
+    /// Fixed values, changed only through [`Self::set`]
+    pub fn new(config: ServerConfig) -> Self {
+        LiveConfig { current: RwLock::new(config), ..Default::default() }
+    }
+
+    /// Read `path` and the environment; reloads read both again
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, MCPError> {
+        let config = LiveConfig { path: Some(path.into()), ..Default::default() };
+        config.reload()?;
+        Ok(config)
+    }
+
+    pub fn current(&self) -> ServerConfig {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Replace the values; returns whether they changed
+    pub fn set(&self, config: ServerConfig) -> bool {
+        let mut current = self.current.write().unwrap();
+        let changed = *current != config;
+        let tools_changed = current.tools_differ(&config);
+        *current = config;
+        drop(current);
+        if changed {
+            self.generation.fetch_add(1, Ordering::AcqRel);
+        }
+        if tools_changed {
+            // Servers that were dropped stop taking notifications
+            self.servers.lock().unwrap().retain(|server| server.send(ServerNotification::ToolListChanged).is_ok());
+        }
+        changed
+    }
+
+    /// Send tool list changes to a server's clients
+    pub(crate) fn attach(&self, notifications: mpsc::UnboundedSender<ServerNotification>) {
+        self.servers.lock().unwrap().push(notifications);
+    }
+
+    /// Changes of the values so far
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Re-read the file and environment; returns whether the values changed.
+    /// On error the previous values stay in effect.
+    pub fn reload(&self) -> Result<bool, MCPError> {
+        let Some(path) = &self.path else { return Ok(false) };
+        let modified = std::fs::metadata(path)?.modified().ok();
+        let config = ServerConfig::from_path(path)?.with_env()?;
+        *self.modified.write().unwrap() = modified;
+        Ok(self.set(config))
+    }
+
+    /// Poll the file every `interval` and reload it when its modification
+    /// time changes, until the config is dropped or the task aborted
+    pub fn watch(self: &Arc<Self>, interval: Duration) -> JoinHandle<()> {
+        let watched = Arc::downgrade(self);
+        let path = self.path.clone();
+        tokio::spawn(async move {
+            let Some(path) = path else { return };
+            loop {
+                tokio::time::sleep(interval).await;
+                let Some(config) = watched.upgrade() else { return };
+                let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+                if modified == *config.modified.read().unwrap() {
+                    continue;
+                }
+                match config.reload() {
+                    Ok(true) => eprintln!("[CONFIG] Reloaded {}", path.display()),
+                    Ok(false) => {}
+                    Err(e) => eprintln!("[CONFIG] Failed to reload {}: {}", path.display(), e),
+                }
+            }
+        })
+    }
+
+    pub fn tool_enabled(&self, name: &str) -> bool {
+        self.current.read().unwrap().tool_enabled(name)
+    }
+
+    pub fn timeout_for(&self, tool: Option<&str>) -> Option<Duration> {
+        self.current.read().unwrap().timeout_for(tool)
+    }
+
+    pub fn log_level(&self) -> Option<String> {
+        self.current.read().unwrap().log_level.clone()
+    }
+
+    /// Count a request of `method` against the in-flight cap.
+    /// `initialize` and `ping` are never limited.
+    pub fn admit(&self, method: &str) -> Result<InFlight<'_>, MCPError> {
+        if matches!(method, "initialize" | "ping") {
+            return Ok(InFlight { config: None });
+        }
+        let limit = self.current.read().unwrap().max_in_flight;
+        let admitted = self.in_flight.fetch_update(Ordering::AcqRel, Ordering::Acquire, |count| match limit {
+            Some(limit) if count >= limit => None,
+            _ => Some(count + 1),
+        });
+        match admitted {
+            Ok(_) => Ok(InFlight { config: Some(self) }),
+            Err(count) => {
+                eprintln!("[BUSY] Rejected request: {} requests in flight", count);
+                Err(MCPError::ServerBusy("too many requests in flight".into()))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::SystemMCPServer;
+    use crate::testing::fixtures;
+    use crate::registry::ToolRegistry;
+    use crate::testing::mock::{MockToolHandler, Reply};
+    use crate::tools::{Tool, ToolInputSchema, ToolResponse};
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_file_and_env() {
+        let file = r#"{ "request_timeout_secs": 30, "tool_timeouts_secs": { "build": 600 }, "disabled_tools": ["bash"] }"#;
+        let vars = [("MCP_MAX_IN_FLIGHT", "8"), ("MCP_DISABLED_TOOLS", "rm, dd"), ("HOME", "/root")]
+            .map(|(key, value)| (key.to_string(), value.to_string()));
+        let config = ServerConfig::parse(Path::new("mcp.json"), file).unwrap().with_vars(vars).unwrap();
+        assert_eq!(config.timeout_for(Some("build")), Some(Duration::from_secs(600)));
+        assert_eq!(config.timeout_for(Some("ls")), Some(Duration::from_secs(30)));
+        assert_eq!(config.max_in_flight, Some(8));
+        assert!(config.tool_enabled("bash") && !config.tool_enabled("dd"));
+
+        assert!(ServerConfig::default().with_vars([("MCP_LOG_LEVEL".into(), "loud".into())]).is_err());
+        assert!(ServerConfig::parse(Path::new("mcp.json"), r#"{ "timeout": 1 }"#).is_err());
+        assert!(level_passes("error", "warning") && !level_passes("info", "warning"));
+    }
+
+    #[tokio::test]
+    async fn test_reload_applies_to_running_server() {
+        let path = std::env::temp_dir().join(format!("mcp-config-{}.json", std::process::id()));
+        std::fs::write(&path, r#"{ "disabled_tools": [] }"#).unwrap();
+        let config = Arc::new(LiveConfig::load(&path).unwrap());
+        let mock = MockToolHandler::new().tool("bash", Reply::text("ok"));
+        let server = SystemMCPServer::<MockToolHandler>::builder()
+            .relaxed_lifecycle()
+            .config(config.clone())
+            .build(mock);
+        assert!(server.handle(fixtures::call_tool("bash").build()).await.unwrap().result.is_some());
+
+        std::fs::write(&path, r#"{ "disabled_tools": ["bash"] }"#).unwrap();
+        assert!(config.reload().unwrap());
+        let tools = server.handle(fixtures::request("tools/list").build()).await.unwrap().result.unwrap();
+        assert_eq!(tools["tools"], serde_json::json!([]));
+        assert!(server.handle(fixtures::call_tool("bash").build()).await.unwrap().error.is_some());
+
+        // A broken edit keeps the previous values
+        std::fs::write(&path, "{").unwrap();
+        assert!(config.reload().is_err());
+        assert!(!config.current().tool_enabled("bash"));
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_tool_changes_notify_and_log_level_reaches_handlers() {
+        let registry = ToolRegistry::new();
+        registry.register(Tool::new("chatty", "logs", ToolInputSchema { schema_type: "object".into(), properties: Default::default(), required: vec![] }), |_, ctx| async move {
+            for level in ["info", "error"] {
+                ctx.progress().send_log(level, None, json!(level)).unwrap();
+            }
+            Ok(ToolResponse::new("ok".into(), false))
+        });
+        let config = Arc::new(LiveConfig::new(ServerConfig { log_level: Some("warning".into()), ..Default::default() }));
+        let mut server = SystemMCPServer::<MockToolHandler>::builder()
+            .relaxed_lifecycle()
+            .tool_registry(registry)
+            .config(config.clone())
+            .build(MockToolHandler::new());
+        let mut notifications = server.take_notification_receiver().unwrap();
+
+        server.handle(fixtures::call_tool("chatty").build()).await.unwrap();
+        let logged: Vec<Value> = std::iter::from_fn(|| notifications.try_recv()).map(|n| n.to_json_rpc()["params"]["level"].clone()).collect();
+        assert_eq!(logged, [json!("error")]);
+
+        // Only changes to the served tools are announced
+        assert!(config.set(ServerConfig { request_timeout_secs: Some(5), ..config.current() }));
+        assert!(notifications.try_recv().is_none());
+        assert!(config.set(ServerConfig { disabled_tools: vec!["chatty".into()], ..config.current() }));
+        assert!(matches!(notifications.try_recv(), Some(ServerNotification::ToolListChanged)));
+    }
+
+    #[tokio::test]
+    async fn test_watch_stops_with_the_config() {
+        let path = std::env::temp_dir().join(format!("mcp-config-watch-{}.json", std::process::id()));
+        std::fs::write(&path, "{}").unwrap();
+        let config = Arc::new(LiveConfig::load(&path).unwrap());
+        let watch = config.watch(Duration::from_millis(5));
+        drop(config);
+        tokio::time::timeout(Duration::from_secs(5), watch).await.unwrap().unwrap();
+        std::fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,365 @@
+//! Per-request information available to handlers.
+//!
+//! Every [`ToolHandler`](crate::server::ToolHandler) method that answers a
+//! request receives a [`RequestContext`]: the request id and `_meta`, what the
+//! client negotiated in `initialize`, a [`ProgressSender`] and a
+//! [`CancellationToken`] that fires when the client cancels the request.
+
+use crate::error::MCPError;
+use crate::flags::FeatureFlags;
+use crate::memory::{MemoryAccountant, MemoryCategory, Reservation};
+use crate::notifications::ProgressSender;
+use crate::outbound::ClientRequests;
+use crate::elicitation::{ElicitParams, ElicitResult, ELICIT_METHOD};
+use crate::roots::{ListRootsResult, Root, LIST_ROOTS_METHOD};
+use crate::sampling::{CreateMessageParams, CreateMessageResult, CREATE_MESSAGE_METHOD};
+use crate::subprocess::{DeadlinePolicy, SubprocessEnv};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::ffi::OsStr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::sync::watch;
+use tokio::time::Instant;
+
+/// What the client declared about itself in `initialize`
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ClientInfo {
+    pub name: String,
+    pub version: String,
+    /// The client's `capabilities` object as sent
+    pub capabilities: Value,
+}
+
+impl ClientInfo {
+    /// Read `clientInfo` and `capabilities` from `initialize` params
+    pub fn from_initialize(params: Option<&Value>) -> Self {
+        let field = |key: &str| params.and_then(|p| p.get("clientInfo")?.get(key)?.as_str()).unwrap_or_default().to_string();
+        ClientInfo {
+            name: field("name"),
+            version: field("version"),
+            capabilities: params.and_then(|p| p.get("capabilities")).cloned().unwrap_or_else(|| Value::Object(Default::default())),
+        }
+    }
+
+    /// Whether the capabilities contain a dotted path, e.g. `"sampling"`,
+    /// `"roots.listChanged"` or `"experimental.images"`
+    pub fn supports(&self, path: &str) -> bool {
+        path.split('.')
+            .try_fold(&self.capabilities, |value, key| value.get(key))
+            .is_some_and(|value| !matches!(value, Value::Null | Value::Bool(false)))
+    }
+}
+
+/// Fires when a request is cancelled; clones share the same state
+#[derive(Debug, Clone)]
+pub struct CancellationToken(Arc<watch::Sender<bool>>);
+
+impl Default for CancellationToken {
+    fn default() -> Self {
+        CancellationToken(Arc::new(watch::Sender::new(false)))
+    }
+}
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.send_replace(true);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    /// Whether both are clones of the same token
+    pub fn same(&self, other: &CancellationToken) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+
+    /// Resolves once [`cancel`](Self::cancel) is called
+    pub async fn cancelled(&self) {
+        let _ = self.0.subscribe().wait_for(|cancelled| *cancelled).await;
+    }
+}
+
+/// Longest correlation id passed on to logs and subprocesses
+pub const MAX_CORRELATION_ID_LEN: usize = 128;
+
+fn valid_correlation_id(id: &str) -> bool {
+    (1..=MAX_CORRELATION_ID_LEN).contains(&id.len())
+        && id.bytes().all(|b| b.is_ascii_alphanumeric() || b"._:/-".contains(&b))
+}
+
+#[derive(Debug, Clone)]
+pub struct RequestContext {
+    request_id: String,
+    session_id: String,
+    meta: Option<Value>,
+    flags: FeatureFlags,
+    client: Option<ClientInfo>,
+    protocol_version: Option<String>,
+    progress: ProgressSender,
+    cancellation: CancellationToken,
+    subprocess_env: Arc<SubprocessEnv>,
+    deadline: Option<Instant>,
+    deadline_policy: DeadlinePolicy,
+    client_requests: Option<Arc<ClientRequests>>,
+    memory: Option<Arc<MemoryAccountant>>,
+}
+
+impl Default for RequestContext {
+    fn default() -> Self {
+        RequestContext::new(String::new(), None, FeatureFlags::default())
+    }
+}
+
+impl RequestContext {
+    pub fn new(request_id: impl Into<String>, meta: Option<Value>, flags: FeatureFlags) -> Self {
+        RequestContext {
+            request_id: request_id.into(),
+            session_id: String::new(),
+            meta,
+            flags,
+            client: None,
+            protocol_version: None,
+            progress: ProgressSender::disconnected(),
+            cancellation: CancellationToken::new(),
+            subprocess_env: Arc::default(),
+            deadline: None,
+            deadline_policy: DeadlinePolicy::default(),
+            client_requests: None,
+            memory: None,
+        }
+    }
+
+    /// Report progress through `progress`
+    pub fn with_progress(mut self, progress: ProgressSender) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Share `cancellation` with whoever may cancel the request
+    pub fn with_cancellation(mut self, cancellation: CancellationToken) -> Self {
+        self.cancellation = cancellation;
+        self
+    }
+
+    /// Spawn subprocesses with `env`'s defaults
+    pub fn with_subprocess_env(mut self, env: Arc<SubprocessEnv>) -> Self {
+        self.subprocess_env = env;
+        self
+    }
+
+    /// The request must be answered by `deadline`; `policy` keeps
+    /// subprocesses clear of it
+    pub fn with_deadline(mut self, deadline: Option<Instant>, policy: DeadlinePolicy) -> Self {
+        self.deadline = deadline;
+        self.deadline_policy = policy;
+        self
+    }
+
+    /// Send requests to the client through `requests`
+    pub(crate) fn with_client_requests(mut self, requests: Arc<ClientRequests>) -> Self {
+        self.client_requests = Some(requests);
+        self
+    }
+
+    /// Charge buffered output to `memory`
+    pub(crate) fn with_memory(mut self, memory: Option<Arc<MemoryAccountant>>) -> Self {
+        self.memory = memory;
+        self
+    }
+
+    pub(crate) fn with_session_id(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = session_id.into();
+        self
+    }
+
+    /// Attach what was negotiated during `initialize`
+    pub fn with_session(mut self, client: Option<ClientInfo>, protocol_version: Option<String>) -> Self {
+        self.client = client;
+        self.protocol_version = protocol_version;
+        self
+    }
+
+    pub fn request_id(&self) -> &str {
+        &self.request_id
+    }
+
+    /// Session the request belongs to
+    pub fn session_id(&self) -> &str {
+        &self.session_id
+    }
+
+    /// The request's `params._meta`
+    pub fn meta(&self) -> Option<&Value> {
+        self.meta.as_ref()
+    }
+
+    /// `_meta.correlationId` if the client set a valid one, otherwise the
+    /// request id, otherwise `"unknown"`. Valid ids are 1 to
+    /// [`MAX_CORRELATION_ID_LEN`] characters of `A-Z a-z 0-9 . _ : / -`, so
+    /// they are safe in log lines and environment variables.
+    pub fn correlation_id(&self) -> &str {
+        let client = self.meta.as_ref()
+            .and_then(|meta| meta.get("correlationId"))
+            .and_then(Value::as_str);
+        [client, Some(self.request_id.as_str())].into_iter().flatten()
+            .find(|id| valid_correlation_id(id))
+            .unwrap_or("unknown")
+    }
+
+    /// Whether feature flag `name` is on
+    pub fn flag(&self, name: &str) -> bool {
+        self.flags.is_enabled(name)
+    }
+
+    /// The client, if the session was initialized
+    pub fn client(&self) -> Option<&ClientInfo> {
+        self.client.as_ref()
+    }
+
+    /// Protocol revision agreed during `initialize`
+    pub fn protocol_version(&self) -> Option<&str> {
+        self.protocol_version.as_deref()
+    }
+
+    pub fn progress(&self) -> &ProgressSender {
+        &self.progress
+    }
+
+    pub fn cancellation(&self) -> &CancellationToken {
+        &self.cancellation
+    }
+
+    /// Whether the client cancelled this request
+    pub fn is_cancelled(&self) -> bool {
+        self.cancellation.is_cancelled()
+    }
+
+    /// When the server's time limit or the client's `_meta.timeoutMs` ends
+    /// the request, whichever comes first
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Timeout for a subprocess given `requested`, clamped so the process is
+    /// reaped before the request deadline; see [`DeadlinePolicy`]
+    pub fn subprocess_timeout(&self, requested: Duration) -> Duration {
+        self.deadline_policy.clamp(requested, self.deadline)
+    }
+
+    /// A command for `program` with the server's subprocess environment
+    pub fn command(&self, program: impl AsRef<OsStr>) -> Command {
+        let mut command = Command::new(program);
+        self.subprocess_env.apply(&mut command);
+        command
+    }
+
+    /// Hold `bytes` of the server's memory ceiling for output the handler
+    /// buffers, growing it as the output does; `None` without a ceiling
+    pub fn reserve_output(&self, bytes: usize) -> Result<Option<Reservation>, MCPError> {
+        let Some(memory) = &self.memory else { return Ok(None) };
+        match memory.reserve(MemoryCategory::Output, bytes) {
+            Some(reservation) => Ok(Some(reservation)),
+            None => {
+                memory.record_rejection();
+                Err(MCPError::OutputTooLarge)
+            }
+        }
+    }
+
+    /// Ask the client's model for a completion with `sampling/createMessage`
+    /// and wait for it. Fails unless the client advertised `sampling`.
+    pub async fn create_message(&self, params: CreateMessageParams) -> Result<CreateMessageResult, MCPError> {
+        if !self.client_supports("sampling") {
+            return Err(MCPError::MethodNotFound(format!("{} (client does not support sampling)", CREATE_MESSAGE_METHOD)));
+        }
+        let result = self.client_request(CREATE_MESSAGE_METHOD, serde_json::to_value(params)?).await?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// Ask the user through the client with `elicitation/create` and wait
+    /// for the answer. Fails unless the client advertised `elicitation`.
+    pub async fn elicit(&self, params: ElicitParams) -> Result<ElicitResult, MCPError> {
+        if !self.client_supports("elicitation") {
+            return Err(MCPError::MethodNotFound(format!("{} (client does not support elicitation)", ELICIT_METHOD)));
+        }
+        let result = self.client_request(ELICIT_METHOD, serde_json::to_value(params)?).await?;
+        Ok(serde_json::from_value(result)?)
+    }
+
+    /// The client's roots from `roots/list`, cached for the session until
+    /// the client reports a change. Fails unless the client advertised `roots`.
+    pub async fn list_roots(&self) -> Result<Vec<Root>, MCPError> {
+        if !self.client_supports("roots") {
+            return Err(MCPError::MethodNotFound(format!("{} (client does not support roots)", LIST_ROOTS_METHOD)));
+        }
+        if let Some(requests) = &self.client_requests
+            && let Some(roots) = requests.cached_roots(&self.session_id)
+        {
+            return Ok(roots);
+        }
+        let generation = self.client_requests.as_ref().map(|requests| requests.roots_generation());
+        let result: ListRootsResult = serde_json::from_value(self.client_request(LIST_ROOTS_METHOD, serde_json::json!({})).await?)?;
+        if let (Some(requests), Some(generation)) = (&self.client_requests, generation) {
+            requests.cache_roots(&self.session_id, generation, result.roots.clone());
+        }
+        Ok(result.roots)
+    }
+
+    async fn client_request(&self, method: &str, params: Value) -> Result<Value, MCPError> {
+        let requests = self.client_requests.as_ref()
+            .ok_or_else(|| MCPError::InternalError(format!("{} needs a client connection", method)))?;
+        requests.request(&self.progress, method, params).await
+    }
+
+    /// Whether the client advertised a capability; see [`ClientInfo::supports`]
+    pub fn client_supports(&self, path: &str) -> bool {
+        self.client.as_ref().is_some_and(|client| client.supports(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_client_capabilities() {
+        let params = json!({
+            "clientInfo": { "name": "editor", "version": "2.1" },
+            "capabilities": { "roots": { "listChanged": true }, "sampling": {}, "experimental": { "images": false } },
+        });
+        let client = ClientInfo::from_initialize(Some(&params));
+        assert_eq!((client.name.as_str(), client.version.as_str()), ("editor", "2.1"));
+        assert!(client.supports("sampling"));
+        assert!(client.supports("roots.listChanged"));
+        assert!(!client.supports("experimental.images"));
+        assert!(!client.supports("elicitation"));
+        assert!(!RequestContext::default().client_supports("sampling"));
+
+        let context = RequestContext::new("7", Some(json!({ "correlationId": "trace-1" })), FeatureFlags::default());
+        assert_eq!(context.correlation_id(), "trace-1");
+        assert_eq!(RequestContext::new("7", None, FeatureFlags::default()).correlation_id(), "7");
+        let forged = RequestContext::new("7", Some(json!({ "correlationId": "x\n[SPAN] tool=rm" })), FeatureFlags::default());
+        assert_eq!(forged.correlation_id(), "7");
+        let unusable = RequestContext::new("\"a b\"", Some(json!({ "correlationId": "" })), FeatureFlags::default());
+        assert_eq!(unusable.correlation_id(), "unknown");
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_reaches_clones() {
+        let context = RequestContext::default().with_cancellation(CancellationToken::new());
+        let token = context.cancellation().clone();
+        let waiter = tokio::spawn(async move { token.cancelled().await });
+        assert!(!context.is_cancelled());
+        context.cancellation().cancel();
+        waiter.await.unwrap();
+        assert!(context.is_cancelled());
+    }
+}
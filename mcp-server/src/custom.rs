@@ -0,0 +1,38 @@
+//! Router for custom (non-spec) JSON-RPC methods.
+//!
+//! Methods registered with [`ServerBuilder::custom_method`] are dispatched
+//! after the built-in MCP methods. Handlers get a [`ToolCaller`] so they can
+//! compose tool calls. Each call is a `tools/call` request of its own, made
+//! in the method's session and cancelled with it, so it goes through the same
+//! middleware, guards, limits and post-processing as one from the client.
+//! Experimental methods use the `x-mcp/` prefix.
+//!
+//! [`ServerBuilder::custom_method`]: crate::server::ServerBuilder::custom_method
+
+use crate::error::MCPError;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Tool access for custom methods
+#[async_trait]
+pub trait ToolCaller: Send + Sync {
+    /// Call a tool through `tools/call`, returning the result object; an
+    /// error response comes back as [`MCPError::PeerError`]
+    async fn call_tool(&self, name: &str, arguments: Value) -> Result<Value, MCPError>;
+
+    /// Whether the tool implements a rollback hook
+    fn supports_rollback(&self, name: &str) -> bool;
+
+    /// Undo a successful call of the tool
+    async fn rollback_tool(&self, name: &str, arguments: &Value) -> Result<(), MCPError>;
+}
+
+#[async_trait]
+pub trait MethodHandler: Send + Sync {
+    async fn handle(&self, tools: &dyn ToolCaller, params: Option<&Value>) -> Result<Value, MCPError>;
+}
+
+/// Custom method name -> handler
+pub type CustomMethods = HashMap<String, Arc<dyn MethodHandler>>;
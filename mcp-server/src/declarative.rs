@@ -0,0 +1,474 @@
+//! Tools declared in JSON (or YAML, feature `yaml`) files.
+//!
+//! Each entry is a regular tool definition plus an `executor` that binds it
+//! to a generic runner, so simple wrapper tools need no Rust:
+//!
+//! ```json
+//! { "tools": [
+//!   { "name": "grep", "description": "Search files",
+//!     "inputSchema": { "type": "object", "properties": { "pattern": { "type": "string" } }, "required": ["pattern"] },
+//!     "executor": { "type": "command", "argv": ["grep", "-rn", "{{pattern}}", "."], "timeoutSecs": 10 } },
+//!   { "name": "weather", "description": "Current weather",
+//!     "inputSchema": { "type": "object", "properties": { "city": { "type": "string" } } },
+//!     "executor": { "type": "http", "method": "GET", "url": "http://localhost:8080/weather?city={{city}}" } }
+//! ] }
+//! ```
+//!
+//! `{{name}}` placeholders are replaced with the call's arguments: each argv
+//! element is one argument (no shell is involved), URL values are
+//! percent-encoded, and a JSON body string that is exactly `"{{name}}"` takes
+//! the argument's JSON value. A `--` goes before the first argv element with
+//! a placeholder (unless the template has one already), so a value such as
+//! `--output=/etc/passwd` is never taken for an option; fixed options must
+//! come before the placeholders. The HTTP executor speaks plain `http://`
+//! only and reads at most [`MAX_HTTP_RESPONSE_BYTES`] of a response.
+//!
+//! Wrap a handler with [`WithDeclarativeTools`] to serve the tools, and call
+//! [`DeclarativeTools::reload`] (or [`DeclarativeTools::watch`]) to pick up
+//! file changes while the server runs.
+
+use crate::changes::ListSource;
+use crate::error::MCPError;
+use crate::context::RequestContext;
+use crate::server::ToolHandler;
+use crate::tools::{Prompt, PromptResponse, Resource, ResourceContent, StreamChunk, Tool, ToolResponse};
+use async_trait::async_trait;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio_stream::Stream;
+
+/// Timeout of command and HTTP executors that do not set one
+pub const DEFAULT_EXECUTOR_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Largest HTTP executor response, headers included
+pub const MAX_HTTP_RESPONSE_BYTES: u64 = 16 * 1024 * 1024;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Executor {
+    Command {
+        argv: Vec<String>,
+        #[serde(rename = "workingDir")]
+        working_dir: Option<PathBuf>,
+        #[serde(rename = "timeoutSecs")]
+        timeout_secs: Option<u64>,
+    },
+    Http {
+        #[serde(default = "default_method")]
+        method: String,
+        url: String,
+        #[serde(default)]
+        headers: BTreeMap<String, String>,
+        body: Option<Value>,
+        #[serde(rename = "timeoutSecs")]
+        timeout_secs: Option<u64>,
+    },
+}
+
+fn default_method() -> String {
+    "GET".into()
+}
+
+/// A tool definition bound to an executor
+#[derive(Debug, Clone, Deserialize)]
+pub struct ToolSpec {
+    #[serde(flatten)]
+    pub tool: Tool,
+    pub executor: Executor,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ToolFile {
+    Wrapped { tools: Vec<ToolSpec> },
+    List(Vec<ToolSpec>),
+}
+
+/// Parse a tool file; `.yaml`/`.yml` files need the `yaml` feature
+pub fn parse_tool_file(path: &Path, text: &str) -> Result<Vec<ToolSpec>, MCPError> {
+    let is_yaml = path.extension().is_some_and(|ext| ext == "yaml" || ext == "yml");
+    let file: ToolFile = if is_yaml {
+        #[cfg(feature = "yaml")]
+        {
+            serde_yaml::from_str(text).map_err(|e| MCPError::InvalidParams(format!("{}: {}", path.display(), e)))?
+        }
+        #[cfg(not(feature = "yaml"))]
+        {
+            return Err(MCPError::InvalidParams(format!("{}: YAML tool files need the `yaml` feature", path.display())));
+        }
+    } else {
+        serde_json::from_str(text)?
+    };
+    Ok(match file {
+        ToolFile::Wrapped { tools } | ToolFile::List(tools) => tools,
+    })
+}
+
+/// Tools loaded from a file, reloadable while the server runs
+#[derive(Debug, Default)]
+pub struct DeclarativeTools {
+    path: Option<PathBuf>,
+    specs: RwLock<BTreeMap<String, ToolSpec>>,
+    modified: RwLock<Option<SystemTime>>,
+}
+
+impl DeclarativeTools {
+    pub fn load(path: impl Into<PathBuf>) -> Result<Self, MCPError> {
+        let tools = DeclarativeTools { path: Some(path.into()), ..Default::default() };
+        tools.reload()?;
+        Ok(tools)
+    }
+
+    /// Re-read the file; returns whether the tool set changed
+    pub fn reload(&self) -> Result<bool, MCPError> {
+        let Some(path) = &self.path else { return Ok(false) };
+        let modified = std::fs::metadata(path)?.modified().ok();
+        let specs: BTreeMap<String, ToolSpec> = parse_tool_file(path, &std::fs::read_to_string(path)?)?
+            .into_iter()
+            .map(|spec| (spec.tool.name.clone(), spec))
+            .collect();
+
+        *self.modified.write().unwrap() = modified;
+        let mut current = self.specs.write().unwrap();
+        let changed = current.len() != specs.len()
+            || current.iter().zip(&specs).any(|((a, x), (b, y))| a != b || json!(x.tool) != json!(y.tool));
+        *current = specs;
+        Ok(changed)
+    }
+
+    /// Poll the file every `interval` and reload it when its modification
+    /// time changes, calling `on_change` when the tool set changed
+    pub fn watch(self: &Arc<Self>, interval: Duration, on_change: impl Fn() + Send + 'static) {
+        let tools = Arc::clone(self);
+        tokio::spawn(async move {
+            let Some(path) = tools.path.clone() else { return };
+            loop {
+                tokio::time::sleep(interval).await;
+                let modified = std::fs::metadata(&path).and_then(|m| m.modified()).ok();
+                if modified == *tools.modified.read().unwrap() {
+                    continue;
+                }
+                match tools.reload() {
+                    Ok(true) => {
+                        eprintln!("[TOOLS] Reloaded {}", path.display());
+                        on_change();
+                    }
+                    Ok(false) => {}
+                    // Keep serving the previous definitions
+                    Err(e) => eprintln!("[TOOLS] Failed to reload {}: {}", path.display(), e),
+                }
+            }
+        });
+    }
+
+    pub fn tools(&self) -> Vec<Tool> {
+        self.specs.read().unwrap().values().map(|spec| spec.tool.clone()).collect()
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.specs.read().unwrap().contains_key(name)
+    }
+
+    pub async fn call(&self, name: &str, args: &Value, ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+        let spec = self.specs.read().unwrap().get(name).cloned().ok_or_else(|| MCPError::UnknownTool(name.into()))?;
+        if let Some(missing) = spec.tool.input_schema.required.iter().find(|key| args.get(key.as_str()).is_none()) {
+            return Err(MCPError::InvalidParams(format!("missing required argument '{}'", missing)));
+        }
+        match &spec.executor {
+            Executor::Command { argv, working_dir, timeout_secs } => {
+                let timeout = timeout_secs.map_or(DEFAULT_EXECUTOR_TIMEOUT, Duration::from_secs);
+                run_command(ctx, argv, working_dir.as_deref(), args, timeout).await
+            }
+            Executor::Http { method, url, headers, body, timeout_secs } => {
+                let timeout = timeout_secs.map_or(DEFAULT_EXECUTOR_TIMEOUT, Duration::from_secs);
+                let request = HttpCall {
+                    method,
+                    url: &interpolate(url, args, percent_encode),
+                    headers,
+                    body: body.as_ref().map(|body| fill_json(body, args)),
+                };
+                tokio::time::timeout(timeout, request.send(args))
+                    .await
+                    .map_err(|_| MCPError::InternalError(format!("HTTP call for {} timed out", name)))?
+            }
+        }
+    }
+}
+
+/// Text form of an argument for interpolation
+fn argument_text(args: &Value, key: &str) -> String {
+    match args.get(key) {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(text)) => text.clone(),
+        Some(other) => other.to_string(),
+    }
+}
+
+/// Replace `{{key}}` placeholders with `encode`d argument text
+fn interpolate(template: &str, args: &Value, encode: fn(&str) -> String) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find("{{") {
+        let Some(len) = rest[start + 2..].find("}}") else { break };
+        out.push_str(&rest[..start]);
+        out.push_str(&encode(&argument_text(args, rest[start + 2..start + 2 + len].trim())));
+        rest = &rest[start + 2 + len + 2..];
+    }
+    out.push_str(rest);
+    out
+}
+
+fn verbatim(text: &str) -> String {
+    text.to_string()
+}
+
+fn percent_encode(text: &str) -> String {
+    text.bytes().map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+        _ => format!("%{:02X}", b),
+    }).collect()
+}
+
+/// Fill a JSON body template; `"{{key}}"` alone takes the argument's value
+fn fill_json(template: &Value, args: &Value) -> Value {
+    match template {
+        Value::String(text) => {
+            let whole = text.strip_prefix("{{").and_then(|t| t.strip_suffix("}}")).map(str::trim);
+            match whole.filter(|key| !key.contains("{{")) {
+                Some(key) => args.get(key).cloned().unwrap_or(Value::Null),
+                None => Value::String(interpolate(text, args, verbatim)),
+            }
+        }
+        Value::Array(items) => Value::Array(items.iter().map(|item| fill_json(item, args)).collect()),
+        Value::Object(map) => Value::Object(map.iter().map(|(k, v)| (k.clone(), fill_json(v, args))).collect()),
+        other => other.clone(),
+    }
+}
+
+/// `argv` with `--` before its first templated argument, ending options there
+fn end_options(argv: &[String]) -> Vec<String> {
+    let mut argv = argv.to_vec();
+    let templated = argv.iter().skip(1).position(|arg| arg.contains("{{")).map(|index| index + 1);
+    if let Some(index) = templated
+        && !argv[1..index].iter().any(|arg| arg == "--")
+    {
+        argv.insert(index, "--".into());
+    }
+    argv
+}
+
+async fn run_command(ctx: &RequestContext, argv: &[String], working_dir: Option<&Path>, args: &Value, timeout: Duration) -> Result<ToolResponse, MCPError> {
+    let argv: Vec<String> = end_options(argv).iter().map(|arg| interpolate(arg, args, verbatim)).collect();
+    let (program, rest) = argv.split_first().ok_or_else(|| MCPError::InvalidParams("empty argv".into()))?;
+    let mut command = ctx.command(program);
+    command.args(rest).kill_on_drop(true);
+    if let Some(dir) = working_dir {
+        command.current_dir(dir);
+    }
+
+    let output = tokio::time::timeout(timeout, command.output())
+        .await
+        .map_err(|_| MCPError::InternalError(format!("{} timed out after {:?}", program, timeout)))??;
+    let stdout = String::from_utf8_lossy(&output.stdout).into_owned();
+    let stderr = String::from_utf8_lossy(&output.stderr).into_owned();
+    let text = if stderr.is_empty() { stdout.clone() } else { format!("{}\nSTDERR:\n{}", stdout, stderr) };
+    Ok(ToolResponse::new(text, !output.status.success()).with_structured_content(json!({
+        "exitCode": output.status.code(),
+        "stdout": stdout,
+        "stderr": stderr,
+    })))
+}
+
+/// A plain `http://` request; also used by [`crate::quarantine::HttpClassifier`]
+pub(crate) struct HttpCall<'a> {
+    pub(crate) method: &'a str,
+    pub(crate) url: &'a str,
+    pub(crate) headers: &'a BTreeMap<String, String>,
+    pub(crate) body: Option<Value>,
+}
+
+impl HttpCall<'_> {
+    pub(crate) async fn send(self, args: &Value) -> Result<ToolResponse, MCPError> {
+        let rest = self.url.strip_prefix("http://")
+            .ok_or_else(|| MCPError::InvalidParams(format!("only http:// URLs are supported: {}", self.url)))?;
+        let (authority, path) = rest.split_at(rest.find('/').unwrap_or(rest.len()));
+        let address = if authority.contains(':') { authority.to_string() } else { format!("{}:80", authority) };
+
+        let body = self.body.map(|body| body.to_string()).unwrap_or_default();
+        let mut request = format!("{} {} HTTP/1.0\r\nHost: {}\r\nConnection: close\r\n", self.method, if path.is_empty() { "/" } else { path }, authority);
+        for (name, value) in self.headers {
+            let value = interpolate(value, args, verbatim);
+            if value.contains(['\r', '\n']) {
+                return Err(MCPError::InvalidParams(format!("header {} contains a line break", name)));
+            }
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        if !body.is_empty() {
+            request.push_str(&format!("Content-Type: application/json\r\nContent-Length: {}\r\n", body.len()));
+        }
+        request.push_str("\r\n");
+        request.push_str(&body);
+
+        let mut stream = tokio::net::TcpStream::connect(&address).await?;
+        stream.write_all(request.as_bytes()).await?;
+        let mut response = Vec::new();
+        stream.take(MAX_HTTP_RESPONSE_BYTES + 1).read_to_end(&mut response).await?;
+        if response.len() as u64 > MAX_HTTP_RESPONSE_BYTES {
+            return Err(MCPError::UnexpectedResponse(format!("response from {} exceeds {} bytes", address, MAX_HTTP_RESPONSE_BYTES)));
+        }
+
+        let response = String::from_utf8_lossy(&response);
+        let (head, body) = response.split_once("\r\n\r\n").unwrap_or((&response, ""));
+        let status: u16 = head.split_whitespace().nth(1).and_then(|code| code.parse().ok())
+            .ok_or_else(|| MCPError::UnexpectedResponse(format!("malformed HTTP response from {}", address)))?;
+        let mut structured = json!({ "status": status });
+        if let Ok(value) = serde_json::from_str::<Value>(body) {
+            structured["body"] = value;
+        }
+        Ok(ToolResponse::new(body.to_string(), status >= 400).with_structured_content(structured))
+    }
+}
+
+impl ListSource for DeclarativeTools {
+    fn tools(&self) -> Vec<Tool> {
+        DeclarativeTools::tools(self)
+    }
+}
+
+/// A handler that also serves declarative tools; everything else goes to
+/// the inner handler
+pub struct WithDeclarativeTools<H> {
+    inner: H,
+    tools: Arc<DeclarativeTools>,
+}
+
+impl<H: ToolHandler> WithDeclarativeTools<H> {
+    pub fn new(inner: H, tools: Arc<DeclarativeTools>) -> Self {
+        WithDeclarativeTools { inner, tools }
+    }
+}
+
+#[async_trait]
+impl<H: ToolHandler> ToolHandler for WithDeclarativeTools<H> {
+    async fn call_tool(&self, name: &str, args: &Value, ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+        if self.tools.contains(name) {
+            self.tools.call(name, args, ctx).await
+        } else {
+            self.inner.call_tool(name, args, ctx).await
+        }
+    }
+
+    async fn list_tools(&self, ctx: &RequestContext) -> Result<Vec<Tool>, MCPError> {
+        let mut tools = self.inner.list_tools(ctx).await?;
+        tools.extend(self.tools.tools());
+        Ok(tools)
+    }
+
+    async fn list_prompts(&self, ctx: &RequestContext) -> Result<Vec<Prompt>, MCPError> {
+        self.inner.list_prompts(ctx).await
+    }
+
+    async fn get_prompt(&self, name: &str, args: &Value, ctx: &RequestContext) -> Result<PromptResponse, MCPError> {
+        self.inner.get_prompt(name, args, ctx).await
+    }
+
+    async fn list_resources(&self, ctx: &RequestContext) -> Result<Vec<Resource>, MCPError> {
+        self.inner.list_resources(ctx).await
+    }
+
+    async fn read_resource(&self, uri: &str, ctx: &RequestContext) -> Result<ResourceContent, MCPError> {
+        self.inner.read_resource(uri, ctx).await
+    }
+
+    async fn call_tool_stream(&self, name: &str, args: &Value, ctx: &RequestContext) -> Result<Pin<Box<dyn Stream<Item = StreamChunk> + Send>>, MCPError> {
+        self.inner.call_tool_stream(name, args, ctx).await
+    }
+
+    fn supports_rollback(&self, name: &str) -> bool {
+        self.inner.supports_rollback(name)
+    }
+
+    async fn rollback_tool(&self, name: &str, args: &Value, ctx: &RequestContext) -> Result<(), MCPError> {
+        self.inner.rollback_tool(name, args, ctx).await
+    }
+
+    async fn on_tool_called(&self, name: &str) {
+        self.inner.on_tool_called(name).await
+    }
+
+    async fn on_tool_completed(&self, name: &str, success: bool) {
+        self.inner.on_tool_completed(name, success).await
+    }
+
+    async fn on_request_cancelled(&self, request_id: &str, reason: Option<&str>) {
+        self.inner.on_request_cancelled(request_id, reason).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tools_from(text: &str) -> DeclarativeTools {
+        let tools = DeclarativeTools::default();
+        let specs = parse_tool_file(Path::new("tools.json"), text).unwrap();
+        *tools.specs.write().unwrap() = specs.into_iter().map(|spec| (spec.tool.name.clone(), spec)).collect();
+        tools
+    }
+
+    #[test]
+    fn test_templates() {
+        let args = json!({ "q": "a b&c", "n": 3, "tags": ["x"] });
+        assert_eq!(interpolate("/search?q={{q}}&n={{ n }}", &args, percent_encode), "/search?q=a%20b%26c&n=3");
+        assert_eq!(fill_json(&json!({ "tags": "{{tags}}", "label": "n={{n}}" }), &args), json!({ "tags": ["x"], "label": "n=3" }));
+
+        let argv = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+        assert_eq!(end_options(&argv(&["grep", "-rn", "{{p}}", "."])), argv(&["grep", "-rn", "--", "{{p}}", "."]));
+        assert_eq!(end_options(&argv(&["git", "--", "{{p}}"])), argv(&["git", "--", "{{p}}"]));
+        assert_eq!(end_options(&argv(&["ls", "-l"])), argv(&["ls", "-l"]));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_command_executor() {
+        let path = std::env::temp_dir().join(format!("mcp-declarative-{}.txt", std::process::id()));
+        std::fs::write(&path, "hi; rm -rf /\n").unwrap();
+        let tools = tools_from(r#"[{ "name": "cat", "inputSchema": { "type": "object", "required": ["path"] },
+            "executor": { "type": "command", "argv": ["cat", "{{path}}"] } }]"#);
+        let response = tools.call("cat", &json!({ "path": path }), &RequestContext::default()).await.unwrap();
+        assert_eq!(response.structured_content.unwrap()["stdout"], "hi; rm -rf /\n");
+        assert!(matches!(tools.call("cat", &json!({}), &RequestContext::default()).await, Err(MCPError::InvalidParams(_))));
+
+        // Values are never options
+        let response = tools.call("cat", &json!({ "path": "--version" }), &RequestContext::default()).await.unwrap();
+        assert!(response.is_error);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_http_executor() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = vec![0; 1024];
+            let len = socket.read(&mut request).await.unwrap();
+            let request = String::from_utf8_lossy(&request[..len]).into_owned();
+            let body = json!({ "echo": request.lines().next() }).to_string();
+            let response = format!("HTTP/1.0 200 OK\r\nContent-Length: {}\r\n\r\n{}", body.len(), body);
+            socket.write_all(response.as_bytes()).await.unwrap();
+        });
+
+        let tools = tools_from(&format!(r#"{{ "tools": [{{ "name": "get", "inputSchema": {{ "type": "object" }},
+            "executor": {{ "type": "http", "url": "http://{}/items/{{{{id}}}}" }} }}] }}"#, addr));
+        let response = tools.call("get", &json!({ "id": "a/b" }), &RequestContext::default()).await.unwrap();
+        assert!(!response.is_error);
+        assert_eq!(response.structured_content.unwrap()["body"]["echo"], "GET /items/a%2Fb HTTP/1.0");
+    }
+}
@@ -0,0 +1,151 @@
+//! Feature flags for dark-launched functionality.
+//!
+//! Flag state is advertised to clients under
+//! `capabilities.experimental.featureFlags` and visible to handlers through
+//! [`RequestContext::flag`]. Tools gated with [`FeatureFlags::gate_tool`] are
+//! hidden from `tools/list` and refused while their flag is off.
+//!
+//! [`RequestContext::flag`]: crate::context::RequestContext::flag
+
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::sync::{Arc, RwLock};
+
+/// Shared flag state; clones refer to the same flags, so a handle kept
+/// outside the server can toggle flags at runtime
+#[derive(Debug, Clone, Default)]
+pub struct FeatureFlags {
+    inner: Arc<RwLock<FlagState>>,
+}
+
+#[derive(Debug, Default)]
+struct FlagState {
+    flags: BTreeMap<String, bool>,
+    // Tool name -> flag that must be on for the tool to be served
+    gated_tools: BTreeMap<String, String>,
+    // Advanced on every change
+    generation: u64,
+}
+
+impl FeatureFlags {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn enable(&self, flag: impl Into<String>) {
+        self.set(flag, true);
+    }
+
+    pub fn disable(&self, flag: impl Into<String>) {
+        self.set(flag, false);
+    }
+
+    pub fn set(&self, flag: impl Into<String>, enabled: bool) {
+        let mut state = self.inner.write().unwrap();
+        state.flags.insert(flag.into(), enabled);
+        state.generation += 1;
+    }
+
+    /// Unknown flags are off
+    pub fn is_enabled(&self, flag: &str) -> bool {
+        self.inner.read().unwrap().flags.get(flag).copied().unwrap_or(false)
+    }
+
+    /// Serve `tool` only while `flag` is on
+    pub fn gate_tool(&self, tool: impl Into<String>, flag: impl Into<String>) {
+        let mut state = self.inner.write().unwrap();
+        let flag = flag.into();
+        state.flags.entry(flag.clone()).or_insert(false);
+        state.gated_tools.insert(tool.into(), flag);
+        state.generation += 1;
+    }
+
+    /// Whether `tool` is ungated or its flag is on
+    pub fn tool_enabled(&self, tool: &str) -> bool {
+        let state = self.inner.read().unwrap();
+        state.gated_tools.get(tool).is_none_or(|flag| state.flags.get(flag).copied().unwrap_or(false))
+    }
+
+    /// Changes so far, for caches of what the flags hide
+    pub fn generation(&self) -> u64 {
+        self.inner.read().unwrap().generation
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.inner.read().unwrap().flags.is_empty()
+    }
+
+    /// Flag states as advertised under `capabilities.experimental.featureFlags`
+    pub fn to_json(&self) -> Value {
+        let flags = self.inner.read().unwrap().flags.iter()
+            .map(|(flag, &enabled)| (flag.clone(), Value::Bool(enabled)))
+            .collect::<Map<_, _>>();
+        Value::Object(flags)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::MCPError;
+    use crate::context::RequestContext;
+    use crate::request::MCPRequest;
+    use crate::server::{SystemMCPServer, ToolHandler};
+    use crate::tools::{Tool, ToolInputSchema, ToolResponse};
+    use async_trait::async_trait;
+    use serde_json::json;
+
+    #[test]
+    fn test_gated_tools_follow_their_flag() {
+        let flags = FeatureFlags::new();
+        flags.gate_tool("search2", "beta_search");
+        assert!(!flags.tool_enabled("search2"));
+        assert!(flags.tool_enabled("search"));
+
+        flags.clone().enable("beta_search");
+        assert!(flags.tool_enabled("search2"));
+        assert_eq!(flags.to_json(), json!({ "beta_search": true }));
+    }
+
+    struct Search;
+
+    #[async_trait]
+    impl ToolHandler for Search {
+        async fn call_tool(&self, _name: &str, _args: &Value, ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+            Ok(ToolResponse::new(format!("beta={}", ctx.flag("beta_search")), false))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_flags_reach_clients_and_handlers() {
+        let tool = |name: &str| Tool {
+            name: name.into(),
+            description: String::new(),
+            input_schema: ToolInputSchema { schema_type: "object".into(), properties: Default::default(), required: vec![] },
+            annotations: None,
+            meta: None,
+        };
+        let flags = FeatureFlags::new();
+        let server = SystemMCPServer::<Search>::builder()
+            .relaxed_lifecycle()
+            .with_tools(vec![tool("search"), tool("search2")])
+            .feature_flags(flags.clone())
+            .flagged_tool("search2", "beta_search")
+            .build(Search);
+        let request = |method: &str, params: Value| {
+            let server = &server;
+            let req = MCPRequest::new(Some(json!(1)), method, Some(params));
+            async move { serde_json::to_value(server.handle(req).await.unwrap()).unwrap() }
+        };
+
+        let init = request("initialize", json!({})).await;
+        assert_eq!(init["result"]["capabilities"]["experimental"]["featureFlags"], json!({ "beta_search": false }));
+        assert_eq!(request("tools/list", json!({})).await["result"]["tools"].as_array().unwrap().len(), 1);
+        assert!(request("tools/call", json!({ "name": "search2" })).await.get("error").is_some());
+
+        flags.enable("beta_search");
+        assert_eq!(request("tools/list", json!({})).await["result"]["tools"].as_array().unwrap().len(), 2);
+        let call = request("tools/call", json!({ "name": "search2" })).await;
+        assert_eq!(call["result"]["content"][0]["text"], "beta=true");
+    }
+}
@@ -0,0 +1,216 @@
+//! Garbage collection of transient resources.
+//!
+//! Deduplicated `cas://` contents, spilled `result://` outputs, results
+//! waiting for `next_page` and `call://` logs live only in memory and would
+//! otherwise pile up for the lifetime of the process. With
+//! [`ServerBuilder::resource_gc`] the server collects them at most once per
+//! [`GcPolicy::interval`], after tool calls:
+//!
+//! - `cas://` entries and spilled outputs nobody has used for the TTL
+//!   expire, as do paged results not continued within it;
+//! - past the size cap, the least recently used `cas://` entries go first,
+//!   entries with links some session has not read yet last;
+//! - finished call logs past their own TTL are dropped.
+//!
+//! With [`GcPolicy::operator_token`] operators can force a collection with
+//! the `x-mcp/cleanup` method, passing the token as `params.token`; it
+//! answers with what was freed and the running totals. Without a token the
+//! method does not exist.
+//!
+//! [`ServerBuilder::resource_gc`]: crate::server::ServerBuilder::resource_gc
+
+use crate::error::MCPError;
+use crate::guards;
+use serde::Serialize;
+use serde_json::Value;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+/// Maintenance method running a collection on demand
+pub const CLEANUP_METHOD: &str = "x-mcp/cleanup";
+
+#[derive(Debug, Clone)]
+pub struct GcPolicy {
+    pub(crate) ttl: Option<Duration>,
+    pub(crate) max_bytes: Option<usize>,
+    pub(crate) interval: Duration,
+    operator_token: Option<String>,
+}
+
+impl Default for GcPolicy {
+    fn default() -> Self {
+        GcPolicy { ttl: None, max_bytes: None, interval: Duration::from_secs(30), operator_token: None }
+    }
+}
+
+impl GcPolicy {
+    /// Collect every 30 seconds, with neither TTL nor size cap
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Expire unreferenced entries unused for `ttl`
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Keep stored contents under `bytes`
+    pub fn max_bytes(mut self, bytes: usize) -> Self {
+        self.max_bytes = Some(bytes);
+        self
+    }
+
+    /// Minimum time between automatic collections
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Serve `x-mcp/cleanup` to callers presenting `token`
+    pub fn operator_token(mut self, token: impl Into<String>) -> Self {
+        self.operator_token = Some(token.into());
+        self
+    }
+}
+
+/// What one collection freed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GcReport {
+    pub removed: usize,
+    pub freed_bytes: usize,
+}
+
+impl GcReport {
+    pub fn merge(self, other: GcReport) -> GcReport {
+        GcReport { removed: self.removed + other.removed, freed_bytes: self.freed_bytes + other.freed_bytes }
+    }
+}
+
+/// Totals since the server started
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GcMetrics {
+    pub runs: u64,
+    pub removed: u64,
+    pub freed_bytes: u64,
+}
+
+#[derive(Debug)]
+pub struct ResourceGc {
+    policy: GcPolicy,
+    last_run: Mutex<Instant>,
+    runs: AtomicU64,
+    removed: AtomicU64,
+    freed_bytes: AtomicU64,
+}
+
+impl ResourceGc {
+    pub fn new(policy: GcPolicy) -> Self {
+        ResourceGc {
+            policy,
+            last_run: Mutex::new(Instant::now()),
+            runs: AtomicU64::new(0),
+            removed: AtomicU64::new(0),
+            freed_bytes: AtomicU64::new(0),
+        }
+    }
+
+    pub fn policy(&self) -> &GcPolicy {
+        &self.policy
+    }
+
+    /// Whether operators may run collections with `x-mcp/cleanup`
+    pub fn serves_cleanup(&self) -> bool {
+        self.policy.operator_token.is_some()
+    }
+
+    /// Check the operator token in the params of `x-mcp/cleanup`
+    pub fn authorize(&self, params: Option<&Value>) -> Result<(), MCPError> {
+        let Some(expected) = &self.policy.operator_token else {
+            return Err(MCPError::MethodNotFound(CLEANUP_METHOD.into()));
+        };
+        match params.and_then(|p| p.get("token")).and_then(Value::as_str) {
+            Some(token) if guards::constant_time_eq(token.as_bytes(), expected.as_bytes()) => Ok(()),
+            Some(_) => Err(MCPError::Unauthorized("invalid operator token".into())),
+            None => Err(MCPError::Unauthorized("missing params.token".into())),
+        }
+    }
+
+    /// Whether an automatic collection is due; claims it if so
+    pub fn due(&self) -> bool {
+        let mut last_run = self.last_run.lock().unwrap();
+        if last_run.elapsed() < self.policy.interval {
+            return false;
+        }
+        *last_run = Instant::now();
+        true
+    }
+
+    pub fn record(&self, report: GcReport) {
+        self.runs.fetch_add(1, Ordering::Relaxed);
+        self.removed.fetch_add(report.removed as u64, Ordering::Relaxed);
+        self.freed_bytes.fetch_add(report.freed_bytes as u64, Ordering::Relaxed);
+        if report.removed > 0 {
+            eprintln!("[GC] Removed {} transient resources ({} bytes)", report.removed, report.freed_bytes);
+        }
+    }
+
+    pub fn metrics(&self) -> GcMetrics {
+        GcMetrics {
+            runs: self.runs.load(Ordering::Relaxed),
+            removed: self.removed.load(Ordering::Relaxed),
+            freed_bytes: self.freed_bytes.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::RequestContext;
+    use crate::error::MCPError;
+    use crate::server::{SystemMCPServer, ToolHandler};
+    use crate::testing::fixtures;
+    use crate::tools::ToolResponse;
+    use serde_json::Value;
+
+    struct Outputs;
+
+    #[async_trait::async_trait]
+    impl ToolHandler for Outputs {
+        async fn call_tool(&self, name: &str, _args: &Value, _ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+            Ok(ToolResponse::new(format!("{} output, long enough to store", name), false))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_method() {
+        let output = "a output, long enough to store".len();
+        let policy = GcPolicy::new().max_bytes(output).interval(Duration::from_secs(3600));
+        let server = SystemMCPServer::<Outputs>::builder()
+            .relaxed_lifecycle()
+            .dedupe_content(8)
+            .resource_gc(policy.clone().operator_token("ops"))
+            .build(Outputs);
+        for tool in ["a", "b", "b"] {
+            server.handle(fixtures::call_tool(tool).build()).await;
+        }
+        let cleanup = |token: &str| fixtures::request(CLEANUP_METHOD).param("token", token).build();
+        assert_eq!(server.handle(cleanup("guess")).await.unwrap().error.unwrap().code, -32001);
+
+        // Over the cap; "b" has an unread link, so only "a" goes
+        let cleanup = server.handle(cleanup("ops")).await.unwrap().result.unwrap();
+        assert_eq!(cleanup["removed"], 1);
+        assert_eq!(cleanup["freedBytes"], output);
+        assert_eq!(cleanup["cas"]["entries"], 1);
+        assert_eq!(cleanup["metrics"]["runs"], 1);
+
+        // Without an operator token there is no such method
+        let server = SystemMCPServer::<Outputs>::builder().relaxed_lifecycle().resource_gc(policy).build(Outputs);
+        let request = fixtures::request(CLEANUP_METHOD).param("token", "ops").build();
+        assert_eq!(server.handle(request).await.unwrap().error.unwrap().code, -32601);
+    }
+}
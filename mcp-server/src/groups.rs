@@ -0,0 +1,291 @@
+//! Tools from several handlers served under namespace prefixes.
+//!
+//! A server aggregating many tools (a filesystem, a git client, a search
+//! index) is easier to browse and to control when they come in groups.
+//! [`ToolGroups`] is a [`ToolHandler`] made of other handlers, each mounted
+//! under a namespace: a `read` tool of the group `fs` is listed as `fs/read`,
+//! and a call of `fs/read` reaches the `fs` handler as `read`, so handlers
+//! written for a server of their own need no changes. Groups can be switched
+//! off and on at runtime; a disabled group's tools are neither listed nor
+//! callable. Clones share the same groups, so keep one to toggle them and
+//! call [`SystemMCPServer::notify_tools_changed`] after a change. The groups'
+//! `tools/list` can be cached when every mounted handler reports a
+//! [`ToolHandler::tools_generation`]; toggling a group advances it.
+//!
+//! Only tools are grouped; prompts and resources of the mounted handlers are
+//! not served.
+//!
+//! [`SystemMCPServer::notify_tools_changed`]: crate::server::SystemMCPServer::notify_tools_changed
+
+use crate::context::RequestContext;
+use crate::error::MCPError;
+use crate::server::ToolHandler;
+use crate::tools::{StreamChunk, Tool, ToolResponse};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use tokio_stream::Stream;
+
+/// Between a group's namespace and its tools' names
+pub const SEPARATOR: char = '/';
+
+#[derive(Clone, Default)]
+pub struct ToolGroups {
+    groups: Arc<RwLock<BTreeMap<String, Group>>>,
+    // Groups toggled so far
+    toggles: Arc<AtomicU64>,
+}
+
+struct Group {
+    handler: Arc<dyn ToolHandler>,
+    // Listed without asking the handler, like the builder's tools
+    tools: Vec<Tool>,
+    enabled: bool,
+}
+
+impl std::fmt::Debug for ToolGroups {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolGroups").field("namespaces", &self.namespaces()).finish()
+    }
+}
+
+impl ToolGroups {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve `handler`'s tools under `namespace`: `tools` and whatever its
+    /// `list_tools` returns. Panics if `namespace` contains [`SEPARATOR`].
+    pub fn group(self, namespace: &str, handler: impl ToolHandler + 'static, tools: Vec<Tool>) -> Self {
+        assert!(!namespace.contains(SEPARATOR), "tool group namespace {:?} contains {:?}", namespace, SEPARATOR);
+        let group = Group { handler: Arc::new(handler), tools, enabled: true };
+        self.groups.write().unwrap().insert(namespace.into(), group);
+        self
+    }
+
+    /// Enable or disable a group; returns whether `namespace` exists
+    pub fn set_enabled(&self, namespace: &str, enabled: bool) -> bool {
+        let mut groups = self.groups.write().unwrap();
+        let Some(group) = groups.get_mut(namespace) else { return false };
+        if group.enabled != enabled {
+            group.enabled = enabled;
+            self.toggles.fetch_add(1, Ordering::AcqRel);
+        }
+        true
+    }
+
+    pub fn enabled(&self, namespace: &str) -> bool {
+        self.groups.read().unwrap().get(namespace).is_some_and(|group| group.enabled)
+    }
+
+    pub fn namespaces(&self) -> Vec<String> {
+        self.groups.read().unwrap().keys().cloned().collect()
+    }
+
+    /// Every group's handler, enabled or not
+    fn handlers(&self) -> Vec<Arc<dyn ToolHandler>> {
+        self.groups.read().unwrap().values().map(|group| group.handler.clone()).collect()
+    }
+
+    /// The enabled group's handler and the tool name within the group
+    fn route<'a>(&self, name: &'a str) -> Option<(Arc<dyn ToolHandler>, &'a str)> {
+        let (namespace, tool) = name.split_once(SEPARATOR)?;
+        let groups = self.groups.read().unwrap();
+        let group = groups.get(namespace).filter(|group| group.enabled)?;
+        Some((group.handler.clone(), tool))
+    }
+}
+
+fn qualified(namespace: &str, mut tool: Tool) -> Tool {
+    tool.name = format!("{}{}{}", namespace, SEPARATOR, tool.name);
+    tool
+}
+
+#[async_trait]
+impl ToolHandler for ToolGroups {
+    async fn call_tool(&self, name: &str, args: &Value, ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+        let (handler, tool) = self.route(name).ok_or_else(|| MCPError::UnknownTool(name.into()))?;
+        match handler.call_tool(tool, args, ctx).await {
+            // Report the name the client called
+            Err(MCPError::UnknownTool(_)) => Err(MCPError::UnknownTool(name.into())),
+            result => result,
+        }
+    }
+
+    async fn list_tools(&self, ctx: &RequestContext) -> Result<Vec<Tool>, MCPError> {
+        let enabled: Vec<(String, Arc<dyn ToolHandler>, Vec<Tool>)> = self.groups.read().unwrap().iter()
+            .filter(|(_, group)| group.enabled)
+            .map(|(namespace, group)| (namespace.clone(), group.handler.clone(), group.tools.clone()))
+            .collect();
+        let mut listed = Vec::new();
+        for (namespace, handler, mut tools) in enabled {
+            tools.extend(handler.list_tools(ctx).await?);
+            listed.extend(tools.into_iter().map(|tool| qualified(&namespace, tool)));
+        }
+        Ok(listed)
+    }
+
+    fn tools_generation(&self) -> Option<u64> {
+        let groups = self.groups.read().unwrap();
+        groups.values().try_fold(self.toggles.load(Ordering::Acquire), |sum, group| {
+            Some(sum.wrapping_add(group.handler.tools_generation()?))
+        })
+    }
+
+    async fn call_tool_stream(&self, name: &str, args: &Value, ctx: &RequestContext) -> Result<Pin<Box<dyn Stream<Item = StreamChunk> + Send>>, MCPError> {
+        let (handler, tool) = self.route(name).ok_or_else(|| MCPError::UnknownTool(name.into()))?;
+        handler.call_tool_stream(tool, args, ctx).await
+    }
+
+    fn supports_rollback(&self, name: &str) -> bool {
+        self.route(name).is_some_and(|(handler, tool)| handler.supports_rollback(tool))
+    }
+
+    async fn rollback_tool(&self, name: &str, args: &Value, ctx: &RequestContext) -> Result<(), MCPError> {
+        let (handler, tool) = self.route(name).ok_or_else(|| MCPError::UnknownTool(name.into()))?;
+        handler.rollback_tool(tool, args, ctx).await
+    }
+
+    async fn on_tool_called(&self, name: &str) {
+        if let Some((handler, tool)) = self.route(name) {
+            handler.on_tool_called(tool).await;
+        }
+    }
+
+    async fn on_tool_completed(&self, name: &str, success: bool) {
+        if let Some((handler, tool)) = self.route(name) {
+            handler.on_tool_completed(tool, success).await;
+        }
+    }
+
+    // Requests and sessions are not tied to one group, so every group hears of them
+
+    async fn on_request_cancelled(&self, request_id: &str, reason: Option<&str>) {
+        for handler in self.handlers() {
+            handler.on_request_cancelled(request_id, reason).await;
+        }
+    }
+
+    async fn on_initialized(&self, session_id: &str) {
+        for handler in self.handlers() {
+            handler.on_initialized(session_id).await;
+        }
+    }
+
+    async fn on_reinitialize(&self, session_id: &str) {
+        for handler in self.handlers() {
+            handler.on_reinitialize(session_id).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::SystemMCPServer;
+    use crate::testing::fixtures;
+    use crate::testing::mock::{MockToolHandler, Reply};
+    use crate::tools::ToolInputSchema;
+    use std::sync::Mutex;
+
+    fn tool(name: &str) -> Tool {
+        Tool::new(name, "test tool", ToolInputSchema { schema_type: "object".into(), properties: Default::default(), required: vec![] })
+    }
+
+    #[tokio::test]
+    async fn test_groups_are_prefixed_and_toggled() {
+        let groups = ToolGroups::new()
+            .group("fs", MockToolHandler::new().tool("read", Reply::text("contents")), vec![tool("read"), tool("write")])
+            .group("git", MockToolHandler::new().list_tools(Reply::value(vec![tool("log")])), vec![]);
+        let server = SystemMCPServer::<ToolGroups>::builder().relaxed_lifecycle().build(groups.clone());
+
+        let listed = server.handle(fixtures::request("tools/list").build()).await.unwrap().result.unwrap();
+        let names: Vec<&str> = listed["tools"].as_array().unwrap().iter().filter_map(|tool| tool["name"].as_str()).collect();
+        assert_eq!(names, ["fs/read", "fs/write", "git/log"]);
+        let read = server.handle(fixtures::call_tool("fs/read").build()).await.unwrap();
+        assert_eq!(read.result.unwrap()["content"][0]["text"], "contents");
+        let unknown = server.handle(fixtures::call_tool("fs/delete").build()).await.unwrap().error.unwrap();
+        assert!(unknown.message.contains("fs/delete"));
+
+        assert!(groups.set_enabled("fs", false));
+        assert!(!groups.set_enabled("net", false));
+        let listed = server.handle(fixtures::request("tools/list").build()).await.unwrap().result.unwrap();
+        assert_eq!(listed["tools"].as_array().unwrap().len(), 1);
+        assert!(server.handle(fixtures::call_tool("fs/read").build()).await.unwrap().error.is_some());
+        assert!(server.handle(fixtures::call_tool("read").build()).await.unwrap().error.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_toggling_invalidates_cached_lists() {
+        let groups = ToolGroups::new()
+            .group("fs", MockToolHandler::new(), vec![tool("read")])
+            .group("git", MockToolHandler::new(), vec![tool("log")]);
+        let server = SystemMCPServer::<ToolGroups>::builder().relaxed_lifecycle().cache_tool_list().build(groups.clone());
+        let count = || async {
+            let listed = server.handle(fixtures::request("tools/list").build()).await.unwrap().result.unwrap();
+            listed["tools"].as_array().unwrap().len()
+        };
+
+        assert_eq!(count().await, 2);
+        groups.set_enabled("git", false);
+        assert_eq!(count().await, 1);
+        groups.set_enabled("git", true);
+        assert_eq!(count().await, 2);
+    }
+
+    /// Records the session and request hooks it hears, tagged with its group
+    struct Hooks(&'static str, Arc<Mutex<Vec<String>>>);
+
+    #[async_trait]
+    impl ToolHandler for Hooks {
+        async fn call_tool(&self, _name: &str, _args: &Value, _ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+            std::future::pending().await
+        }
+
+        async fn on_request_cancelled(&self, request_id: &str, _reason: Option<&str>) {
+            self.1.lock().unwrap().push(format!("{} cancelled {}", self.0, request_id));
+        }
+
+        async fn on_initialized(&self, session_id: &str) {
+            self.1.lock().unwrap().push(format!("{} initialized {}", self.0, session_id));
+        }
+
+        async fn on_reinitialize(&self, session_id: &str) {
+            self.1.lock().unwrap().push(format!("{} reinitialize {}", self.0, session_id));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_hooks_reach_every_group() {
+        let heard = Arc::new(Mutex::new(Vec::new()));
+        let groups = ToolGroups::new()
+            .group("fs", Hooks("fs", heard.clone()), vec![])
+            .group("git", Hooks("git", heard.clone()), vec![]);
+        groups.set_enabled("git", false);
+        let server = Arc::new(SystemMCPServer::<ToolGroups>::builder().build(groups));
+
+        server.handle(fixtures::initialize().build()).await;
+        server.handle(fixtures::notification("notifications/initialized").build()).await;
+        server.handle(fixtures::initialize().build()).await;
+        server.handle(fixtures::notification("notifications/initialized").build()).await;
+        let call = tokio::spawn({
+            let server = server.clone();
+            async move { server.handle(fixtures::call_tool("fs/wait").id("r-1").build()).await }
+        });
+        while server.in_flight() == 0 {
+            tokio::task::yield_now().await;
+        }
+        server.handle(fixtures::cancelled("r-1", None)).await;
+        call.await.unwrap();
+
+        let heard = heard.lock().unwrap().clone();
+        for group in ["fs", "git"] {
+            for hook in ["initialized default", "reinitialize default", "cancelled r-1"] {
+                assert!(heard.contains(&format!("{} {}", group, hook)), "{} missed {}", group, hook);
+            }
+        }
+    }
+}
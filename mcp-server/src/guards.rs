@@ -0,0 +1,319 @@
+//! Ready-made middleware for locking a server down: token auth, strict
+//! envelope parsing, rate limiting and an audit trail.
+//!
+//! [`StrictParsing`] doubles as the check behind [`ServerBuilder::strict`],
+//! which applies it ahead of every middleware layer. Its errors locate the
+//! offending member in `data`, so a client under development learns what to
+//! fix rather than just that something was wrong.
+//!
+//! [`ServerBuilder::strict`]: crate::server::ServerBuilder::strict
+
+use crate::error::MCPError;
+use crate::middleware::{IncomingRequest, Middleware};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use std::sync::Mutex;
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Require `params._meta.authToken` to match a shared secret
+#[derive(Debug, Clone)]
+pub struct RequireToken {
+    token: String,
+}
+
+impl RequireToken {
+    pub fn new(token: impl Into<String>) -> Self {
+        RequireToken { token: token.into() }
+    }
+}
+
+/// Compare without short-circuiting on the first differing byte
+pub(crate) fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[async_trait]
+impl Middleware for RequireToken {
+    async fn on_request(&self, request: &mut IncomingRequest) -> Result<(), MCPError> {
+        let token = request.request.params.as_ref()
+            .and_then(|p| p.get("_meta")?.get("authToken"))
+            .and_then(Value::as_str);
+        match token {
+            Some(token) if constant_time_eq(token.as_bytes(), self.token.as_bytes()) => Ok(()),
+            Some(_) => Err(MCPError::Unauthorized("invalid auth token".into())),
+            None => Err(MCPError::Unauthorized("missing _meta.authToken".into())),
+        }
+    }
+}
+
+/// Reject envelopes with unknown top-level members, a missing or wrong
+/// `jsonrpc` version, an id that is not a string or integer, non-object
+/// `params`, or params of the wrong shape for a standard method
+#[derive(Debug, Clone, Default)]
+pub struct StrictParsing;
+
+/// JSON type a params member must have
+#[derive(Debug, Clone, Copy)]
+enum Shape {
+    String,
+    Object,
+    Id,
+}
+
+impl Shape {
+    fn name(self) -> &'static str {
+        match self {
+            Shape::String => "string",
+            Shape::Object => "object",
+            Shape::Id => "string or integer",
+        }
+    }
+
+    fn accepts(self, value: &Value) -> bool {
+        match self {
+            Shape::String => value.is_string(),
+            Shape::Object => value.is_object(),
+            Shape::Id => is_valid_id(value),
+        }
+    }
+}
+
+/// Members of the standard methods' params: name, shape, required
+fn params_shape(method: &str) -> &'static [(&'static str, Shape, bool)] {
+    match method {
+        "initialize" => &[("protocolVersion", Shape::String, true), ("capabilities", Shape::Object, true), ("clientInfo", Shape::Object, true)],
+        "tools/call" | "prompts/get" => &[("name", Shape::String, true), ("arguments", Shape::Object, false)],
+        "resources/read" | "resources/subscribe" | "resources/unsubscribe" => &[("uri", Shape::String, true)],
+        "tools/list" | "prompts/list" | "resources/list" | "resources/templates/list" => &[("cursor", Shape::String, false)],
+        "completion/complete" => &[("ref", Shape::Object, true), ("argument", Shape::Object, true)],
+        "logging/setLevel" => &[("level", Shape::String, true)],
+        "notifications/cancelled" => &[("requestId", Shape::Id, true), ("reason", Shape::String, false)],
+        _ => &[],
+    }
+}
+
+/// JSON-RPC 2.0 ids are strings or integers; null only in error responses
+pub(crate) fn is_valid_id(id: &Value) -> bool {
+    id.is_string() || id.is_i64() || id.is_u64()
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+fn invalid_request(pointer: &str, message: String, expected: &str, received: Option<&Value>) -> MCPError {
+    MCPError::InvalidRequestAt {
+        pointer: pointer.into(),
+        message,
+        expected: Some(expected.into()),
+        received: received.map(|value| type_name(value).to_string()),
+    }
+}
+
+fn invalid_param(pointer: String, message: String, expected: &str, received: Option<&Value>) -> MCPError {
+    MCPError::InvalidParamAt {
+        pointer,
+        message,
+        expected: Some(expected.into()),
+        received: received.map(|value| type_name(value).to_string()),
+    }
+}
+
+impl StrictParsing {
+    /// Check one request against JSON-RPC 2.0 and the shape of its method's params
+    pub fn check(request: &IncomingRequest) -> Result<(), MCPError> {
+        if let Some(raw) = request.raw() {
+            let envelope: serde_json::Map<String, Value> = serde_json::from_slice(raw)?;
+            if let Some(key) = envelope.keys().find(|k| !matches!(k.as_str(), "jsonrpc" | "id" | "method" | "params")) {
+                return Err(invalid_request(
+                    &format!("/{}", key),
+                    format!("unknown request member {:?}", key),
+                    "one of jsonrpc, id, method, params",
+                    envelope.get(key),
+                ));
+            }
+        }
+
+        let req = &request.request;
+        match req.jsonrpc_version() {
+            Some("2.0") => {}
+            None => return Err(invalid_request("/jsonrpc", "missing jsonrpc member".into(), "\"2.0\"", None)),
+            Some(version) => {
+                return Err(MCPError::InvalidRequestAt {
+                    pointer: "/jsonrpc".into(),
+                    message: format!("unsupported jsonrpc version {:?}", version),
+                    expected: Some("\"2.0\"".into()),
+                    received: Some(format!("{:?}", version)),
+                });
+            }
+        }
+        if let Some(id) = &req.id
+            && !is_valid_id(id)
+        {
+            return Err(invalid_request("/id", format!("id must be a string or integer, not {}", type_name(id)), "string or integer", Some(id)));
+        }
+
+        let Some(params) = &req.params else {
+            return match params_shape(&req.method).iter().find(|(_, _, required)| *required) {
+                Some((name, shape, _)) => Err(invalid_param(format!("/{}", name), format!("{} requires params", req.method), shape.name(), None)),
+                None => Ok(()),
+            };
+        };
+        let Some(members) = params.as_object() else {
+            return Err(invalid_param(String::new(), "params must be an object".into(), "object", Some(params)));
+        };
+        for (name, shape, required) in params_shape(&req.method) {
+            match members.get(*name) {
+                None if *required => {
+                    return Err(invalid_param(format!("/{}", name), format!("missing {}", name), shape.name(), None));
+                }
+                Some(value) if !shape.accepts(value) => {
+                    return Err(invalid_param(
+                        format!("/{}", name),
+                        format!("{} must be a {}, not {}", name, shape.name(), type_name(value)),
+                        shape.name(),
+                        Some(value),
+                    ));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Middleware for StrictParsing {
+    async fn on_request(&self, request: &mut IncomingRequest) -> Result<(), MCPError> {
+        StrictParsing::check(request)
+    }
+}
+
+/// Token bucket over all requests
+#[derive(Debug)]
+pub struct RateLimit {
+    per_second: f64,
+    burst: f64,
+    bucket: Mutex<(f64, Instant)>,
+}
+
+impl RateLimit {
+    /// Allow `per_second` requests on average with bursts of up to `burst`
+    pub fn new(per_second: u32, burst: u32) -> Self {
+        RateLimit {
+            per_second: f64::from(per_second),
+            burst: f64::from(burst.max(1)),
+            bucket: Mutex::new((f64::from(burst.max(1)), Instant::now())),
+        }
+    }
+}
+
+#[async_trait]
+impl Middleware for RateLimit {
+    async fn on_request(&self, _request: &mut IncomingRequest) -> Result<(), MCPError> {
+        let mut bucket = self.bucket.lock().unwrap();
+        let (tokens, last) = &mut *bucket;
+        let now = Instant::now();
+        *tokens = (*tokens + now.duration_since(*last).as_secs_f64() * self.per_second).min(self.burst);
+        *last = now;
+
+        if *tokens < 1.0 {
+            return Err(MCPError::RateLimited);
+        }
+        *tokens -= 1.0;
+        Ok(())
+    }
+}
+
+/// Write one `[AUDIT]` JSON line to stderr per request, before any other check
+#[derive(Debug, Clone, Default)]
+pub struct AuditLog;
+
+#[async_trait]
+impl Middleware for AuditLog {
+    async fn on_request(&self, request: &mut IncomingRequest) -> Result<(), MCPError> {
+        let req = &request.request;
+        let params = req.params.as_ref();
+        let record = json!({
+            "timestampMs": SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0),
+            "id": req.id,
+            "method": req.method,
+            "tool": params.and_then(|p| p.get("name")),
+            "uri": params.and_then(|p| p.get("uri")),
+            "correlationId": params.and_then(|p| p.get("_meta")?.get("correlationId")),
+        });
+        eprintln!("[AUDIT] {}", record);
+        Ok(())
+    }
+}
+
+/// Whether a `tools/list` entry may modify its environment. Follows the
+/// spec defaults: tools are destructive unless annotated read-only or
+/// explicitly non-destructive.
+pub fn is_destructive(tool: &Value) -> bool {
+    let hint = |name: &str| tool.get("annotations").and_then(|a| a.get(name)).and_then(Value::as_bool);
+    hint("readOnlyHint") != Some(true) && hint("destructiveHint") != Some(false)
+}
+
+/// Whether a `tools/list` entry is annotated `readOnlyHint: true`
+pub fn is_read_only(tool: &Value) -> bool {
+    tool.get("annotations").and_then(|a| a.get("readOnlyHint")).and_then(Value::as_bool) == Some(true)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::ServerBuilder;
+    use crate::testing::fixtures;
+    use crate::testing::mock::MockToolHandler;
+
+    fn incoming(raw: &str) -> IncomingRequest {
+        IncomingRequest::parse(raw.as_bytes()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_guards() {
+        let auth = RequireToken::new("s3cret");
+        let mut ok = incoming(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list","params":{"_meta":{"authToken":"s3cret"}}}"#);
+        let mut bad = incoming(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list","params":{"_meta":{"authToken":"guess"}}}"#);
+        assert!(auth.on_request(&mut ok).await.is_ok());
+        assert!(matches!(auth.on_request(&mut bad).await, Err(MCPError::Unauthorized(_))));
+
+        let mut extra = incoming(r#"{"jsonrpc":"2.0","id":1,"method":"tools/list","extra":true}"#);
+        assert!(StrictParsing.on_request(&mut ok).await.is_ok());
+        assert!(StrictParsing.on_request(&mut extra).await.is_err());
+
+        let limit = RateLimit::new(0, 2);
+        assert!(limit.on_request(&mut ok).await.is_ok());
+        assert!(limit.on_request(&mut ok).await.is_ok());
+        assert!(matches!(limit.on_request(&mut ok).await, Err(MCPError::RateLimited)));
+    }
+
+    #[tokio::test]
+    async fn test_secure_throttles_token_guesses() {
+        let server = ServerBuilder::secure("s3cret").build(MockToolHandler::new());
+        let guess = || fixtures::request("ping").meta("authToken", "guess").build();
+        let mut codes = Vec::new();
+        for _ in 0..60 {
+            codes.push(server.handle(guess()).await.unwrap().error.unwrap().code);
+        }
+        assert_eq!(codes[0], -32001);
+        assert_eq!(codes.last(), Some(&-32029));
+    }
+
+    #[test]
+    fn test_destructive_defaults() {
+        assert!(is_destructive(&json!({ "name": "bash" })));
+        assert!(!is_destructive(&json!({ "name": "ls", "annotations": { "readOnlyHint": true } })));
+        assert!(!is_destructive(&json!({ "name": "mkdir", "annotations": { "destructiveHint": false } })));
+        assert!(!is_read_only(&json!({ "name": "mkdir", "annotations": { "destructiveHint": false } })));
+    }
+}
@@ -0,0 +1,137 @@
+//! Server clock resource for detecting stalls and clock drift.
+//!
+//! With [`ServerBuilder::heartbeat`] the server lists `mcp://server/heartbeat`
+//! and, while a runner is serving, bumps it every interval and sends
+//! `notifications/resources/updated` to subscribers. Reading it returns the
+//! beat count with monotonic and wall-clock timestamps, so a client can tell
+//! a stalled server (no updates) from a skewed clock (wall time off from its own).
+//!
+//! [`ServerBuilder::heartbeat`]: crate::server::ServerBuilder::heartbeat
+
+use crate::tools::{Resource, ResourceContent};
+use serde_json::json;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// URI of the heartbeat resource
+pub const HEARTBEAT_URI: &str = "mcp://server/heartbeat";
+
+#[derive(Debug)]
+pub struct Heartbeat {
+    interval: Duration,
+    started: Instant,
+    beats: AtomicU64,
+    running: AtomicBool,
+}
+
+impl Heartbeat {
+    /// Beat every `interval`
+    pub fn new(interval: Duration) -> Self {
+        Heartbeat {
+            interval: interval.max(Duration::from_millis(1)),
+            started: Instant::now(),
+            beats: AtomicU64::new(0),
+            running: AtomicBool::new(false),
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    /// Listing entry for the heartbeat resource
+    pub fn resource() -> Resource {
+        Resource::new(HEARTBEAT_URI, "heartbeat")
+            .with_description("Server clock, updated periodically; subscribe to detect stalls and clock drift")
+            .with_mime_type("application/json")
+    }
+
+    /// Record a beat; returns the new count
+    pub fn beat(&self) -> u64 {
+        self.beats.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Current state as resource contents
+    pub fn read(&self) -> ResourceContent {
+        let wall_clock = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let state = json!({
+            "sequence": self.beats.load(Ordering::Relaxed),
+            "intervalMs": self.interval.as_millis() as u64,
+            "monotonicMs": self.started.elapsed().as_millis() as u64,
+            "wallClockMs": wall_clock.as_millis() as u64,
+        });
+        ResourceContent {
+            uri: HEARTBEAT_URI.into(),
+            mime_type: "application/json".into(),
+            text: state.to_string(),
+            blob: None,
+        }
+    }
+
+    /// Claim the ticker; false if one is already running
+    pub(crate) fn start(&self) -> bool {
+        !self.running.swap(true, Ordering::AcqRel)
+    }
+
+    pub(crate) fn stop(&self) {
+        self.running.store(false, Ordering::Release);
+    }
+}
+
+/// Running ticker; stops when dropped
+#[derive(Debug)]
+pub struct HeartbeatTicker {
+    pub(crate) task: tokio::task::JoinHandle<()>,
+    pub(crate) heartbeat: Arc<Heartbeat>,
+}
+
+impl Drop for HeartbeatTicker {
+    fn drop(&mut self) {
+        self.task.abort();
+        self.heartbeat.stop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::MCPError;
+    use crate::context::RequestContext;
+    use crate::server::{SystemMCPServer, ToolHandler};
+    use crate::testing::fixtures;
+    use crate::tools::ToolResponse;
+    use serde_json::Value;
+
+    struct Noop;
+
+    #[async_trait::async_trait]
+    impl ToolHandler for Noop {
+        async fn call_tool(&self, _name: &str, _args: &Value, _ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+            Ok(ToolResponse::new(String::new(), false))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_get_beats() {
+        let mut server = SystemMCPServer::<Noop>::builder()
+            .relaxed_lifecycle()
+            .heartbeat(Duration::from_millis(10))
+            .build(Noop);
+        let mut notifications = server.take_notification_receiver().unwrap();
+        let listed = server.handle(fixtures::request("resources/list").build()).await.unwrap().result.unwrap();
+        assert_eq!(listed["resources"][0]["uri"], HEARTBEAT_URI);
+
+        server.handle(fixtures::request("resources/subscribe").param("uri", HEARTBEAT_URI).build()).await;
+        let _ticker = server.start_heartbeat().unwrap();
+        let update = tokio::time::timeout(Duration::from_secs(1), notifications.recv()).await.unwrap().unwrap();
+        assert_eq!(update.to_json_rpc()["params"]["uri"], HEARTBEAT_URI);
+
+        let read = fixtures::request("resources/read").param("uri", HEARTBEAT_URI).build();
+        let contents = server.handle(read).await.unwrap().result.unwrap();
+        let state: Value = serde_json::from_str(contents["text"].as_str().unwrap()).unwrap();
+        assert!(state["sequence"].as_u64().unwrap() >= 1);
+        assert!(state["wallClockMs"].as_u64().unwrap() > 0);
+        assert!(server.start_heartbeat().is_none());
+    }
+}
@@ -0,0 +1,720 @@
+//! Streamable HTTP transport (feature `http`).
+//!
+//! One endpoint (`/mcp` by default) serves the whole protocol:
+//!
+//! - `POST` carries one JSON-RPC message. Requests are answered with an
+//!   `application/json` body, notifications with `202 Accepted`. An
+//!   `initialize` without an `Mcp-Session-Id` header opens a session and
+//!   returns its id in that header; every other message must carry it.
+//! - `GET` opens a `text/event-stream` of the session's notifications. Each
+//!   event has an `id:`; a client that reconnects with `Last-Event-ID` first
+//!   receives the buffered events it missed, so a flaky network does not lose
+//!   progress updates. Without the header the stream starts at the next event.
+//! - `DELETE` ends the session.
+//!
+//! Browsers let any page talk to a server on localhost, and DNS rebinding
+//! gets around the same-origin policy, so a request whose `Origin` is not in
+//! [`HttpServer::allowed_origins`] (loopback origins by default) is refused
+//! with `403`, as the specification requires. Requests without the header do
+//! not come from a browser and are let through.
+//!
+//! Sessions cost a server instance each: at most
+//! [`HttpServer::max_sessions`] are open at once, and one that sees no request
+//! and has no open stream for [`HttpServer::session_idle_timeout`] is closed.
+//! A request has [`HttpServer::read_timeout`] to arrive in full, so clients
+//! that trickle bytes cannot hold connections open.
+//!
+//! With [`HttpServer::resume_tokens`] every response also carries an
+//! encrypted `Mcp-Resume-Token`; presenting it with an unknown session id
+//! rebuilds the session, so any replica sharing the key can serve it. Each
+//! token replaces the previous one, and `DELETE` revokes them all; see
+//! [`crate::resume`].
+//!
+//! Every session gets its own server instance from `make_server`, as with the
+//! WebSocket transport.
+
+use crate::error::MCPError;
+use crate::middleware::IncomingRequest;
+use crate::resume::{ResumeTokens, RESUME_TOKEN_HEADER};
+use crate::server::{SystemMCPServer, ToolHandler};
+use crate::session::{random_id, DEFAULT_SESSION};
+use crate::transport::ACCEPT_BACKOFF;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, ToSocketAddrs};
+use tokio::sync::Notify;
+
+pub const SESSION_HEADER: &str = "Mcp-Session-Id";
+
+/// Notifications kept per session for `Last-Event-ID` replay
+pub const DEFAULT_EVENT_BUFFER: usize = 1024;
+
+/// Sessions open at once before `initialize` is answered with `503`
+pub const DEFAULT_MAX_SESSIONS: usize = 1024;
+
+/// How long a session without requests or streams is kept
+pub const DEFAULT_SESSION_IDLE_TIMEOUT: Duration = Duration::from_secs(30 * 60);
+
+/// Time a client has to send a whole request
+pub const DEFAULT_READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+const MAX_HEADER_BYTES: usize = 64 * 1024;
+const MAX_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+/// Event id and JSON payload
+pub type Event = (u64, Arc<str>);
+
+/// Outbound events of one session, numbered from 1, with the most recent
+/// `capacity` kept for replay
+#[derive(Debug)]
+pub struct EventBuffer {
+    capacity: usize,
+    events: Mutex<(u64, VecDeque<Event>)>,
+    pushed: Notify,
+}
+
+impl EventBuffer {
+    pub fn new(capacity: usize) -> Self {
+        EventBuffer { capacity: capacity.max(1), events: Mutex::new((0, VecDeque::new())), pushed: Notify::new() }
+    }
+
+    /// Append an event; returns its id
+    pub fn push(&self, data: impl Into<Arc<str>>) -> u64 {
+        let mut guard = self.events.lock().unwrap();
+        let (last_id, events) = &mut *guard;
+        *last_id += 1;
+        if events.len() == self.capacity {
+            events.pop_front();
+        }
+        events.push_back((*last_id, data.into()));
+        let id = *last_id;
+        drop(guard);
+        self.pushed.notify_waiters();
+        id
+    }
+
+    /// Id of the latest event, 0 before the first
+    pub fn last_id(&self) -> u64 {
+        self.events.lock().unwrap().0
+    }
+
+    /// Buffered events after `last_event_id` (all of them for `None`)
+    pub fn since(&self, last_event_id: Option<u64>) -> Vec<Event> {
+        let after = last_event_id.unwrap_or(0);
+        self.events.lock().unwrap().1.iter().filter(|(id, _)| *id > after).cloned().collect()
+    }
+
+    /// Whether events after `last_event_id` were already evicted
+    pub fn lost_since(&self, last_event_id: u64) -> bool {
+        self.events.lock().unwrap().1.front().is_some_and(|(oldest, _)| *oldest > last_event_id + 1)
+    }
+}
+
+struct HttpSession<H: ToolHandler> {
+    server: SystemMCPServer<H>,
+    events: EventBuffer,
+    closed: Notify,
+    last_used: Mutex<Instant>,
+    streams: AtomicUsize,
+}
+
+impl<H: ToolHandler> HttpSession<H> {
+    fn touch(&self) {
+        *self.last_used.lock().unwrap() = Instant::now();
+    }
+
+    fn idle_for(&self, timeout: Duration) -> bool {
+        self.streams.load(Ordering::Acquire) == 0 && self.last_used.lock().unwrap().elapsed() >= timeout
+    }
+}
+
+/// Counts an open event stream against its session's idle expiry
+struct StreamGuard<'a, H: ToolHandler>(&'a HttpSession<H>);
+
+impl<'a, H: ToolHandler> StreamGuard<'a, H> {
+    fn new(session: &'a HttpSession<H>) -> Self {
+        session.streams.fetch_add(1, Ordering::AcqRel);
+        StreamGuard(session)
+    }
+}
+
+impl<H: ToolHandler> Drop for StreamGuard<'_, H> {
+    fn drop(&mut self) {
+        self.0.touch();
+        self.0.streams.fetch_sub(1, Ordering::AcqRel);
+    }
+}
+
+/// Streamable HTTP front end creating one server per session
+pub struct HttpServer<H: ToolHandler, F> {
+    make_server: F,
+    endpoint: String,
+    event_buffer: usize,
+    resume_tokens: Option<ResumeTokens>,
+    allowed_origins: Option<Vec<String>>,
+    max_sessions: usize,
+    session_idle_timeout: Duration,
+    read_timeout: Duration,
+    sessions: Mutex<HashMap<String, Arc<HttpSession<H>>>>,
+}
+
+struct HttpRequest {
+    method: String,
+    path: String,
+    headers: Vec<(String, String)>,
+    body: Vec<u8>,
+}
+
+impl HttpRequest {
+    fn header(&self, name: &str) -> Option<&str> {
+        self.headers.iter().find(|(n, _)| n.eq_ignore_ascii_case(name)).map(|(_, v)| v.as_str())
+    }
+}
+
+fn status_text(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        202 => "Accepted",
+        204 => "No Content",
+        400 => "Bad Request",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        503 => "Service Unavailable",
+        _ => "Error",
+    }
+}
+
+async fn respond(stream: &mut TcpStream, status: u16, headers: &[(&str, &str)], body: &[u8]) -> Result<(), MCPError> {
+    let mut head = format!("HTTP/1.1 {} {}\r\nContent-Length: {}\r\nConnection: close\r\n", status, status_text(status), body.len());
+    for (name, value) in headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(body).await?;
+    stream.flush().await?;
+    Ok(())
+}
+
+async fn read_request(stream: &mut TcpStream) -> Result<Result<HttpRequest, u16>, MCPError> {
+    let mut buf = Vec::with_capacity(4096);
+    let (head_len, method, path, headers) = loop {
+        let mut chunk = [0; 4096];
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(MCPError::IoError(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+        buf.extend_from_slice(&chunk[..n]);
+
+        let mut parsed = [httparse::EMPTY_HEADER; 64];
+        let mut request = httparse::Request::new(&mut parsed);
+        match request.parse(&buf) {
+            Ok(httparse::Status::Complete(len)) => {
+                let headers = request.headers.iter()
+                    .map(|h| (h.name.to_string(), String::from_utf8_lossy(h.value).into_owned()))
+                    .collect::<Vec<_>>();
+                break (len, request.method.unwrap_or_default().to_string(), request.path.unwrap_or_default().to_string(), headers);
+            }
+            Ok(httparse::Status::Partial) if buf.len() < MAX_HEADER_BYTES => continue,
+            Ok(httparse::Status::Partial) => return Ok(Err(413)),
+            Err(_) => return Ok(Err(400)),
+        }
+    };
+
+    let mut request = HttpRequest { method, path, headers, body: buf.split_off(head_len) };
+    let length = match request.header("content-length").map(str::parse::<usize>) {
+        None => 0,
+        Some(Ok(length)) if length <= MAX_BODY_BYTES => length,
+        Some(Ok(_)) => return Ok(Err(413)),
+        Some(Err(_)) => return Ok(Err(400)),
+    };
+    if request.body.len() < length {
+        let start = request.body.len();
+        request.body.resize(length, 0);
+        stream.read_exact(&mut request.body[start..]).await?;
+    }
+    request.body.truncate(length);
+    Ok(Ok(request))
+}
+
+/// Whether `origin` (`scheme://host[:port]`) names this machine
+fn is_loopback_origin(origin: &str) -> bool {
+    let Some((scheme, authority)) = origin.split_once("://") else { return false };
+    let host = match authority.strip_prefix('[') {
+        Some(v6) => v6.split_once(']').map_or("", |(host, _)| host),
+        None => authority.split(':').next().unwrap_or(""),
+    };
+    matches!(scheme, "http" | "https") && matches!(host, "localhost" | "127.0.0.1" | "::1")
+}
+
+impl<H, F> HttpServer<H, F>
+where
+    H: ToolHandler + 'static,
+    F: Fn() -> SystemMCPServer<H> + Send + Sync + 'static,
+{
+    pub fn new(make_server: F) -> Self {
+        HttpServer {
+            make_server,
+            endpoint: "/mcp".into(),
+            event_buffer: DEFAULT_EVENT_BUFFER,
+            resume_tokens: None,
+            allowed_origins: None,
+            max_sessions: DEFAULT_MAX_SESSIONS,
+            session_idle_timeout: DEFAULT_SESSION_IDLE_TIMEOUT,
+            read_timeout: DEFAULT_READ_TIMEOUT,
+            sessions: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Path of the MCP endpoint; `/mcp` by default
+    pub fn endpoint(mut self, path: impl Into<String>) -> Self {
+        self.endpoint = path.into();
+        self
+    }
+
+    /// Notifications buffered per session for resumption
+    pub fn event_buffer(mut self, events: usize) -> Self {
+        self.event_buffer = events;
+        self
+    }
+
+    /// Return resume tokens with every response and accept them for
+    /// sessions this process does not know
+    pub fn resume_tokens(mut self, tokens: ResumeTokens) -> Self {
+        self.resume_tokens = Some(tokens);
+        self
+    }
+
+    /// Origins browsers may send requests from, e.g. `https://app.example`;
+    /// replaces the loopback default
+    pub fn allowed_origins<I: IntoIterator<Item = impl Into<String>>>(mut self, origins: I) -> Self {
+        self.allowed_origins = Some(origins.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Sessions open at once; [`DEFAULT_MAX_SESSIONS`] by default
+    pub fn max_sessions(mut self, sessions: usize) -> Self {
+        self.max_sessions = sessions;
+        self
+    }
+
+    /// Close sessions without requests or streams for this long
+    pub fn session_idle_timeout(mut self, timeout: Duration) -> Self {
+        self.session_idle_timeout = timeout;
+        self
+    }
+
+    /// Time a client has to send a whole request
+    pub fn read_timeout(mut self, timeout: Duration) -> Self {
+        self.read_timeout = timeout;
+        self
+    }
+
+    /// Accept connections on `listener`, logging failed accepts and going on
+    pub async fn serve(self, listener: TcpListener) -> Result<(), MCPError> {
+        let this = Arc::new(self);
+        let sweeper = Arc::downgrade(&this);
+        let period = (this.session_idle_timeout / 4).max(Duration::from_millis(10));
+        tokio::spawn(async move {
+            let mut ticks = tokio::time::interval(period);
+            loop {
+                ticks.tick().await;
+                let Some(this) = sweeper.upgrade() else { break };
+                this.expire_idle();
+            }
+        });
+        loop {
+            let (mut stream, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    eprintln!("[HTTP] Accept failed: {}", e);
+                    tokio::time::sleep(ACCEPT_BACKOFF).await;
+                    continue;
+                }
+            };
+            let this = this.clone();
+            tokio::spawn(async move {
+                if let Err(e) = this.handle_connection(&mut stream).await {
+                    eprintln!("[HTTP] Connection {} ended with error: {}", peer, e);
+                }
+            });
+        }
+    }
+
+    fn origin_allowed(&self, origin: &str) -> bool {
+        match &self.allowed_origins {
+            Some(allowed) => allowed.iter().any(|allowed| allowed.eq_ignore_ascii_case(origin)),
+            None => is_loopback_origin(origin),
+        }
+    }
+
+    fn session(&self, id: &str) -> Option<Arc<HttpSession<H>>> {
+        let session = self.sessions.lock().unwrap().get(id).cloned()?;
+        session.touch();
+        Some(session)
+    }
+
+    /// Close sessions idle for longer than the timeout
+    fn expire_idle(&self) {
+        let mut sessions = self.sessions.lock().unwrap();
+        sessions.retain(|id, session| {
+            if !session.idle_for(self.session_idle_timeout) {
+                return true;
+            }
+            eprintln!("[HTTP] Session {} expired", id);
+            session.closed.notify_waiters();
+            false
+        });
+    }
+
+    /// `None` when the session limit is reached
+    fn open_session(&self, id: String) -> Option<(String, Arc<HttpSession<H>>)> {
+        self.expire_idle();
+        if self.sessions.lock().unwrap().len() >= self.max_sessions {
+            eprintln!("[HTTP] Refused a session: {} are open", self.max_sessions);
+            return None;
+        }
+        let server = (self.make_server)();
+        server.scope_session_store(&id);
+        let notifications = server.claim_notification_receiver();
+        let session = Arc::new(HttpSession {
+            server,
+            events: EventBuffer::new(self.event_buffer),
+            closed: Notify::new(),
+            last_used: Mutex::new(Instant::now()),
+            streams: AtomicUsize::new(0),
+        });
+
+        // Buffer the session's notifications as numbered events
+        if let Some(mut notifications) = notifications {
+            let pump = Arc::downgrade(&session);
+            tokio::spawn(async move {
+                while let Some(notification) = notifications.recv().await {
+                    let Some(session) = pump.upgrade() else { break };
+                    session.events.push(notification.to_json_rpc().to_string());
+                }
+            });
+        }
+        self.sessions.lock().unwrap().insert(id.clone(), session.clone());
+        Some((id, session))
+    }
+
+    /// Rebuild a session another replica created from its resume token; the
+    /// error is the status to answer with
+    fn resume_session(&self, id: &str, request: &HttpRequest) -> Result<Arc<HttpSession<H>>, u16> {
+        let (Some(tokens), Some(token)) = (&self.resume_tokens, request.header(RESUME_TOKEN_HEADER)) else {
+            return Err(404);
+        };
+        let state = match tokens.redeem(id, token) {
+            Ok(state) => state,
+            Err(e) => {
+                eprintln!("[HTTP] Refused to resume session: {}", e);
+                return Err(404);
+            }
+        };
+        let (_, session) = self.open_session(id.to_string()).ok_or(503u16)?;
+        session.server.restore_session(DEFAULT_SESSION, state);
+        eprintln!("[HTTP] Resumed session {}", id);
+        Ok(session)
+    }
+
+    async fn handle_connection(&self, stream: &mut TcpStream) -> Result<(), MCPError> {
+        let request = match tokio::time::timeout(self.read_timeout, read_request(stream)).await {
+            Ok(request) => request?,
+            Err(_) => Err(408),
+        };
+        let request = match request {
+            Ok(request) => request,
+            Err(status) => return respond(stream, status, &[], b"").await,
+        };
+        if request.path.split('?').next() != Some(self.endpoint.as_str()) {
+            return respond(stream, 404, &[], b"").await;
+        }
+        if let Some(origin) = request.header("origin")
+            && !self.origin_allowed(origin)
+        {
+            eprintln!("[HTTP] Refused a request from origin {}", origin);
+            return respond(stream, 403, &[], b"").await;
+        }
+        match request.method.as_str() {
+            "POST" => self.handle_post(stream, request).await,
+            "GET" => self.handle_get(stream, request).await,
+            "DELETE" => {
+                let id = request.header(SESSION_HEADER);
+                let removed = id.and_then(|id| self.sessions.lock().unwrap().remove(id));
+                match (id, removed) {
+                    (Some(id), Some(session)) => {
+                        if let Some(tokens) = &self.resume_tokens {
+                            tokens.revoke(id);
+                        }
+                        session.server.close_session(DEFAULT_SESSION).await;
+                        session.closed.notify_waiters();
+                        respond(stream, 204, &[], b"").await
+                    }
+                    _ => respond(stream, 404, &[], b"").await,
+                }
+            }
+            _ => respond(stream, 405, &[("Allow", "GET, POST, DELETE")], b"").await,
+        }
+    }
+
+    async fn handle_post(&self, stream: &mut TcpStream, request: HttpRequest) -> Result<(), MCPError> {
+        let json = [("Content-Type", "application/json")];
+        let incoming = match IncomingRequest::parse(&request.body) {
+            Ok(incoming) => incoming,
+            Err(e) => return respond(stream, 400, &json, &serde_json::to_vec(&IncomingRequest::rejection(&e))?).await,
+        };
+
+        let (id, session) = match request.header(SESSION_HEADER) {
+            Some(id) => match self.session(id).map_or_else(|| self.resume_session(id, &request), Ok) {
+                Ok(session) => (id.to_string(), session),
+                Err(status) => return respond(stream, status, &[], b"").await,
+            },
+            None if incoming.request.method == "initialize" => match self.open_session(random_id()?) {
+                Some(opened) => opened,
+                None => return respond(stream, 503, &[("Retry-After", "60")], b"").await,
+            },
+            None => return respond(stream, 400, &[], b"missing Mcp-Session-Id").await,
+        };
+
+        let (response, _held) = session.server.handle_holding_output(incoming).await;
+        let token = match (&self.resume_tokens, session.server.session(DEFAULT_SESSION)) {
+            (Some(tokens), Some(state)) => Some(tokens.issue(&id, &state)?),
+            _ => None,
+        };
+        let mut headers = vec![(SESSION_HEADER, id.as_str())];
+        if let Some(token) = &token {
+            headers.push((RESUME_TOKEN_HEADER, token.as_str()));
+        }
+        match response {
+            Some(response) => {
+                headers.push(json[0]);
+                respond(stream, 200, &headers, &serde_json::to_vec(&response)?).await
+            }
+            None => respond(stream, 202, &headers, b"").await,
+        }
+    }
+
+    async fn handle_get(&self, stream: &mut TcpStream, request: HttpRequest) -> Result<(), MCPError> {
+        let Some(session) = request.header(SESSION_HEADER).and_then(|id| self.session(id)) else {
+            return respond(stream, 404, &[], b"").await;
+        };
+        let mut cursor = match request.header("last-event-id").and_then(|id| id.trim().parse::<u64>().ok()) {
+            Some(last) => {
+                if session.events.lost_since(last) {
+                    eprintln!("[HTTP] Events after {} were evicted before the client resumed", last);
+                }
+                last
+            }
+            // A fresh stream only carries what happens from now on
+            None => session.events.last_id(),
+        };
+        let _stream = StreamGuard::new(&session);
+
+        let head = "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n";
+        stream.write_all(head.as_bytes()).await?;
+        stream.flush().await?;
+        loop {
+            // Register before reading the buffer so no push is missed
+            let pushed = session.events.pushed.notified();
+            let closed = session.closed.notified();
+            for (id, data) in session.events.since(Some(cursor)) {
+                stream.write_all(format!("id: {}\ndata: {}\n\n", id, data).as_bytes()).await?;
+                cursor = id;
+            }
+            stream.flush().await?;
+            tokio::select! {
+                _ = pushed => {}
+                _ = closed => return Ok(()),
+            }
+        }
+    }
+}
+
+/// Serve Streamable HTTP on `addr`, building a fresh server per session with
+/// `make_server`
+pub async fn serve<H, F>(addr: impl ToSocketAddrs, make_server: F) -> Result<(), MCPError>
+where
+    H: ToolHandler + 'static,
+    F: Fn() -> SystemMCPServer<H> + Send + Sync + 'static,
+{
+    HttpServer::new(make_server).serve(TcpListener::bind(addr).await?).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::RequestContext;
+    use crate::tools::ToolResponse;
+    use async_trait::async_trait;
+    use serde_json::{json, Value};
+    use std::net::SocketAddr;
+
+    struct Steps;
+
+    #[async_trait]
+    impl ToolHandler for Steps {
+        async fn call_tool(&self, _name: &str, _args: &Value, ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+            for step in 1..=3 {
+                let _ = ctx.progress().send_progress("steps", step as f64 / 3.0, None).await;
+            }
+            Ok(ToolResponse::new("done".into(), false))
+        }
+    }
+
+    async fn post(addr: SocketAddr, session: Option<&str>, body: Value) -> String {
+        post_with(addr, session.map(|id| (SESSION_HEADER, id)).as_slice(), body).await
+    }
+
+    async fn post_with(addr: SocketAddr, headers: &[(&str, &str)], body: Value) -> String {
+        let body = body.to_string();
+        let headers: String = headers.iter().map(|(name, value)| format!("{}: {}\r\n", name, value)).collect();
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!("POST /mcp HTTP/1.1\r\nHost: test\r\n{}Content-Length: {}\r\n\r\n{}", headers, body.len(), body);
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    async fn delete(addr: SocketAddr, session: &str) -> String {
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!("DELETE /mcp HTTP/1.1\r\nHost: test\r\n{}: {}\r\nContent-Length: 0\r\n\r\n", SESSION_HEADER, session);
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn test_resume_from_last_event_id() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(HttpServer::new(|| SystemMCPServer::<Steps>::builder().build(Steps)).serve(listener));
+
+        let init = post(addr, None, json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} })).await;
+        let session = init.lines()
+            .find_map(|line| line.strip_prefix("Mcp-Session-Id: "))
+            .expect("session header")
+            .to_string();
+        assert!(post(addr, None, json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" })).await.starts_with("HTTP/1.1 400"));
+        post(addr, Some(&session), json!({ "jsonrpc": "2.0", "method": "notifications/initialized" })).await;
+
+        let call = post(addr, Some(&session), json!({ "jsonrpc": "2.0", "id": 3, "method": "tools/call", "params": { "name": "steps" } })).await;
+        assert!(call.contains(r#""text":"done""#));
+
+        // The client saw event 1 before its connection dropped
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!("GET /mcp HTTP/1.1\r\nHost: test\r\n{}: {}\r\nLast-Event-ID: 1\r\n\r\n", SESSION_HEADER, session);
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut received = String::new();
+        while received.matches("\n\n").count() < 2 {
+            let mut chunk = [0; 4096];
+            let n = stream.read(&mut chunk).await.unwrap();
+            assert!(n > 0, "stream closed early: {}", received);
+            received.push_str(&String::from_utf8_lossy(&chunk[..n]));
+        }
+        let ids: Vec<&str> = received.lines().filter_map(|line| line.strip_prefix("id: ")).collect();
+        assert_eq!(ids, ["2", "3"]);
+
+        // Without Last-Event-ID only new events arrive
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        let request = format!("GET /mcp HTTP/1.1\r\nHost: test\r\n{}: {}\r\n\r\n", SESSION_HEADER, session);
+        stream.write_all(request.as_bytes()).await.unwrap();
+        let mut received = String::new();
+        while !received.contains("\r\n\r\n") {
+            let mut chunk = [0; 4096];
+            let n = stream.read(&mut chunk).await.unwrap();
+            received.push_str(&String::from_utf8_lossy(&chunk[..n]));
+        }
+        post(addr, Some(&session), json!({ "jsonrpc": "2.0", "id": 4, "method": "tools/call", "params": { "name": "steps" } })).await;
+        while received.matches("\n\n").count() < 2 {
+            let mut chunk = [0; 4096];
+            let n = stream.read(&mut chunk).await.unwrap();
+            assert!(n > 0, "stream closed early: {}", received);
+            received.push_str(&String::from_utf8_lossy(&chunk[..n]));
+        }
+        assert_eq!(received.lines().find_map(|line| line.strip_prefix("id: ")), Some("4"));
+    }
+
+    #[tokio::test]
+    async fn test_origins_session_limits_and_timeouts() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = HttpServer::new(|| SystemMCPServer::<Steps>::builder().build(Steps))
+            .max_sessions(1)
+            .session_idle_timeout(Duration::from_millis(200))
+            .read_timeout(Duration::from_millis(100));
+        tokio::spawn(server.serve(listener));
+        let init = json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} });
+
+        let rebound = post_with(addr, &[("Origin", "http://attacker.example")], init.clone()).await;
+        assert!(rebound.starts_with("HTTP/1.1 403"), "{}", rebound);
+        let local = post_with(addr, &[("Origin", "http://localhost:6274")], init.clone()).await;
+        assert!(local.starts_with("HTTP/1.1 200"), "{}", local);
+        let session = header(&local, SESSION_HEADER).to_string();
+        assert!(post(addr, None, init.clone()).await.starts_with("HTTP/1.1 503"));
+
+        // Idle sessions are closed and make room for new ones
+        tokio::time::sleep(Duration::from_millis(400)).await;
+        let list = json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" });
+        assert!(post(addr, Some(&session), list).await.starts_with("HTTP/1.1 404"));
+        assert!(post(addr, None, init).await.starts_with("HTTP/1.1 200"));
+
+        // A request that never finishes its headers
+        let mut stream = TcpStream::connect(addr).await.unwrap();
+        stream.write_all(b"POST /mcp HTTP/1.1\r\nHost: te").await.unwrap();
+        let mut response = String::new();
+        stream.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 408"), "{}", response);
+    }
+
+    #[test]
+    fn test_loopback_origins() {
+        for origin in ["http://localhost", "http://127.0.0.1:8080", "https://[::1]:3000"] {
+            assert!(is_loopback_origin(origin), "{}", origin);
+        }
+        for origin in ["null", "http://localhost.attacker.example", "http://127.0.0.1.nip.io", "file://localhost"] {
+            assert!(!is_loopback_origin(origin), "{}", origin);
+        }
+    }
+
+    fn header<'a>(response: &'a str, name: &str) -> &'a str {
+        response.lines().find_map(|line| line.strip_prefix(name)?.strip_prefix(": ")).expect(name)
+    }
+
+    #[tokio::test]
+    async fn test_resume_token_on_another_replica() {
+        let mut addrs = Vec::new();
+        for _ in 0..2 {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            addrs.push(listener.local_addr().unwrap());
+            let replica = HttpServer::new(|| SystemMCPServer::<Steps>::builder().build(Steps))
+                .resume_tokens(ResumeTokens::from_secret("a secret shared by every replica!").unwrap());
+            tokio::spawn(replica.serve(listener));
+        }
+
+        let init = post(addrs[0], None, json!({ "jsonrpc": "2.0", "id": 1, "method": "initialize", "params": {} })).await;
+        let (session, token) = (header(&init, SESSION_HEADER), header(&init, RESUME_TOKEN_HEADER));
+        let list = json!({ "jsonrpc": "2.0", "id": 2, "method": "tools/list" });
+
+        assert!(post(addrs[1], Some(session), list.clone()).await.starts_with("HTTP/1.1 404"));
+        let resumed = post_with(addrs[1], &[(SESSION_HEADER, session), (RESUME_TOKEN_HEADER, token)], list.clone()).await;
+        assert!(resumed.starts_with("HTTP/1.1 200"), "{}", resumed);
+        // The replica now knows the session without the token
+        let listed = post(addrs[1], Some(session), list.clone()).await;
+        assert!(listed.starts_with("HTTP/1.1 200"));
+
+        // A replica that saw the session closed does not resume it
+        let latest = header(&listed, RESUME_TOKEN_HEADER);
+        assert!(delete(addrs[1], session).await.starts_with("HTTP/1.1 204"));
+        let closed = post_with(addrs[1], &[(SESSION_HEADER, session), (RESUME_TOKEN_HEADER, latest)], list).await;
+        assert!(closed.starts_with("HTTP/1.1 404"), "{}", closed);
+    }
+}
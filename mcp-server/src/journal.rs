@@ -0,0 +1,157 @@
+//! Opt-in request journal with deterministic replay.
+//!
+//! With [`ServerBuilder::journal`] every inbound message and the response the
+//! server produced are appended as one JSON line. [`replay`] feeds a journal
+//! back through another server instance (typically built with a fresh
+//! handler) and reports every response that differs from the recording.
+//!
+//! [`ServerBuilder::journal`]: crate::server::ServerBuilder::journal
+
+use crate::error::MCPError;
+use crate::response::MCPResponse;
+use crate::server::{SystemMCPServer, ToolHandler};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// One recorded request/response pair
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JournalEntry {
+    pub seq: u64,
+    #[serde(rename = "timestampMs")]
+    pub timestamp_ms: u64,
+    /// The message exactly as received (as a JSON string)
+    pub request: String,
+    /// The response sent, or `None` for notifications
+    pub response: Option<Value>,
+}
+
+/// Append-only JSON-lines journal file
+#[derive(Debug)]
+pub struct Journal {
+    file: Mutex<File>,
+    seq: AtomicU64,
+}
+
+impl Journal {
+    /// Open `path` for appending, creating it if needed
+    pub fn create(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Journal {
+            file: Mutex::new(file),
+            seq: AtomicU64::new(0),
+        })
+    }
+
+    pub fn record(&self, request: &[u8], response: Option<&MCPResponse>) {
+        let entry = JournalEntry {
+            seq: self.seq.fetch_add(1, Ordering::Relaxed),
+            timestamp_ms: SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_millis() as u64).unwrap_or(0),
+            request: String::from_utf8_lossy(request).into_owned(),
+            response: response.and_then(|r| serde_json::to_value(r).ok()),
+        };
+
+        let Ok(mut line) = serde_json::to_vec(&entry) else { return };
+        line.push(b'\n');
+        if let Err(e) = self.file.lock().unwrap().write_all(&line) {
+            eprintln!("[JOURNAL] Failed to record request: {}", e);
+        }
+    }
+}
+
+/// A response that differs from the journal
+#[derive(Debug, Clone, Serialize)]
+pub struct ReplayDiff {
+    pub seq: u64,
+    pub request: String,
+    pub expected: Option<Value>,
+    pub actual: Option<Value>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ReplayReport {
+    pub replayed: usize,
+    pub diffs: Vec<ReplayDiff>,
+}
+
+impl ReplayReport {
+    pub fn is_identical(&self) -> bool {
+        self.diffs.is_empty()
+    }
+}
+
+pub fn read_journal(path: impl AsRef<Path>) -> Result<Vec<JournalEntry>, MCPError> {
+    let reader = BufReader::new(File::open(path)?);
+    let mut entries = Vec::new();
+    for line in reader.lines() {
+        let line = line?;
+        if !line.trim().is_empty() {
+            entries.push(serde_json::from_str(&line)?);
+        }
+    }
+    Ok(entries)
+}
+
+/// Re-feed every journaled request through `server` in order and diff the
+/// responses against the recording
+pub async fn replay<H: ToolHandler>(server: &SystemMCPServer<H>, path: impl AsRef<Path>) -> Result<ReplayReport, MCPError> {
+    let mut report = ReplayReport::default();
+    for entry in read_journal(path)? {
+        let response = server.handle_raw(entry.request.as_bytes()).await;
+        let actual = response.as_ref().map(serde_json::to_value).transpose()?;
+
+        report.replayed += 1;
+        if actual != entry.response {
+            report.diffs.push(ReplayDiff {
+                seq: entry.seq,
+                request: entry.request,
+                expected: entry.response,
+                actual,
+            });
+        }
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures;
+    use crate::testing::mock::{MockToolHandler, Reply};
+
+    #[tokio::test]
+    async fn test_replay_reports_changed_responses() {
+        let path = std::env::temp_dir().join(format!("mcp-journal-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let server = |text: &str| SystemMCPServer::<MockToolHandler>::builder()
+            .relaxed_lifecycle()
+            .build(MockToolHandler::new().tool("echo", Reply::text(text)));
+
+        let recording = SystemMCPServer::<MockToolHandler>::builder()
+            .relaxed_lifecycle()
+            .journal(Journal::create(&path).unwrap())
+            .build(MockToolHandler::new().tool("echo", Reply::text("a")));
+        let call = fixtures::call_tool("echo").id(1).to_json().to_string();
+        recording.handle_raw(call.as_bytes()).await;
+        recording.handle_raw(b"{not json").await;
+        recording.handle(fixtures::notification("notifications/initialized").build()).await;
+
+        let entries = read_journal(&path).unwrap();
+        assert_eq!(entries.iter().map(|entry| entry.seq).collect::<Vec<_>>(), [0, 1, 2]);
+        assert_eq!(entries[0].request, call);
+        assert_eq!(entries[1].response.as_ref().unwrap()["error"]["code"], -32700);
+        assert!(entries[2].response.is_none());
+
+        assert!(replay(&server("a"), &path).await.unwrap().is_identical());
+        let report = replay(&server("b"), &path).await.unwrap();
+        assert_eq!((report.replayed, report.diffs.len()), (3, 1));
+        assert_eq!(report.diffs[0].seq, 0);
+        assert_eq!(report.diffs[0].actual.as_ref().unwrap()["result"]["content"][0]["text"], "b");
+        std::fs::remove_file(&path).unwrap();
+    }
+}
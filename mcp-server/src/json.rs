@@ -0,0 +1,25 @@
+//! JSON decoding of incoming messages.
+//!
+//! With the `simd-json` feature, messages are parsed with simd-json instead.
+//! Anything it rejects is re-parsed with serde_json, so exotic inputs still
+//! work and errors keep serde_json's positions. Whether it pays off depends on
+//! the CPU and payload mix; run `benches/json_parse.rs` on the target machine
+//! before enabling it.
+
+use crate::error::MCPError;
+use serde::de::DeserializeOwned;
+
+#[cfg(feature = "simd-json")]
+pub fn from_slice<T: DeserializeOwned>(raw: &[u8]) -> Result<T, MCPError> {
+    // simd-json parses in place, so it needs its own copy
+    let mut buffer = raw.to_vec();
+    match simd_json::serde::from_slice(&mut buffer) {
+        Ok(value) => Ok(value),
+        Err(_) => Ok(serde_json::from_slice(raw)?),
+    }
+}
+
+#[cfg(not(feature = "simd-json"))]
+pub fn from_slice<T: DeserializeOwned>(raw: &[u8]) -> Result<T, MCPError> {
+    Ok(serde_json::from_slice(raw)?)
+}
@@ -0,0 +1,132 @@
+//! Pings from the server that reclaim dead connections.
+//!
+//! A client that vanishes without closing its connection (a laptop going to
+//! sleep, a proxy dropping the stream) leaves the runner waiting for input
+//! forever, holding the session and any calls it started. With
+//! [`ServerBuilder::keepalive`] a runner sends the client `ping` every
+//! interval over each of its transports. Any answer, even an error, counts;
+//! after [`Keepalive::max_missed`] unanswered pings in a row the runner stops
+//! serving that transport and ends the sessions only it used.
+//!
+//! [`ServerBuilder::keepalive`]: crate::server::ServerBuilder::keepalive
+
+use crate::server::{SystemMCPServer, ToolHandler};
+use std::time::Duration;
+
+/// Unanswered pings in a row before a connection counts as dead
+pub const DEFAULT_MAX_MISSED: u32 = 3;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Keepalive {
+    interval: Duration,
+    max_missed: u32,
+}
+
+impl Keepalive {
+    /// Ping every `interval`, allowing each ping that long to be answered
+    pub fn new(interval: Duration) -> Self {
+        Keepalive { interval: interval.max(Duration::from_millis(1)), max_missed: DEFAULT_MAX_MISSED }
+    }
+
+    /// Unanswered pings in a row before the connection is torn down
+    pub fn max_missed(mut self, max_missed: u32) -> Self {
+        self.max_missed = max_missed.max(1);
+        self
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+}
+
+/// Ping the client of the current transport until it misses too many in a
+/// row; never completes without a keepalive configured
+pub(crate) async fn monitor<H: ToolHandler>(server: &SystemMCPServer<H>) {
+    let Some(keepalive) = server.keepalive() else { return std::future::pending().await };
+    let mut missed = 0;
+    while missed < keepalive.max_missed {
+        tokio::time::sleep(keepalive.interval).await;
+        match server.ping_client(keepalive.interval).await {
+            Ok(()) => missed = 0,
+            Err(e) => {
+                missed += 1;
+                eprintln!("[KEEPALIVE] Missed ping {}/{}: {}", missed, keepalive.max_missed, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures;
+    use crate::testing::mock::MockToolHandler;
+    use crate::transport::in_process;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_dead_client_is_torn_down() {
+        let (mut client, transport) = in_process();
+        let server = std::sync::Arc::new(SystemMCPServer::<MockToolHandler>::builder()
+            .relaxed_lifecycle()
+            .keepalive(Keepalive::new(Duration::from_millis(20)).max_missed(2))
+            .build(MockToolHandler::new()));
+        let runner = tokio::spawn({
+            let server = server.clone();
+            async move { server.runner().without_signals().run_with_transport(transport).await }
+        });
+
+        client.send_raw(serde_json::to_string(&fixtures::initialize().build()).unwrap()).unwrap();
+        assert!(client.next_notification().await.unwrap()["result"]["capabilities"].is_object());
+        let ping = client.next_notification().await.unwrap();
+        assert_eq!(ping["method"], "ping");
+        client.send_raw(json!({ "jsonrpc": "2.0", "id": ping["id"], "result": {} }).to_string()).unwrap();
+        assert!(!server.session_ids().is_empty());
+
+        // Stop answering; the client is still connected but never replies
+        let finished = tokio::time::timeout(Duration::from_secs(5), runner).await.expect("runner kept waiting");
+        assert!(finished.unwrap().is_ok());
+        assert!(server.session_ids().is_empty());
+        drop(client);
+    }
+
+    #[tokio::test]
+    async fn test_only_the_silent_transport_closes() {
+        let (mut silent, silent_transport) = in_process();
+        let (mut alive, alive_transport) = in_process();
+        let server = std::sync::Arc::new(SystemMCPServer::<MockToolHandler>::builder()
+            .relaxed_lifecycle()
+            .keepalive(Keepalive::new(Duration::from_millis(20)).max_missed(2))
+            .build(MockToolHandler::new()));
+        tokio::spawn({
+            let server = server.clone();
+            async move {
+                let runner = server.runner().without_signals().add_transport(silent_transport).add_transport(alive_transport);
+                runner.run().await
+            }
+        });
+        silent.send_raw(serde_json::to_string(&fixtures::initialize().build()).unwrap()).unwrap();
+
+        // Pings until the transport is dropped
+        let closed = tokio::time::timeout(Duration::from_secs(5), async {
+            while let Some(message) = silent.next_notification().await {
+                assert!(message["method"] == "ping" || message["result"].is_object());
+            }
+        });
+        let answering = async {
+            loop {
+                let message = alive.next_notification().await.unwrap();
+                alive.send_raw(json!({ "jsonrpc": "2.0", "id": message["id"], "result": {} }).to_string()).unwrap();
+            }
+        };
+        tokio::select! {
+            closed = closed => closed.expect("silent transport kept open"),
+            _ = answering => unreachable!(),
+        }
+
+        // The other transport still shares the session
+        assert!(!server.session_ids().is_empty());
+        let listed = alive.request("tools/list", json!({})).await.unwrap();
+        assert!(listed["result"]["tools"].is_array());
+    }
+}
@@ -0,0 +1,81 @@
+//! MCP server runtime built on tokio.
+
+pub mod args;
+pub mod batch;
+pub mod call_log;
+pub mod cas;
+pub mod changes;
+#[cfg(feature = "chaos")]
+pub mod chaos;
+pub mod completion;
+pub mod concurrency;
+pub mod config;
+pub mod context;
+pub mod custom;
+pub mod declarative;
+pub mod flags;
+pub mod gc;
+pub mod groups;
+pub mod guards;
+pub mod heartbeat;
+#[cfg(feature = "http")]
+pub mod http;
+pub mod journal;
+pub mod keepalive;
+pub mod list_cache;
+pub mod json;
+pub mod macros;
+pub mod memory;
+pub mod metrics;
+pub mod middleware;
+#[cfg(windows)]
+pub mod named_pipe;
+#[cfg(feature = "noise")]
+pub mod noise;
+pub mod notifications;
+pub mod outbound;
+pub mod pagination;
+pub mod parallel;
+pub mod prelude;
+pub mod priority;
+pub mod quarantine;
+pub mod ready;
+#[cfg(feature = "redis")]
+pub mod redis_store;
+pub mod registry;
+pub mod result_limit;
+pub mod result_pages;
+#[cfg(feature = "http")]
+pub mod resume;
+pub mod runner;
+pub mod select;
+pub mod server;
+pub mod session;
+pub mod session_store;
+pub mod shutdown;
+pub mod subprocess;
+pub mod testing;
+pub mod tool_docs;
+pub mod trace_diff;
+pub mod uri_resolver;
+pub mod transport;
+#[cfg(feature = "jsonschema")]
+pub mod validation;
+pub mod versioning;
+#[cfg(feature = "websocket")]
+pub mod websocket;
+
+pub use mcp_types::{error, request, response, tools};
+
+pub use mcp_types::*;
+pub use completion::CompletionProvider;
+pub use context::{ClientInfo, RequestContext};
+pub use flags::FeatureFlags;
+pub use groups::ToolGroups;
+pub use middleware::{IncomingRequest, Middleware, Next, ToolCallGuard};
+pub use registry::{NoTools, ToolRegistry, TypedTool};
+pub use notifications::{NotificationReceiver, ProgressPolicy, ProgressSender, ServerNotification, StagedProgress};
+pub use subprocess::{DeadlinePolicy, SubprocessEnv};
+pub use uri_resolver::UriResolver;
+pub use server::{JsonRpcVersion, ReinitializePolicy, ServerBuilder, SystemMCPServer, ToolHandler, PROTOCOL_VERSION, SUPPORTED_PROTOCOL_VERSIONS};
+pub use transport::{in_process, Framing, InProcessClient, InProcessTransport, IoRetryPolicy, StdioTransport, Transport, TransportSet};
@@ -0,0 +1,176 @@
+//! Cached `tools/list` pages.
+//!
+//! Listing tools serializes every definition, schemas included, and pages
+//! the result; with hundreds of tools and clients that poll, that work
+//! dominates list latency. With [`ServerBuilder::cache_tool_list`] the server
+//! keeps each page it sends, serialized, keyed by the session and the
+//! request's cursor and page size hint, and answers the same request from
+//! the cache until the tools may have changed: a [`ToolRegistry`]
+//! (un)registration, a feature flag or live config change, a new
+//! [`ToolHandler::tools_generation`], or
+//! [`SystemMCPServer::notify_tools_changed`]. Each of those advances a
+//! generation counter, and a cache that sees a new generation starts over.
+//!
+//! A handler that does not report a `tools_generation` may list anything at
+//! any time, so `tools/list` is not cached for it.
+//!
+//! [`ServerBuilder::cache_tool_list`]: crate::server::ServerBuilder::cache_tool_list
+//! [`ToolRegistry`]: crate::registry::ToolRegistry
+//! [`ToolHandler::tools_generation`]: crate::server::ToolHandler::tools_generation
+//! [`SystemMCPServer::notify_tools_changed`]: crate::server::SystemMCPServer::notify_tools_changed
+
+use serde_json::value::RawValue;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Pages kept per generation before the cache starts over
+pub const MAX_CACHED_PAGES: usize = 256;
+
+/// What selects a page: the session, the cursor and the page size hint
+type PageKey = (String, Option<String>, Option<String>);
+
+#[derive(Debug, Default)]
+struct Pages {
+    generation: u64,
+    pages: HashMap<PageKey, Box<RawValue>>,
+}
+
+#[derive(Debug, Default)]
+pub struct ToolListCache {
+    // Changes announced to the server itself
+    changes: AtomicU64,
+    pages: Mutex<Pages>,
+}
+
+impl ToolListCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drop every cached page
+    pub fn invalidate(&self) {
+        self.changes.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Invalidations so far, to be added into the listing's generation
+    pub fn changes(&self) -> u64 {
+        self.changes.load(Ordering::Acquire)
+    }
+
+    /// The page sent to `session` for `params` while the tools were at
+    /// `generation`
+    pub fn get(&self, generation: u64, session: &str, params: Option<&Value>) -> Option<Value> {
+        let pages = self.pages.lock().unwrap();
+        let page = pages.pages.get(&page_key(session, params)).filter(|_| pages.generation == generation)?;
+        serde_json::from_str(page.get()).ok()
+    }
+
+    /// Keep `page` unless a newer generation was cached meanwhile
+    pub fn insert(&self, generation: u64, session: &str, params: Option<&Value>, page: &Value) {
+        let Ok(page) = serde_json::value::to_raw_value(page) else { return };
+        let mut pages = self.pages.lock().unwrap();
+        if pages.generation > generation {
+            return;
+        }
+        if pages.generation < generation || pages.pages.len() >= MAX_CACHED_PAGES {
+            *pages = Pages { generation, pages: HashMap::new() };
+        }
+        pages.pages.insert(page_key(session, params), page);
+    }
+}
+
+fn page_key(session: &str, params: Option<&Value>) -> PageKey {
+    let cursor = params.and_then(|p| p.get("cursor")).filter(|cursor| !cursor.is_null());
+    let page_size = params.and_then(|p| p.get("_meta")).and_then(|meta| meta.get("pageSize"));
+    (session.into(), cursor.map(Value::to_string), page_size.map(Value::to_string))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::flags::FeatureFlags;
+    use crate::pagination::Paginator;
+    use crate::registry::ToolRegistry;
+    use crate::server::SystemMCPServer;
+    use crate::testing::fixtures;
+    use crate::testing::mock::{MockToolHandler, Reply};
+    use crate::tools::{Tool, ToolInputSchema, ToolResponse};
+
+    fn tool(name: &str) -> Tool {
+        Tool::new(name, "test tool", ToolInputSchema { schema_type: "object".into(), properties: Default::default(), required: vec![] })
+    }
+
+    #[test]
+    fn test_stale_and_unrelated_params() {
+        let cache = ToolListCache::new();
+        let params = serde_json::json!({ "cursor": "2", "_meta": { "pageSize": 5, "progressToken": 1 } });
+        cache.insert(3, "a", Some(&params), &Value::from("page"));
+        let polled = serde_json::json!({ "cursor": "2", "_meta": { "pageSize": 5, "progressToken": 2 } });
+        assert_eq!(cache.get(3, "a", Some(&polled)), Some(Value::from("page")));
+        assert_eq!(cache.get(3, "a", None), None);
+        // Handlers may list different tools to each session
+        assert_eq!(cache.get(3, "b", Some(&params)), None);
+
+        // A listing that started before a change does not replace newer pages
+        cache.insert(2, "a", Some(&params), &Value::from("stale"));
+        assert_eq!(cache.get(3, "a", Some(&params)), Some(Value::from("page")));
+        assert_eq!(cache.get(4, "a", Some(&params)), None);
+    }
+
+    #[tokio::test]
+    async fn test_pages_are_cached_until_tools_change() {
+        let registry = ToolRegistry::new();
+        for name in ["a", "b", "c"] {
+            registry.register(tool(name), |_, _| async { Ok(ToolResponse::new("ok".into(), false)) });
+        }
+        let flags = FeatureFlags::new();
+        let server = SystemMCPServer::<MockToolHandler>::builder()
+            .relaxed_lifecycle()
+            .pagination(Paginator::new(2, 1, 10))
+            .tool_registry(registry.clone())
+            .feature_flags(flags.clone())
+            .cache_tool_list()
+            .build(MockToolHandler::new());
+        let list = |cursor: &str| {
+            let request = fixtures::request("tools/list").param("cursor", cursor).build();
+            let server = &server;
+            async move {
+                let page = server.handle(request).await.unwrap().result.unwrap();
+                page["tools"].as_array().unwrap().iter().map(|tool| tool["name"].as_str().unwrap().to_string()).collect::<Vec<_>>()
+            }
+        };
+        let listings = || server.handler().calls().iter().filter(|call| call.method == "tools/list").count();
+
+        assert_eq!(list("0").await, ["a", "b"]);
+        assert_eq!(list("0").await, ["a", "b"]);
+        assert_eq!(list("2").await, ["c"]);
+        assert_eq!(listings(), 2);
+
+        registry.register(tool("d"), |_, _| async { Ok(ToolResponse::new("ok".into(), false)) });
+        assert_eq!(list("2").await, ["c", "d"]);
+        flags.gate_tool("c", "beta");
+        assert_eq!(list("2").await, ["d"]);
+        registry.unregister("a");
+        assert_eq!(list("0").await, ["b", "d"]);
+        assert!(server.notify_tools_changed());
+        list("0").await;
+        assert_eq!(listings(), 6);
+    }
+
+    #[tokio::test]
+    async fn test_handlers_without_generation_are_not_cached() {
+        let handler = MockToolHandler::new()
+            .list_tools(Reply::value(vec![tool("a")]))
+            .list_tools(Reply::value(vec![tool("a"), tool("b")]));
+        let server = SystemMCPServer::<MockToolHandler>::builder().relaxed_lifecycle().cache_tool_list().build(handler);
+        let count = || async {
+            let listed = server.handle(fixtures::request("tools/list").build()).await.unwrap().result.unwrap();
+            listed["tools"].as_array().unwrap().len()
+        };
+
+        assert_eq!(count().await, 1);
+        assert_eq!(count().await, 2);
+    }
+}
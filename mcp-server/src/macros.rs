@@ -1,7 +1,7 @@
 /// Dispatches tool calls to handler methods based on tool name
 ///
 /// # Example
-/// ```
+/// ```ignore
 /// tool_dispatch!(self, name, args, progress_sender, {
 ///     "run_command" => handle_run_command,
 ///     "list_directory" => handle_list_directory,
@@ -102,3 +102,25 @@ macro_rules! extract_optional {
             .unwrap_or($default)
     };
 }
+
+/// Runs labeled sub-operations concurrently and merges their results,
+/// see [`parallel::join_tools`](crate::parallel::join_tools)
+///
+/// # Example
+/// ```ignore
+/// let response = parallel!(
+///     "disk" => self.check_disk(args),
+///     "memory" => self.check_memory(args),
+/// ).await;
+/// ```
+#[macro_export]
+macro_rules! parallel {
+    ($($label:expr => $operation:expr),+ $(,)?) => {
+        $crate::parallel::join_tools(vec![
+            $((
+                ::std::string::ToString::to_string(&$label),
+                ::std::boxed::Box::pin($operation) as $crate::parallel::SubOperation<'_>,
+            )),+
+        ])
+    };
+}
@@ -0,0 +1,276 @@
+//! Memory accounting with a configurable ceiling.
+//!
+//! Buffers that can grow with client traffic (tool output, the CAS store,
+//! queued notifications) reserve their size from a shared
+//! [`MemoryAccountant`]. Under pressure the server sheds load in order:
+//! progress notifications are dropped first, then caches are evicted, and
+//! finally new large results are rejected.
+//!
+//! A tool result stays reserved until its response is written: runners
+//! handle requests inside [`holding_output`], which hands the reservation
+//! back with the response for the transport to drop after sending it.
+
+use crate::tools::{ContentBlock, ToolResponse};
+use serde::Serialize;
+use std::cell::RefCell;
+use std::future::Future;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// Fraction of the ceiling above which progress notifications are dropped
+pub const NOTIFICATION_SHED_PRESSURE: f64 = 0.75;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryCategory {
+    Notifications,
+    Output,
+    Cache,
+}
+
+impl MemoryCategory {
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct MemoryStats {
+    pub ceiling: usize,
+    pub used: usize,
+    pub pressure: f64,
+    pub notifications: usize,
+    pub output: usize,
+    pub cache: usize,
+    #[serde(rename = "shedNotifications")]
+    pub shed_notifications: u64,
+    pub evictions: u64,
+    #[serde(rename = "rejectedResults")]
+    pub rejected_results: u64,
+}
+
+#[derive(Debug)]
+pub struct MemoryAccountant {
+    ceiling: usize,
+    // Sum of `used`, checked and raised in one step
+    total: AtomicUsize,
+    used: [AtomicUsize; 3],
+    shed_notifications: AtomicU64,
+    evictions: AtomicU64,
+    rejected_results: AtomicU64,
+}
+
+impl MemoryAccountant {
+    pub fn new(ceiling: usize) -> Self {
+        MemoryAccountant {
+            ceiling,
+            total: AtomicUsize::new(0),
+            used: Default::default(),
+            shed_notifications: AtomicU64::new(0),
+            evictions: AtomicU64::new(0),
+            rejected_results: AtomicU64::new(0),
+        }
+    }
+
+    pub fn ceiling(&self) -> usize {
+        self.ceiling
+    }
+
+    pub fn used(&self) -> usize {
+        self.total.load(Ordering::Acquire)
+    }
+
+    /// Share of the ceiling in use, from 0.0 upwards
+    pub fn pressure(&self) -> f64 {
+        if self.ceiling == 0 {
+            return 0.0;
+        }
+        self.used() as f64 / self.ceiling as f64
+    }
+
+    /// Reserve `bytes`, failing when that would exceed the ceiling
+    pub fn try_reserve(&self, category: MemoryCategory, bytes: usize) -> bool {
+        let reserved = self.total.fetch_update(Ordering::AcqRel, Ordering::Acquire, |total| {
+            total.checked_add(bytes).filter(|&total| total <= self.ceiling)
+        });
+        if reserved.is_err() {
+            return false;
+        }
+        self.used[category.index()].fetch_add(bytes, Ordering::Relaxed);
+        true
+    }
+
+    /// Like [`try_reserve`](Self::try_reserve), releasing the bytes on drop
+    pub fn reserve(self: &Arc<Self>, category: MemoryCategory, bytes: usize) -> Option<Reservation> {
+        self.try_reserve(category, bytes).then(|| Reservation { accountant: self.clone(), category, bytes })
+    }
+
+    pub fn release(&self, category: MemoryCategory, bytes: usize) {
+        let released = self.used[category.index()].fetch_update(Ordering::Relaxed, Ordering::Relaxed, |used| {
+            Some(used.saturating_sub(bytes))
+        });
+        // Only what the category actually held comes off the total
+        let held = released.unwrap_or_default().min(bytes);
+        self.total.fetch_sub(held, Ordering::AcqRel);
+    }
+
+    /// Whether a progress notification may be queued; counts it as shed if not
+    pub fn admit_notification(&self) -> bool {
+        if self.pressure() >= NOTIFICATION_SHED_PRESSURE {
+            self.shed_notifications.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        true
+    }
+
+    pub fn record_eviction(&self) {
+        self.evictions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn record_rejection(&self) {
+        self.rejected_results.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn stats(&self) -> MemoryStats {
+        let used = |category: MemoryCategory| self.used[category.index()].load(Ordering::Relaxed);
+        MemoryStats {
+            ceiling: self.ceiling,
+            used: self.used(),
+            pressure: self.pressure(),
+            notifications: used(MemoryCategory::Notifications),
+            output: used(MemoryCategory::Output),
+            cache: used(MemoryCategory::Cache),
+            shed_notifications: self.shed_notifications.load(Ordering::Relaxed),
+            evictions: self.evictions.load(Ordering::Relaxed),
+            rejected_results: self.rejected_results.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Bytes held until dropped
+#[derive(Debug)]
+pub struct Reservation {
+    accountant: Arc<MemoryAccountant>,
+    category: MemoryCategory,
+    bytes: usize,
+}
+
+impl Reservation {
+    /// Hold `bytes` more; false, holding as much as before, past the ceiling
+    pub fn try_grow(&mut self, bytes: usize) -> bool {
+        if !self.accountant.try_reserve(self.category, bytes) {
+            return false;
+        }
+        self.bytes += bytes;
+        true
+    }
+
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Take over what `other` holds, to be released together
+    fn absorb(&mut self, mut other: Reservation) {
+        debug_assert_eq!(self.category, other.category);
+        self.bytes += std::mem::take(&mut other.bytes);
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.accountant.release(self.category, self.bytes);
+    }
+}
+
+tokio::task_local! {
+    // Memory of the response being produced, kept until it is written
+    static HELD_OUTPUT: RefCell<Option<Reservation>>;
+}
+
+/// Keep `reservation` until the response of the current request is written,
+/// along with what it already holds; outside [`holding_output`] it is
+/// dropped right away
+pub(crate) fn hold_output(reservation: Reservation) {
+    let _ = HELD_OUTPUT.try_with(|held| match &mut *held.borrow_mut() {
+        Some(held) => held.absorb(reservation),
+        held => *held = Some(reservation),
+    });
+}
+
+/// Run `future`, also returning what it held with [`hold_output`]
+pub(crate) async fn holding_output<F: Future>(future: F) -> (F::Output, Option<Reservation>) {
+    let mut scoped = std::pin::pin!(HELD_OUTPUT.scope(RefCell::new(None), future));
+    let output = scoped.as_mut().await;
+    let held = scoped.as_mut().take_value().and_then(RefCell::into_inner);
+    (output, held)
+}
+
+/// Approximate bytes a tool result holds
+pub fn result_size(response: &ToolResponse) -> usize {
+    let content: usize = response.content.iter().map(block_size).sum();
+    let structured = response.structured_content.as_ref().map_or(0, |v| v.to_string().len());
+    content + structured
+}
+
+/// Approximate bytes a content block holds
+pub fn block_size(block: &ContentBlock) -> usize {
+    match block {
+        ContentBlock::Text(c) => c.text.len(),
+        ContentBlock::Image(c) => c.data.encoded_len(),
+        ContentBlock::Audio(c) => c.data.encoded_len(),
+        ContentBlock::ResourceLink(c) => c.uri.len() + c.name.len(),
+        ContentBlock::Resource(c) => c.resource.text.len() + c.resource.blob.as_ref().map_or(0, |b| b.encoded_len()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ceiling_and_shedding() {
+        let accountant = MemoryAccountant::new(100);
+        assert!(accountant.try_reserve(MemoryCategory::Cache, 60));
+        assert!(accountant.admit_notification());
+        assert!(accountant.try_reserve(MemoryCategory::Output, 20));
+        assert!(!accountant.admit_notification());
+        assert!(!accountant.try_reserve(MemoryCategory::Output, 30));
+
+        accountant.release(MemoryCategory::Cache, 60);
+        assert!(accountant.try_reserve(MemoryCategory::Output, 30));
+        let stats = accountant.stats();
+        assert_eq!((stats.used, stats.cache, stats.shed_notifications), (50, 0, 1));
+    }
+
+    #[test]
+    fn test_concurrent_reservations_stay_under_the_ceiling() {
+        let accountant = Arc::new(MemoryAccountant::new(1_000));
+        let threads: Vec<_> = (0..8).map(|_| {
+            let accountant = accountant.clone();
+            std::thread::spawn(move || (0..1_000).filter(|_| accountant.try_reserve(MemoryCategory::Output, 7)).count())
+        }).collect();
+        let reserved: usize = threads.into_iter().map(|thread| thread.join().unwrap()).sum();
+        assert_eq!(reserved, 1_000 / 7);
+        assert_eq!(accountant.used(), reserved * 7);
+
+        accountant.release(MemoryCategory::Output, accountant.used());
+        let mut reservation = accountant.reserve(MemoryCategory::Output, 600).unwrap();
+        assert!(!reservation.try_grow(500));
+        assert!(reservation.try_grow(400));
+        assert_eq!((reservation.bytes(), accountant.used()), (1_000, 1_000));
+        drop(reservation);
+        assert_eq!(accountant.used(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_output_is_held_until_handed_back() {
+        let accountant = Arc::new(MemoryAccountant::new(100));
+        let (_, held) = holding_output(async { hold_output(accountant.reserve(MemoryCategory::Output, 40).unwrap()) }).await;
+        assert_eq!(accountant.used(), 40);
+        drop(held);
+        assert_eq!(accountant.used(), 0);
+
+        // Nobody to hand it to
+        hold_output(accountant.reserve(MemoryCategory::Output, 40).unwrap());
+        assert_eq!(accountant.used(), 0);
+    }
+}
@@ -0,0 +1,193 @@
+//! Per-tool call counts, error counts and latency percentiles.
+//!
+//! Every server tracks how often each tool is called, how often the call
+//! fails (an error response, a result with `isError`, a timeout or a
+//! cancellation, or arguments or a result breaking the tool's schema) and
+//! how long the call takes; [`SystemMCPServer::metrics`] returns the current
+//! figures.
+//! Percentiles are taken over each tool's most recent [`LATENCY_SAMPLES`]
+//! calls, so a tool that recently became slow shows it. Calls of unknown
+//! tools are not counted. With [`ServerBuilder::metrics_resource`] the same
+//! figures are listed as the `metrics://tools` resource for operators
+//! watching from a client.
+//!
+//! [`SystemMCPServer::metrics`]: crate::server::SystemMCPServer::metrics
+//! [`ServerBuilder::metrics_resource`]: crate::server::ServerBuilder::metrics_resource
+
+use crate::tools::{Resource, ResourceContent};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// URI of the metrics resource
+pub const METRICS_URI: &str = "metrics://tools";
+
+/// Latest calls per tool that percentiles are computed over
+pub const LATENCY_SAMPLES: usize = 1024;
+
+/// One tool's figures
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ToolStats {
+    pub calls: u64,
+    pub errors: u64,
+    pub p50: Duration,
+    pub p90: Duration,
+    pub p99: Duration,
+    pub max: Duration,
+}
+
+impl ToolStats {
+    fn to_json(self) -> Value {
+        let ms = |latency: Duration| latency.as_secs_f64() * 1000.0;
+        json!({
+            "calls": self.calls,
+            "errors": self.errors,
+            "latencyMs": { "p50": ms(self.p50), "p90": ms(self.p90), "p99": ms(self.p99), "max": ms(self.max) },
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counters {
+    calls: u64,
+    errors: u64,
+    latencies: VecDeque<Duration>,
+}
+
+#[derive(Debug, Default)]
+pub struct ToolMetrics {
+    tools: Mutex<HashMap<String, Counters>>,
+}
+
+impl ToolMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Count a finished call of `tool`
+    pub fn record(&self, tool: &str, latency: Duration, failed: bool) {
+        let mut tools = self.tools.lock().unwrap();
+        let counters = match tools.get_mut(tool) {
+            Some(counters) => counters,
+            None => tools.entry(tool.into()).or_default(),
+        };
+        counters.calls += 1;
+        counters.errors += u64::from(failed);
+        if counters.latencies.len() == LATENCY_SAMPLES {
+            counters.latencies.pop_front();
+        }
+        counters.latencies.push_back(latency);
+    }
+
+    /// Figures of every tool called so far, by name
+    pub fn snapshot(&self) -> BTreeMap<String, ToolStats> {
+        let tools = self.tools.lock().unwrap();
+        tools.iter().map(|(name, counters)| {
+            let mut sorted: Vec<Duration> = counters.latencies.iter().copied().collect();
+            sorted.sort_unstable();
+            // Nearest rank
+            let percentile = |p: usize| match sorted.len() {
+                0 => Duration::ZERO,
+                n => sorted[(n * p).div_ceil(100).clamp(1, n) - 1],
+            };
+            let stats = ToolStats {
+                calls: counters.calls,
+                errors: counters.errors,
+                p50: percentile(50),
+                p90: percentile(90),
+                p99: percentile(99),
+                max: sorted.last().copied().unwrap_or_default(),
+            };
+            (name.clone(), stats)
+        }).collect()
+    }
+
+    /// Listing entry for the metrics resource
+    pub fn resource() -> Resource {
+        Resource::new(METRICS_URI, "tool metrics")
+            .with_description("Per-tool call counts, error counts and latency percentiles")
+            .with_mime_type("application/json")
+    }
+
+    /// Current figures as resource contents
+    pub fn read(&self) -> ResourceContent {
+        let tools: Map<String, Value> = self.snapshot().into_iter().map(|(name, stats)| (name, stats.to_json())).collect();
+        ResourceContent {
+            uri: METRICS_URI.into(),
+            mime_type: "application/json".into(),
+            text: json!({ "tools": tools }).to_string(),
+            blob: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::SystemMCPServer;
+    use crate::testing::fixtures;
+    use crate::error::MCPError;
+    use crate::testing::mock::{MockToolHandler, Reply};
+    use crate::tools::ToolResponse;
+
+    #[test]
+    fn test_percentiles() {
+        let metrics = ToolMetrics::new();
+        for ms in 1..=100 {
+            metrics.record("build", Duration::from_millis(ms), ms > 95);
+        }
+        let stats = metrics.snapshot()["build"];
+        assert_eq!((stats.calls, stats.errors), (100, 5));
+        assert_eq!((stats.p50, stats.p90, stats.p99), (Duration::from_millis(50), Duration::from_millis(90), Duration::from_millis(99)));
+        assert_eq!(stats.max, Duration::from_millis(100));
+
+        for _ in 0..LATENCY_SAMPLES {
+            metrics.record("build", Duration::from_millis(1), false);
+        }
+        assert_eq!(metrics.snapshot()["build"].max, Duration::from_millis(1));
+    }
+
+    #[tokio::test]
+    async fn test_calls_are_counted() {
+        let handler = MockToolHandler::new()
+            .tool("lint", Reply::text("clean"))
+            .tool("lint", Reply::value(ToolResponse::new("2 warnings".into(), true)))
+            .tool("fetch", Reply::error(|| MCPError::CommandTimeout));
+        let server = SystemMCPServer::<MockToolHandler>::builder()
+            .relaxed_lifecycle()
+            .metrics_resource()
+            .build(handler);
+        server.handle(fixtures::call_tool("lint").build()).await.unwrap();
+        server.handle(fixtures::call_tool("lint").build()).await.unwrap();
+        server.handle(fixtures::call_tool("fetch").build()).await.unwrap();
+        server.handle(fixtures::call_tool("missing").build()).await.unwrap();
+
+        let metrics = server.metrics();
+        assert_eq!((metrics["lint"].calls, metrics["lint"].errors), (2, 1));
+        assert_eq!((metrics["fetch"].calls, metrics["fetch"].errors), (1, 1));
+        // Unknown names would let clients grow the table without bound
+        assert!(!metrics.contains_key("missing"));
+
+        let listed = server.handle(fixtures::request("resources/list").build()).await.unwrap().result.unwrap();
+        assert!(listed["resources"].as_array().unwrap().iter().any(|resource| resource["uri"] == METRICS_URI));
+        let read = server.handle(fixtures::request("resources/read").param("uri", METRICS_URI).build()).await.unwrap().result.unwrap();
+        let figures: Value = serde_json::from_str(read["text"].as_str().unwrap()).unwrap();
+        assert_eq!(figures["tools"]["lint"]["calls"], 2);
+        assert!(figures["tools"]["lint"]["latencyMs"]["p99"].is_number());
+    }
+
+    #[tokio::test]
+    async fn test_timeouts_are_failures() {
+        let server = SystemMCPServer::<MockToolHandler>::builder()
+            .relaxed_lifecycle()
+            .tool_timeout("build", Duration::from_millis(20))
+            .build(MockToolHandler::new().tool("build", Reply::hang()));
+        let error = server.handle(fixtures::call_tool("build").build()).await.unwrap().error.unwrap();
+        assert_eq!(error.code, MCPError::RequestTimeout(String::new()).to_json_rpc_error().code);
+
+        let stats = server.metrics()["build"];
+        assert_eq!((stats.calls, stats.errors), (1, 1));
+        assert!(stats.max >= Duration::from_millis(20));
+    }
+}
@@ -0,0 +1,267 @@
+//! Request middleware.
+//!
+//! Middleware runs around dispatch and sees both the parsed [`MCPRequest`]
+//! and, when the request came through [`SystemMCPServer::handle_raw`], the
+//! exact bytes received from the transport — enough to verify HMAC or other
+//! signatures computed over the original payload.
+//!
+//! Most middleware only needs [`Middleware::on_request`] to inspect, rewrite
+//! or reject a request, and [`Middleware::on_response`] to rewrite the
+//! answer. Middleware that wraps the whole call (timing, retries, caching)
+//! overrides [`Middleware::handle`] and decides whether and when to call
+//! [`Next::run`]. Layers run in the order they were added, so the first one
+//! sees the request first and the response last.
+//!
+//! Middleware sees requests as the client sent them. A check on tool calls
+//! belongs in a [`ToolCallGuard`] instead: guards run at dispatch, after
+//! method aliases and tool versions are resolved, so they also see calls
+//! made through an alias or by a custom method.
+//!
+//! [`SystemMCPServer::handle_raw`]: crate::server::SystemMCPServer::handle_raw
+
+use crate::context::RequestContext;
+use crate::error::MCPError;
+use crate::json;
+use crate::outbound;
+use crate::request::MCPRequest;
+use crate::response::MCPResponse;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::sync::Arc;
+
+/// A request together with the buffer it was parsed from
+#[derive(Debug, Clone)]
+pub struct IncomingRequest {
+    raw: Option<Arc<[u8]>>,
+    pub request: MCPRequest,
+}
+
+impl IncomingRequest {
+    /// Parse a request from raw transport bytes, keeping the original buffer.
+    /// A response to one of the server's own requests is wrapped for
+    /// [`crate::outbound`]. Valid JSON that is not a request fails with
+    /// [`MCPError::InvalidRequestAt`], anything else with the JSON error.
+    pub fn parse(raw: &[u8]) -> Result<Self, MCPError> {
+        let request = match json::from_slice(raw) {
+            Ok(request) => request,
+            Err(err) => match json::from_slice::<Value>(raw) {
+                Ok(message) if outbound::is_response(&message) => {
+                    MCPRequest::new(None, outbound::RESPONSE_METHOD, Some(message))
+                }
+                Ok(_) => {
+                    return Err(MCPError::InvalidRequestAt {
+                        pointer: String::new(),
+                        message: err.to_string(),
+                        expected: Some("request".into()),
+                        received: None,
+                    });
+                }
+                Err(_) => return Err(err),
+            },
+        };
+        Ok(IncomingRequest { raw: Some(Arc::from(raw)), request })
+    }
+
+    /// The answer to bytes [`parse`](Self::parse) refused: -32600 for JSON
+    /// that is not a request, -32700 otherwise
+    pub fn rejection(error: &MCPError) -> MCPResponse {
+        match error {
+            MCPError::InvalidRequestAt { .. } => MCPResponse::v2_error(None, error.to_json_rpc_error()),
+            _ => MCPResponse::parse_error(),
+        }
+    }
+
+    /// Wrap an already parsed request; no raw bytes are available
+    pub fn from_request(request: MCPRequest) -> Self {
+        IncomingRequest { raw: None, request }
+    }
+
+    /// Bytes exactly as received, if the request came from a transport
+    pub fn raw(&self) -> Option<&[u8]> {
+        self.raw.as_deref()
+    }
+}
+
+#[async_trait]
+pub trait Middleware: Send + Sync {
+    /// Inspect or modify a request before dispatch; an error short-circuits
+    /// the request and is returned to the client
+    async fn on_request(&self, request: &mut IncomingRequest) -> Result<(), MCPError> {
+        let _ = request;
+        Ok(())
+    }
+
+    /// Inspect or modify the response to `request`; not called for notifications
+    async fn on_response(&self, request: &MCPRequest, response: &mut MCPResponse) {
+        let _ = (request, response);
+    }
+
+    /// Run this layer around the rest of the stack. The request is passed
+    /// down by reference, so layers that rewrite it do so for the layers
+    /// outside them too.
+    async fn handle(&self, request: &mut IncomingRequest, next: Next<'_>) -> Option<MCPResponse> {
+        if let Err(err) = self.on_request(request).await {
+            return next.reject(&request.request, err);
+        }
+        let mut response = next.run(request).await;
+        if let Some(response) = &mut response {
+            self.on_response(&request.request, response).await;
+        }
+        response
+    }
+}
+
+pub(crate) type MiddlewareStack = Vec<Arc<dyn Middleware>>;
+
+/// A check run on each tool call right before the tool runs; see the module
+/// docs
+#[async_trait]
+pub trait ToolCallGuard: Send + Sync {
+    /// An error refuses the call and is returned to the client
+    async fn check(&self, tool: &str, args: &Value, ctx: &RequestContext) -> Result<(), MCPError>;
+}
+
+/// What the innermost layer calls: the server's own dispatch
+#[async_trait]
+pub(crate) trait Endpoint: Send + Sync {
+    async fn dispatch(&self, request: &MCPRequest) -> Option<MCPResponse>;
+
+    /// The error response for `request`, or `None` for a notification
+    fn reject(&self, request: &MCPRequest, error: MCPError) -> Option<MCPResponse>;
+}
+
+/// The layers after the current one, ending in dispatch
+pub struct Next<'a> {
+    stack: &'a [Arc<dyn Middleware>],
+    endpoint: &'a dyn Endpoint,
+}
+
+impl<'a> Next<'a> {
+    pub(crate) fn new(stack: &'a [Arc<dyn Middleware>], endpoint: &'a dyn Endpoint) -> Self {
+        Next { stack, endpoint }
+    }
+
+    /// Pass `request` on and return what the rest of the stack answered
+    pub async fn run(self, request: &mut IncomingRequest) -> Option<MCPResponse> {
+        match self.stack.split_first() {
+            Some((layer, stack)) => layer.handle(request, Next { stack, endpoint: self.endpoint }).await,
+            None => self.endpoint.dispatch(&request.request).await,
+        }
+    }
+
+    /// Answer `request` with `error` without running the rest of the stack
+    pub fn reject(&self, request: &MCPRequest, error: MCPError) -> Option<MCPResponse> {
+        self.endpoint.reject(request, error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::RequestContext;
+    use crate::server::{SystemMCPServer, ToolHandler};
+    use crate::tools::ToolResponse;
+    use serde_json::{json, Value};
+
+    struct EchoHandler;
+
+    #[async_trait]
+    impl ToolHandler for EchoHandler {
+        async fn call_tool(&self, _name: &str, _args: &Value, _ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+            Ok(ToolResponse::new("ok".into(), false))
+        }
+    }
+
+    /// Accepts only requests whose raw bytes carry a marker
+    struct RequireMarker;
+
+    #[async_trait]
+    impl Middleware for RequireMarker {
+        async fn on_request(&self, request: &mut IncomingRequest) -> Result<(), MCPError> {
+            match request.raw() {
+                Some(raw) if raw.windows(6).any(|w| w == b"signed") => Ok(()),
+                _ => Err(MCPError::InvalidParams("missing signature".into())),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_raw_messages_that_are_not_requests() {
+        let server = SystemMCPServer::<EchoHandler>::builder().relaxed_lifecycle().build(EchoHandler);
+        assert_eq!(server.handle_raw(b"{\"jsonrpc\": ").await.unwrap().error.unwrap().code, -32700);
+        for message in [&b"[1, 2]"[..], b"7", b"{\"jsonrpc\": \"2.0\", \"id\": 1}"] {
+            assert_eq!(server.handle_raw(message).await.unwrap().error.unwrap().code, -32600);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_middleware_sees_raw_bytes() {
+        let server = SystemMCPServer::<EchoHandler>::builder()
+            .relaxed_lifecycle()
+            .layer(RequireMarker)
+            .build(EchoHandler);
+
+        let signed = br#"{"jsonrpc":"2.0","id":1,"method":"tools/list","params":{"_meta":{"sig":"signed"}}}"#;
+        assert!(server.handle_raw(signed).await.unwrap().is_success());
+
+        let unsigned = br#"{"jsonrpc":"2.0","id":2,"method":"tools/list"}"#;
+        let response = server.handle_raw(unsigned).await.unwrap();
+        assert_eq!(response.error.unwrap().code, -32602);
+
+        // Parsed requests carry no raw bytes
+        let parsed = serde_json::from_value(json!({"jsonrpc": "2.0", "id": 3, "method": "tools/list"})).unwrap();
+        assert!(server.handle(parsed).await.unwrap().is_error());
+    }
+
+    /// Records the order layers see requests and responses in
+    struct Trace(&'static str, Arc<std::sync::Mutex<Vec<String>>>);
+
+    #[async_trait]
+    impl Middleware for Trace {
+        async fn on_request(&self, _request: &mut IncomingRequest) -> Result<(), MCPError> {
+            self.1.lock().unwrap().push(format!("{} request", self.0));
+            Ok(())
+        }
+
+        async fn on_response(&self, _request: &MCPRequest, response: &mut MCPResponse) {
+            self.1.lock().unwrap().push(format!("{} response", self.0));
+            if let Some(result) = &mut response.result {
+                result["_meta"] = json!({ "tracedBy": self.0 });
+            }
+        }
+    }
+
+    /// Answers `ping` itself without reaching dispatch
+    struct Pong;
+
+    #[async_trait]
+    impl Middleware for Pong {
+        async fn handle(&self, request: &mut IncomingRequest, next: Next<'_>) -> Option<MCPResponse> {
+            match request.request.method.as_str() {
+                "ping" => Some(MCPResponse::success(request.request.id.clone(), json!({ "pong": true }))),
+                _ => next.run(request).await,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_layers_wrap_dispatch() {
+        let log = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let server = SystemMCPServer::<EchoHandler>::builder()
+            .relaxed_lifecycle()
+            .layer(Trace("outer", log.clone()))
+            .layer(Pong)
+            .layer(Trace("inner", log.clone()))
+            .build(EchoHandler);
+
+        let list = MCPRequest::new(Some(json!(1)), "tools/list", None);
+        let response = server.handle(list).await.unwrap();
+        assert_eq!(response.result.unwrap()["_meta"]["tracedBy"], "outer");
+        assert_eq!(*log.lock().unwrap(), ["outer request", "inner request", "inner response", "outer response"]);
+
+        log.lock().unwrap().clear();
+        let ping = MCPRequest::new(Some(json!(2)), "ping", None);
+        assert_eq!(server.handle(ping).await.unwrap().result.unwrap()["pong"], true);
+        assert_eq!(*log.lock().unwrap(), ["outer request", "outer response"]);
+    }
+}
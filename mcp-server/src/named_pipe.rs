@@ -0,0 +1,127 @@
+//! Named pipe transport (Windows).
+//!
+//! Messages use the same [`Framing`] as [`StdioTransport`], which this
+//! transport wraps. [`serve`] keeps a pipe instance listening at all times and
+//! gives every client its own server instance, like the WebSocket transport.
+
+use crate::error::MCPError;
+use crate::middleware::IncomingRequest;
+use crate::notifications::ServerNotification;
+use crate::response::MCPResponse;
+use crate::server::{SystemMCPServer, ToolHandler};
+use crate::transport::{Framing, StdioTransport, Transport};
+use async_trait::async_trait;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite, BufReader, ReadHalf, WriteHalf};
+use tokio::net::windows::named_pipe::{ClientOptions, NamedPipeClient, NamedPipeServer, ServerOptions};
+
+pub struct NamedPipeTransport<P> {
+    inner: StdioTransport<BufReader<ReadHalf<P>>, WriteHalf<P>>,
+}
+
+impl<P: AsyncRead + AsyncWrite + Send> NamedPipeTransport<P> {
+    /// Wrap a connected pipe end
+    pub fn new(pipe: P) -> Self {
+        let (reader, writer) = tokio::io::split(pipe);
+        NamedPipeTransport { inner: StdioTransport::from_parts(BufReader::new(reader), writer) }
+    }
+
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.inner = self.inner.with_framing(framing);
+        self
+    }
+
+    pub fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.inner = self.inner.with_max_message_size(bytes);
+        self
+    }
+}
+
+impl NamedPipeTransport<NamedPipeServer> {
+    /// Create the pipe `name` (e.g. `\\.\pipe\mcp`) and wait for one client
+    pub async fn accept(name: &str) -> Result<Self, MCPError> {
+        let pipe = ServerOptions::new().first_pipe_instance(true).create(name)?;
+        pipe.connect().await?;
+        Ok(Self::new(pipe))
+    }
+}
+
+impl NamedPipeTransport<NamedPipeClient> {
+    /// Connect to a server listening on `name`
+    pub fn connect(name: &str) -> Result<Self, MCPError> {
+        Ok(Self::new(ClientOptions::new().open(name)?))
+    }
+}
+
+#[async_trait]
+impl<P: AsyncRead + AsyncWrite + Send> Transport for NamedPipeTransport<P> {
+    async fn recv(&mut self) -> Result<Option<IncomingRequest>, MCPError> {
+        self.inner.recv().await
+    }
+
+    async fn send(&mut self, response: MCPResponse) -> Result<(), MCPError> {
+        self.inner.send(response).await
+    }
+
+    async fn send_notification(&mut self, notification: ServerNotification) -> Result<(), MCPError> {
+        self.inner.send_notification(notification).await
+    }
+}
+
+/// Accept clients on pipe `name`, building a fresh server per connection
+/// with `make_server`
+pub async fn serve<H, F>(name: &str, framing: Framing, make_server: F) -> Result<(), MCPError>
+where
+    H: ToolHandler + 'static,
+    F: Fn() -> SystemMCPServer<H>,
+{
+    let mut pipe = ServerOptions::new().first_pipe_instance(true).create(name)?;
+    loop {
+        pipe.connect().await?;
+        // Create the next instance before handing this one off so clients
+        // never see the pipe missing
+        let connected = std::mem::replace(&mut pipe, ServerOptions::new().create(name)?);
+        let server = make_server();
+        tokio::spawn(async move {
+            let transport = NamedPipeTransport::new(connected).with_framing(framing);
+            if let Err(e) = Arc::new(server).runner().without_signals().run_with_transport(transport).await {
+                eprintln!("[PIPE] Connection ended with error: {}", e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures;
+    use crate::testing::mock::{MockToolHandler, Reply};
+    use serde_json::Value;
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let name = format!(r"\\.\pipe\mcp-test-{}", std::process::id());
+        let listener = name.clone();
+        tokio::spawn(async move {
+            serve(&listener, Framing::NewlineDelimited, || {
+                SystemMCPServer::<MockToolHandler>::builder().build(MockToolHandler::new().tool("echo", Reply::text("pong")))
+            }).await.unwrap();
+        });
+
+        let client = loop {
+            match ClientOptions::new().open(&name) {
+                Ok(client) => break client,
+                Err(_) => tokio::time::sleep(std::time::Duration::from_millis(10)).await,
+            }
+        };
+        let (reader, mut writer) = tokio::io::split(client);
+        let request = fixtures::call_tool("echo").id(1).arg("x", 1).to_json();
+        writer.write_all(format!("{}\n", request).as_bytes()).await.unwrap();
+
+        let mut line = String::new();
+        BufReader::new(reader).read_line(&mut line).await.unwrap();
+        let reply: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(reply["result"]["content"][0]["text"], "pong");
+    }
+}
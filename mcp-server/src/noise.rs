@@ -0,0 +1,506 @@
+//! Noise-encrypted transport (feature `noise`).
+//!
+//! When MCP traffic crosses a relay nobody should trust (a cloud tunnel, a
+//! shared broker), [`NoiseTransport`] encrypts and authenticates every
+//! message end to end with the Noise protocol framework. The server logic
+//! sees an ordinary [`Transport`]. Either side may use a pre-shared key
+//! (`Noise_NNpsk0_25519_ChaChaPoly_BLAKE2s`) or a static X25519 key pair
+//! (`Noise_XX_25519_ChaChaPoly_BLAKE2s`). With a static key pair each side
+//! lists the public keys it trusts and checks the peer's as soon as the
+//! handshake reveals it, so a client pins the server's key and a relay cannot
+//! stand in for either end.
+//!
+//! On the wire every Noise message is a 2-byte big-endian length followed by
+//! the message. After the handshake, each plaintext starts with a flag byte
+//! that is 1 while more chunks of the same JSON-RPC message follow, so
+//! messages may exceed Noise's 64 KiB limit.
+//!
+//! Keys rotate two ways:
+//! - [`NoiseConfig::rotate`] swaps the keys future handshakes use;
+//! - [`NoiseConfig::rekey_after`] rekeys established sessions every N
+//!   messages in each direction. Both peers must use the same N.
+//!
+//! A handshake must finish within [`NoiseConfig::handshake_timeout`], and a
+//! message reassembled from chunks may not grow beyond
+//! [`NoiseConfig::max_message_size`]. Receiving is cancel safe: a message
+//! partly read when `recv` is dropped is completed by the next call.
+//!
+//! [`NoiseConfig::metrics`] counts handshakes, failures and rekeys.
+
+use crate::error::MCPError;
+use crate::middleware::IncomingRequest;
+use crate::notifications::ServerNotification;
+use crate::response::MCPResponse;
+use crate::transport::{self, Transport, DEFAULT_MAX_MESSAGE_SIZE};
+use async_trait::async_trait;
+use serde::Serialize;
+use snow::{HandshakeState, TransportState};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Handshake pattern for [`NoiseKeys::PreShared`]
+pub const PSK_PATTERN: &str = "Noise_NNpsk0_25519_ChaChaPoly_BLAKE2s";
+/// Handshake pattern for [`NoiseKeys::Static`]
+pub const STATIC_PATTERN: &str = "Noise_XX_25519_ChaChaPoly_BLAKE2s";
+
+/// Largest Noise message
+const MAX_MESSAGE: usize = 65535;
+/// Plaintext per message: the AEAD tag and the continuation flag take the rest
+const MAX_CHUNK: usize = MAX_MESSAGE - 16 - 1;
+
+/// Time a handshake may take unless [`NoiseConfig::handshake_timeout`] is set
+pub const DEFAULT_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+#[derive(Clone)]
+pub enum NoiseKeys {
+    /// A 32-byte secret both peers know
+    PreShared([u8; 32]),
+    /// This side's X25519 private key; either side accepts only peers whose
+    /// public key is in `trusted`, which must not be empty
+    Static { private: [u8; 32], trusted: Vec<[u8; 32]> },
+}
+
+impl std::fmt::Debug for NoiseKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NoiseKeys::PreShared(_) => f.write_str("PreShared(..)"),
+            NoiseKeys::Static { trusted, .. } => write!(f, "Static {{ trusted: {} keys, .. }}", trusted.len()),
+        }
+    }
+}
+
+/// Handshake and rekey counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NoiseMetrics {
+    pub handshakes: u64,
+    pub handshake_failures: u64,
+    pub last_handshake_micros: u64,
+    pub rekeys: u64,
+}
+
+#[derive(Debug)]
+pub struct NoiseConfig {
+    keys: RwLock<NoiseKeys>,
+    rekey_after: Option<u64>,
+    handshake_timeout: Duration,
+    max_message_size: usize,
+    handshakes: AtomicU64,
+    handshake_failures: AtomicU64,
+    last_handshake_micros: AtomicU64,
+    rekeys: AtomicU64,
+}
+
+impl NoiseConfig {
+    pub fn new(keys: NoiseKeys) -> Self {
+        NoiseConfig {
+            keys: RwLock::new(keys),
+            rekey_after: None,
+            handshake_timeout: DEFAULT_HANDSHAKE_TIMEOUT,
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            handshakes: AtomicU64::new(0),
+            handshake_failures: AtomicU64::new(0),
+            last_handshake_micros: AtomicU64::new(0),
+            rekeys: AtomicU64::new(0),
+        }
+    }
+
+    /// Rekey each direction of a session after every `messages` Noise messages
+    pub fn rekey_after(mut self, messages: u64) -> Self {
+        self.rekey_after = Some(messages.max(1));
+        self
+    }
+
+    /// Give up on peers that have not completed the handshake after `timeout`
+    pub fn handshake_timeout(mut self, timeout: Duration) -> Self {
+        self.handshake_timeout = timeout;
+        self
+    }
+
+    /// Close sessions whose peer sends a message of more than `bytes`
+    pub fn max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = bytes;
+        self
+    }
+
+    /// Use `keys` for handshakes from now on; established sessions keep theirs
+    pub fn rotate(&self, keys: NoiseKeys) {
+        *self.keys.write().unwrap() = keys;
+        eprintln!("[NOISE] Rotated keys for new sessions");
+    }
+
+    pub fn metrics(&self) -> NoiseMetrics {
+        NoiseMetrics {
+            handshakes: self.handshakes.load(Ordering::Relaxed),
+            handshake_failures: self.handshake_failures.load(Ordering::Relaxed),
+            last_handshake_micros: self.last_handshake_micros.load(Ordering::Relaxed),
+            rekeys: self.rekeys.load(Ordering::Relaxed),
+        }
+    }
+
+    /// A fresh X25519 key pair for [`NoiseKeys::Static`]: (private, public)
+    pub fn generate_keypair() -> Result<([u8; 32], [u8; 32]), MCPError> {
+        let keypair = snow::Builder::new(params(STATIC_PATTERN)?).generate_keypair().map_err(noise_error)?;
+        let key = |bytes: Vec<u8>| <[u8; 32]>::try_from(bytes).map_err(|_| MCPError::InternalError("X25519 key is not 32 bytes".into()));
+        Ok((key(keypair.private)?, key(keypair.public)?))
+    }
+
+    fn handshake_state(&self, initiator: bool) -> Result<(HandshakeState, Vec<[u8; 32]>), MCPError> {
+        let keys = self.keys.read().unwrap().clone();
+        let (builder, trusted) = match &keys {
+            NoiseKeys::PreShared(psk) => (snow::Builder::new(params(PSK_PATTERN)?).psk(0, psk), Vec::new()),
+            NoiseKeys::Static { trusted, .. } if trusted.is_empty() => {
+                return Err(MCPError::Unauthorized("NoiseKeys::Static without trusted keys would accept any peer".into()));
+            }
+            NoiseKeys::Static { private, trusted } => {
+                (snow::Builder::new(params(STATIC_PATTERN)?).local_private_key(private), trusted.clone())
+            }
+        };
+        let state = if initiator { builder.build_initiator() } else { builder.build_responder() };
+        Ok((state.map_err(noise_error)?, trusted))
+    }
+}
+
+fn params(pattern: &str) -> Result<snow::params::NoiseParams, MCPError> {
+    pattern.parse().map_err(noise_error)
+}
+
+fn noise_error(e: snow::Error) -> MCPError {
+    MCPError::IntegrityError(format!("noise: {}", e))
+}
+
+/// An encrypted, authenticated message stream over a reader/writer pair
+pub struct NoiseStream<R, W> {
+    reader: R,
+    writer: W,
+    state: TransportState,
+    config: Arc<NoiseConfig>,
+    sent: u64,
+    received: u64,
+    buffer: Vec<u8>,
+    // Bytes read past the last whole frame
+    pending: Vec<u8>,
+    // Chunks of a message whose last chunk has not arrived
+    partial: Vec<u8>,
+}
+
+impl<R, W> std::fmt::Debug for NoiseStream<R, W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("NoiseStream").field("sent", &self.sent).field("received", &self.received).finish_non_exhaustive()
+    }
+}
+
+impl<R, W> NoiseStream<R, W>
+where
+    R: AsyncRead + Unpin + Send,
+    W: AsyncWrite + Unpin + Send,
+{
+    /// Perform the responder (server) side of the handshake
+    pub async fn accept(reader: R, writer: W, config: Arc<NoiseConfig>) -> Result<Self, MCPError> {
+        Self::handshake(reader, writer, config, false).await
+    }
+
+    /// Perform the initiator (client) side of the handshake
+    pub async fn connect(reader: R, writer: W, config: Arc<NoiseConfig>) -> Result<Self, MCPError> {
+        Self::handshake(reader, writer, config, true).await
+    }
+
+    async fn handshake(mut reader: R, mut writer: W, config: Arc<NoiseConfig>, initiator: bool) -> Result<Self, MCPError> {
+        let started = Instant::now();
+        let mut buffer = vec![0; MAX_MESSAGE];
+        let mut pending = Vec::new();
+        let handshake = async {
+            let (mut state, trusted) = config.handshake_state(initiator)?;
+            while !state.is_handshake_finished() {
+                if state.is_my_turn() {
+                    let len = state.write_message(&[], &mut buffer).map_err(noise_error)?;
+                    write_frame(&mut writer, &buffer[..len]).await?;
+                } else {
+                    let frame = read_frame(&mut reader, &mut pending).await?
+                        .ok_or_else(|| MCPError::Unauthorized("peer disconnected during the Noise handshake".into()))?;
+                    state.read_message(&frame, &mut buffer).map_err(noise_error)?;
+                    // Before answering, so an untrusted peer learns nothing more
+                    if let Some(remote) = state.get_remote_static()
+                        && !trusted.iter().any(|key| key[..] == *remote)
+                    {
+                        return Err(MCPError::Unauthorized("Noise peer key is not trusted".into()));
+                    }
+                }
+            }
+            if !trusted.is_empty() && state.get_remote_static().is_none() {
+                return Err(MCPError::Unauthorized("Noise peer sent no static key".into()));
+            }
+            state.into_transport_mode().map_err(noise_error)
+        };
+        let result = tokio::time::timeout(config.handshake_timeout, handshake).await
+            .unwrap_or_else(|_| Err(MCPError::RequestTimeout("Noise handshake".into())));
+
+        match result {
+            Ok(state) => {
+                config.handshakes.fetch_add(1, Ordering::Relaxed);
+                config.last_handshake_micros.store(started.elapsed().as_micros() as u64, Ordering::Relaxed);
+                Ok(NoiseStream { reader, writer, state, config, sent: 0, received: 0, buffer, pending, partial: Vec::new() })
+            }
+            Err(e) => {
+                config.handshake_failures.fetch_add(1, Ordering::Relaxed);
+                eprintln!("[NOISE] Handshake failed: {}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// The peer's static public key, with [`NoiseKeys::Static`]
+    pub fn remote_static(&self) -> Option<&[u8]> {
+        self.state.get_remote_static()
+    }
+
+    /// Encrypt and send one message
+    pub async fn send(&mut self, message: &[u8]) -> Result<(), MCPError> {
+        let chunks: Vec<&[u8]> = if message.is_empty() { vec![&[]] } else { message.chunks(MAX_CHUNK).collect() };
+        let mut plaintext = Vec::with_capacity(MAX_CHUNK + 1);
+        for (i, chunk) in chunks.iter().enumerate() {
+            plaintext.clear();
+            plaintext.push(u8::from(i + 1 < chunks.len()));
+            plaintext.extend_from_slice(chunk);
+            let len = self.state.write_message(&plaintext, &mut self.buffer).map_err(noise_error)?;
+            write_frame(&mut self.writer, &self.buffer[..len]).await?;
+            self.sent += 1;
+            if self.rekey_due(self.sent) {
+                self.state.rekey_outgoing();
+            }
+        }
+        self.writer.flush().await?;
+        Ok(())
+    }
+
+    /// Next decrypted message, or `None` once the peer disconnected
+    pub async fn recv(&mut self) -> Result<Option<Vec<u8>>, MCPError> {
+        loop {
+            let Some(frame) = read_frame(&mut self.reader, &mut self.pending).await? else {
+                if self.partial.is_empty() {
+                    return Ok(None);
+                }
+                return Err(MCPError::IoError(std::io::ErrorKind::UnexpectedEof.into()));
+            };
+            // From here on nothing awaits, so a whole frame is consumed or none
+            let len = self.state.read_message(&frame, &mut self.buffer).map_err(noise_error)?;
+            self.received += 1;
+            if self.rekey_due(self.received) {
+                self.state.rekey_incoming();
+            }
+            let (more, chunk) = self.buffer[..len].split_first()
+                .ok_or_else(|| MCPError::IntegrityError("noise: empty message".into()))?;
+            if self.partial.len() + chunk.len() > self.config.max_message_size {
+                self.partial = Vec::new();
+                return Err(transport::too_large(self.config.max_message_size));
+            }
+            self.partial.extend_from_slice(chunk);
+            if *more == 0 {
+                return Ok(Some(std::mem::take(&mut self.partial)));
+            }
+        }
+    }
+
+    fn rekey_due(&self, count: u64) -> bool {
+        let due = self.config.rekey_after.is_some_and(|after| count.is_multiple_of(after));
+        if due {
+            self.config.rekeys.fetch_add(1, Ordering::Relaxed);
+        }
+        due
+    }
+}
+
+async fn write_frame(writer: &mut (impl AsyncWrite + Unpin), frame: &[u8]) -> Result<(), MCPError> {
+    writer.write_u16(frame.len() as u16).await?;
+    writer.write_all(frame).await?;
+    Ok(())
+}
+
+/// Next length-prefixed frame, or `None` at end of input between frames.
+/// Bytes of an incomplete frame stay in `pending`, so a cancelled call
+/// loses nothing.
+async fn read_frame(reader: &mut (impl AsyncRead + Unpin), pending: &mut Vec<u8>) -> Result<Option<Vec<u8>>, MCPError> {
+    loop {
+        if let [high, low, rest @ ..] = pending.as_slice()
+            && rest.len() >= usize::from(u16::from_be_bytes([*high, *low]))
+        {
+            let len = 2 + usize::from(u16::from_be_bytes([*high, *low]));
+            let frame = pending[2..len].to_vec();
+            pending.drain(..len);
+            return Ok(Some(frame));
+        }
+        pending.reserve(2 + MAX_MESSAGE);
+        // read_buf is cancel safe: bytes are in `pending` once it returns
+        if reader.read_buf(pending).await? == 0 {
+            if pending.is_empty() {
+                return Ok(None);
+            }
+            return Err(MCPError::IoError(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+    }
+}
+
+/// Server side of a Noise-encrypted connection
+#[derive(Debug)]
+pub struct NoiseTransport<R, W> {
+    stream: NoiseStream<R, W>,
+}
+
+impl<R, W> NoiseTransport<R, W>
+where
+    R: AsyncRead + Unpin + Send,
+    W: AsyncWrite + Unpin + Send,
+{
+    /// Complete the handshake with the client on `reader`/`writer`
+    pub async fn accept(reader: R, writer: W, config: Arc<NoiseConfig>) -> Result<Self, MCPError> {
+        Ok(NoiseTransport { stream: NoiseStream::accept(reader, writer, config).await? })
+    }
+
+    async fn send_json(&mut self, message: &impl Serialize) -> Result<(), MCPError> {
+        let bytes = serde_json::to_vec(message)?;
+        self.stream.send(&bytes).await
+    }
+}
+
+#[async_trait]
+impl<R, W> Transport for NoiseTransport<R, W>
+where
+    R: AsyncRead + Unpin + Send,
+    W: AsyncWrite + Unpin + Send,
+{
+    async fn recv(&mut self) -> Result<Option<IncomingRequest>, MCPError> {
+        match self.stream.recv().await? {
+            Some(message) => IncomingRequest::parse(&message).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    async fn send(&mut self, response: MCPResponse) -> Result<(), MCPError> {
+        self.send_json(&response).await
+    }
+
+    async fn send_notification(&mut self, notification: ServerNotification) -> Result<(), MCPError> {
+        self.send_json(&notification.to_json_rpc()).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::{json, Value};
+    use tokio::io::{split, DuplexStream, ReadHalf, WriteHalf};
+
+    type Halves = (ReadHalf<DuplexStream>, WriteHalf<DuplexStream>);
+
+    fn pipe() -> (Halves, Halves) {
+        let (client, server) = tokio::io::duplex(1 << 16);
+        (split(client), split(server))
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_with_rekeying() {
+        let config = Arc::new(NoiseConfig::new(NoiseKeys::PreShared([7; 32])).rekey_after(2));
+        let ((client_r, client_w), (server_r, server_w)) = pipe();
+        let server = tokio::spawn({
+            let config = config.clone();
+            async move {
+                let mut transport = NoiseTransport::accept(server_r, server_w, config).await.unwrap();
+                while let Some(request) = transport.recv().await.unwrap() {
+                    let echoed = request.request.params.unwrap_or(Value::Null);
+                    transport.send(MCPResponse::success(request.request.id, echoed)).await.unwrap();
+                }
+            }
+        });
+
+        let mut client = NoiseStream::connect(client_r, client_w, config.clone()).await.unwrap();
+        // Bigger than one Noise message, and enough messages to rekey
+        for text in ["a".repeat(10), "b".repeat(200_000), "c".repeat(10)] {
+            let request = json!({ "jsonrpc": "2.0", "id": 1, "method": "echo", "params": { "text": text } });
+            client.send(&serde_json::to_vec(&request).unwrap()).await.unwrap();
+            let response: Value = serde_json::from_slice(&client.recv().await.unwrap().unwrap()).unwrap();
+            assert_eq!(response["result"]["text"], text);
+        }
+        drop(client);
+        server.await.unwrap();
+
+        let metrics = config.metrics();
+        assert_eq!((metrics.handshakes, metrics.handshake_failures), (2, 0));
+        assert!(metrics.rekeys > 0);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_wrong_and_untrusted_keys() {
+        let server_config = Arc::new(NoiseConfig::new(NoiseKeys::PreShared([1; 32])));
+        let ((client_r, client_w), (server_r, server_w)) = pipe();
+        let accept = tokio::spawn(NoiseTransport::accept(server_r, server_w, server_config.clone()));
+        let wrong = Arc::new(NoiseConfig::new(NoiseKeys::PreShared([2; 32])));
+        let _ = NoiseStream::connect(client_r, client_w, wrong).await;
+        assert!(accept.await.unwrap().is_err());
+        assert_eq!(server_config.metrics().handshake_failures, 1);
+
+        let (server_private, server_public) = NoiseConfig::generate_keypair().unwrap();
+        let (client_private, client_public) = NoiseConfig::generate_keypair().unwrap();
+        let (other_private, _) = NoiseConfig::generate_keypair().unwrap();
+        let server_config = Arc::new(NoiseConfig::new(NoiseKeys::Static { private: server_private, trusted: vec![client_public] }));
+        for (private, trusted) in [(client_private, true), (other_private, false)] {
+            let ((client_r, client_w), (server_r, server_w)) = pipe();
+            let accept = tokio::spawn(NoiseTransport::accept(server_r, server_w, server_config.clone()));
+            let client = Arc::new(NoiseConfig::new(NoiseKeys::Static { private, trusted: vec![server_public] }));
+            let _client = NoiseStream::connect(client_r, client_w, client).await;
+            assert_eq!(accept.await.unwrap().is_ok(), trusted);
+        }
+
+        // The client pins the server: a relay with its own key is refused
+        let ((client_r, client_w), (server_r, server_w)) = pipe();
+        let relay = Arc::new(NoiseConfig::new(NoiseKeys::Static { private: other_private, trusted: vec![client_public] }));
+        let accept = tokio::spawn(NoiseTransport::accept(server_r, server_w, relay));
+        let client = Arc::new(NoiseConfig::new(NoiseKeys::Static { private: client_private, trusted: vec![server_public] }));
+        assert!(matches!(NoiseStream::connect(client_r, client_w, client).await, Err(MCPError::Unauthorized(_))));
+        assert!(accept.await.unwrap().is_err());
+
+        let ((client_r, client_w), _server) = pipe();
+        let anyone = Arc::new(NoiseConfig::new(NoiseKeys::Static { private: client_private, trusted: vec![] }));
+        assert!(NoiseStream::connect(client_r, client_w, anyone).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cancel_safety_and_limits() {
+        let config = Arc::new(NoiseConfig::new(NoiseKeys::PreShared([3; 32])).max_message_size(70_000));
+        // Room for whole frames written before the server reads
+        let (client_end, server_end) = tokio::io::duplex(1 << 20);
+        let ((client_r, client_w), (server_r, server_w)) = (split(client_end), split(server_end));
+        let accept = tokio::spawn(NoiseStream::accept(server_r, server_w, config.clone()));
+        let mut client = NoiseStream::connect(client_r, client_w, config.clone()).await.unwrap();
+        let mut server = accept.await.unwrap().unwrap();
+
+        // Two chunks; recv is cancelled while the second has not arrived
+        let message = "m".repeat(MAX_CHUNK + 10);
+        let (first, second) = message.as_bytes().split_at(MAX_CHUNK);
+        let mut plaintext = vec![1];
+        plaintext.extend_from_slice(first);
+        let len = client.state.write_message(&plaintext, &mut client.buffer).unwrap();
+        write_frame(&mut client.writer, &client.buffer[..len]).await.unwrap();
+        assert!(tokio::time::timeout(Duration::from_millis(20), server.recv()).await.is_err());
+        let mut plaintext = vec![0];
+        plaintext.extend_from_slice(second);
+        let len = client.state.write_message(&plaintext, &mut client.buffer).unwrap();
+        // Half a frame, then cancel again
+        let frame = client.buffer[..len].to_vec();
+        client.writer.write_u16(frame.len() as u16).await.unwrap();
+        client.writer.write_all(&frame[..5]).await.unwrap();
+        assert!(tokio::time::timeout(Duration::from_millis(20), server.recv()).await.is_err());
+        client.writer.write_all(&frame[5..]).await.unwrap();
+        assert_eq!(server.recv().await.unwrap().unwrap(), message.as_bytes());
+
+        let oversized = vec![b'x'; 140_000];
+        let (sent, received) = tokio::join!(client.send(&oversized), server.recv());
+        assert!(sent.is_ok() && matches!(received, Err(MCPError::IoError(_))));
+
+        // A peer that connects and says nothing
+        let ((_client_r, _client_w), (server_r, server_w)) = pipe();
+        let impatient = Arc::new(NoiseConfig::new(NoiseKeys::PreShared([3; 32])).handshake_timeout(Duration::from_millis(20)));
+        assert!(matches!(NoiseStream::accept(server_r, server_w, impatient).await, Err(MCPError::RequestTimeout(_))));
+    }
+}
@@ -0,0 +1,513 @@
+use crate::call_log::CallLogs;
+use crate::config;
+use crate::context::RequestContext;
+use crate::error::MCPError;
+use crate::memory::{MemoryAccountant, MemoryCategory};
+use crate::priority::Priority;
+use crate::tools::ProgressNotificationMessage;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+
+/// Notification types for multiplexed output
+#[derive(Debug, Clone)]
+pub enum ServerNotification {
+    Progress {
+        request_id: String,
+        progress: f64,
+        message: Option<String>,
+        /// Sent as `params._meta`, e.g. a [`StagedProgress`] stage
+        meta: Option<Value>,
+    },
+    /// A subscribed resource changed
+    ResourceUpdated {
+        uri: String,
+    },
+    /// The set of tools changed
+    ToolListChanged,
+    /// The set of prompts changed
+    PromptListChanged,
+    /// The set of resources changed
+    ResourceListChanged,
+    /// A log message for the client (`notifications/message`)
+    Log {
+        level: String,
+        logger: Option<String>,
+        data: Value,
+    },
+    /// A request to the client, answered through [`crate::outbound::ClientRequests`]
+    Request {
+        id: String,
+        method: String,
+        params: Value,
+    },
+    /// A notification for the transport the originating request arrived on,
+    /// when a runner serves several transports
+    Routed {
+        origin: usize,
+        notification: Box<ServerNotification>,
+    },
+}
+
+impl ServerNotification {
+    /// The JSON-RPC notification sent to the client
+    pub fn to_json_rpc(&self) -> Value {
+        match self {
+            ServerNotification::Progress { request_id, progress, message, meta } => {
+                let mut notification = json!(ProgressNotificationMessage::new(request_id.clone(), *progress, message.clone()));
+                if let Some(meta) = meta {
+                    notification["params"]["_meta"] = meta.clone();
+                }
+                notification
+            }
+            ServerNotification::ResourceUpdated { uri } => json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/resources/updated",
+                "params": { "uri": uri },
+            }),
+            ServerNotification::ToolListChanged => json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/tools/list_changed",
+            }),
+            ServerNotification::PromptListChanged => json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/prompts/list_changed",
+            }),
+            ServerNotification::ResourceListChanged => json!({
+                "jsonrpc": "2.0",
+                "method": "notifications/resources/list_changed",
+            }),
+            ServerNotification::Log { level, logger, data } => {
+                let mut params = json!({ "level": level, "data": data });
+                if let Some(logger) = logger {
+                    params["logger"] = json!(logger);
+                }
+                json!({
+                    "jsonrpc": "2.0",
+                    "method": "notifications/message",
+                    "params": params,
+                })
+            }
+            ServerNotification::Request { id, method, params } => json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "method": method,
+                "params": params,
+            }),
+            ServerNotification::Routed { notification, .. } => notification.to_json_rpc(),
+        }
+    }
+
+    /// Approximate bytes held while queued
+    pub fn approx_size(&self) -> usize {
+        match self {
+            ServerNotification::Progress { request_id, message, meta, .. } => {
+                std::mem::size_of::<Self>()
+                    + request_id.len()
+                    + message.as_ref().map_or(0, String::len)
+                    + meta.as_ref().map_or(0, |meta| meta.to_string().len())
+            }
+            ServerNotification::ResourceUpdated { uri } => std::mem::size_of::<Self>() + uri.len(),
+            ServerNotification::ToolListChanged
+            | ServerNotification::PromptListChanged
+            | ServerNotification::ResourceListChanged => std::mem::size_of::<Self>(),
+            ServerNotification::Log { level, logger, data } => {
+                std::mem::size_of::<Self>() + level.len() + logger.as_ref().map_or(0, String::len) + data.to_string().len()
+            }
+            ServerNotification::Request { id, method, params } => {
+                std::mem::size_of::<Self>() + id.len() + method.len() + params.to_string().len()
+            }
+            ServerNotification::Routed { notification, .. } => std::mem::size_of::<Self>() + notification.approx_size(),
+        }
+    }
+}
+
+/// How often progress notifications for one call may be sent
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ProgressPolicy {
+    /// Send every update
+    #[default]
+    Unthrottled,
+    /// At most one update per interval
+    Fixed(Duration),
+    /// Interval follows the measured client latency, within bounds
+    Adaptive { min: Duration, max: Duration },
+}
+
+impl ProgressPolicy {
+    /// Adaptive between 20ms (local clients) and 2s (slow links)
+    pub fn adaptive() -> Self {
+        ProgressPolicy::Adaptive { min: Duration::from_millis(20), max: Duration::from_secs(2) }
+    }
+}
+
+/// Debounce interval as a multiple of the smoothed delivery latency
+const ADAPTIVE_LATENCY_FACTOR: u32 = 4;
+
+/// Per-session progress debounce driven by a [`ProgressPolicy`].
+///
+/// Delivery latency is fed back by the transport loop through
+/// [`NotificationReceiver::record_delivery`] and smoothed as an EWMA.
+#[derive(Debug)]
+pub struct ProgressThrottle {
+    policy: ProgressPolicy,
+    // Smoothed delivery latency in microseconds
+    latency_us: AtomicU64,
+    // Keyed by session and request id: ids are only unique within a session
+    last_sent: Mutex<HashMap<(String, String), Instant>>,
+}
+
+impl ProgressThrottle {
+    pub fn new(policy: ProgressPolicy) -> Self {
+        ProgressThrottle {
+            policy,
+            latency_us: AtomicU64::new(0),
+            last_sent: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn record_latency(&self, latency: Duration) {
+        let sample = latency.as_micros().min(u64::MAX as u128) as u64;
+        let _ = self.latency_us.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |current| {
+            Some(if current == 0 { sample } else { (current * 7 + sample) / 8 })
+        });
+    }
+
+    pub fn latency(&self) -> Duration {
+        Duration::from_micros(self.latency_us.load(Ordering::Relaxed))
+    }
+
+    /// Minimum time between two updates of the same call
+    pub fn interval(&self) -> Duration {
+        match self.policy {
+            ProgressPolicy::Unthrottled => Duration::ZERO,
+            ProgressPolicy::Fixed(interval) => interval,
+            ProgressPolicy::Adaptive { min, max } => (self.latency() * ADAPTIVE_LATENCY_FACTOR).clamp(min, max),
+        }
+    }
+
+    /// Whether an update of `session`'s call may be sent now; completion
+    /// always passes
+    pub fn admit(&self, session: &str, request_id: &str, progress: f64) -> bool {
+        let key = (session.to_string(), request_id.to_string());
+        let mut last_sent = self.last_sent.lock().unwrap();
+        if progress >= 1.0 {
+            last_sent.remove(&key);
+            return true;
+        }
+        let now = Instant::now();
+        if last_sent.get(&key).is_some_and(|last| now.duration_since(*last) < self.interval()) {
+            return false;
+        }
+        last_sent.insert(key, now);
+        true
+    }
+
+    /// Forget a call's state once it has finished
+    pub fn finish(&self, session: &str, request_id: &str) {
+        self.last_sent.lock().unwrap().remove(&(session.to_string(), request_id.to_string()));
+    }
+}
+
+/// Receiving end of the notification queue
+#[derive(Debug)]
+pub struct NotificationReceiver {
+    receiver: mpsc::UnboundedReceiver<ServerNotification>,
+    memory: Option<Arc<MemoryAccountant>>,
+    throttle: Option<Arc<ProgressThrottle>>,
+}
+
+impl NotificationReceiver {
+    pub fn new(receiver: mpsc::UnboundedReceiver<ServerNotification>, memory: Option<Arc<MemoryAccountant>>) -> Self {
+        Self { receiver, memory, throttle: None }
+    }
+
+    /// Feed delivery latency into the session's progress throttle
+    pub fn with_throttle(mut self, throttle: Arc<ProgressThrottle>) -> Self {
+        self.throttle = Some(throttle);
+        self
+    }
+
+    /// Report how long writing a notification to the client took
+    pub fn record_delivery(&self, latency: Duration) {
+        if let Some(throttle) = &self.throttle {
+            throttle.record_latency(latency);
+        }
+    }
+
+    pub async fn recv(&mut self) -> Option<ServerNotification> {
+        let notification = self.receiver.recv().await?;
+        self.release(&notification);
+        Some(notification)
+    }
+
+    pub fn try_recv(&mut self) -> Option<ServerNotification> {
+        let notification = self.receiver.try_recv().ok()?;
+        self.release(&notification);
+        Some(notification)
+    }
+
+    fn release(&self, notification: &ServerNotification) {
+        if let Some(memory) = &self.memory {
+            memory.release(MemoryCategory::Notifications, notification.approx_size());
+        }
+    }
+}
+
+/// Progress sender for handlers to use
+#[derive(Debug, Clone)]
+pub struct ProgressSender {
+    sender: mpsc::UnboundedSender<ServerNotification>,
+    // Call log resource (`call://{request_id}/log`) mirroring progress messages
+    log: Option<(Arc<CallLogs>, String)>,
+    memory: Option<Arc<MemoryAccountant>>,
+    throttle: Option<Arc<ProgressThrottle>>,
+    priority: Priority,
+    // Transport of the request, when a runner serves several
+    origin: Option<usize>,
+    // Minimum level of `notifications/message` the session takes
+    log_level: Option<String>,
+    // Session of the request, which its progress is throttled within
+    session: String,
+}
+
+impl ProgressSender {
+    /// Create a new progress sender from an unbounded channel sender
+    pub fn new(sender: mpsc::UnboundedSender<ServerNotification>) -> Self {
+        Self { sender, log: None, memory: None, throttle: None, priority: Priority::Interactive, origin: None, log_level: None, session: String::new() }
+    }
+
+    /// A sender whose notifications go nowhere, for calls made outside a server
+    pub fn disconnected() -> Self {
+        Self::new(mpsc::unbounded_channel().0)
+    }
+
+    /// Priority lane of the call this sender belongs to
+    pub fn with_priority(mut self, priority: Priority) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    pub fn priority(&self) -> Priority {
+        self.priority
+    }
+
+    /// Transport this sender routes to, inside a multi-transport runner
+    pub fn origin(&self) -> Option<usize> {
+        self.origin
+    }
+
+    /// Route progress back to transport `origin`
+    pub fn with_origin(mut self, origin: Option<usize>) -> Self {
+        self.origin = origin;
+        self
+    }
+
+    /// Debounce updates according to the session's progress policy
+    pub fn with_throttle(mut self, throttle: Arc<ProgressThrottle>) -> Self {
+        self.throttle = Some(throttle);
+        self
+    }
+
+    /// Account queued notifications against `memory`, dropping them under pressure
+    pub fn with_memory(mut self, memory: Arc<MemoryAccountant>) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+
+    /// Also append progress messages to the call log at `uri`
+    pub fn with_call_log(mut self, logs: Arc<CallLogs>, uri: String) -> Self {
+        self.log = Some((logs, uri));
+        self
+    }
+
+    /// Throttle progress within `session`, whose request ids may repeat
+    /// those of other sessions
+    pub fn with_session(mut self, session: impl Into<String>) -> Self {
+        self.session = session.into();
+        self
+    }
+
+    /// Drop log messages below `level`; `None` passes everything
+    pub fn with_log_level(mut self, level: Option<String>) -> Self {
+        self.log_level = level;
+        self
+    }
+
+    /// Send `notifications/message` at `level` (`"debug"` to `"emergency"`)
+    /// unless the session asked for more severe messages only
+    pub fn send_log(&self, level: &str, logger: Option<String>, data: Value) -> Result<(), mpsc::error::SendError<ServerNotification>> {
+        if self.log_level.as_deref().is_some_and(|minimum| !config::level_passes(level, minimum)) {
+            return Ok(());
+        }
+        self.send(ServerNotification::Log { level: level.into(), logger, data })
+    }
+
+    /// Append a line to the call log without sending a notification
+    pub fn log(&self, line: impl Into<String>) {
+        if let Some((logs, uri)) = &self.log {
+            logs.append(uri, line.into());
+        }
+    }
+
+    /// Queue `notification` for the transport of the request, bypassing
+    /// throttling and memory accounting
+    pub(crate) fn send(&self, notification: ServerNotification) -> Result<(), mpsc::error::SendError<ServerNotification>> {
+        match self.origin {
+            Some(origin) => self.sender.send(ServerNotification::Routed { origin, notification: Box::new(notification) }),
+            None => self.sender.send(notification),
+        }
+    }
+
+    /// Send a progress notification
+    pub async fn send_progress(&self, request_id: &str, progress: f64, message: Option<String>) -> Result<(), mpsc::error::SendError<ServerNotification>> {
+        self.queue_progress(request_id, progress, message, None)
+    }
+
+    /// Send a progress notification carrying `meta` as `params._meta`,
+    /// throttled like any other update
+    pub async fn send_progress_with_meta(&self, request_id: &str, progress: f64, message: Option<String>, meta: Value) -> Result<(), mpsc::error::SendError<ServerNotification>> {
+        self.queue_progress(request_id, progress, message, Some(meta))
+    }
+
+    fn queue_progress(&self, request_id: &str, progress: f64, message: Option<String>, meta: Option<Value>) -> Result<(), mpsc::error::SendError<ServerNotification>> {
+        if let Some(message) = &message {
+            self.log(format!("[{:>3.0}%] {}", progress * 100.0, message));
+        }
+        if self.throttle.as_ref().is_some_and(|throttle| !throttle.admit(&self.session, request_id, progress)) {
+            return Ok(());
+        }
+        let mut notification = ServerNotification::Progress {
+            request_id: request_id.to_string(),
+            progress,
+            message,
+            meta,
+        };
+        if let Some(origin) = self.origin {
+            notification = ServerNotification::Routed { origin, notification: Box::new(notification) };
+        }
+        if let Some(memory) = &self.memory
+            && !(memory.admit_notification() && memory.try_reserve(MemoryCategory::Notifications, notification.approx_size()))
+        {
+            // Shedding: progress is advisory, the call itself carries on
+            return Ok(());
+        }
+        self.sender.send(notification)
+    }
+}
+
+/// Progress through a fixed pipeline of stages, such as a build runner's
+/// fetch, compile and link. Each update carries
+/// `_meta: { "stage": ..., "step": 3, "of": 7 }` (steps count from 1) so a
+/// client can render the pipeline; `progress` stays a plain fraction for
+/// clients that ignore it. Stages are anything serializable, typically an
+/// enum with `#[serde(rename_all = "lowercase")]`.
+#[derive(Debug, Clone)]
+pub struct StagedProgress<S> {
+    sender: ProgressSender,
+    request_id: String,
+    stages: Vec<S>,
+}
+
+impl<S: Serialize + PartialEq> StagedProgress<S> {
+    pub fn new(ctx: &RequestContext, stages: impl IntoIterator<Item = S>) -> Self {
+        StagedProgress {
+            sender: ctx.progress().clone(),
+            request_id: ctx.request_id().to_string(),
+            stages: stages.into_iter().collect(),
+        }
+    }
+
+    /// Report that `stage` started
+    pub async fn enter(&self, stage: &S, message: Option<String>) -> Result<(), MCPError> {
+        self.update(stage, 0.0, message).await
+    }
+
+    /// Report that `stage` is `fraction` done
+    pub async fn update(&self, stage: &S, fraction: f64, message: Option<String>) -> Result<(), MCPError> {
+        let index = self.stages.iter().position(|known| known == stage)
+            .ok_or_else(|| MCPError::InternalError(format!("unknown stage {}", json!(stage))))?;
+        let progress = (index as f64 + fraction.clamp(0.0, 1.0)) / self.stages.len() as f64;
+        self.send(index, progress, message).await
+    }
+
+    /// Report that the last stage completed
+    pub async fn finish(&self, message: Option<String>) -> Result<(), MCPError> {
+        match self.stages.len() {
+            0 => Ok(()),
+            len => self.send(len - 1, 1.0, message).await,
+        }
+    }
+
+    async fn send(&self, index: usize, progress: f64, message: Option<String>) -> Result<(), MCPError> {
+        let meta = json!({ "stage": self.stages[index], "step": index + 1, "of": self.stages.len() });
+        self.sender.send_progress_with_meta(&self.request_id, progress, message, meta).await
+            .map_err(|_| MCPError::InternalError("progress channel closed".into()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_adaptive_throttle() {
+        let min = Duration::from_millis(10);
+        let max = Duration::from_millis(500);
+        let throttle = ProgressThrottle::new(ProgressPolicy::Adaptive { min, max });
+        assert_eq!(throttle.interval(), min);
+
+        throttle.record_latency(Duration::from_millis(50));
+        assert_eq!(throttle.interval(), Duration::from_millis(200));
+        throttle.record_latency(Duration::from_secs(10));
+        assert_eq!(throttle.interval(), max);
+
+        assert!(throttle.admit("a", "call", 0.1));
+        assert!(!throttle.admit("a", "call", 0.2));
+        assert!(throttle.admit("a", "other", 0.2));
+        // Another session's request with the same id has its own budget
+        assert!(throttle.admit("b", "call", 0.2));
+        assert!(throttle.admit("a", "call", 1.0));
+    }
+
+    #[derive(Serialize, PartialEq)]
+    #[serde(rename_all = "lowercase")]
+    enum Stage {
+        Fetching,
+        Compiling,
+        Linking,
+    }
+
+    #[tokio::test]
+    async fn test_staged_progress() {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let ctx = RequestContext::new("build-1", None, Default::default()).with_progress(ProgressSender::new(tx));
+        let stages = StagedProgress::new(&ctx, [Stage::Fetching, Stage::Compiling, Stage::Linking]);
+
+        stages.enter(&Stage::Fetching, None).await.unwrap();
+        stages.update(&Stage::Compiling, 0.5, Some("crate 3/6".into())).await.unwrap();
+        stages.finish(None).await.unwrap();
+
+        let sent: Vec<Value> = std::iter::from_fn(|| rx.try_recv().ok()).map(|n| n.to_json_rpc()["params"].clone()).collect();
+        assert_eq!(sent.len(), 3);
+        assert_eq!(sent[0]["_meta"], json!({ "stage": "fetching", "step": 1, "of": 3 }));
+        assert_eq!((sent[1]["progress"].clone(), sent[1]["_meta"]["stage"].clone()), (json!(0.5), json!("compiling")));
+        assert_eq!((sent[2]["progress"].clone(), sent[2]["_meta"]["step"].clone()), (json!(1.0), json!(3)));
+
+        // Stage updates are throttled too; completion always goes out
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let throttle = Arc::new(ProgressThrottle::new(ProgressPolicy::Fixed(Duration::from_secs(60))));
+        let ctx = RequestContext::new("build-2", None, Default::default())
+            .with_progress(ProgressSender::new(tx).with_throttle(throttle));
+        let stages = StagedProgress::new(&ctx, [Stage::Fetching, Stage::Compiling, Stage::Linking]);
+        stages.enter(&Stage::Fetching, None).await.unwrap();
+        stages.update(&Stage::Compiling, 0.5, None).await.unwrap();
+        stages.finish(None).await.unwrap();
+        let steps: Vec<Value> = std::iter::from_fn(|| rx.try_recv().ok()).map(|n| n.to_json_rpc()["params"]["_meta"]["step"].clone()).collect();
+        assert_eq!(steps, [json!(1), json!(3)]);
+    }
+}
@@ -0,0 +1,278 @@
+//! Requests from the server to the client, such as `sampling/createMessage`.
+//!
+//! A handler's request goes out through the notification queue, so a runner
+//! writes it to the transport the call came from. The client's response
+//! arrives like any other message: [`IncomingRequest::parse`] wraps it as a
+//! `$/response` pseudo-notification and the server hands it to
+//! [`ClientRequests`] before middleware runs, which wakes the waiting handler.
+//!
+//! Requests get random ids and wait under the transport they went out on, so
+//! a response is only taken from the connection that was asked, and a client
+//! cannot answer for another by guessing ids. A handler waits at most
+//! [`DEFAULT_CLIENT_REQUEST_TIMEOUT`] unless the builder sets another limit.
+//!
+//! The client's roots are cached per session until it sends
+//! `notifications/roots/list_changed`.
+//!
+//! [`IncomingRequest::parse`]: crate::middleware::IncomingRequest::parse
+
+use crate::error::{JsonRpcError, MCPError};
+use crate::notifications::{ProgressSender, ServerNotification};
+use crate::roots::Root;
+use crate::session::random_id;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// Method under which client responses reach the server
+pub(crate) const RESPONSE_METHOD: &str = "$/response";
+
+/// How long a handler waits for the client's answer by default
+pub const DEFAULT_CLIENT_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
+
+type Reply = Result<Value, JsonRpcError>;
+
+/// Transport a request went out on, and its id
+type PendingKey = (Option<usize>, String);
+
+/// Outbound requests waiting for the client's response
+#[derive(Debug)]
+pub struct ClientRequests {
+    timeout: Duration,
+    pending: Mutex<HashMap<PendingKey, oneshot::Sender<Reply>>>,
+    // Session id -> roots from its last roots/list
+    roots: Mutex<HashMap<String, Vec<Root>>>,
+    // Bumped on every invalidation, so a stale answer is not cached
+    roots_generation: AtomicU64,
+}
+
+impl Default for ClientRequests {
+    fn default() -> Self {
+        Self::new(DEFAULT_CLIENT_REQUEST_TIMEOUT)
+    }
+}
+
+impl ClientRequests {
+    /// Give up on requests the client has not answered within `timeout`
+    pub fn new(timeout: Duration) -> Self {
+        ClientRequests {
+            timeout,
+            pending: Mutex::new(HashMap::new()),
+            roots: Mutex::new(HashMap::new()),
+            roots_generation: AtomicU64::new(0),
+        }
+    }
+
+    /// Send `method` to the client through `sender` and wait for its result
+    pub(crate) async fn request(&self, sender: &ProgressSender, method: &str, params: Value) -> Result<Value, MCPError> {
+        // Not a number, so it cannot be mistaken for one of the client's own ids
+        let id = format!("server-{}", random_id()?);
+        let key = (sender.origin(), id.clone());
+        let (reply_tx, reply_rx) = oneshot::channel();
+        self.pending.lock().unwrap().insert(key.clone(), reply_tx);
+        // Forget the request if the caller gives up (cancellation, timeout)
+        let _pending = Forget { requests: self, key: &key };
+
+        sender.send(ServerNotification::Request { id: id.clone(), method: method.into(), params })
+            .map_err(|_| MCPError::InternalError("no connection to the client".into()))?;
+        match tokio::time::timeout(self.timeout, reply_rx).await {
+            Ok(Ok(Ok(result))) => Ok(result),
+            Ok(Ok(Err(error))) => Err(MCPError::PeerError(error)),
+            Ok(Err(_)) => Err(MCPError::InternalError(format!("{} {} was dropped", method, id))),
+            Err(_) => Err(MCPError::RequestTimeout(format!("{} got no answer within {:?}", method, self.timeout))),
+        }
+    }
+
+    /// Deliver a response from the client on transport `origin`; false if
+    /// nothing there waits for it
+    pub(crate) fn complete(&self, origin: Option<usize>, response: &Value) -> bool {
+        let Some(id) = response.get("id").and_then(Value::as_str) else { return false };
+        let Some(waiter) = self.pending.lock().unwrap().remove(&(origin, id.to_string())) else {
+            eprintln!("[OUTBOUND] Response to unknown request {}", id);
+            return false;
+        };
+        let reply = match response.get("error") {
+            Some(error) => Err(serde_json::from_value(error.clone()).unwrap_or_else(|_| JsonRpcError {
+                code: -32603,
+                message: error.to_string(),
+                data: None,
+            })),
+            None => Ok(response.get("result").cloned().unwrap_or(Value::Null)),
+        };
+        waiter.send(reply).is_ok()
+    }
+
+    pub(crate) fn cached_roots(&self, session_id: &str) -> Option<Vec<Root>> {
+        self.roots.lock().unwrap().get(session_id).cloned()
+    }
+
+    pub(crate) fn roots_generation(&self) -> u64 {
+        self.roots_generation.load(Ordering::Acquire)
+    }
+
+    /// Cache `roots` unless they were invalidated since `generation`
+    pub(crate) fn cache_roots(&self, session_id: &str, generation: u64, roots: Vec<Root>) {
+        let mut cached = self.roots.lock().unwrap();
+        if self.roots_generation() == generation {
+            cached.insert(session_id.to_string(), roots);
+        }
+    }
+
+    pub(crate) fn forget_roots(&self, session_id: &str) {
+        let mut cached = self.roots.lock().unwrap();
+        self.roots_generation.fetch_add(1, Ordering::AcqRel);
+        cached.remove(session_id);
+    }
+
+    /// Requests still waiting for a response
+    pub fn pending(&self) -> usize {
+        self.pending.lock().unwrap().len()
+    }
+}
+
+struct Forget<'a> {
+    requests: &'a ClientRequests,
+    key: &'a PendingKey,
+}
+
+impl Drop for Forget<'_> {
+    fn drop(&mut self) {
+        self.requests.pending.lock().unwrap().remove(self.key);
+    }
+}
+
+/// Whether a parsed message is a response rather than a request
+pub(crate) fn is_response(message: &Value) -> bool {
+    message.get("method").is_none()
+        && message.get("id").is_some()
+        && (message.get("result").is_some() || message.get("error").is_some())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::RequestContext;
+    use crate::server::{SystemMCPServer, ToolHandler};
+    use crate::tools::{PromptMessage, ToolResponse};
+    use crate::transport::{in_process, InProcessClient};
+    use crate::CreateMessageParams;
+    use serde_json::json;
+
+    struct Summarizer;
+
+    #[async_trait::async_trait]
+    impl ToolHandler for Summarizer {
+        async fn call_tool(&self, _name: &str, args: &Value, ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+            let text = args["text"].as_str().unwrap_or_default();
+            let params = CreateMessageParams::new(vec![PromptMessage::user(format!("Summarize: {}", text))], 50);
+            let message = ctx.create_message(params).await?;
+            Ok(ToolResponse::new(message.text().unwrap_or_default().into(), false))
+        }
+    }
+
+    /// Initialize with `capabilities`, as the capability checks require
+    async fn initialize(client: &mut InProcessClient, capabilities: Value) {
+        let init = json!({ "jsonrpc": "2.0", "id": 0, "method": "initialize", "params": { "capabilities": capabilities } });
+        client.send_raw(init.to_string()).unwrap();
+        assert_eq!(client.next_notification().await.unwrap()["id"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_tool_samples_through_client() {
+        let (mut client, transport) = in_process();
+        tokio::spawn(async move {
+            let server = std::sync::Arc::new(SystemMCPServer::<Summarizer>::builder().relaxed_lifecycle().build(Summarizer));
+            server.runner().without_signals().run_with_transport(transport).await.unwrap();
+        });
+        initialize(&mut client, json!({ "sampling": {} })).await;
+
+        let call = json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/call", "params": { "name": "summarize", "arguments": { "text": "long" } } });
+        client.send_raw(call.to_string()).unwrap();
+        let sampling = client.next_notification().await.unwrap();
+        assert_eq!(sampling["method"], "sampling/createMessage");
+        assert_eq!(sampling["params"]["messages"][0]["content"]["text"], "Summarize: long");
+
+        let reply = json!({
+            "jsonrpc": "2.0",
+            "id": sampling["id"],
+            "result": { "role": "assistant", "content": { "type": "text", "text": "short" }, "model": "m" },
+        });
+        client.send_raw(reply.to_string()).unwrap();
+        let response = client.next_notification().await.unwrap();
+        assert_eq!((response["id"].clone(), response["result"]["content"][0]["text"].clone()), (json!(1), json!("short")));
+    }
+
+    struct RootCounter;
+
+    #[async_trait::async_trait]
+    impl ToolHandler for RootCounter {
+        async fn call_tool(&self, _name: &str, _args: &Value, ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+            let roots = ctx.list_roots().await?;
+            Ok(ToolResponse::new(roots.len().to_string(), false))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_roots_cached_until_changed() {
+        let (mut client, transport) = in_process();
+        tokio::spawn(async move {
+            let server = std::sync::Arc::new(SystemMCPServer::<RootCounter>::builder().relaxed_lifecycle().build(RootCounter));
+            server.runner().without_signals().run_with_transport(transport).await.unwrap();
+        });
+        initialize(&mut client, json!({ "roots": { "listChanged": true } })).await;
+        let call = |id: u64| json!({ "jsonrpc": "2.0", "id": id, "method": "tools/call", "params": { "name": "roots" } }).to_string();
+        let roots = |id: &Value, count: usize| {
+            let roots: Vec<Value> = (0..count).map(|i| json!({ "uri": format!("file:///r{}", i) })).collect();
+            json!({ "jsonrpc": "2.0", "id": id, "result": { "roots": roots } }).to_string()
+        };
+
+        client.send_raw(call(1)).unwrap();
+        let request = client.next_notification().await.unwrap();
+        assert_eq!(request["method"], "roots/list");
+        client.send_raw(roots(&request["id"], 1)).unwrap();
+        assert_eq!(client.next_notification().await.unwrap()["result"]["content"][0]["text"], "1");
+
+        // Answered from the cache
+        client.send_raw(call(2)).unwrap();
+        let response = client.next_notification().await.unwrap();
+        assert_eq!((response["id"].clone(), response["result"]["content"][0]["text"].clone()), (json!(2), json!("1")));
+
+        client.send_raw(json!({ "jsonrpc": "2.0", "method": "notifications/roots/list_changed" }).to_string()).unwrap();
+        client.send_raw(call(3)).unwrap();
+        let request = client.next_notification().await.unwrap();
+        assert_eq!(request["method"], "roots/list");
+        client.send_raw(roots(&request["id"], 2)).unwrap();
+        assert_eq!(client.next_notification().await.unwrap()["result"]["content"][0]["text"], "2");
+    }
+
+    #[tokio::test]
+    async fn test_responses_only_from_the_asked_transport() {
+        let requests = ClientRequests::new(Duration::from_millis(50));
+        let (tx, mut rx) = tokio::sync::mpsc::unbounded_channel();
+        let sender = ProgressSender::new(tx).with_origin(Some(0));
+        let answer = async {
+            let Some(ServerNotification::Routed { origin: 0, notification }) = rx.recv().await else { panic!("not routed") };
+            let ServerNotification::Request { id, .. } = *notification else { panic!("not a request") };
+            let reply = json!({ "jsonrpc": "2.0", "id": id, "result": {} });
+            assert!(!requests.complete(Some(1), &reply));
+            assert!(requests.complete(Some(0), &reply));
+        };
+        let (result, ()) = tokio::join!(requests.request(&sender, "ping", json!({})), answer);
+        assert!(result.is_ok());
+
+        // Nobody answers
+        assert!(matches!(requests.request(&sender, "ping", json!({})).await, Err(MCPError::RequestTimeout(_))));
+        assert_eq!(requests.pending(), 0);
+    }
+
+    #[test]
+    fn test_unmatched_responses() {
+        let requests = ClientRequests::default();
+        assert!(!requests.complete(None, &json!({ "jsonrpc": "2.0", "id": "server-9", "result": {} })));
+        assert!(is_response(&json!({ "jsonrpc": "2.0", "id": 1, "error": { "code": 1, "message": "no" } })));
+        assert!(!is_response(&json!({ "jsonrpc": "2.0", "id": 1, "method": "ping" })));
+    }
+}
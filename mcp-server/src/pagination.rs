@@ -0,0 +1,94 @@
+//! Cursor pagination for list endpoints.
+//!
+//! With [`ServerBuilder::pagination`] the `tools/list`, `prompts/list` and
+//! `resources/list` results are split into pages. Clients may hint a page
+//! size with `_meta.pageSize`; the hint is clamped to the server's bounds so
+//! capable clients save round-trips without requesting unbounded pages.
+//!
+//! [`ServerBuilder::pagination`]: crate::server::ServerBuilder::pagination
+
+use crate::error::MCPError;
+use serde_json::{Map, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Paginator {
+    default_page_size: usize,
+    min_page_size: usize,
+    max_page_size: usize,
+}
+
+impl Default for Paginator {
+    fn default() -> Self {
+        Paginator::new(50, 1, 500)
+    }
+}
+
+impl Paginator {
+    /// Pages of `default_page_size` items; client hints are clamped to
+    /// `min_page_size..=max_page_size`
+    pub fn new(default_page_size: usize, min_page_size: usize, max_page_size: usize) -> Self {
+        let min_page_size = min_page_size.max(1);
+        let max_page_size = max_page_size.max(min_page_size);
+        Paginator {
+            default_page_size: default_page_size.clamp(min_page_size, max_page_size),
+            min_page_size,
+            max_page_size,
+        }
+    }
+
+    /// Page size for a request, honouring a `_meta.pageSize` hint
+    pub fn page_size(&self, params: Option<&Value>) -> usize {
+        params
+            .and_then(|p| p.get("_meta"))
+            .and_then(|meta| meta.get("pageSize"))
+            .and_then(Value::as_u64)
+            .map_or(self.default_page_size, |hint| {
+                usize::try_from(hint).unwrap_or(usize::MAX).clamp(self.min_page_size, self.max_page_size)
+            })
+    }
+
+    /// Slice the array under `key` in a list result according to the
+    /// request's `cursor`, adding `nextCursor` when items remain
+    pub fn page(&self, list: &Map<String, Value>, key: &str, params: Option<&Value>) -> Result<Value, MCPError> {
+        let items = list.get(key).and_then(Value::as_array).map(Vec::as_slice).unwrap_or_default();
+        let start = match params.and_then(|p| p.get("cursor")) {
+            None | Some(Value::Null) => 0,
+            Some(cursor) => cursor.as_str()
+                .and_then(|cursor| cursor.parse::<usize>().ok())
+                .filter(|&offset| offset <= items.len())
+                .ok_or_else(|| MCPError::InvalidParams(format!("invalid cursor {}", cursor)))?,
+        };
+        let end = start.saturating_add(self.page_size(params)).min(items.len());
+
+        let mut page = list.clone();
+        page.insert(key.into(), Value::Array(items[start..end].to_vec()));
+        if end < items.len() {
+            page.insert("nextCursor".into(), Value::String(end.to_string()));
+        }
+        Ok(Value::Object(page))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_page_size_hints_are_clamped() {
+        let paginator = Paginator::new(2, 1, 3);
+        let Value::Object(list) = json!({ "tools": [1, 2, 3, 4, 5] }) else { unreachable!() };
+
+        let first = paginator.page(&list, "tools", None).unwrap();
+        assert_eq!(first, json!({ "tools": [1, 2], "nextCursor": "2" }));
+
+        let params = json!({ "cursor": "2", "_meta": { "pageSize": 100000 } });
+        assert_eq!(paginator.page(&list, "tools", Some(&params)).unwrap(), json!({ "tools": [3, 4, 5] }));
+
+        let params = json!({ "_meta": { "pageSize": 0 } });
+        assert_eq!(paginator.page(&list, "tools", Some(&params)).unwrap()["tools"], json!([1]));
+
+        let params = json!({ "cursor": "9" });
+        assert!(matches!(paginator.page(&list, "tools", Some(&params)), Err(MCPError::InvalidParams(_))));
+    }
+}
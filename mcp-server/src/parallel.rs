@@ -0,0 +1,90 @@
+//! Concurrent sub-operations inside one tool call.
+//!
+//! [`join_tools`] (or the [`parallel!`](crate::parallel!) macro) polls
+//! several sub-operations concurrently and merges their results into one
+//! [`ToolResponse`] with a labeled section per operation. Nothing is spawned:
+//! the sub-operations live inside the calling future, so cancelling the
+//! `tools/call` drops all of them together.
+
+use crate::error::MCPError;
+use crate::tools::{ContentBlock, ToolResponse};
+use serde_json::{Map, Value};
+use std::future::Future;
+use std::pin::Pin;
+
+/// A boxed sub-operation; see [`parallel!`](crate::parallel!)
+pub type SubOperation<'a> = Pin<Box<dyn Future<Output = Result<ToolResponse, MCPError>> + Send + 'a>>;
+
+/// Run `operations` concurrently and merge them in the given order.
+///
+/// Each section starts with a `## {label}` text block. A failed operation
+/// contributes its error message and marks the merged result as an error;
+/// the other operations still complete. Structured content is collected
+/// under each label.
+pub async fn join_tools(operations: Vec<(String, SubOperation<'_>)>) -> ToolResponse {
+    let (labels, futures): (Vec<String>, Vec<SubOperation<'_>>) = operations.into_iter().unzip();
+    let results = futures_util::future::join_all(futures).await;
+
+    let mut content = Vec::new();
+    let mut structured = Map::new();
+    let mut is_error = false;
+    for (label, result) in labels.into_iter().zip(results) {
+        content.push(ContentBlock::text(format!("## {}", label)));
+        match result {
+            Ok(response) => {
+                is_error |= response.is_error;
+                content.extend(response.content);
+                if let Some(value) = response.structured_content {
+                    structured.insert(label, value);
+                }
+            }
+            Err(e) => {
+                is_error = true;
+                content.push(ContentBlock::text(format!("Error: {}", e)));
+            }
+        }
+    }
+
+    let response = ToolResponse::from_content(content, is_error);
+    if structured.is_empty() {
+        response
+    } else {
+        response.with_structured_content(Value::Object(structured))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::MCPError;
+    use crate::tools::{ContentBlock, ToolResponse};
+    use serde_json::json;
+    use std::sync::Arc;
+    use tokio::sync::Barrier;
+
+    #[tokio::test]
+    async fn test_runs_concurrently_and_merges() {
+        // Both operations wait on each other, so this only finishes if they run concurrently
+        let barrier = Arc::new(Barrier::new(2));
+        let op = |text: &'static str| {
+            let barrier = barrier.clone();
+            async move {
+                barrier.wait().await;
+                Ok(ToolResponse::new(text.into(), false).with_structured_content(json!(text)))
+            }
+        };
+
+        let merged = crate::parallel!(
+            "lint" => op("clean"),
+            "test" => op("passed"),
+            "build" => async { Err::<ToolResponse, _>(MCPError::InternalError("no compiler".into())) },
+        ).await;
+
+        let texts: Vec<&str> = merged.content.iter().map(|block| match block {
+            ContentBlock::Text(text) => text.text.as_str(),
+            _ => "",
+        }).collect();
+        assert_eq!(texts, ["## lint", "clean", "## test", "passed", "## build", "Error: Internal error: no compiler"]);
+        assert!(merged.is_error);
+        assert_eq!(merged.structured_content, Some(json!({ "lint": "clean", "test": "passed" })));
+    }
+}
@@ -0,0 +1,152 @@
+//! Priority lanes for tool calls.
+//!
+//! A call is [`Priority::Background`] when its tool is registered with
+//! [`ServerBuilder::background_tools`] or the request hints
+//! `_meta.priority: "background"`. The lane is the server's decision: a
+//! client may move its own call to the background, but `"interactive"`
+//! never lifts a background tool out of it. Handlers read the lane from
+//! [`ProgressSender::priority`] and can apply [`BackgroundLimits`] to
+//! subprocesses they spawn, so heavy background commands yield CPU and disk
+//! to interactive calls on the same host.
+//!
+//! [`ServerBuilder::background_tools`]: crate::server::ServerBuilder::background_tools
+//! [`ProgressSender::priority`]: crate::notifications::ProgressSender::priority
+
+use serde_json::Value;
+use std::path::PathBuf;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Priority {
+    #[default]
+    Interactive,
+    Background,
+}
+
+impl Priority {
+    /// The lane hinted by `_meta.priority`, if any
+    pub fn from_meta(meta: Option<&Value>) -> Option<Self> {
+        match meta?.get("priority")?.as_str()? {
+            "interactive" => Some(Priority::Interactive),
+            "background" => Some(Priority::Background),
+            _ => None,
+        }
+    }
+}
+
+/// How far background subprocesses are deprioritised
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackgroundLimits {
+    /// Added to the server's niceness (0..=19); the sum is capped at 19
+    pub nice: i32,
+    /// Put the process in the idle I/O scheduling class (Linux)
+    pub idle_io: bool,
+    /// cgroup v2 directory to move the process into (Linux)
+    pub cgroup: Option<PathBuf>,
+}
+
+impl Default for BackgroundLimits {
+    fn default() -> Self {
+        BackgroundLimits { nice: 10, idle_io: true, cgroup: None }
+    }
+}
+
+impl BackgroundLimits {
+    /// Lower the priority of the process `command` spawns. Takes effect in
+    /// the child before exec; no-op off Unix.
+    pub fn apply(&self, command: &mut tokio::process::Command) {
+        #[cfg(unix)]
+        {
+            let increment = self.nice.clamp(0, 19);
+            let idle_io = self.idle_io;
+            // SAFETY: the closure only makes async-signal-safe syscalls
+            unsafe {
+                command.pre_exec(move || {
+                    // Failures are ignored: a command that runs at normal priority is better than none.
+                    // getpriority may return -1 as a valid niceness, which only makes the sum smaller.
+                    let current = libc::getpriority(libc::PRIO_PROCESS, 0);
+                    libc::setpriority(libc::PRIO_PROCESS, 0, (current + increment).min(19));
+                    #[cfg(target_os = "linux")]
+                    if idle_io {
+                        const IOPRIO_WHO_PROCESS: libc::c_long = 1;
+                        const IOPRIO_CLASS_IDLE: libc::c_long = 3;
+                        libc::syscall(libc::SYS_ioprio_set, IOPRIO_WHO_PROCESS, 0 as libc::c_long, IOPRIO_CLASS_IDLE << 13);
+                    }
+                    #[cfg(not(target_os = "linux"))]
+                    let _ = idle_io;
+                    Ok(())
+                });
+            }
+        }
+        #[cfg(not(unix))]
+        let _ = command;
+    }
+
+    /// Move a spawned process into the configured cgroup
+    pub fn attach(&self, pid: Option<u32>) {
+        let (Some(cgroup), Some(pid)) = (&self.cgroup, pid) else { return };
+        if let Err(e) = std::fs::write(cgroup.join("cgroup.procs"), pid.to_string()) {
+            eprintln!("[PRIORITY] Failed to move {} into {}: {}", pid, cgroup.display(), e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::ToolRegistry;
+    use crate::server::SystemMCPServer;
+    use crate::testing::fixtures;
+    use crate::testing::mock::MockToolHandler;
+    use crate::tools::{Tool, ToolInputSchema, ToolResponse};
+    use serde_json::json;
+
+    #[test]
+    fn test_priority_hint() {
+        assert_eq!(Priority::from_meta(Some(&json!({ "priority": "background" }))), Some(Priority::Background));
+        assert_eq!(Priority::from_meta(Some(&json!({ "priority": "urgent" }))), None);
+        assert_eq!(Priority::from_meta(None), None);
+    }
+
+    #[cfg(target_os = "linux")]
+    #[tokio::test]
+    async fn test_background_niceness() {
+        let mut command = tokio::process::Command::new("sh");
+        command.args(["-c", "cut -d' ' -f19 /proc/self/stat"]).stdout(std::process::Stdio::piped());
+        BackgroundLimits { nice: 7, idle_io: false, cgroup: None }.apply(&mut command);
+        let output = command.output().await.unwrap();
+        let nice: i32 = String::from_utf8_lossy(&output.stdout).trim().parse().unwrap();
+        // An increment on top of whatever the server runs at
+        let server = unsafe { libc::getpriority(libc::PRIO_PROCESS, 0) };
+        assert_eq!(nice, (server + 7).min(19));
+    }
+
+    #[tokio::test]
+    async fn test_clients_cannot_leave_the_background_lane() {
+        let registry = ToolRegistry::new();
+        let schema = ToolInputSchema { schema_type: "object".into(), properties: Default::default(), required: vec![] };
+        for name in ["build", "ls"] {
+            registry.register(Tool::new(name, "lane", schema.clone()), |_, ctx| async move {
+                let lane = if ctx.progress().priority() == Priority::Background { "background" } else { "interactive" };
+                Ok(ToolResponse::new(lane.into(), false))
+            });
+        }
+        let server = SystemMCPServer::<MockToolHandler>::builder()
+            .relaxed_lifecycle()
+            .tool_registry(registry)
+            .background_tools(["build"])
+            .build(MockToolHandler::new());
+        let lane = |tool: &str, hint: Option<&str>| {
+            let mut call = fixtures::call_tool(tool);
+            if let Some(hint) = hint {
+                call = call.meta("priority", hint);
+            }
+            let request = call.build();
+            let server = &server;
+            async move { server.handle(request).await.unwrap().result.unwrap()["content"][0]["text"].clone() }
+        };
+
+        assert_eq!(lane("build", Some("interactive")).await, "background");
+        assert_eq!(lane("ls", Some("background")).await, "background");
+        assert_eq!(lane("ls", None).await, "interactive");
+    }
+}
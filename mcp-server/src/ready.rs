@@ -0,0 +1,135 @@
+//! Readiness signalling for supervised deployments.
+//!
+//! Configure one or more [`ReadySignal`]s with [`ServerBuilder::ready_signal`]
+//! and call [`SystemMCPServer::signal_ready`] once the transport is bound.
+//! The server runs a self-check first and only signals when it passes.
+//!
+//! [`ServerBuilder::ready_signal`]: crate::server::ServerBuilder::ready_signal
+//! [`SystemMCPServer::signal_ready`]: crate::server::SystemMCPServer::signal_ready
+
+use crate::error::MCPError;
+use crate::tools::ServerCapabilities;
+use serde_json::{json, Value};
+use std::path::PathBuf;
+
+#[derive(Debug, Clone)]
+pub enum ReadySignal {
+    /// Write one JSON line (`{"event":"ready",...}`) to stderr
+    Stderr,
+    /// Create (or truncate) a file containing the same JSON
+    File(PathBuf),
+    /// Send `READY=1` to systemd's `$NOTIFY_SOCKET`; a no-op when unset
+    SdNotify,
+}
+
+impl ReadySignal {
+    pub fn emit(&self, info: &Value) -> Result<(), MCPError> {
+        match self {
+            ReadySignal::Stderr => {
+                eprintln!("{}", info);
+                Ok(())
+            }
+            ReadySignal::File(path) => {
+                std::fs::write(path, format!("{}\n", info))?;
+                Ok(())
+            }
+            ReadySignal::SdNotify => sd_notify("READY=1"),
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn sd_notify(state: &str) -> Result<(), MCPError> {
+    match std::env::var_os("NOTIFY_SOCKET") {
+        Some(path) => notify_socket(&path.to_string_lossy(), state),
+        None => Ok(()),
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn notify_socket(path: &str, state: &str) -> Result<(), MCPError> {
+    use std::os::linux::net::SocketAddrExt;
+    use std::os::unix::net::{SocketAddr, UnixDatagram};
+
+    // A leading '@' names a socket in the abstract namespace
+    let addr = match path.strip_prefix('@') {
+        Some(name) => SocketAddr::from_abstract_name(name.as_bytes())?,
+        None => SocketAddr::from_pathname(path)?,
+    };
+    UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), &addr)?;
+    Ok(())
+}
+
+#[cfg(not(target_os = "linux"))]
+fn sd_notify(_state: &str) -> Result<(), MCPError> {
+    Ok(())
+}
+
+/// Check advertised capabilities and tools are well-formed before signalling
+/// readiness
+pub fn self_check(capabilities: &ServerCapabilities, tools: &[Value]) -> Result<(), MCPError> {
+    for tool in tools {
+        let name = tool.get("name").and_then(Value::as_str).unwrap_or_default();
+        if name.is_empty() {
+            return Err(MCPError::InternalError("self-check: tool without a name".into()));
+        }
+        if !tool.get("inputSchema").is_some_and(Value::is_object) {
+            return Err(MCPError::InternalError(format!("self-check: tool {} has no input schema", name)));
+        }
+    }
+    serde_json::to_value(capabilities)?;
+    Ok(())
+}
+
+/// Payload of the ready line
+pub fn ready_info(server: &Value, protocol_version: &str) -> Value {
+    json!({
+        "event": "ready",
+        "server": server,
+        "protocolVersion": protocol_version,
+        "pid": std::process::id(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::SystemMCPServer;
+    use crate::testing::mock::MockToolHandler;
+    use crate::tools::{Tool, ToolInputSchema};
+
+    #[test]
+    fn test_ready_file_after_self_check() {
+        let path = std::env::temp_dir().join(format!("mcp-ready-{}", std::process::id()));
+        let tool = Tool::new("look", "Look", ToolInputSchema { schema_type: "object".into(), properties: Default::default(), required: vec![] });
+        let server = SystemMCPServer::<MockToolHandler>::builder()
+            .with_tools(vec![tool])
+            .ready_signal(ReadySignal::File(path.clone()))
+            .build(MockToolHandler::new());
+
+        server.signal_ready().unwrap();
+        let info: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(info["event"], "ready");
+        assert_eq!(info["pid"], std::process::id());
+
+        // Nothing is signalled while the self-check fails
+        assert!(self_check(&ServerCapabilities::default(), &[json!({ "name": "look" })]).is_err());
+        assert!(self_check(&ServerCapabilities::default(), &[json!({ "inputSchema": {} })]).is_err());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_sd_notify() {
+        use std::os::unix::net::UnixDatagram;
+
+        let path = std::env::temp_dir().join(format!("mcp-notify-{}", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+        let socket = UnixDatagram::bind(&path).unwrap();
+        notify_socket(&path.to_string_lossy(), "READY=1").unwrap();
+        let mut buf = [0; 16];
+        let len = socket.recv(&mut buf).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(&buf[..len], b"READY=1");
+    }
+}
@@ -0,0 +1,149 @@
+//! Redis-backed [`SessionStore`] (feature `redis`).
+//!
+//! Each session is a JSON string under `{prefix}session:{id}`, expiring
+//! after the configured TTL of inactivity: every load restarts the clock.
+//! Resource updates are published on `{prefix}resource-updates`; a
+//! subscription that drops is renewed with backoff, and updates published
+//! while it was down are lost.
+
+use crate::error::MCPError;
+use crate::session::SessionState;
+use crate::session_store::{ResourceUpdate, SessionStore};
+use async_trait::async_trait;
+use futures_util::StreamExt;
+use redis::aio::{MultiplexedConnection, PubSub};
+use redis::{AsyncCommands, Expiry};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Sessions idle longer than this are dropped unless [`RedisSessionStore::ttl`] is set
+pub const DEFAULT_SESSION_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// First and longest wait before renewing a lost subscription
+const RESUBSCRIBE_BACKOFF: (Duration, Duration) = (Duration::from_millis(100), Duration::from_secs(30));
+
+fn redis_error(e: redis::RedisError) -> MCPError {
+    MCPError::InternalError(format!("redis: {}", e))
+}
+
+pub struct RedisSessionStore {
+    client: redis::Client,
+    connection: MultiplexedConnection,
+    prefix: String,
+    ttl: Duration,
+}
+
+impl RedisSessionStore {
+    /// Connect to e.g. `redis://127.0.0.1/`
+    pub async fn connect(url: &str) -> Result<Self, MCPError> {
+        let client = redis::Client::open(url).map_err(redis_error)?;
+        let connection = client.get_multiplexed_async_connection().await.map_err(redis_error)?;
+        Ok(RedisSessionStore { client, connection, prefix: "mcp:".into(), ttl: DEFAULT_SESSION_TTL })
+    }
+
+    /// Key prefix, `mcp:` by default; replicas of one deployment must agree
+    pub fn prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.prefix = prefix.into();
+        self
+    }
+
+    pub fn ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = ttl;
+        self
+    }
+
+    fn key(&self, session_id: &str) -> String {
+        format!("{}session:{}", self.prefix, session_id)
+    }
+
+    fn channel(&self) -> String {
+        format!("{}resource-updates", self.prefix)
+    }
+}
+
+async fn subscribe(client: &redis::Client, channel: &str) -> Result<PubSub, MCPError> {
+    let mut pubsub = client.get_async_pubsub().await.map_err(redis_error)?;
+    pubsub.subscribe(channel).await.map_err(redis_error)?;
+    Ok(pubsub)
+}
+
+#[async_trait]
+impl SessionStore for RedisSessionStore {
+    async fn load(&self, session_id: &str) -> Result<Option<SessionState>, MCPError> {
+        let ttl = Expiry::EX(self.ttl.as_secs().max(1));
+        let json: Option<String> = self.connection.clone().get_ex(self.key(session_id), ttl).await.map_err(redis_error)?;
+        json.map(|json| serde_json::from_str(&json).map_err(MCPError::from)).transpose()
+    }
+
+    async fn save(&self, session_id: &str, state: &SessionState) -> Result<(), MCPError> {
+        let json = serde_json::to_string(state)?;
+        self.connection.clone().set_ex::<_, _, ()>(self.key(session_id), json, self.ttl.as_secs().max(1)).await.map_err(redis_error)
+    }
+
+    async fn remove(&self, session_id: &str) -> Result<(), MCPError> {
+        self.connection.clone().del::<_, ()>(self.key(session_id)).await.map_err(redis_error)
+    }
+
+    async fn publish(&self, update: &ResourceUpdate) -> Result<(), MCPError> {
+        let json = serde_json::to_string(update)?;
+        self.connection.clone().publish::<_, _, ()>(self.channel(), json).await.map_err(redis_error)
+    }
+
+    async fn updates(&self) -> Result<mpsc::UnboundedReceiver<ResourceUpdate>, MCPError> {
+        let (client, channel) = (self.client.clone(), self.channel());
+        let mut pubsub = Some(subscribe(&client, &channel).await?);
+        let (sender, receiver) = mpsc::unbounded_channel();
+        tokio::spawn(async move {
+            let mut backoff = RESUBSCRIBE_BACKOFF.0;
+            loop {
+                if let Some(subscribed) = pubsub.take() {
+                    backoff = RESUBSCRIBE_BACKOFF.0;
+                    let mut messages = subscribed.into_on_message();
+                    while let Some(message) = messages.next().await {
+                        match serde_json::from_slice::<ResourceUpdate>(message.get_payload_bytes()) {
+                            Ok(update) => {
+                                if sender.send(update).is_err() {
+                                    return;
+                                }
+                            }
+                            Err(e) => eprintln!("[SESSION] Ignoring malformed resource update: {}", e),
+                        }
+                    }
+                    eprintln!("[SESSION] Lost the resource update subscription");
+                }
+                if sender.is_closed() {
+                    return;
+                }
+                tokio::time::sleep(backoff).await;
+                backoff = (backoff * 2).min(RESUBSCRIBE_BACKOFF.1);
+                match subscribe(&client, &channel).await {
+                    Ok(subscribed) => pubsub = Some(subscribed),
+                    Err(e) => eprintln!("[SESSION] Failed to resubscribe to resource updates: {}", e),
+                }
+            }
+        });
+        Ok(receiver)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Needs a server: `REDIS_URL=redis://127.0.0.1/ cargo test --features redis -- --ignored`
+    #[tokio::test]
+    #[ignore]
+    async fn test_round_trip() {
+        let url = std::env::var("REDIS_URL").unwrap_or_else(|_| "redis://127.0.0.1/".into());
+        let store = RedisSessionStore::connect(&url).await.unwrap().prefix("mcp-test:");
+        let state = SessionState { initialized: true, ..SessionState::default() };
+        store.save("s1", &state).await.unwrap();
+        assert_eq!(store.load("s1").await.unwrap(), Some(state));
+
+        let mut updates = store.updates().await.unwrap();
+        let update = ResourceUpdate { replica: "r1".into(), uri: "file:///a".into() };
+        store.publish(&update).await.unwrap();
+        assert_eq!(updates.recv().await.unwrap(), update);
+        store.remove("s1").await.unwrap();
+    }
+}
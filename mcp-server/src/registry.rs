@@ -0,0 +1,238 @@
+//! Tools registered and removed while the server runs.
+//!
+//! A [`ToolRegistry`] maps tool definitions to async closures. Hand it to
+//! [`ServerBuilder::tool_registry`] and the server lists its tools after the
+//! handler's and routes their calls to the closures, so nothing needs a
+//! `call_tool` match arm. Clones share the same tools: keep one outside the
+//! server and [`register`](ToolRegistry::register) or
+//! [`unregister`](ToolRegistry::unregister) at any time; each change sends
+//! `notifications/tools/list_changed` to the clients of every server using
+//! the registry.
+//!
+//! A [`TypedTool`] takes its arguments as a struct instead of a `Value`:
+//! they are deserialized before the closure runs, and arguments that do not
+//! fit are answered with -32602 locating the offending field (see
+//! [`crate::args`]).
+//!
+//! A server whose tools all live in a registry can use [`NoTools`] as its
+//! handler.
+//!
+//! [`ServerBuilder::tool_registry`]: crate::server::ServerBuilder::tool_registry
+
+use crate::args;
+use crate::context::RequestContext;
+use crate::error::MCPError;
+use crate::notifications::ServerNotification;
+use crate::server::ToolHandler;
+use crate::tools::{Tool, ToolResponse};
+use async_trait::async_trait;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex, RwLock};
+use std::sync::atomic::{AtomicU64, Ordering};
+use tokio::sync::mpsc;
+
+type ToolFuture = Pin<Box<dyn Future<Output = Result<ToolResponse, MCPError>> + Send>>;
+type ToolFn = Arc<dyn Fn(Value, RequestContext) -> ToolFuture + Send + Sync>;
+
+#[derive(Clone, Default)]
+pub struct ToolRegistry {
+    inner: Arc<Registry>,
+}
+
+#[derive(Default)]
+struct Registry {
+    tools: RwLock<BTreeMap<String, (Tool, ToolFn)>>,
+    // Notification queues of the servers serving the registry
+    servers: Mutex<Vec<mpsc::UnboundedSender<ServerNotification>>>,
+    // Advanced on every change, for caches of the listing
+    generation: AtomicU64,
+}
+
+impl std::fmt::Debug for ToolRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ToolRegistry").field("tools", &self.names()).finish()
+    }
+}
+
+impl ToolRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve `tool` by calling `handler` with the call's arguments; replaces
+    /// a tool of the same name. Returns whether one was replaced.
+    pub fn register<F, Fut>(&self, tool: Tool, handler: F) -> bool
+    where
+        F: Fn(Value, RequestContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<ToolResponse, MCPError>> + Send + 'static,
+    {
+        let handler: ToolFn = Arc::new(move |args, ctx| Box::pin(handler(args, ctx)));
+        let replaced = self.inner.tools.write().unwrap().insert(tool.name.clone(), (tool, handler)).is_some();
+        self.notify();
+        replaced
+    }
+
+    /// Serve a tool whose arguments deserialize into `Args`; see [`TypedTool`]
+    pub fn register_typed<Args: DeserializeOwned + Send + 'static>(&self, tool: TypedTool<Args>) -> bool {
+        let TypedTool { tool, handler, .. } = tool;
+        self.register(tool, move |args, ctx| {
+            let handler = handler.clone();
+            async move { handler(args::from_value(&args)?, ctx).await }
+        })
+    }
+
+    /// Stop serving `name`; returns whether it was registered
+    pub fn unregister(&self, name: &str) -> bool {
+        let removed = self.inner.tools.write().unwrap().remove(name).is_some();
+        if removed {
+            self.notify();
+        }
+        removed
+    }
+
+    pub fn contains(&self, name: &str) -> bool {
+        self.inner.tools.read().unwrap().contains_key(name)
+    }
+
+    /// Registrations and removals so far
+    pub fn generation(&self) -> u64 {
+        self.inner.generation.load(Ordering::Acquire)
+    }
+
+    pub fn names(&self) -> Vec<String> {
+        self.inner.tools.read().unwrap().keys().cloned().collect()
+    }
+
+    /// Definitions of the registered tools, by name
+    pub fn tools(&self) -> Vec<Tool> {
+        self.inner.tools.read().unwrap().values().map(|(tool, _)| tool.clone()).collect()
+    }
+
+    pub async fn call(&self, name: &str, args: &Value, ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+        // Not held across the call, so a tool may (un)register tools
+        let handler = self.inner.tools.read().unwrap().get(name).map(|(_, handler)| handler.clone());
+        let handler = handler.ok_or_else(|| MCPError::UnknownTool(name.into()))?;
+        handler(args.clone(), ctx.clone()).await
+    }
+
+    /// Send list changes to a server's clients
+    pub(crate) fn attach(&self, notifications: mpsc::UnboundedSender<ServerNotification>) {
+        self.inner.servers.lock().unwrap().push(notifications);
+    }
+
+    fn notify(&self) {
+        self.inner.generation.fetch_add(1, Ordering::AcqRel);
+        // Servers that were dropped stop taking notifications
+        self.inner.servers.lock().unwrap().retain(|server| server.send(ServerNotification::ToolListChanged).is_ok());
+    }
+}
+
+/// A tool definition with an async function taking deserialized arguments
+pub struct TypedTool<Args> {
+    tool: Tool,
+    handler: Arc<dyn Fn(Args, RequestContext) -> ToolFuture + Send + Sync>,
+    _args: PhantomData<fn(Args)>,
+}
+
+impl<Args: DeserializeOwned + Send + 'static> TypedTool<Args> {
+    pub fn new<F, Fut>(tool: Tool, handler: F) -> Self
+    where
+        F: Fn(Args, RequestContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<ToolResponse, MCPError>> + Send + 'static,
+    {
+        TypedTool { tool, handler: Arc::new(move |args, ctx| Box::pin(handler(args, ctx))), _args: PhantomData }
+    }
+
+    pub fn tool(&self) -> &Tool {
+        &self.tool
+    }
+
+    /// Deserialize `args` and run the function on them
+    pub async fn call(&self, args: &Value, ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+        (self.handler)(args::from_value(args)?, ctx.clone()).await
+    }
+}
+
+/// A handler without tools of its own, for servers whose tools all live in
+/// a [`ToolRegistry`]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoTools;
+
+#[async_trait]
+impl ToolHandler for NoTools {
+    async fn call_tool(&self, name: &str, _args: &Value, _ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+        Err(MCPError::UnknownTool(name.into()))
+    }
+
+    fn tools_generation(&self) -> Option<u64> {
+        Some(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::SystemMCPServer;
+    use crate::testing::fixtures;
+    use crate::tools::ToolInputSchema;
+    use serde_json::json;
+
+    fn tool(name: &str) -> Tool {
+        Tool::new(name, "test tool", ToolInputSchema { schema_type: "object".into(), properties: Default::default(), required: vec![] })
+    }
+
+    #[tokio::test]
+    async fn test_runtime_registration() {
+        let registry = ToolRegistry::new();
+        let mut server = SystemMCPServer::<NoTools>::builder()
+            .relaxed_lifecycle()
+            .tool_registry(registry.clone())
+            .build(NoTools);
+        let mut notifications = server.take_notification_receiver().unwrap();
+
+        registry.register(tool("greet"), |args, _ctx| async move {
+            Ok(ToolResponse::new(format!("hello {}", args["who"].as_str().unwrap_or("you")), false))
+        });
+        assert!(matches!(notifications.try_recv(), Some(ServerNotification::ToolListChanged)));
+        let init = server.handle(fixtures::initialize().build()).await.unwrap().result.unwrap();
+        assert_eq!(init["capabilities"]["tools"]["listChanged"], true);
+
+        let listed = server.handle(fixtures::request("tools/list").build()).await.unwrap().result.unwrap();
+        assert_eq!(listed["tools"][0]["name"], "greet");
+        let called = server.handle(fixtures::call_tool("greet").arg("who", json!("mcp")).build()).await.unwrap();
+        assert_eq!(called.result.unwrap()["content"][0]["text"], "hello mcp");
+
+        assert!(registry.unregister("greet"));
+        assert!(!registry.unregister("greet"));
+        assert!(matches!(notifications.try_recv(), Some(ServerNotification::ToolListChanged)));
+        assert!(notifications.try_recv().is_none());
+        assert!(server.handle(fixtures::call_tool("greet").build()).await.unwrap().error.is_some());
+    }
+
+    #[derive(serde::Deserialize)]
+    struct Resize {
+        width: u32,
+        height: Option<u32>,
+    }
+
+    #[tokio::test]
+    async fn test_typed_arguments() {
+        let registry = ToolRegistry::new();
+        registry.register_typed(TypedTool::new(tool("resize"), |args: Resize, _ctx| async move {
+            Ok(ToolResponse::new(format!("{}x{}", args.width, args.height.unwrap_or(args.width)), false))
+        }));
+        let server = SystemMCPServer::<NoTools>::builder().relaxed_lifecycle().tool_registry(registry).build(NoTools);
+
+        let ok = server.handle(fixtures::call_tool("resize").arg("width", json!(64)).build()).await.unwrap();
+        assert_eq!(ok.result.unwrap()["content"][0]["text"], "64x64");
+        let bad = server.handle(fixtures::call_tool("resize").arg("width", json!("wide")).build()).await.unwrap();
+        let error = bad.error.unwrap();
+        assert_eq!(error.code, -32602);
+        assert_eq!((error.data.as_ref().unwrap()["pointer"].clone(), error.data.unwrap()["expected"].clone()), (json!("/width"), json!("u32")));
+    }
+}
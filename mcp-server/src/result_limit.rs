@@ -0,0 +1,343 @@
+//! A ceiling on the serialized size of tool results.
+//!
+//! A tool that dumps a build log or a table scan can produce a result of
+//! hundreds of megabytes, sent as one JSON line that clients fail to parse or
+//! hold. With [`ServerBuilder::max_result_size`] the server measures each
+//! result as it would be sent and, when it is over the limit, cuts its text
+//! blocks down to fit, in order, at character boundaries. What happens to the
+//! cut text depends on [`Overflow`]: it is dropped behind a marker saying how
+//! much was left out, or kept under a `result://{id}` resource that a
+//! `resource_link` after the shortened block points to. Results that are
+//! still too large once their text is cut (big images or structured content)
+//! are answered with [`MCPError::OutputTooLarge`].
+//!
+//! Spilled outputs have random ids and can only be read by the session the
+//! result went to. The latest [`MAX_SPILLED`] outputs are kept, up to
+//! [`MAX_SPILLED_BYTES`] in all; they are charged to the memory ceiling like
+//! the CAS store and expire with [`crate::gc`]. Text that cannot be kept is
+//! truncated instead.
+//!
+//! [`ServerBuilder::max_result_size`]: crate::server::ServerBuilder::max_result_size
+
+use crate::error::MCPError;
+use crate::gc::{GcPolicy, GcReport};
+use crate::memory::{MemoryAccountant, MemoryCategory};
+use crate::session::random_id;
+use crate::tools::{Annotations, ContentBlock, ResourceContent, ResourceLink, ToolResponse};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// URI scheme of spilled outputs
+pub const SPILL_SCHEME: &str = "result://";
+
+/// Spilled outputs kept before the oldest is dropped
+pub const MAX_SPILLED: usize = 64;
+
+/// Bytes of spilled text kept before the oldest is dropped
+pub const MAX_SPILLED_BYTES: usize = 64 << 20;
+
+/// Room left per cut block for the marker or link that follows it
+const NOTE_ALLOWANCE: usize = 384;
+
+/// What happens to text cut from an oversized result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Overflow {
+    /// Drop it, ending the block with a marker
+    Truncate,
+    /// Keep it as a resource and link to it
+    Spill,
+}
+
+#[derive(Debug)]
+struct Output {
+    session: String,
+    text: String,
+    last_used: Instant,
+}
+
+#[derive(Debug, Default)]
+struct Spilled {
+    order: VecDeque<String>,
+    outputs: HashMap<String, Output>,
+    bytes: usize,
+}
+
+impl Spilled {
+    fn pop_oldest(&mut self) -> Option<usize> {
+        let uri = self.order.pop_front()?;
+        let output = self.outputs.remove(&uri)?;
+        self.bytes -= output.text.len();
+        Some(output.text.len())
+    }
+}
+
+#[derive(Debug)]
+pub struct ResultLimit {
+    max_bytes: usize,
+    overflow: Overflow,
+    spilled: Mutex<Spilled>,
+    memory: Option<Arc<MemoryAccountant>>,
+}
+
+impl ResultLimit {
+    /// Keep results to `max_bytes` of JSON
+    pub fn new(max_bytes: usize, overflow: Overflow) -> Self {
+        ResultLimit { max_bytes, overflow, spilled: Mutex::default(), memory: None }
+    }
+
+    /// Account spilled text against `memory`
+    pub fn with_memory(mut self, memory: Arc<MemoryAccountant>) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+
+    /// Cut `response` for `session` down to the limit; errors if cutting
+    /// text is not enough
+    pub fn apply(&self, session: &str, response: &mut ToolResponse) -> Result<(), MCPError> {
+        let size = serialized_size(response);
+        if size <= self.max_bytes {
+            return Ok(());
+        }
+        let texts: Vec<usize> = response.content.iter().filter_map(ContentBlock::as_text).map(escaped_len).collect();
+        let fixed = size - texts.iter().sum::<usize>();
+        let mut budget = self.max_bytes.saturating_sub(fixed + NOTE_ALLOWANCE * texts.len());
+
+        let mut content = Vec::with_capacity(response.content.len());
+        for block in std::mem::take(&mut response.content) {
+            let ContentBlock::Text(mut text) = block else {
+                content.push(block);
+                continue;
+            };
+            let kept = prefix_within(&text.text, budget);
+            budget -= escaped_len(&text.text[..kept]);
+            if kept == text.text.len() {
+                content.push(ContentBlock::Text(text));
+                continue;
+            }
+            let rest = text.text.split_off(kept);
+            let total = kept + rest.len();
+            let link = match self.overflow {
+                Overflow::Truncate => None,
+                Overflow::Spill => self.spill(session, format!("{}{}", text.text, rest), text.annotations.clone())?,
+            };
+            match link {
+                Some(link) => {
+                    text.text.push_str(&format!("\n[truncated: {} of {} bytes in the linked resource]", rest.len(), total));
+                    content.push(ContentBlock::Text(text));
+                    content.push(ContentBlock::ResourceLink(link));
+                }
+                None => {
+                    text.text.push_str(&format!("\n[truncated: {} of {} bytes omitted]", rest.len(), total));
+                    content.push(ContentBlock::Text(text));
+                }
+            }
+        }
+        response.content = content;
+
+        let size = serialized_size(response);
+        if size > self.max_bytes {
+            eprintln!("[LIMIT] Result of {} bytes exceeds {} after cutting its text", size, self.max_bytes);
+            return Err(MCPError::OutputTooLarge);
+        }
+        Ok(())
+    }
+
+    /// Keep `text` for `session`; `None` when it cannot be kept
+    fn spill(&self, session: &str, text: String, annotations: Option<Annotations>) -> Result<Option<ResourceLink>, MCPError> {
+        let size = text.len();
+        if size > MAX_SPILLED_BYTES {
+            return Ok(None);
+        }
+        let id = random_id()?;
+        let uri = format!("{}{}", SPILL_SCHEME, id);
+        let mut spilled = self.spilled.lock().unwrap();
+        let mut freed = 0;
+        while spilled.order.len() >= MAX_SPILLED || spilled.bytes + size > MAX_SPILLED_BYTES {
+            freed += spilled.pop_oldest().unwrap_or_default();
+        }
+        if let Some(memory) = &self.memory {
+            memory.release(MemoryCategory::Cache, freed);
+            if memory.used().saturating_sub(spilled.bytes) + size > memory.ceiling() {
+                return Ok(None);
+            }
+            // Older outputs make room for newer ones
+            while !memory.try_reserve(MemoryCategory::Cache, size) {
+                let Some(bytes) = spilled.pop_oldest() else { return Ok(None) };
+                memory.release(MemoryCategory::Cache, bytes);
+            }
+        }
+        spilled.order.push_back(uri.clone());
+        spilled.bytes += size;
+        spilled.outputs.insert(uri.clone(), Output { session: session.into(), text, last_used: Instant::now() });
+        Ok(Some(ResourceLink {
+            uri,
+            name: format!("result-{}", &id[..12]),
+            description: Some("Full text of a tool result that was cut to fit the size limit".into()),
+            mime_type: Some("text/plain".into()),
+            size: Some(size as u64),
+            annotations,
+        }))
+    }
+
+    /// Contents for a `result://{id}` URI spilled for `session`
+    pub fn read(&self, session: &str, uri: &str) -> Option<ResourceContent> {
+        let mut spilled = self.spilled.lock().unwrap();
+        let output = spilled.outputs.get_mut(uri).filter(|output| output.session == session)?;
+        output.last_used = Instant::now();
+        Some(ResourceContent { uri: uri.into(), mime_type: "text/plain".into(), text: output.text.clone(), blob: None })
+    }
+
+    /// Drop outputs unread for the TTL, then the oldest until under the size cap
+    pub fn collect(&self, policy: &GcPolicy) -> GcReport {
+        let mut spilled = self.spilled.lock().unwrap();
+        let mut report = GcReport::default();
+        if let Some(ttl) = policy.ttl {
+            let expired: Vec<String> = spilled.outputs.iter()
+                .filter(|(_, output)| output.last_used.elapsed() >= ttl)
+                .map(|(uri, _)| uri.clone())
+                .collect();
+            spilled.order.retain(|uri| !expired.contains(uri));
+            for uri in expired {
+                if let Some(output) = spilled.outputs.remove(&uri) {
+                    spilled.bytes -= output.text.len();
+                    report.removed += 1;
+                    report.freed_bytes += output.text.len();
+                }
+            }
+        }
+        if let Some(max_bytes) = policy.max_bytes {
+            while spilled.bytes > max_bytes
+                && let Some(bytes) = spilled.pop_oldest()
+            {
+                report.removed += 1;
+                report.freed_bytes += bytes;
+            }
+        }
+        if let Some(memory) = &self.memory {
+            memory.release(MemoryCategory::Cache, report.freed_bytes);
+        }
+        report
+    }
+}
+
+/// Bytes of `response` as JSON, without building the string
+pub fn serialized_size(response: &ToolResponse) -> usize {
+    struct Counter(usize);
+
+    impl std::io::Write for Counter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0 += buf.len();
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let mut counter = Counter(0);
+    // Writing to a counter cannot fail
+    let _ = serde_json::to_writer(&mut counter, response);
+    counter.0
+}
+
+/// Bytes of `c` inside a JSON string
+fn escaped_char_len(c: char) -> usize {
+    match c {
+        '"' | '\\' | '\n' | '\r' | '\t' | '\u{08}' | '\u{0c}' => 2,
+        c if (c as u32) < 0x20 => 6,
+        c => c.len_utf8(),
+    }
+}
+
+fn escaped_len(text: &str) -> usize {
+    text.chars().map(escaped_char_len).sum()
+}
+
+/// Length of the longest prefix of `text` taking at most `budget` bytes as JSON
+fn prefix_within(text: &str, budget: usize) -> usize {
+    let mut used = 0;
+    for (index, c) in text.char_indices() {
+        used += escaped_char_len(c);
+        if used > budget {
+            return index;
+        }
+    }
+    text.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_truncation() {
+        let limit = ResultLimit::new(1024, Overflow::Truncate);
+        let mut small = ToolResponse::new("short".into(), false);
+        limit.apply("s", &mut small).unwrap();
+        assert_eq!(small.content[0].as_text(), Some("short"));
+
+        let mut large = ToolResponse::from_content(vec![ContentBlock::text("é\"".repeat(1000)), ContentBlock::text("tail")], false);
+        limit.apply("s", &mut large).unwrap();
+        assert!(serialized_size(&large) <= 1024);
+        let text = large.content[0].as_text().unwrap();
+        assert!(text.starts_with("é\"") && text.ends_with("bytes omitted]"));
+        assert!(large.content[1].as_text().unwrap().ends_with("of 4 bytes omitted]"));
+
+        let mut structured = ToolResponse::new(String::new(), false).with_structured_content(serde_json::json!("x".repeat(2000)));
+        assert!(matches!(limit.apply("s", &mut structured), Err(MCPError::OutputTooLarge)));
+    }
+
+    #[test]
+    fn test_spilled_outputs_are_scoped_and_bounded() {
+        let memory = Arc::new(MemoryAccountant::new(10_000));
+        let limit = ResultLimit::new(1024, Overflow::Spill).with_memory(memory.clone());
+        let spill = |session: &str, bytes: usize| {
+            let mut response = ToolResponse::new("x".repeat(bytes), false);
+            limit.apply(session, &mut response).unwrap();
+            response.content.get(1).and_then(|link| match link {
+                ContentBlock::ResourceLink(link) => Some(link.uri.clone()),
+                _ => None,
+            })
+        };
+
+        let first = spill("a", 5_000).unwrap();
+        assert!(limit.read("b", &first).is_none());
+        assert_eq!(limit.read("a", &first).unwrap().text.len(), 5_000);
+        assert_eq!(memory.stats().cache, 5_000);
+
+        // The older output makes room under the ceiling
+        let second = spill("a", 8_000).unwrap();
+        assert!(limit.read("a", &first).is_none());
+        assert_eq!(memory.stats().cache, 8_000);
+        assert_eq!(spill("a", 20_000), None);
+
+        let report = limit.collect(&GcPolicy::new().ttl(std::time::Duration::ZERO));
+        assert_eq!((report.removed, report.freed_bytes), (1, 8_000));
+        assert!(limit.read("a", &second).is_none());
+        assert_eq!(memory.stats().cache, 0);
+    }
+
+    #[tokio::test]
+    async fn test_spilled_output_is_readable() {
+        use crate::server::SystemMCPServer;
+        use crate::testing::fixtures;
+        use crate::testing::mock::{MockToolHandler, Reply};
+
+        let output = "line\n".repeat(1000);
+        let server = SystemMCPServer::<MockToolHandler>::builder()
+            .relaxed_lifecycle()
+            .max_result_size(1024, Overflow::Spill)
+            .build(MockToolHandler::new().tool("build", Reply::text(output.clone())));
+        let result = server.handle(fixtures::call_tool("build").build()).await.unwrap().result.unwrap();
+        assert!(result.to_string().len() <= 1024);
+        let link = &result["content"][1];
+        assert_eq!(link["type"], "resource_link");
+        assert_eq!(link["size"], output.len());
+
+        let read = fixtures::request("resources/read").param("uri", link["uri"].as_str().unwrap()).build();
+        let contents = server.handle(read).await.unwrap().result.unwrap();
+        assert_eq!(contents["text"], output);
+    }
+}
@@ -0,0 +1,194 @@
+//! Paging of tool results with many content blocks.
+//!
+//! With [`ServerBuilder::paginate_results`] a tool may return thousands of
+//! content blocks (search hits, directory entries) and the server sends only
+//! the first page. The rest is kept server-side under a cursor, reported as
+//! `_meta.nextCursor` on the result and in a trailing text block, and fetched
+//! with the built-in `next_page` tool.
+//!
+//! Cursors are random and continue a result only for the session it was
+//! sent to, so one client cannot read pages of another's results. Results
+//! not continued within the [`crate::gc`] TTL are dropped.
+//!
+//! [`ServerBuilder::paginate_results`]: crate::server::ServerBuilder::paginate_results
+
+use crate::error::MCPError;
+use crate::gc::{GcPolicy, GcReport};
+use crate::memory;
+use crate::session::random_id;
+use crate::tools::{ContentBlock, Tool, ToolAnnotations, ToolInputSchema, ToolProperty, ToolResponse};
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Built-in tool that continues a paged result
+pub const NEXT_PAGE_TOOL: &str = "next_page";
+
+/// Results kept for continuation before the oldest is dropped
+const MAX_PENDING: usize = 64;
+
+#[derive(Debug, Default)]
+struct Pending {
+    order: VecDeque<String>,
+    // Cursor to the owning session, when the page was sent, and the blocks
+    // not sent yet
+    remaining: HashMap<String, (String, Instant, Vec<ContentBlock>)>,
+}
+
+#[derive(Debug)]
+pub struct ResultPages {
+    page_size: usize,
+    pending: Mutex<Pending>,
+}
+
+impl ResultPages {
+    /// Send at most `page_size` content blocks per result
+    pub fn new(page_size: usize) -> Self {
+        ResultPages {
+            page_size: page_size.max(1),
+            pending: Mutex::new(Pending::default()),
+        }
+    }
+
+    /// Definition of the `next_page` tool
+    pub fn tool() -> Tool {
+        let mut schema = ToolInputSchema {
+            schema_type: "object".into(),
+            properties: Default::default(),
+            required: vec!["cursor".into()],
+        };
+        schema.properties.insert("cursor".into(), ToolProperty::string("nextCursor from the previous page"));
+        Tool::new(NEXT_PAGE_TOOL, "Fetch the next page of a tool result that was cut short", schema)
+            .with_annotations(ToolAnnotations { read_only_hint: Some(true), ..Default::default() })
+    }
+
+    /// Keep all but the first page of `response` for `session`; returns the
+    /// cursor of the rest
+    pub fn paginate(&self, session: &str, response: &mut ToolResponse) -> Result<Option<String>, MCPError> {
+        if response.content.len() <= self.page_size {
+            return Ok(None);
+        }
+        let cursor = random_id()?;
+        let rest = response.content.split_off(self.page_size);
+        response.content.push(ContentBlock::text(format!(
+            "[{} more results; call {} with {{\"cursor\": \"{}\"}}]",
+            rest.len(), NEXT_PAGE_TOOL, cursor,
+        )));
+
+        let mut pending = self.pending.lock().unwrap();
+        if pending.order.len() >= MAX_PENDING
+            && let Some(oldest) = pending.order.pop_front()
+        {
+            pending.remaining.remove(&oldest);
+        }
+        pending.order.push_back(cursor.clone());
+        pending.remaining.insert(cursor.clone(), (session.to_string(), Instant::now(), rest));
+        Ok(Some(cursor))
+    }
+
+    /// Answer a `next_page` call from `session`; returns the page and the
+    /// cursor of what remains
+    pub fn next_page(&self, session: &str, args: &Value) -> Result<(ToolResponse, Option<String>), MCPError> {
+        let cursor = args.get("cursor").and_then(Value::as_str)
+            .ok_or_else(|| MCPError::InvalidParams("next_page needs a cursor".into()))?;
+        let content = {
+            let mut pending = self.pending.lock().unwrap();
+            let owned = pending.remaining.get(cursor).is_some_and(|(owner, _, _)| owner == session);
+            if owned {
+                pending.order.retain(|c| c != cursor);
+            }
+            owned.then(|| pending.remaining.remove(cursor)).flatten()
+        };
+        let (_, _, content) = content.ok_or_else(|| MCPError::InvalidParams(format!("unknown or expired cursor {}", cursor)))?;
+        let mut response = ToolResponse::from_content(content, false);
+        let next = self.paginate(session, &mut response)?;
+        Ok((response, next))
+    }
+
+    /// Number of results waiting for `next_page`
+    pub fn pending(&self) -> usize {
+        self.pending.lock().unwrap().remaining.len()
+    }
+
+    /// Drop results not continued within the TTL
+    pub fn collect(&self, policy: &GcPolicy) -> GcReport {
+        let Some(ttl) = policy.ttl else { return GcReport::default() };
+        let mut pending = self.pending.lock().unwrap();
+        let mut report = GcReport::default();
+        pending.remaining.retain(|_, (_, sent, content)| {
+            if sent.elapsed() < ttl {
+                return true;
+            }
+            report.removed += 1;
+            report.freed_bytes += content.iter().map(memory::block_size).sum::<usize>();
+            false
+        });
+        let Pending { order, remaining } = &mut *pending;
+        order.retain(|cursor| remaining.contains_key(cursor));
+        report
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::RequestContext;
+    use crate::server::{SystemMCPServer, ToolHandler};
+    use crate::testing::fixtures;
+    use serde_json::json;
+
+    #[test]
+    fn test_pages_until_exhausted() {
+        let pages = ResultPages::new(2);
+        let mut response = ToolResponse::from_content((0..5).map(|i| ContentBlock::text(i.to_string())).collect(), false);
+        let cursor = pages.paginate("a", &mut response).unwrap().unwrap();
+        assert_eq!(response.content.len(), 3);
+        assert!(response.content[2].as_text().unwrap().contains("3 more results"));
+
+        // Another session cannot continue it, and does not use it up
+        assert!(matches!(pages.next_page("b", &json!({ "cursor": cursor })), Err(MCPError::InvalidParams(_))));
+        let (second, cursor) = pages.next_page("a", &json!({ "cursor": cursor })).unwrap();
+        assert_eq!(second.content[0].as_text(), Some("2"));
+        let (last, done) = pages.next_page("a", &json!({ "cursor": cursor.unwrap() })).unwrap();
+        assert_eq!((last.content.len(), done), (1, None));
+        assert_eq!(pages.pending(), 0);
+        assert!(matches!(pages.next_page("a", &json!({ "cursor": "1" })), Err(MCPError::InvalidParams(_))));
+
+        // Results nobody continues are collected
+        let mut response = ToolResponse::from_content((0..3).map(|i| ContentBlock::text(i.to_string())).collect(), false);
+        pages.paginate("a", &mut response).unwrap();
+        assert_eq!(pages.collect(&GcPolicy::new()), GcReport::default());
+        assert_eq!(pages.collect(&GcPolicy::new().ttl(std::time::Duration::ZERO)), GcReport { removed: 1, freed_bytes: 1 });
+        assert_eq!(pages.pending(), 0);
+    }
+
+    struct Listing;
+
+    #[async_trait::async_trait]
+    impl ToolHandler for Listing {
+        async fn call_tool(&self, _name: &str, _args: &Value, _ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+            Ok(ToolResponse::from_content((0..250).map(|i| ContentBlock::text(format!("file{}", i))).collect(), false))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_next_page_tool() {
+        let server = SystemMCPServer::<Listing>::builder().relaxed_lifecycle().paginate_results(100).build(Listing);
+        let tools = server.handle(fixtures::request("tools/list").build()).await.unwrap().result.unwrap();
+        assert_eq!(tools["tools"][0]["name"], NEXT_PAGE_TOOL);
+
+        let first = server.handle(fixtures::call_tool("ls").build()).await.unwrap().result.unwrap();
+        assert_eq!(first["content"].as_array().unwrap().len(), 101);
+        let mut cursor = first["_meta"]["nextCursor"].clone();
+        let mut seen = 100;
+        while !cursor.is_null() {
+            let call = fixtures::call_tool(NEXT_PAGE_TOOL).arg("cursor", cursor).build();
+            let page = server.handle(call).await.unwrap().result.unwrap();
+            assert_eq!(page["content"][0]["text"], format!("file{}", seen));
+            seen += page["content"].as_array().unwrap().len().min(100);
+            cursor = page["_meta"]["nextCursor"].clone();
+        }
+        assert_eq!(seen, 250);
+    }
+}
@@ -0,0 +1,205 @@
+//! Encrypted session resume tokens (feature `http`).
+//!
+//! A token carries a session's essential state — negotiated protocol
+//! version, log level, subscriptions and principal — sealed with
+//! ChaCha20-Poly1305 under a key shared by all replicas. The HTTP transport
+//! returns a fresh token with every response; a client that lands on a
+//! replica which never saw its session presents the token and the session is
+//! rebuilt there, so deployments need no sticky sessions or shared store.
+//!
+//! Tokens are short-lived and numbered per session. A replica refuses a
+//! token older than the newest one it issued or redeemed for the session,
+//! so each response's token supersedes the last, and it refuses every token
+//! of a session the client closed with `DELETE`. Replicas do not share
+//! that knowledge: a token stays redeemable on a replica that never saw its
+//! successor until it expires, so keep [`ResumeTokens::max_age`] short.
+
+use crate::error::MCPError;
+use crate::session::SessionState;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use hkdf::Hkdf;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+pub const RESUME_TOKEN_HEADER: &str = "Mcp-Resume-Token";
+
+/// Tokens older than this are refused unless [`ResumeTokens::max_age`] is set
+pub const DEFAULT_TOKEN_MAX_AGE: Duration = Duration::from_secs(30 * 60);
+
+/// Shortest secret [`ResumeTokens::from_secret`] accepts
+pub const MIN_SECRET_LEN: usize = 32;
+
+const NONCE_LEN: usize = 12;
+
+// Sequence number of a closed session; every token is older
+const CLOSED: u64 = u64::MAX;
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Sealed {
+    session_id: String,
+    issued_at: u64,
+    /// Position among the session's tokens
+    seq: u64,
+    state: SessionState,
+}
+
+/// Newest token sequence number seen for a session, and when
+#[derive(Debug, Clone, Copy)]
+struct Latest {
+    seq: u64,
+    at: u64,
+}
+
+fn now_secs() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs())
+}
+
+fn invalid() -> MCPError {
+    MCPError::Unauthorized("invalid or expired resume token".into())
+}
+
+/// Issues and redeems resume tokens under one key
+pub struct ResumeTokens {
+    cipher: ChaCha20Poly1305,
+    max_age: Duration,
+    latest: Mutex<HashMap<String, Latest>>,
+}
+
+impl ResumeTokens {
+    /// Seal tokens under `key`, which must be uniformly random
+    pub fn new(key: [u8; 32]) -> Self {
+        ResumeTokens {
+            cipher: ChaCha20Poly1305::new(Key::from_slice(&key)),
+            max_age: DEFAULT_TOKEN_MAX_AGE,
+            latest: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Derive the key from a secret shared by the replicas with HKDF-SHA256;
+    /// fails for secrets shorter than [`MIN_SECRET_LEN`] bytes
+    pub fn from_secret(secret: impl AsRef<[u8]>) -> Result<Self, MCPError> {
+        let secret = secret.as_ref();
+        if secret.len() < MIN_SECRET_LEN {
+            return Err(MCPError::InvalidParams(format!("resume token secret must be at least {} bytes", MIN_SECRET_LEN)));
+        }
+        let mut key = [0u8; 32];
+        Hkdf::<Sha256>::new(None, secret)
+            .expand(b"mcp-server resume token key", &mut key)
+            .map_err(|_| MCPError::InternalError("failed to derive resume token key".into()))?;
+        Ok(Self::new(key))
+    }
+
+    pub fn max_age(mut self, max_age: Duration) -> Self {
+        self.max_age = max_age;
+        self
+    }
+
+    /// Seal `state` of `session_id` into a URL-safe token that supersedes
+    /// the session's earlier ones
+    pub fn issue(&self, session_id: &str, state: &SessionState) -> Result<String, MCPError> {
+        let now = now_secs();
+        let seq = {
+            let mut latest = self.latest.lock().unwrap();
+            self.prune(&mut latest, now);
+            let entry = latest.entry(session_id.to_string()).or_insert(Latest { seq: 0, at: now });
+            if entry.seq == CLOSED {
+                return Err(invalid());
+            }
+            *entry = Latest { seq: entry.seq + 1, at: now };
+            entry.seq
+        };
+        let sealed = Sealed { session_id: session_id.into(), issued_at: now, seq, state: state.clone() };
+        let mut nonce = [0u8; NONCE_LEN];
+        getrandom::fill(&mut nonce).map_err(|e| MCPError::InternalError(format!("no randomness for nonce: {}", e)))?;
+        let ciphertext = self.cipher.encrypt(Nonce::from_slice(&nonce), serde_json::to_vec(&sealed)?.as_slice())
+            .map_err(|_| MCPError::InternalError("failed to seal resume token".into()))?;
+        Ok(URL_SAFE_NO_PAD.encode([nonce.as_slice(), &ciphertext].concat()))
+    }
+
+    /// State of `session_id` from a token issued for that session
+    pub fn redeem(&self, session_id: &str, token: &str) -> Result<SessionState, MCPError> {
+        let bytes = URL_SAFE_NO_PAD.decode(token.trim()).map_err(|_| invalid())?;
+        if bytes.len() <= NONCE_LEN {
+            return Err(invalid());
+        }
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        let plaintext = self.cipher.decrypt(Nonce::from_slice(nonce), ciphertext).map_err(|_| invalid())?;
+        let sealed: Sealed = serde_json::from_slice(&plaintext).map_err(|_| invalid())?;
+        let now = now_secs();
+        if sealed.session_id != session_id || now.saturating_sub(sealed.issued_at) > self.max_age.as_secs() {
+            return Err(invalid());
+        }
+        let mut latest = self.latest.lock().unwrap();
+        self.prune(&mut latest, now);
+        if latest.get(session_id).is_some_and(|latest| sealed.seq < latest.seq) {
+            return Err(invalid());
+        }
+        latest.insert(session_id.to_string(), Latest { seq: sealed.seq, at: now });
+        Ok(sealed.state)
+    }
+
+    /// Refuse every token of `session_id` from now on
+    pub fn revoke(&self, session_id: &str) {
+        let now = now_secs();
+        let mut latest = self.latest.lock().unwrap();
+        self.prune(&mut latest, now);
+        latest.insert(session_id.to_string(), Latest { seq: CLOSED, at: now });
+    }
+
+    /// Forget sessions whose tokens have all expired
+    fn prune(&self, latest: &mut HashMap<String, Latest>, now: u64) {
+        latest.retain(|_, latest| now.saturating_sub(latest.at) <= self.max_age.as_secs());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SECRET: &str = "a secret shared by every replica!";
+
+    #[test]
+    fn test_tokens_round_trip_only_for_their_session() {
+        let tokens = ResumeTokens::from_secret(SECRET).unwrap();
+        let state = SessionState {
+            initialized: true,
+            log_level: Some("debug".into()),
+            subscriptions: ["file:///a".to_string()].into(),
+            principal: Some("alice".into()),
+            ..SessionState::default()
+        };
+        let token = tokens.issue("s1", &state).unwrap();
+
+        assert_eq!(ResumeTokens::from_secret(SECRET).unwrap().redeem("s1", &token).unwrap(), state);
+        assert!(tokens.redeem("s2", &token).is_err());
+        assert!(ResumeTokens::from_secret("another secret, just as long as it").unwrap().redeem("s1", &token).is_err());
+        assert!(tokens.redeem("s1", &token[1..]).is_err());
+        assert!(ResumeTokens::from_secret("short").is_err());
+    }
+
+    #[test]
+    fn test_tokens_are_superseded_and_revoked() {
+        let (a, b) = (ResumeTokens::from_secret(SECRET).unwrap(), ResumeTokens::from_secret(SECRET).unwrap());
+        let state = SessionState::default();
+        let first = a.issue("s1", &state).unwrap();
+        let second = a.issue("s1", &state).unwrap();
+        assert!(a.redeem("s1", &first).is_err());
+
+        // Moving to another replica supersedes what the first one issued
+        assert!(b.redeem("s1", &second).is_ok());
+        let third = b.issue("s1", &state).unwrap();
+        assert!(b.redeem("s1", &second).is_err());
+        assert!(a.redeem("s1", &third).is_ok());
+
+        a.revoke("s1");
+        assert!(a.redeem("s1", &third).is_err());
+        assert!(a.issue("s1", &state).is_err());
+    }
+}
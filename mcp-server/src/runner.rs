@@ -0,0 +1,663 @@
+//! The read/dispatch/respond loop.
+//!
+//! [`ServerRunner`] owns a server's connection: it reads requests from a
+//! [`Transport`], forwards queued notifications while requests run, and
+//! returns on end of input, after a termination signal or once
+//! [`SystemMCPServer::shutdown`] is called. Obtain one with
+//! [`SystemMCPServer::runner`], or use [`SystemMCPServer::run_stdio`].
+//!
+//! Up to [`ServerBuilder::max_concurrent_requests`] requests are handled
+//! concurrently, each on a task of its own so a handler that hogs the CPU
+//! does not hold up reading and writing. Further requests wait in a queue of
+//! at most [`ServerBuilder::max_queued_requests`]; requests beyond that are
+//! answered with a busy error (-32005). Notifications such as
+//! `notifications/cancelled` are always handled at once. Responses are
+//! written one at a time, in completion order. The runner shares the server
+//! with those tasks, so it is started from an `Arc<SystemMCPServer>`.
+//!
+//! Ordering: notifications queued before a response is ready (such as a
+//! call's progress) are written before that response, and queued
+//! notifications are preferred over reading the next request.
+//!
+//! One runner can also serve several transports at once (say stdio for a
+//! CLI and a Unix socket for an IDE plugin) with
+//! [`add_transport`](ServerRunner::add_transport) or a [`TransportSet`].
+//! Responses and progress go back to the transport the request came from;
+//! other notifications go to every transport. The transports share one
+//! session unless the server demultiplexes sessions, and request ids used
+//! concurrently on different transports should not collide, since
+//! cancellation is by id.
+//!
+//! With [`ServerBuilder::keepalive`] each transport's client is pinged, and
+//! a transport whose client stops answering is closed; see
+//! [`crate::keepalive`].
+//!
+//! [`ServerBuilder::max_concurrent_requests`]: crate::server::ServerBuilder::max_concurrent_requests
+//! [`ServerBuilder::max_queued_requests`]: crate::server::ServerBuilder::max_queued_requests
+//! [`ServerBuilder::keepalive`]: crate::server::ServerBuilder::keepalive
+
+use crate::error::MCPError;
+use crate::keepalive;
+use crate::memory::Reservation;
+use crate::middleware::{Endpoint, IncomingRequest};
+use crate::notifications::{NotificationReceiver, ServerNotification};
+use crate::response::MCPResponse;
+use crate::server::{SystemMCPServer, ToolHandler};
+use crate::shutdown::{terminate_signal, DEFAULT_SHUTDOWN_DEADLINE};
+use crate::transport::{forward_notification, StdioTransport, Transport, TransportSet};
+use futures_util::FutureExt;
+use futures_util::stream::{FuturesUnordered, StreamExt};
+use std::collections::{HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::task::{JoinError, JoinHandle, JoinSet};
+use tokio::time::Instant;
+
+tokio::task_local! {
+    // Index of the transport the request being handled arrived on
+    static ORIGIN: usize;
+}
+
+/// Transport of the request being handled, inside a multi-transport runner
+pub(crate) fn current_origin() -> Option<usize> {
+    ORIGIN.try_with(|origin| *origin).ok()
+}
+
+pub struct ServerRunner<H: ToolHandler + 'static> {
+    server: Arc<SystemMCPServer<H>>,
+    shutdown_deadline: Duration,
+    handle_signals: bool,
+    transports: Vec<Box<dyn Transport>>,
+}
+
+/// Next queued notification; never resolves without a receiver
+async fn next_notification(notifications: &mut Option<NotificationReceiver>) -> Option<ServerNotification> {
+    match notifications {
+        Some(notifications) => notifications.recv().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Answer `incoming` with a busy error, as its queue is full
+fn refuse<H: ToolHandler>(server: &SystemMCPServer<H>, incoming: &IncomingRequest) -> Option<MCPResponse> {
+    eprintln!("[RUNNER] Request queue full, refusing {}", incoming.request.method);
+    Endpoint::reject(server, &incoming.request, MCPError::ServerBusy("request queue is full".into()))
+}
+
+/// A handler task that panicked or was aborted has no response to send
+fn joined<T>(result: Result<T, JoinError>) -> Option<T> {
+    result.map_err(|e| eprintln!("[RUNNER] Request task failed: {}", e)).ok()
+}
+
+impl<H: ToolHandler + 'static> ServerRunner<H> {
+    pub fn new(server: Arc<SystemMCPServer<H>>) -> Self {
+        ServerRunner {
+            server,
+            shutdown_deadline: DEFAULT_SHUTDOWN_DEADLINE,
+            handle_signals: true,
+            transports: Vec::new(),
+        }
+    }
+
+    /// Serve `transport` alongside the others added; see [`run`](Self::run)
+    pub fn add_transport(mut self, transport: impl Transport + 'static) -> Self {
+        self.transports.push(Box::new(transport));
+        self
+    }
+
+    /// Time in-flight requests get after a termination signal before they
+    /// are cancelled
+    pub fn shutdown_deadline(mut self, deadline: Duration) -> Self {
+        self.shutdown_deadline = deadline;
+        self
+    }
+
+    /// Ignore SIGTERM/SIGINT, e.g. for one of many connections in a process
+    pub fn without_signals(mut self) -> Self {
+        self.handle_signals = false;
+        self
+    }
+
+    pub async fn run_stdio(self) -> Result<(), MCPError> {
+        self.run_with_transport(StdioTransport::new()).await
+    }
+
+    /// Serve until the peer disconnects or a termination signal arrives.
+    /// After end of input, requests already received are still answered.
+    pub async fn run_with_transport<T: Transport>(self, mut transport: T) -> Result<(), MCPError> {
+        let server = &*self.server;
+        let limit = server.max_concurrent_requests();
+        let max_queued = server.max_queued_requests();
+        let mut notifications = server.claim_notification_receiver();
+        follow_resource_updates(server).await;
+        let _running = server.shutdown_control().runner();
+        let _heartbeat = server.start_heartbeat();
+        let shutdown = shutdown_requested(server, self.handle_signals);
+        tokio::pin!(shutdown);
+        let keepalive = keepalive::monitor(server);
+        tokio::pin!(keepalive);
+
+        // Dropping the set (say, on a transport error) aborts what still runs
+        let mut in_flight = JoinSet::new();
+        // Requests received while `limit` requests were in flight
+        let mut queued: VecDeque<IncomingRequest> = VecDeque::new();
+        // Sessions the client used, ended if it stops answering pings
+        let mut sessions: HashSet<String> = HashSet::new();
+        let mut reading = true;
+        let mut stopping = false;
+        let mut deadline = None;
+
+        loop {
+            while in_flight.len() < limit && let Some(incoming) = queued.pop_front() {
+                in_flight.spawn(handle(self.server.clone(), incoming));
+            }
+            if !reading && in_flight.is_empty() {
+                while let Some(notification) = notifications.as_mut().and_then(NotificationReceiver::try_recv) {
+                    forward(&mut transport, &notifications, notification).await?;
+                }
+                return Ok(());
+            }
+
+            tokio::select! {
+                biased;
+                signal = &mut shutdown, if !stopping => {
+                    eprintln!("[SHUTDOWN] Received {}, waiting up to {:?} for {} in-flight request(s)", signal, self.shutdown_deadline, in_flight.len());
+                    if !queued.is_empty() {
+                        eprintln!("[SHUTDOWN] Dropping {} queued request(s)", queued.len());
+                        queued.clear();
+                    }
+                    reading = false;
+                    stopping = true;
+                    deadline = Some(Instant::now() + self.shutdown_deadline);
+                }
+                Some(notification) = next_notification(&mut notifications) => {
+                    forward(&mut transport, &notifications, notification).await?;
+                }
+                Some(joined_task) = in_flight.join_next() => {
+                    // Flush what the handler queued so its progress precedes the response
+                    while let Some(notification) = notifications.as_mut().and_then(NotificationReceiver::try_recv) {
+                        forward(&mut transport, &notifications, notification).await?;
+                    }
+                    if let Some((Some(response), held)) = joined(joined_task) {
+                        transport.send(response).await?;
+                        drop(held);
+                    }
+                }
+                _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                    server.cancel_all("server shutting down").await;
+                    deadline = None;
+                }
+                _ = &mut keepalive, if reading => {
+                    eprintln!("[KEEPALIVE] Client stopped answering pings, closing the connection");
+                    reading = false;
+                    queued.clear();
+                    server.cancel_all("client stopped answering pings").await;
+                    for session in sessions.drain() {
+                        server.end_session(&session);
+                    }
+                }
+                received = transport.recv(), if reading => match received {
+                    // Notifications such as cancellation bypass the limit
+                    Ok(Some(incoming)) if incoming.request.is_notification() || in_flight.len() < limit => {
+                        sessions.insert(server.session_of(&incoming.request));
+                        in_flight.spawn(handle(self.server.clone(), incoming));
+                    }
+                    Ok(Some(incoming)) if queued.len() >= max_queued => {
+                        if let Some(response) = refuse(server, &incoming) {
+                            transport.send(response).await?;
+                        }
+                    }
+                    Ok(Some(incoming)) => {
+                        sessions.insert(server.session_of(&incoming.request));
+                        queued.push_back(incoming);
+                    }
+                    Ok(None) => reading = false,
+                    Err(e @ (MCPError::JsonError(_) | MCPError::InvalidRequestAt { .. })) => {
+                        eprintln!("Failed to parse request: {}", e);
+                        transport.send(IncomingRequest::rejection(&e)).await?;
+                    }
+                    Err(e) => return Err(e),
+                },
+            }
+        }
+    }
+}
+
+impl<H: ToolHandler + 'static> ServerRunner<H> {
+    /// Serve the transports given to [`add_transport`](Self::add_transport)
+    /// until all have disconnected or a termination signal arrives
+    pub async fn run(self) -> Result<(), MCPError> {
+        self.run_transports(TransportSet::new()).await
+    }
+
+    /// Like [`run`](Self::run), also serving transports added to `set`;
+    /// keeps running while any of the set's handles is alive
+    pub async fn run_transports(mut self, set: TransportSet) -> Result<(), MCPError> {
+        let shared = self.server.clone();
+        let server = &*shared;
+        let limit = server.max_concurrent_requests();
+        let max_queued = server.max_queued_requests();
+        let mut notifications = server.claim_notification_receiver();
+        follow_resource_updates(server).await;
+        let _running = server.shutdown_control().runner();
+        let _heartbeat = server.start_heartbeat();
+        let shutdown = shutdown_requested(server, self.handle_signals);
+        tokio::pin!(shutdown);
+
+        let (incoming_tx, mut incoming) = mpsc::unbounded_channel();
+        let mut connections = Connections {
+            incoming: incoming_tx,
+            outgoing: Vec::new(),
+            pumps: Vec::new(),
+            closed: Vec::new(),
+            sessions: Vec::new(),
+            open: 0,
+        };
+        // Each transport's keepalive, yielding its origin once the client is gone
+        let mut keepalives = FuturesUnordered::new();
+        let watch = |origin: usize| ORIGIN.scope(origin, keepalive::monitor(server)).map(move |_| origin);
+        for transport in self.transports.drain(..) {
+            keepalives.push(watch(connections.connect(transport)));
+        }
+        let mut added = set.into_receiver();
+        let mut accepting = true;
+
+        let mut in_flight = JoinSet::new();
+        let mut queued: VecDeque<(usize, IncomingRequest)> = VecDeque::new();
+        let mut stopping = false;
+        let mut deadline = None;
+
+        loop {
+            while in_flight.len() < limit && let Some((origin, incoming)) = queued.pop_front() {
+                in_flight.spawn(handle_from(shared.clone(), origin, incoming));
+            }
+            let reading = !stopping && (accepting || connections.open > 0);
+            if !reading && in_flight.is_empty() {
+                while let Some(notification) = notifications.as_mut().and_then(NotificationReceiver::try_recv) {
+                    connections.notify(notification);
+                }
+                connections.finish().await;
+                return Ok(());
+            }
+
+            tokio::select! {
+                biased;
+                signal = &mut shutdown, if !stopping => {
+                    eprintln!("[SHUTDOWN] Received {}, waiting up to {:?} for {} in-flight request(s)", signal, self.shutdown_deadline, in_flight.len());
+                    if !queued.is_empty() {
+                        eprintln!("[SHUTDOWN] Dropping {} queued request(s)", queued.len());
+                        queued.clear();
+                    }
+                    stopping = true;
+                    deadline = Some(Instant::now() + self.shutdown_deadline);
+                }
+                Some(notification) = next_notification(&mut notifications) => connections.notify(notification),
+                Some(joined_task) = in_flight.join_next() => {
+                    while let Some(notification) = notifications.as_mut().and_then(NotificationReceiver::try_recv) {
+                        connections.notify(notification);
+                    }
+                    if let Some((origin, (Some(response), held))) = joined(joined_task) {
+                        connections.send(origin, Outgoing::Response(response, held));
+                    }
+                }
+                _ = tokio::time::sleep_until(deadline.unwrap_or_else(Instant::now)), if deadline.is_some() => {
+                    server.cancel_all("server shutting down").await;
+                    deadline = None;
+                }
+                transport = added.recv(), if accepting && !stopping => match transport {
+                    Some(transport) => keepalives.push(watch(connections.connect(transport))),
+                    None => accepting = false,
+                },
+                Some(origin) = keepalives.next(), if !stopping => {
+                    if connections.close(origin) {
+                        eprintln!("[KEEPALIVE] Client of transport {} stopped answering pings, closing it", origin);
+                        connections.pumps[origin].abort();
+                        queued.retain(|(queued_from, _)| *queued_from != origin);
+                        for session in connections.orphaned_sessions(origin) {
+                            server.end_session(&session);
+                        }
+                    }
+                }
+                Some((origin, received)) = incoming.recv(), if !stopping => match received {
+                    Some(request) if connections.closed[origin] => drop(request),
+                    Some(request) => {
+                        connections.sessions[origin].insert(server.session_of(&request.request));
+                        if request.request.is_notification() || in_flight.len() < limit {
+                            in_flight.spawn(handle_from(shared.clone(), origin, request));
+                        } else if queued.len() >= max_queued {
+                            if let Some(response) = ORIGIN.sync_scope(origin, || refuse(server, &request)) {
+                                connections.send(origin, Outgoing::Response(response, None));
+                            }
+                        } else {
+                            queued.push_back((origin, request));
+                        }
+                    }
+                    None => {
+                        connections.close(origin);
+                    }
+                },
+            }
+        }
+    }
+}
+
+/// The first termination signal (if handled) or call to
+/// [`SystemMCPServer::shutdown`]
+async fn shutdown_requested<H: ToolHandler>(server: &SystemMCPServer<H>, handle_signals: bool) -> &'static str {
+    let signal = async {
+        if handle_signals { terminate_signal().await } else { std::future::pending().await }
+    };
+    tokio::select! {
+        signal = signal => signal,
+        _ = server.shutdown_control().requested() => "shutdown request",
+    }
+}
+
+/// Serving goes on without cross-replica updates if the store is down
+async fn follow_resource_updates<H: ToolHandler>(server: &SystemMCPServer<H>) {
+    if let Err(e) = server.follow_resource_updates().await {
+        eprintln!("[SESSION] Not following other replicas' resource updates: {}", e);
+    }
+}
+
+/// A response, with the memory it holds until written
+type Handled = (Option<MCPResponse>, Option<Reservation>);
+
+async fn handle<H: ToolHandler>(server: Arc<SystemMCPServer<H>>, incoming: IncomingRequest) -> Handled {
+    server.handle_holding_output(incoming).await
+}
+
+async fn handle_from<H: ToolHandler>(server: Arc<SystemMCPServer<H>>, origin: usize, incoming: IncomingRequest) -> (usize, Handled) {
+    (origin, ORIGIN.scope(origin, server.handle_holding_output(incoming)).await)
+}
+
+enum Outgoing {
+    Response(MCPResponse, Option<Reservation>),
+    Notification(ServerNotification),
+}
+
+/// Transports of a multi-transport runner, each driven by its own task
+struct Connections {
+    incoming: mpsc::UnboundedSender<(usize, Option<IncomingRequest>)>,
+    outgoing: Vec<mpsc::UnboundedSender<Outgoing>>,
+    pumps: Vec<JoinHandle<()>>,
+    // Transports no longer reading, by origin
+    closed: Vec<bool>,
+    // Sessions each transport's requests used
+    sessions: Vec<HashSet<String>>,
+    // Transports still reading
+    open: usize,
+}
+
+impl Connections {
+    /// Start serving `transport`; returns its origin
+    fn connect(&mut self, transport: Box<dyn Transport>) -> usize {
+        let origin = self.outgoing.len();
+        let (sender, receiver) = mpsc::unbounded_channel();
+        let pump = pump(origin, transport, self.incoming.clone(), receiver);
+        self.outgoing.push(sender);
+        self.pumps.push(tokio::spawn(pump));
+        self.closed.push(false);
+        self.sessions.push(HashSet::new());
+        self.open += 1;
+        origin
+    }
+
+    /// Stop counting `origin` as reading; false if it already stopped
+    fn close(&mut self, origin: usize) -> bool {
+        if std::mem::replace(&mut self.closed[origin], true) {
+            return false;
+        }
+        self.open -= 1;
+        true
+    }
+
+    /// Sessions `origin` used that no open transport uses
+    fn orphaned_sessions(&mut self, origin: usize) -> Vec<String> {
+        let used = std::mem::take(&mut self.sessions[origin]);
+        used.into_iter()
+            .filter(|session| !(0..self.sessions.len()).any(|other| !self.closed[other] && self.sessions[other].contains(session)))
+            .collect()
+    }
+
+    fn send(&self, origin: usize, message: Outgoing) {
+        // A transport that failed has stopped taking messages
+        let _ = self.outgoing[origin].send(message);
+    }
+
+    fn notify(&self, notification: ServerNotification) {
+        match notification {
+            ServerNotification::Routed { origin, notification } => self.send(origin, Outgoing::Notification(*notification)),
+            notification => {
+                for origin in 0..self.outgoing.len() {
+                    self.send(origin, Outgoing::Notification(notification.clone()));
+                }
+            }
+        }
+    }
+
+    /// Let every transport write what is queued for it, then stop
+    async fn finish(self) {
+        drop(self.outgoing);
+        for pump in self.pumps {
+            let _ = pump.await;
+        }
+    }
+}
+
+/// Read requests from one transport and write what the runner sends it
+async fn pump(
+    origin: usize,
+    mut transport: Box<dyn Transport>,
+    incoming: mpsc::UnboundedSender<(usize, Option<IncomingRequest>)>,
+    mut outgoing: mpsc::UnboundedReceiver<Outgoing>,
+) {
+    let mut reading = true;
+    loop {
+        tokio::select! {
+            biased;
+            message = outgoing.recv() => {
+                let sent = match message {
+                    Some(Outgoing::Response(response, held)) => {
+                        let sent = transport.send(response).await;
+                        drop(held);
+                        sent
+                    }
+                    Some(Outgoing::Notification(notification)) => transport.send_notification(notification).await,
+                    None => return,
+                };
+                if let Err(e) = sent {
+                    eprintln!("[RUNNER] Transport {} failed: {}", origin, e);
+                    if reading {
+                        let _ = incoming.send((origin, None));
+                    }
+                    return;
+                }
+            }
+            received = transport.recv(), if reading => match received {
+                Ok(Some(request)) => {
+                    let _ = incoming.send((origin, Some(request)));
+                }
+                Err(e @ (MCPError::JsonError(_) | MCPError::InvalidRequestAt { .. })) => {
+                    eprintln!("Failed to parse request: {}", e);
+                    let _ = transport.send(IncomingRequest::rejection(&e)).await;
+                }
+                closed => {
+                    if let Err(e) = closed {
+                        eprintln!("[RUNNER] Transport {} failed: {}", origin, e);
+                    }
+                    reading = false;
+                    let _ = incoming.send((origin, None));
+                }
+            },
+        }
+    }
+}
+
+async fn forward<T: Transport>(
+    transport: &mut T,
+    notifications: &Option<NotificationReceiver>,
+    notification: ServerNotification,
+) -> Result<(), MCPError> {
+    match notifications {
+        Some(notifications) => forward_notification(transport, notifications, notification).await,
+        None => transport.send_notification(notification).await,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::error::MCPError;
+    use crate::context::RequestContext;
+    use crate::server::{SystemMCPServer, ToolHandler};
+    use crate::tools::ToolResponse;
+    use crate::transport::{in_process, TransportSet};
+    use async_trait::async_trait;
+    use serde_json::{json, Value};
+
+    struct Steps;
+
+    #[async_trait]
+    impl ToolHandler for Steps {
+        async fn call_tool(&self, name: &str, _args: &Value, ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+            if name == "hang" {
+                return std::future::pending().await;
+            }
+            if name == "spin" {
+                // Holds its worker thread, as CPU-bound work does
+                std::thread::sleep(std::time::Duration::from_millis(300));
+                return Ok(ToolResponse::new("spun".into(), false));
+            }
+            for step in 1..=3 {
+                let _ = ctx.progress().send_progress(ctx.request_id(), step as f64 / 3.0, None).await;
+            }
+            Ok(ToolResponse::new("done".into(), false))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_progress_precedes_response() {
+        let (mut client, transport) = in_process();
+        tokio::spawn(async move {
+            let server = std::sync::Arc::new(SystemMCPServer::<Steps>::builder().relaxed_lifecycle().build(Steps));
+            server.runner().without_signals().run_with_transport(transport).await.unwrap();
+        });
+
+        for id in 1..=3 {
+            client.send_raw(json!({ "jsonrpc": "2.0", "id": id, "method": "tools/call", "params": { "name": "steps" } }).to_string()).unwrap();
+        }
+        // Requests run concurrently, so their messages interleave
+        let mut progress = std::collections::HashMap::new();
+        let mut answered = 0;
+        while answered < 3 {
+            let message = client.next_notification().await.unwrap();
+            match message.get("id") {
+                Some(id) => {
+                    assert_eq!(progress.get(&id.to_string()), Some(&3), "{}", message);
+                    answered += 1;
+                }
+                None => *progress.entry(message["params"]["requestId"].as_str().unwrap().to_string()).or_insert(0) += 1,
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_requests_and_cancellation() {
+        let (mut client, transport) = in_process();
+        tokio::spawn(async move {
+            let server = std::sync::Arc::new(SystemMCPServer::<Steps>::builder().relaxed_lifecycle().max_concurrent_requests(2).build(Steps));
+            server.runner().without_signals().run_with_transport(transport).await.unwrap();
+        });
+
+        let call = |id: u64, name: &str| json!({ "jsonrpc": "2.0", "id": id, "method": "tools/call", "params": { "name": name } }).to_string();
+        client.send_raw(call(1, "hang")).unwrap();
+        client.send_raw(call(2, "hang")).unwrap();
+        // Over the limit: waits for a slot
+        client.send_raw(call(3, "steps")).unwrap();
+        // Handled at once even though both slots are taken
+        client.notify("notifications/cancelled", json!({ "requestId": "1" })).unwrap();
+
+        let mut ids = Vec::new();
+        while ids.len() < 2 {
+            let message = client.next_notification().await.unwrap();
+            if let Some(id) = message.get("id") {
+                ids.push(id.clone());
+            }
+        }
+        assert_eq!(ids, [json!(1), json!(3)]);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_busy_handlers_do_not_block_io() {
+        let (mut client, transport) = in_process();
+        tokio::spawn(async move {
+            let server = std::sync::Arc::new(SystemMCPServer::<Steps>::builder()
+                .relaxed_lifecycle()
+                .max_concurrent_requests(2)
+                .max_queued_requests(1)
+                .build(Steps));
+            server.runner().without_signals().run_with_transport(transport).await.unwrap();
+        });
+
+        let call = |id: u64, name: &str| json!({ "jsonrpc": "2.0", "id": id, "method": "tools/call", "params": { "name": name } }).to_string();
+        client.send_raw(call(11, "spin")).unwrap();
+        // Answered while the spinning call still holds its thread
+        let ping = client.request("ping", json!({})).await.unwrap();
+        assert!(ping["result"].is_object());
+
+        client.send_raw(call(12, "hang")).unwrap();
+        client.send_raw(call(13, "hang")).unwrap();
+        // Neither a slot nor room in the queue
+        client.send_raw(call(14, "hang")).unwrap();
+        let busy = client.next_notification().await.unwrap();
+        assert_eq!((busy["id"].clone(), busy["error"]["code"].clone()), (json!(14), json!(-32005)));
+        assert_eq!(client.next_notification().await.unwrap()["id"], 11);
+    }
+
+    #[tokio::test]
+    async fn test_multiple_transports() {
+        let (mut first, first_transport) = in_process();
+        let (mut second, second_transport) = in_process();
+        let set = TransportSet::new();
+        set.add(second_transport);
+        tokio::spawn(async move {
+            let server = std::sync::Arc::new(SystemMCPServer::<Steps>::builder().relaxed_lifecycle().build(Steps));
+            server.runner().without_signals().add_transport(first_transport).run_transports(set).await.unwrap();
+        });
+
+        // The same id on both transports: each gets only its own messages
+        for client in [&first, &second] {
+            client.send_raw(json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/call", "params": { "name": "steps" } }).to_string()).unwrap();
+        }
+        for client in [&mut first, &mut second] {
+            for _ in 0..3 {
+                assert_eq!(client.next_notification().await.unwrap()["method"], "notifications/progress");
+            }
+            assert_eq!(client.next_notification().await.unwrap()["result"]["content"][0]["text"], "done");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_drains_runner() {
+        let (mut client, transport) = in_process();
+        let server = std::sync::Arc::new(SystemMCPServer::<Steps>::builder().relaxed_lifecycle().build(Steps));
+        let runner = tokio::spawn({
+            let server = server.clone();
+            async move {
+                server.runner().without_signals().shutdown_deadline(std::time::Duration::from_millis(50)).run_with_transport(transport).await
+            }
+        });
+
+        let call = |id: u64, name: &str| json!({ "jsonrpc": "2.0", "id": id, "method": "tools/call", "params": { "name": name } }).to_string();
+        client.send_raw(call(1, "hang")).unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        server.shutdown().await;
+        assert!(runner.await.unwrap().is_ok());
+        let reply = client.next_notification().await.unwrap();
+        assert_eq!((reply["id"].clone(), reply["error"]["code"].clone()), (json!(1), json!(-32800)));
+
+        let late = crate::request::MCPRequest::new(Some(json!(2)), "tools/list", None);
+        assert_eq!(server.handle(late).await.unwrap().error.unwrap().code, -32000);
+    }
+}
@@ -0,0 +1,139 @@
+//! Field selection for structured tool results (`_meta.select`).
+//!
+//! Each selector is either a JSON pointer (`/rows/0/name`) or a dotted field
+//! mask (`rows.name`). Object keys are kept along the selected paths; on
+//! arrays a numeric segment picks an element and any other segment is applied
+//! to every element.
+
+use crate::error::MCPError;
+use serde_json::{Map, Value};
+
+/// Parse the `select` list from a request's `_meta`
+pub fn parse_selectors(meta: &Value) -> Result<Option<Vec<String>>, MCPError> {
+    match meta.get("select") {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Array(items)) => items
+            .iter()
+            .map(|item| {
+                item.as_str()
+                    .map(str::to_owned)
+                    .ok_or_else(|| MCPError::InvalidParams("_meta.select entries must be strings".into()))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map(Some),
+        Some(_) => Err(MCPError::InvalidParams("_meta.select must be an array of strings".into())),
+    }
+}
+
+fn segments(selector: &str) -> Vec<String> {
+    if let Some(pointer) = selector.strip_prefix('/') {
+        pointer
+            .split('/')
+            .map(|s| s.replace("~1", "/").replace("~0", "~"))
+            .collect()
+    } else {
+        selector.split('.').filter(|s| !s.is_empty()).map(str::to_owned).collect()
+    }
+}
+
+fn select_path(value: &Value, path: &[String]) -> Option<Value> {
+    let Some((head, rest)) = path.split_first() else {
+        return Some(value.clone());
+    };
+
+    match value {
+        Value::Object(map) => {
+            let selected = select_path(map.get(head)?, rest)?;
+            let mut out = Map::new();
+            out.insert(head.clone(), selected);
+            Some(Value::Object(out))
+        }
+        Value::Array(items) => match head.parse::<usize>() {
+            Ok(index) => Some(Value::Array(vec![select_path(items.get(index)?, rest)?])),
+            Err(_) => Some(Value::Array(
+                items.iter().map(|item| select_path(item, path).unwrap_or(Value::Null)).collect(),
+            )),
+        },
+        _ => None,
+    }
+}
+
+fn merge(into: &mut Value, from: Value) {
+    match (into, from) {
+        (Value::Object(a), Value::Object(b)) => {
+            for (key, value) in b {
+                match a.get_mut(&key) {
+                    Some(existing) => merge(existing, value),
+                    None => {
+                        a.insert(key, value);
+                    }
+                }
+            }
+        }
+        (Value::Array(a), Value::Array(b)) if a.len() == b.len() => {
+            for (existing, value) in a.iter_mut().zip(b) {
+                merge(existing, value);
+            }
+        }
+        (_, Value::Null) => {}
+        (slot, value) => *slot = value,
+    }
+}
+
+/// Project `value` onto the given selectors; unknown paths are skipped
+pub fn apply_selection(value: &Value, selectors: &[String]) -> Value {
+    let mut result = match value {
+        Value::Array(_) => Value::Array(vec![]),
+        _ => Value::Object(Map::new()),
+    };
+    for selector in selectors {
+        if let Some(selected) = select_path(value, &segments(selector)) {
+            merge(&mut result, selected);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_field_mask_over_arrays() {
+        let value = json!({
+            "exitCode": 0,
+            "rows": [{ "name": "a", "size": 1 }, { "name": "b", "size": 2 }],
+        });
+        let selected = apply_selection(&value, &["rows.name".into(), "exitCode".into()]);
+        assert_eq!(selected, json!({ "exitCode": 0, "rows": [{ "name": "a" }, { "name": "b" }] }));
+    }
+
+    #[test]
+    fn test_json_pointer() {
+        let value = json!({ "rows": [{ "name": "a" }, { "name": "b" }], "meta": { "a/b": 1 } });
+        assert_eq!(apply_selection(&value, &["/rows/1/name".into()]), json!({ "rows": [{ "name": "b" }] }));
+        assert_eq!(apply_selection(&value, &["/meta/a~1b".into()]), json!({ "meta": { "a/b": 1 } }));
+    }
+
+    #[test]
+    fn test_unknown_paths_are_skipped() {
+        let value = json!({ "a": 1 });
+        assert_eq!(apply_selection(&value, &["missing".into()]), json!({}));
+    }
+
+    #[tokio::test]
+    async fn test_bad_selection_fails_before_the_call() {
+        use crate::server::SystemMCPServer;
+        use crate::testing::fixtures;
+        use crate::testing::mock::{MockToolHandler, Reply};
+
+        let server = SystemMCPServer::<MockToolHandler>::builder()
+            .relaxed_lifecycle()
+            .build(MockToolHandler::new().tool("rm", Reply::text("removed")));
+        let call = fixtures::call_tool("rm").meta("select", 42).build();
+        let error = server.handle(call).await.unwrap().error.unwrap();
+        assert_eq!(error.code, -32602);
+        assert!(server.handler().calls().iter().all(|call| call.method != "tools/call"));
+    }
+}
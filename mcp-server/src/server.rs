@@ -0,0 +1,2989 @@
+use crate::batch::{BatchCall, BATCH_CALL_METHOD};
+use crate::call_log::{self, CallLogs, CALL_LOG_SCHEME};
+use crate::cas::{ContentStore, CAS_SCHEME};
+use crate::changes::{ChangeLog, ListDiff, ListKind, ListSource, CHANGES_URI};
+use crate::completion::{self, CompletionProvider};
+use crate::concurrency::ConcurrencyLimits;
+use crate::config::{self, LiveConfig};
+use crate::custom::{CustomMethods, MethodHandler, ToolCaller};
+use crate::context::{CancellationToken, ClientInfo, RequestContext};
+use crate::error::MCPError;
+use crate::flags::FeatureFlags;
+use crate::gc::{GcMetrics, GcPolicy, GcReport, ResourceGc, CLEANUP_METHOD};
+#[cfg(feature = "zstd")]
+use crate::compression;
+use crate::integrity;
+use crate::heartbeat::{Heartbeat, HeartbeatTicker, HEARTBEAT_URI};
+use crate::guards::{self, AuditLog, RateLimit, RequireToken, StrictParsing};
+use crate::journal::Journal;
+use crate::keepalive::Keepalive;
+use crate::list_cache::ToolListCache;
+#[cfg(feature = "jsonschema")]
+use crate::validation::{self, ArgumentValidator, OutputValidation, OutputValidator};
+use crate::metrics::{ToolMetrics, ToolStats, METRICS_URI};
+use crate::memory::{self, MemoryAccountant, MemoryCategory, MemoryStats};
+use crate::outbound::{self, ClientRequests};
+use crate::priority::Priority;
+use crate::subprocess::{self, DeadlinePolicy, SubprocessEnv};
+use crate::result_limit::{Overflow, ResultLimit, SPILL_SCHEME};
+use crate::result_pages::{ResultPages, NEXT_PAGE_TOOL};
+use crate::ready::{self, ReadySignal};
+use crate::registry::ToolRegistry;
+use crate::middleware::{Endpoint, IncomingRequest, Middleware, MiddlewareStack, Next, ToolCallGuard};
+use crate::request::MCPRequest;
+pub use crate::response::JsonRpcVersion;
+use crate::response::MCPResponse;
+use crate::pagination::Paginator;
+use crate::notifications::{NotificationReceiver, ProgressPolicy, ProgressSender, ProgressThrottle, ServerNotification};
+use crate::runner::{self, ServerRunner};
+use crate::select::{apply_selection, parse_selectors};
+use crate::session::{SessionState, Sessions};
+use crate::session_store::{ResourceUpdate, SessionStore, DEFAULT_SESSION_REFRESH};
+use crate::shutdown::{ShutdownControl, DEFAULT_SHUTDOWN_DEADLINE};
+use crate::tool_docs;
+use crate::uri_resolver::UriResolver;
+use crate::versioning::{Deprecation, ToolVersions};
+use crate::transport::Transport;
+use crate::tools::{
+    Annotations, CompleteResult, Completion, CompletionReference, CompletionsCapability, ContentBlock,
+    InitializeResponse, LoggingCapability, Prompt, PromptResponse, PromptsCapability, Resource, ResourceContent,
+    ResourcesCapability, ServerCapabilities, ServerInfo, StreamChunk, Tool, ToolResponse, ToolsCapability,
+};
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::pin::Pin;
+use std::sync::{Arc, OnceLock};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio_stream::Stream;
+
+/// Application logic behind a server. Only `call_tool` is required: tools,
+/// prompts and resources given to the builder are listed without handler
+/// code, and every other method defaults to an empty list or a not-found
+/// error (see `examples/minimal.rs`). Methods answering a request get its
+/// [`RequestContext`], with progress reporting and cancellation.
+#[async_trait]
+pub trait ToolHandler: Send + Sync {
+    // Tool methods
+    async fn call_tool(&self, name: &str, args: &Value, ctx: &RequestContext) -> Result<ToolResponse, MCPError>;
+
+    /// Tools known only at runtime, listed after the builder's tools
+    async fn list_tools(&self, ctx: &RequestContext) -> Result<Vec<Tool>, MCPError> {
+        let _ = ctx;
+        Ok(vec![]) // Default: only the builder's tools
+    }
+
+    /// Advances whenever `list_tools` may answer differently, letting
+    /// [`ServerBuilder::cache_tool_list`] keep its pages; `None`, the default,
+    /// keeps `tools/list` from being cached
+    fn tools_generation(&self) -> Option<u64> {
+        None
+    }
+
+    // Prompt methods
+    async fn list_prompts(&self, ctx: &RequestContext) -> Result<Vec<Prompt>, MCPError> {
+        let _ = ctx;
+        Ok(vec![]) // Default: no prompts
+    }
+
+    async fn get_prompt(&self, name: &str, args: &Value, ctx: &RequestContext) -> Result<PromptResponse, MCPError> {
+        let _ = (args, ctx);
+        Err(MCPError::UnknownPrompt(name.into()))
+    }
+
+    // Resource methods
+    async fn list_resources(&self, ctx: &RequestContext) -> Result<Vec<Resource>, MCPError> {
+        let _ = ctx;
+        Ok(vec![]) // Default: no resources
+    }
+
+    async fn read_resource(&self, uri: &str, ctx: &RequestContext) -> Result<ResourceContent, MCPError> {
+        let _ = ctx;
+        Err(MCPError::ResourceNotFound(uri.into()))
+    }
+
+    // Streaming method for long-running operations using tokio streams
+    async fn call_tool_stream(&self, name: &str, args: &Value, ctx: &RequestContext) -> Result<Pin<Box<dyn Stream<Item = StreamChunk> + Send>>, MCPError> {
+        let _ = (name, args, ctx);
+        Err(MCPError::StreamError("Streaming not supported".into()))
+    }
+
+    // Rollback hook for x-mcp/batch-call
+    fn supports_rollback(&self, name: &str) -> bool {
+        let _ = name;
+        false
+    }
+
+    async fn rollback_tool(&self, name: &str, args: &Value, ctx: &RequestContext) -> Result<(), MCPError> {
+        let _ = (args, ctx);
+        Err(MCPError::InvalidParams(format!("tool {} does not support rollback", name)))
+    }
+
+    /// Requests for methods neither built in nor registered with
+    /// [`ServerBuilder::custom_method`], e.g. vendor extensions
+    async fn experimental_method(&self, method: &str, params: Option<&Value>, ctx: &RequestContext) -> Result<Value, MCPError> {
+        let _ = (params, ctx);
+        Err(MCPError::MethodNotFound(method.into()))
+    }
+
+    // Observability hooks
+    async fn on_tool_called(&self, name: &str) {
+        let _ = name;
+    }
+
+    async fn on_tool_completed(&self, name: &str, success: bool) {
+        let _ = (name, success);
+    }
+
+    // Cancellation hook
+    async fn on_request_cancelled(&self, request_id: &str, reason: Option<&str>) {
+        eprintln!("[CANCEL] Request {} cancelled: {:?}", request_id, reason);
+    }
+
+    /// The client finished the handshake with `notifications/initialized`;
+    /// start background work (watchers, warmup) here. Runs once per
+    /// handshake and holds up the session's next message, so spawn anything slow.
+    async fn on_initialized(&self, session_id: &str) {
+        let _ = session_id;
+    }
+
+    /// `initialize` arrived for a session that was already initialized and
+    /// [`ReinitializePolicy::Reset`] is about to clear its state
+    async fn on_reinitialize(&self, session_id: &str) {
+        let _ = session_id;
+    }
+}
+
+/// Requests handled at once unless [`ServerBuilder::max_concurrent_requests`] is set
+pub const DEFAULT_MAX_CONCURRENT_REQUESTS: usize = 16;
+
+/// Requests waiting for a slot unless [`ServerBuilder::max_queued_requests`] is set
+pub const DEFAULT_MAX_QUEUED_REQUESTS: usize = 256;
+
+/// Random enough to tell replicas apart
+fn replica_id() -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).map_or(0, |d| d.as_nanos());
+    let seed = format!("{}:{}:{}", std::process::id(), nanos, COUNTER.fetch_add(1, Ordering::Relaxed));
+    Sha256::digest(seed.as_bytes())[..8].iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub use mcp_types::protocol::{negotiate_protocol_version, PROTOCOL_VERSION, SUPPORTED_PROTOCOL_VERSIONS};
+
+/// `initialize` instructions of a [`ServerBuilder::read_only`] server
+const READ_ONLY_INSTRUCTIONS: &str = "This server is in read-only mode: only tools that do not modify their environment are available, and resource subscriptions are disabled.";
+
+/// What to do when `initialize` is re-sent on an initialized session
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ReinitializePolicy {
+    /// Cancel the session's requests in flight, drop subscriptions and log
+    /// level, then negotiate again
+    #[default]
+    Reset,
+    /// Answer with [`MCPError::AlreadyInitialized`] and keep the session as is
+    Reject,
+}
+
+/// Annotations filled into tool results that do not set their own
+#[derive(Debug, Clone, Default)]
+struct ContentAnnotations {
+    default: Option<Annotations>,
+    per_tool: HashMap<String, Annotations>,
+}
+
+impl ContentAnnotations {
+    /// Override for `tool`, matching `name@version` by its base name too
+    fn for_tool(&self, tool: &str) -> Option<&Annotations> {
+        let base = tool.rsplit_once('@').map_or(tool, |(base, _)| base);
+        self.per_tool.get(tool)
+            .or_else(|| self.per_tool.get(base))
+            .or(self.default.as_ref())
+    }
+
+    fn apply(&self, tool: &str, content: &mut [ContentBlock]) {
+        let Some(annotations) = self.for_tool(tool) else { return };
+        for block in content {
+            block.annotations_mut().get_or_insert_with(|| annotations.clone());
+        }
+    }
+}
+
+/// Time limits for handling a request, most specific first: per tool, per
+/// method, then the default
+#[derive(Debug, Clone, Default)]
+struct Timeouts {
+    default: Option<Duration>,
+    per_method: HashMap<String, Duration>,
+    per_tool: HashMap<String, Duration>,
+}
+
+impl Timeouts {
+    fn for_request(&self, method: &str, req: &MCPRequest) -> Option<Duration> {
+        let tool = (method == "tools/call")
+            .then(|| req.params.as_ref()?.get("name")?.as_str())
+            .flatten();
+        tool.and_then(|tool| self.per_tool.get(tool))
+            .or_else(|| self.per_method.get(method))
+            .or(self.default.as_ref())
+            .copied()
+    }
+}
+
+/// Sleep for `limit`, or forever without one
+async fn sleep_for(limit: Option<Duration>) {
+    match limit {
+        Some(limit) => tokio::time::sleep(limit).await,
+        None => std::future::pending().await,
+    }
+}
+
+pub struct ServerBuilder {
+    capabilities: ServerCapabilities,
+    // tools/list, prompts/list and resources/list entries given to the builder
+    tool_list: Vec<Value>,
+    tool_registry: Option<ToolRegistry>,
+    prompt_list: Vec<Value>,
+    resource_list: Vec<Value>,
+    method_aliases: HashMap<String, String>,
+    content_store: Option<ContentStore>,
+    gc: Option<ResourceGc>,
+    result_pages: Option<ResultPages>,
+    result_limit: Option<ResultLimit>,
+    heartbeat: Option<Arc<Heartbeat>>,
+    keepalive: Option<Keepalive>,
+    client_request_timeout: Duration,
+    tool_docs: bool,
+    change_log: Option<Arc<dyn ListSource>>,
+    metrics_resource: bool,
+    tool_list_cache: Option<ToolListCache>,
+    uri_resolver: Option<UriResolver>,
+    tool_versions: ToolVersions,
+    deprecated_tools: HashMap<String, Deprecation>,
+    output_schemas: HashMap<String, Value>,
+    warn_deprecated_calls: bool,
+    middleware: MiddlewareStack,
+    tool_call_guards: Vec<Arc<dyn ToolCallGuard>>,
+    session_key: Option<String>,
+    journal: Option<Journal>,
+    disabled_methods: HashSet<String>,
+    // Capabilities the application asked for, checked by build()
+    declared: HashSet<&'static str>,
+    prompts: Vec<Prompt>,
+    completion_provider: Option<Arc<dyn CompletionProvider>>,
+    content_annotations: ContentAnnotations,
+    call_logs: Option<Arc<CallLogs>>,
+    ready_signals: Vec<ReadySignal>,
+    memory: Option<Arc<MemoryAccountant>>,
+    deny_destructive_tools: bool,
+    read_only: bool,
+    content_digests: bool,
+    timeouts: Timeouts,
+    relaxed_lifecycle: bool,
+    strict: bool,
+    #[cfg(feature = "zstd")]
+    compress_contents: Option<usize>,
+    #[cfg(feature = "jsonschema")]
+    argument_validator: Option<ArgumentValidator>,
+    #[cfg(feature = "jsonschema")]
+    output_validator: Option<OutputValidator>,
+    progress_policy: ProgressPolicy,
+    paginator: Option<Paginator>,
+    custom_methods: CustomMethods,
+    background_tools: HashSet<String>,
+    max_concurrent_requests: usize,
+    max_queued_requests: usize,
+    accept_jsonrpc_1: bool,
+    concurrency: ConcurrencyLimits,
+    config: Option<Arc<LiveConfig>>,
+    flags: FeatureFlags,
+    subprocess_env: SubprocessEnv,
+    deadline_policy: DeadlinePolicy,
+    session_store: Option<Arc<dyn SessionStore>>,
+    session_refresh: Duration,
+    reinitialize_policy: ReinitializePolicy,
+}
+
+impl Default for ServerBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ServerBuilder {
+    pub fn new() -> Self {
+        ServerBuilder {
+            capabilities: ServerCapabilities {
+                tools: Some(ToolsCapability::default()),
+                prompts: Some(PromptsCapability::default()),
+                resources: Some(ResourcesCapability::default()),
+                ..ServerCapabilities::default()
+            },
+            tool_list: Vec::new(),
+            tool_registry: None,
+            prompt_list: Vec::new(),
+            resource_list: Vec::new(),
+            method_aliases: HashMap::new(),
+            content_store: None,
+            gc: None,
+            result_pages: None,
+            result_limit: None,
+            heartbeat: None,
+            keepalive: None,
+            client_request_timeout: outbound::DEFAULT_CLIENT_REQUEST_TIMEOUT,
+            tool_docs: false,
+            change_log: None,
+            metrics_resource: false,
+            tool_list_cache: None,
+            uri_resolver: None,
+            tool_versions: ToolVersions::default(),
+            deprecated_tools: HashMap::new(),
+            output_schemas: HashMap::new(),
+            warn_deprecated_calls: false,
+            middleware: Vec::new(),
+            tool_call_guards: Vec::new(),
+            session_key: None,
+            journal: None,
+            disabled_methods: HashSet::new(),
+            declared: HashSet::new(),
+            prompts: Vec::new(),
+            completion_provider: None,
+            content_annotations: ContentAnnotations::default(),
+            call_logs: None,
+            ready_signals: Vec::new(),
+            memory: None,
+            deny_destructive_tools: false,
+            read_only: false,
+            content_digests: false,
+            timeouts: Timeouts::default(),
+            relaxed_lifecycle: false,
+            strict: false,
+            #[cfg(feature = "zstd")]
+            compress_contents: None,
+            #[cfg(feature = "jsonschema")]
+            argument_validator: None,
+            #[cfg(feature = "jsonschema")]
+            output_validator: None,
+            progress_policy: ProgressPolicy::Unthrottled,
+            paginator: None,
+            custom_methods: CustomMethods::new(),
+            background_tools: HashSet::new(),
+            max_concurrent_requests: DEFAULT_MAX_CONCURRENT_REQUESTS,
+            max_queued_requests: DEFAULT_MAX_QUEUED_REQUESTS,
+            accept_jsonrpc_1: cfg!(any(feature = "jsonrpc-1", feature = "schema-june-2025")),
+            concurrency: ConcurrencyLimits::default(),
+            config: None,
+            flags: FeatureFlags::new(),
+            subprocess_env: SubprocessEnv::default(),
+            deadline_policy: DeadlinePolicy::default(),
+            session_store: None,
+            session_refresh: DEFAULT_SESSION_REFRESH,
+            reinitialize_policy: ReinitializePolicy::default(),
+        }
+    }
+
+    /// Tools only: prompts, resources, completion and logging are disabled
+    pub fn minimal() -> Self {
+        Self::new().disable_methods([
+            "prompts/list",
+            "prompts/get",
+            "resources/list",
+            "resources/read",
+            "resources/subscribe",
+            "resources/unsubscribe",
+            "completion/complete",
+            "logging/setLevel",
+        ])
+    }
+
+    /// Everything on: content dedupe, live call logs, a generous memory
+    /// ceiling, adaptive progress, paged lists and a ready line on stderr
+    pub fn full() -> Self {
+        Self::new()
+            .dedupe_content(4 * 1024)
+            .call_logs(Duration::from_secs(300))
+            .memory_ceiling(512 * 1024 * 1024)
+            .progress_policy(ProgressPolicy::adaptive())
+            .pagination(Paginator::default())
+            .ready_signal(ReadySignal::Stderr)
+    }
+
+    /// Locked down: audit log, strict envelopes, 20 requests/s,
+    /// `_meta.authToken` required, destructive tools refused and a tight
+    /// memory ceiling. The rate limit comes before the token check, so
+    /// guessing tokens is throttled too.
+    pub fn secure(auth_token: impl Into<String>) -> Self {
+        Self::new()
+            .layer(AuditLog)
+            .layer(StrictParsing)
+            .layer(RateLimit::new(20, 40))
+            .layer(RequireToken::new(auth_token))
+            .deny_destructive_tools()
+            .memory_ceiling(64 * 1024 * 1024)
+    }
+
+    /// Hide and refuse tools that are not annotated read-only or
+    /// non-destructive
+    pub fn deny_destructive_tools(mut self) -> Self {
+        self.deny_destructive_tools = true;
+        self
+    }
+
+    /// Serve only tools annotated read-only and refuse the rest, disable
+    /// resource subscriptions, and say so in `initialize`'s `instructions`
+    /// and `_meta.readOnly`; for demo and audit deployments
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Attach `_meta.sha256` to resource contents returned by
+    /// `resources/read` and embedded in tool results
+    pub fn content_digests(mut self) -> Self {
+        self.content_digests = true;
+        self
+    }
+
+    /// Zstd-compress resource contents of at least `min_size` bytes for
+    /// clients that advertise `experimental.zstd`
+    #[cfg(feature = "zstd")]
+    pub fn compress_contents(mut self, min_size: usize) -> Self {
+        self.compress_contents = Some(min_size);
+        self
+    }
+
+    /// Check tool call arguments against the tool's input schema before the
+    /// handler runs; see [`crate::validation`]
+    #[cfg(feature = "jsonschema")]
+    pub fn validate_arguments(mut self) -> Self {
+        self.argument_validator = Some(ArgumentValidator::new());
+        self
+    }
+
+    /// List `schema` as the `outputSchema` of the tool `name`, the shape of
+    /// its results' `structuredContent`
+    pub fn output_schema(mut self, name: impl Into<String>, schema: Value) -> Self {
+        self.output_schemas.insert(name.into(), schema);
+        self
+    }
+
+    /// Check the structured content of tool results against the tool's
+    /// [`output_schema`](Self::output_schema); see [`crate::validation`]
+    #[cfg(feature = "jsonschema")]
+    pub fn validate_output(mut self, policy: OutputValidation) -> Self {
+        self.output_validator = Some(OutputValidator::new(policy));
+        self
+    }
+
+    /// Serve requests before the `initialize` handshake has completed, for
+    /// clients that skip `notifications/initialized` or `initialize` itself
+    pub fn relaxed_lifecycle(mut self) -> Self {
+        self.relaxed_lifecycle = true;
+        self
+    }
+
+    /// Reject requests that bend JSON-RPC 2.0 or send misshapen params for a
+    /// standard method, before any middleware runs; see [`StrictParsing`]
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// Fail any request not answered within `limit`
+    pub fn request_timeout(mut self, limit: Duration) -> Self {
+        self.timeouts.default = Some(limit);
+        self
+    }
+
+    /// Time limit for `method`, overriding [`Self::request_timeout`]
+    pub fn method_timeout(mut self, method: impl Into<String>, limit: Duration) -> Self {
+        self.timeouts.per_method.insert(method.into(), limit);
+        self
+    }
+
+    /// Time limit for calls to `tool`, overriding method and default limits
+    pub fn tool_timeout(mut self, tool: impl Into<String>, limit: Duration) -> Self {
+        self.timeouts.per_tool.insert(tool.into(), limit);
+        self
+    }
+
+    /// Cap bytes held in tool output, the CAS store and queued notifications.
+    /// Under pressure progress notifications are dropped, then caches are
+    /// evicted, then oversized results are rejected.
+    pub fn memory_ceiling(mut self, bytes: usize) -> Self {
+        self.memory = Some(Arc::new(MemoryAccountant::new(bytes)));
+        self
+    }
+
+    /// Debounce progress notifications per call. With
+    /// [`ProgressPolicy::Adaptive`] the interval follows the delivery latency
+    /// reported through [`NotificationReceiver::record_delivery`].
+    pub fn progress_policy(mut self, policy: ProgressPolicy) -> Self {
+        self.progress_policy = policy;
+        self
+    }
+
+    /// Requests a runner handles at once; more wait in arrival order
+    pub fn max_concurrent_requests(mut self, limit: usize) -> Self {
+        self.max_concurrent_requests = limit.max(1);
+        self
+    }
+
+    /// Requests that may wait for one of those; more are refused as busy
+    pub fn max_queued_requests(mut self, limit: usize) -> Self {
+        self.max_queued_requests = limit;
+        self
+    }
+
+    /// Also serve JSON-RPC 1.0 requests (no `jsonrpc` member, or `"1.0"`),
+    /// answering each in the version it used. On by default with the
+    /// `jsonrpc-1` or `schema-june-2025` features.
+    pub fn accept_jsonrpc_1(mut self, accept: bool) -> Self {
+        self.accept_jsonrpc_1 = accept;
+        self
+    }
+
+    /// Cap requests in flight across the server and calls per tool; see
+    /// [`ConcurrencyLimits`]
+    pub fn concurrency_limits(mut self, limits: ConcurrencyLimits) -> Self {
+        self.concurrency = limits;
+        self
+    }
+
+    /// Take timeouts, the in-flight cap, the default log level and the
+    /// served tools from `config`, read again for every request; see
+    /// [`crate::config`]
+    pub fn config(mut self, config: Arc<LiveConfig>) -> Self {
+        self.config = Some(config);
+        self
+    }
+
+    /// Run these tools in the background lane, whatever calls hint
+    pub fn background_tools<I, S>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.background_tools.extend(tools.into_iter().map(Into::into));
+        self
+    }
+
+    /// Use `flags` as the server's feature flags, keeping the handle to
+    /// toggle them at runtime
+    pub fn feature_flags(mut self, flags: FeatureFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// Environment defaults for subprocesses tools spawn through
+    /// [`RequestContext::command`]
+    pub fn subprocess_env(mut self, env: SubprocessEnv) -> Self {
+        self.subprocess_env = env;
+        self
+    }
+
+    /// How subprocess timeouts are clamped to the request deadline; see
+    /// [`RequestContext::subprocess_timeout`]
+    pub fn deadline_policy(mut self, policy: DeadlinePolicy) -> Self {
+        self.deadline_policy = policy;
+        self
+    }
+
+    /// Hide and refuse `tool` while feature flag `flag` is off
+    pub fn flagged_tool(self, tool: impl Into<String>, flag: impl Into<String>) -> Self {
+        self.flags.gate_tool(tool, flag);
+        self
+    }
+
+    /// Share session state and resource updates with other replicas through
+    /// `store`; see [`crate::session_store`]
+    pub fn session_store(mut self, store: Arc<dyn SessionStore>) -> Self {
+        self.session_store = Some(store);
+        self
+    }
+
+    /// How long a session loaded from the session store is used before it
+    /// is loaded again; defaults to [`DEFAULT_SESSION_REFRESH`]
+    pub fn session_refresh(mut self, refresh: Duration) -> Self {
+        self.session_refresh = refresh;
+        self
+    }
+
+    /// How a repeated `initialize` on one session is handled
+    pub fn reinitialize_policy(mut self, policy: ReinitializePolicy) -> Self {
+        self.reinitialize_policy = policy;
+        self
+    }
+
+    /// Serve a non-spec method; built-in methods take precedence
+    pub fn custom_method(mut self, method: impl Into<String>, handler: impl MethodHandler + 'static) -> Self {
+        self.custom_methods.insert(method.into(), Arc::new(handler));
+        self
+    }
+
+    /// Enable the experimental `x-mcp/batch-call` method
+    pub fn batch_calls(self) -> Self {
+        self.custom_method(BATCH_CALL_METHOD, BatchCall)
+    }
+
+    /// Split list results into pages, honouring clamped `_meta.pageSize` hints
+    pub fn pagination(mut self, paginator: Paginator) -> Self {
+        self.paginator = Some(paginator);
+        self
+    }
+
+    /// Answer repeated `tools/list` requests from cached pages until the
+    /// tools change (see [`crate::list_cache`])
+    pub fn cache_tool_list(mut self) -> Self {
+        self.tool_list_cache = Some(ToolListCache::new());
+        self
+    }
+
+    /// Announce readiness this way from [`SystemMCPServer::signal_ready`];
+    /// may be given more than once
+    pub fn ready_signal(mut self, signal: ReadySignal) -> Self {
+        self.ready_signals.push(signal);
+        self
+    }
+
+    /// Expose each tool call's progress messages as `call://{request_id}/log`,
+    /// kept for `ttl` after the call finishes
+    pub fn call_logs(mut self, ttl: Duration) -> Self {
+        self.call_logs = Some(Arc::new(CallLogs::new(ttl)));
+        self
+    }
+
+    /// Collect transient resources according to `policy`; see [`crate::gc`]
+    pub fn resource_gc(mut self, policy: GcPolicy) -> Self {
+        self.gc = Some(ResourceGc::new(policy));
+        self
+    }
+
+    /// Annotations applied to every tool result block that carries none
+    pub fn default_annotations(mut self, annotations: Annotations) -> Self {
+        self.content_annotations.default = Some(annotations);
+        self
+    }
+
+    /// Annotations for result blocks of one tool, taking precedence over
+    /// [`default_annotations`](Self::default_annotations)
+    pub fn tool_annotations(mut self, tool: impl Into<String>, annotations: Annotations) -> Self {
+        self.content_annotations.per_tool.insert(tool.into(), annotations);
+        self
+    }
+
+    /// Hard-disable methods regardless of handler support: they answer
+    /// `-32601` and the matching capability is no longer advertised
+    pub fn disable_methods<I, S>(mut self, methods: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.disabled_methods.extend(methods.into_iter().map(Into::into));
+        self
+    }
+
+    fn strip_disabled_capabilities(&mut self) {
+        let disabled = |method: &str| self.disabled_methods.contains(method);
+        let (tools, prompts, resources, subscribe, complete) = (
+            disabled("tools/list") || disabled("tools/call"),
+            disabled("prompts/list") || disabled("prompts/get"),
+            disabled("resources/list") || disabled("resources/read"),
+            disabled("resources/subscribe"),
+            disabled("completion/complete"),
+        );
+
+        if tools {
+            self.capabilities.tools = None;
+            self.tool_list.clear();
+        }
+        if prompts {
+            self.capabilities.prompts = None;
+            self.prompt_list.clear();
+        }
+        if resources {
+            self.capabilities.resources = None;
+            self.resource_list.clear();
+        } else if subscribe
+            && let Some(resources) = &mut self.capabilities.resources
+        {
+            resources.subscribe = None;
+        }
+        if complete {
+            self.capabilities.completions = None;
+        }
+        if disabled("logging/setLevel") {
+            self.capabilities.logging = None;
+        }
+    }
+
+    /// Answer `completion/complete` for arguments without an `enum` schema
+    pub fn completion_provider(mut self, provider: impl CompletionProvider + 'static) -> Self {
+        self.completion_provider = Some(Arc::new(provider));
+        self
+    }
+
+    /// Record every request and response to `journal` for later replay
+    pub fn journal(mut self, journal: Journal) -> Self {
+        self.journal = Some(journal);
+        self
+    }
+
+    /// Keep separate session state per value of `params._meta[key]`, for
+    /// gateways that tunnel several clients through one connection
+    pub fn demultiplex_sessions(mut self, key: impl Into<String>) -> Self {
+        self.session_key = Some(key.into());
+        self
+    }
+
+    /// Add a middleware; middleware runs in the order it was added
+    pub fn layer(mut self, middleware: impl Middleware + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Check every tool call with `guard` before the tool runs; guards run
+    /// in the order they were added
+    pub fn tool_call_guard(mut self, guard: impl ToolCallGuard + 'static) -> Self {
+        self.tool_call_guards.push(Arc::new(guard));
+        self
+    }
+
+    /// Register `tool` as `version` of the tool named `tool.name`; see
+    /// [`crate::versioning`] for how calls are routed
+    pub fn with_tool_version(mut self, version: u32, tool: Tool) -> Self {
+        self.tool_versions.register(version, tool);
+        self
+    }
+
+    /// Version of `name` used when a call does not select one
+    pub fn default_tool_version(mut self, name: &str, version: u32) -> Self {
+        self.tool_versions.set_default(name, version);
+        self
+    }
+
+    /// Mark one version of `name` deprecated; see [`crate::versioning`]
+    pub fn deprecate_tool_version(mut self, name: &str, version: u32, deprecation: Deprecation) -> Self {
+        self.tool_versions.deprecate(name, version, deprecation);
+        self
+    }
+
+    /// Mark the tool `name` deprecated in `tools/list`; see [`crate::versioning`]
+    pub fn deprecate_tool(mut self, name: &str, deprecation: Deprecation) -> Self {
+        self.deprecated_tools.insert(name.into(), deprecation);
+        self
+    }
+
+    /// Send a session a `warning` log notification the first time it calls
+    /// a tool marked with [`deprecate_tool`](Self::deprecate_tool) or
+    /// [`deprecate_tool_version`](Self::deprecate_tool_version)
+    pub fn warn_deprecated_calls(mut self) -> Self {
+        self.warn_deprecated_calls = true;
+        self
+    }
+
+    /// Replace repeated text results of at least `min_bytes` with
+    /// `cas://{hash}` resource links
+    pub fn dedupe_content(mut self, min_bytes: usize) -> Self {
+        self.content_store = Some(ContentStore::new(min_bytes));
+        self
+    }
+
+    /// Send tool results with more than `page_size` content blocks a page at
+    /// a time, continued through the built-in `next_page` tool
+    pub fn paginate_results(mut self, page_size: usize) -> Self {
+        self.result_pages = Some(ResultPages::new(page_size));
+        self
+    }
+
+    /// Cut the text of tool results whose JSON exceeds `max_bytes`; see
+    /// [`crate::result_limit`]
+    pub fn max_result_size(mut self, max_bytes: usize, overflow: Overflow) -> Self {
+        self.result_limit = Some(ResultLimit::new(max_bytes, overflow));
+        self
+    }
+
+    /// List `mcp://server/heartbeat` and update it every `interval` while a
+    /// runner is serving
+    pub fn heartbeat(mut self, interval: Duration) -> Self {
+        self.heartbeat = Some(Arc::new(Heartbeat::new(interval)));
+        self
+    }
+
+    /// Serve the tools of `registry`, which may change at runtime, and
+    /// advertise `tools.listChanged`; see [`crate::registry`]
+    pub fn tool_registry(mut self, registry: ToolRegistry) -> Self {
+        self.capabilities.tools.get_or_insert_with(Default::default).list_changed = Some(true);
+        self.tool_registry = Some(registry);
+        self
+    }
+
+    /// How long a handler waits for the client to answer a request such as
+    /// `sampling/createMessage`
+    pub fn client_request_timeout(mut self, timeout: Duration) -> Self {
+        self.client_request_timeout = timeout;
+        self
+    }
+
+    /// Ping the client over each transport and drop connections that stop
+    /// answering; see [`crate::keepalive`]
+    pub fn keepalive(mut self, keepalive: Keepalive) -> Self {
+        self.keepalive = Some(keepalive);
+        self
+    }
+
+    /// Serve markdown docs of every tool as `tool://{name}/docs`
+    pub fn tool_docs(mut self) -> Self {
+        self.tool_docs = true;
+        self
+    }
+
+    /// Track the lists of `source` for [`SystemMCPServer::reload_registries`]
+    /// and list `mcp://server/changes`, the differences it found
+    pub fn change_log(mut self, source: Arc<dyn ListSource>) -> Self {
+        self.change_log = Some(source);
+        self
+    }
+
+    /// List `metrics://tools`, the figures of [`SystemMCPServer::metrics`]
+    pub fn metrics_resource(mut self) -> Self {
+        self.metrics_resource = true;
+        self
+    }
+
+    /// Resolve relative URIs in tool results' resource links against
+    /// `resolver`'s base instead of passing them through
+    pub fn relative_uris(mut self, resolver: UriResolver) -> Self {
+        self.uri_resolver = Some(resolver);
+        self
+    }
+
+    /// Route requests for a legacy method name to a canonical one, e.g.
+    /// `alias("tools/invoke", "tools/call")`
+    pub fn alias(mut self, from: impl Into<String>, to: impl Into<String>) -> Self {
+        self.method_aliases.insert(from.into(), to.into());
+        self
+    }
+
+    pub fn with_tools(mut self, tools: Vec<Tool>) -> Self {
+        self.tool_list = tools.into_iter().map(|t| serde_json::to_value(t).unwrap()).collect();
+        self
+    }
+
+    pub fn with_prompts(mut self, prompts: Vec<Prompt>) -> Self {
+        self.prompt_list = prompts.iter().map(|p| serde_json::to_value(p).unwrap()).collect();
+        self.prompts = prompts;
+        self
+    }
+
+    pub fn with_resources(mut self, resources: Vec<Resource>) -> Self {
+        self.resource_list = resources.into_iter().map(|r| serde_json::to_value(r).unwrap()).collect();
+        self
+    }
+
+    /// Advertise `capability` for tools, e.g. with `listChanged`
+    pub fn tools_capability(mut self, capability: ToolsCapability) -> Self {
+        self.capabilities.tools = Some(capability);
+        self
+    }
+
+    pub fn prompts_capability(mut self, capability: PromptsCapability) -> Self {
+        self.declared.insert("prompts");
+        self.capabilities.prompts = Some(capability);
+        self
+    }
+
+    pub fn resources_capability(mut self, capability: ResourcesCapability) -> Self {
+        self.declared.insert("resources");
+        if capability.subscribe == Some(true) {
+            self.declared.insert("resources.subscribe");
+        }
+        self.capabilities.resources = Some(capability);
+        self
+    }
+
+    /// Declare prompt support; [`build`](Self::build) checks it can be served
+    pub fn enable_prompts(mut self) -> Self {
+        self.declared.insert("prompts");
+        self.capabilities.prompts.get_or_insert_with(Default::default);
+        self
+    }
+
+    pub fn enable_resources(mut self) -> Self {
+        self.declared.insert("resources");
+        self.capabilities.resources.get_or_insert_with(Default::default);
+        self
+    }
+
+    /// Advertise `resources.subscribe`
+    pub fn with_subscribe(self, subscribe: bool) -> Self {
+        let mut builder = self.enable_resources();
+        if subscribe {
+            builder.declared.insert("resources.subscribe");
+        } else {
+            builder.declared.remove("resources.subscribe");
+        }
+        builder.capabilities.resources.get_or_insert_with(Default::default).subscribe = Some(subscribe);
+        builder
+    }
+
+    /// Advertise `tools.listChanged`; prompts and resources are fixed at
+    /// build, so their lists never change
+    pub fn with_list_changed(mut self, list_changed: bool) -> Self {
+        self.capabilities.tools.get_or_insert_with(Default::default).list_changed = Some(list_changed);
+        self
+    }
+
+    /// Advertise `logging`: clients may set a level and get `notifications/message`
+    pub fn enable_logging(mut self) -> Self {
+        self.declared.insert("logging");
+        self.capabilities.logging = Some(LoggingCapability::default());
+        self
+    }
+
+    /// Advertise `completions`; needs a [`completion_provider`](Self::completion_provider)
+    /// or prompt arguments with an `enum`
+    pub fn enable_completions(mut self) -> Self {
+        self.declared.insert("completions");
+        self
+    }
+
+    /// Declared capabilities the server could not serve as configured, with
+    /// the reason
+    fn unserved_capabilities(&self, can_complete: bool) -> Vec<(&'static str, &'static str)> {
+        let disabled = |method: &str| self.disabled_methods.contains(method);
+        let declared = |capability: &str| self.declared.contains(capability);
+        // Built-in stores serve resources of their own schemes
+        let serves_resources = !self.resource_list.is_empty()
+            || self.content_store.is_some()
+            || self.result_limit.is_some()
+            || self.call_logs.is_some();
+        let mut unserved = Vec::new();
+        if declared("prompts") && (disabled("prompts/list") || disabled("prompts/get")) {
+            unserved.push(("prompts", "prompts/list or prompts/get is disabled"));
+        } else if declared("prompts") && self.prompt_list.is_empty() {
+            unserved.push(("prompts", "no prompts are registered"));
+        }
+        if declared("resources") && (disabled("resources/list") || disabled("resources/read")) {
+            unserved.push(("resources", "resources/list or resources/read is disabled"));
+        } else if declared("resources") && !serves_resources {
+            unserved.push(("resources", "no resources are registered"));
+        }
+        if declared("resources.subscribe") && disabled("resources/subscribe") {
+            unserved.push(("resources.subscribe", "resources/subscribe is disabled"));
+        }
+        if declared("logging") && disabled("logging/setLevel") {
+            unserved.push(("logging", "logging/setLevel is disabled"));
+        }
+        if declared("completions") && disabled("completion/complete") {
+            unserved.push(("completions", "completion/complete is disabled"));
+        } else if declared("completions") && !can_complete {
+            unserved.push(("completions", "there is no completion provider and no prompt argument has an enum"));
+        }
+        unserved
+    }
+
+    /// Schemas the server validates against that do not compile: input
+    /// schemas of the builder's and registry's tools, and output schemas
+    #[cfg(feature = "jsonschema")]
+    fn broken_schemas(&self) -> Vec<MCPError> {
+        let mut broken = Vec::new();
+        if self.argument_validator.is_some() {
+            let registered = self.tool_registry.iter().flat_map(ToolRegistry::tools).filter_map(|tool| serde_json::to_value(tool).ok());
+            for tool in self.tool_list.iter().cloned().chain(registered) {
+                let name = tool["name"].as_str().unwrap_or_default();
+                broken.extend(validation::check_schema(name, "Input", &tool["inputSchema"]).err());
+            }
+        }
+        if self.output_validator.is_some() {
+            for (name, schema) in &self.output_schemas {
+                broken.extend(validation::check_schema(name, "Output", schema).err());
+            }
+        }
+        broken
+    }
+
+    /// Stop advertising `capability`
+    fn withdraw(&mut self, capability: &str) {
+        let capabilities = &mut self.capabilities;
+        match capability {
+            "prompts" => capabilities.prompts = None,
+            "resources" => capabilities.resources = None,
+            "resources.subscribe" => {
+                if let Some(resources) = &mut capabilities.resources {
+                    resources.subscribe = None;
+                }
+            }
+            "logging" => capabilities.logging = None,
+            "completions" => capabilities.completions = None,
+            _ => {}
+        }
+    }
+
+    /// Build the server. A declared capability that cannot be served is
+    /// logged and not advertised, and a validated schema that does not
+    /// compile is logged; see [`try_build`](Self::try_build).
+    pub fn build<H: ToolHandler>(mut self, handler: H) -> SystemMCPServer<H> {
+        let can_complete = self.add_builtins();
+        for (capability, why) in self.unserved_capabilities(can_complete) {
+            eprintln!("[CAPABILITY] Not advertising {}: it is declared but {}", capability, why);
+            self.withdraw(capability);
+        }
+        #[cfg(feature = "jsonschema")]
+        for broken in self.broken_schemas() {
+            eprintln!("[VALIDATE] {}; calls of the tool will fail", broken);
+        }
+        self.finish(handler)
+    }
+
+    /// Build the server, failing if a capability declared with `enable_*`,
+    /// `with_subscribe` or `*_capability` cannot be served as configured, or
+    /// a schema the server validates against does not compile
+    pub fn try_build<H: ToolHandler>(mut self, handler: H) -> Result<SystemMCPServer<H>, MCPError> {
+        let can_complete = self.add_builtins();
+        if let Some((capability, why)) = self.unserved_capabilities(can_complete).into_iter().next() {
+            return Err(MCPError::InternalError(format!("capability {} is declared but {}", capability, why)));
+        }
+        #[cfg(feature = "jsonschema")]
+        if let Some(broken) = self.broken_schemas().into_iter().next() {
+            return Err(broken);
+        }
+        Ok(self.finish(handler))
+    }
+
+    /// Add the built-in tools and resources that were enabled; returns
+    /// whether completions can be served
+    fn add_builtins(&mut self) -> bool {
+        self.tool_list.extend(self.tool_versions.list().into_iter().map(|t| serde_json::to_value(t).unwrap()));
+        if self.heartbeat.is_some() {
+            self.resource_list.push(serde_json::to_value(Heartbeat::resource()).unwrap());
+            self.capabilities.resources.get_or_insert_with(Default::default).subscribe = Some(true);
+        }
+        if self.change_log.is_some() {
+            self.resource_list.push(serde_json::to_value(ChangeLog::resource()).unwrap());
+        }
+        if self.metrics_resource {
+            self.resource_list.push(serde_json::to_value(ToolMetrics::resource()).unwrap());
+        }
+        if self.result_pages.is_some() {
+            self.tool_list.push(serde_json::to_value(ResultPages::tool()).unwrap());
+        }
+        let has_enum_arguments = self.prompts.iter()
+            .flat_map(|p| p.arguments.iter().flatten())
+            .any(|a| !a.enum_values().is_empty());
+        let can_complete = has_enum_arguments || self.completion_provider.is_some();
+        if can_complete {
+            self.capabilities.completions = Some(CompletionsCapability::default());
+        }
+        if self.read_only {
+            self.disabled_methods.extend(["resources/subscribe".to_string(), "resources/unsubscribe".to_string()]);
+        }
+        can_complete
+    }
+
+    fn finish<H: ToolHandler>(mut self, handler: H) -> SystemMCPServer<H> {
+        self.strip_disabled_capabilities();
+        let read_only = self.read_only;
+        let allowed_tools = (self.deny_destructive_tools || read_only).then(|| {
+            self.tool_list.retain(|tool| tool_permitted(tool, read_only));
+            self.tool_list.iter().filter_map(|tool| tool.get("name")?.as_str().map(String::from)).collect()
+        });
+        // After filtering, so refused tools are not documented
+        if self.tool_docs && !self.disabled_methods.contains("resources/list") {
+            let docs = self.tool_list.iter()
+                .filter_map(tool_docs::resource)
+                .map(|resource| serde_json::to_value(resource).unwrap());
+            self.resource_list.extend(docs);
+        }
+
+        let content_store = match (self.content_store, &self.memory) {
+            (Some(store), Some(memory)) => Some(store.with_memory(memory.clone())),
+            (store, _) => store,
+        };
+        let result_limit = match (self.result_limit, &self.memory) {
+            (Some(limit), Some(memory)) => Some(limit.with_memory(memory.clone())),
+            (limit, _) => limit,
+        };
+
+        let progress_throttle = (self.progress_policy != ProgressPolicy::Unthrottled)
+            .then(|| Arc::new(ProgressThrottle::new(self.progress_policy)));
+        let (notification_tx, notification_rx) = mpsc::unbounded_channel();
+        if let Some(registry) = &self.tool_registry {
+            registry.attach(notification_tx.clone());
+        }
+        if let Some(config) = &self.config {
+            config.attach(notification_tx.clone());
+        }
+        let mut notification_rx = NotificationReceiver::new(notification_rx, self.memory.clone());
+        if let Some(throttle) = &progress_throttle {
+            notification_rx = notification_rx.with_throttle(throttle.clone());
+        }
+        let alias_usage = self.method_aliases.keys()
+            .map(|alias| (alias.clone(), AtomicU64::new(0)))
+            .collect();
+        SystemMCPServer {
+            handler,
+            capabilities: self.capabilities,
+            tool_list: self.tool_list,
+            tool_registry: self.tool_registry,
+            prompt_list: self.prompt_list,
+            resource_list: self.resource_list,
+            method_aliases: self.method_aliases,
+            alias_usage,
+            content_store,
+            gc: self.gc,
+            result_pages: self.result_pages,
+            result_limit,
+            heartbeat: self.heartbeat,
+            keepalive: self.keepalive,
+            tool_docs: self.tool_docs,
+            metrics_resource: self.metrics_resource,
+            tool_list_cache: self.tool_list_cache,
+            metrics: ToolMetrics::new(),
+            changes: self.change_log.map(ChangeLog::new),
+            uri_resolver: self.uri_resolver,
+            tool_versions: self.tool_versions,
+            deprecated_tools: self.deprecated_tools,
+            output_schemas: self.output_schemas,
+            warn_deprecated_calls: self.warn_deprecated_calls,
+            middleware: self.middleware,
+            tool_call_guards: self.tool_call_guards,
+            sessions: Arc::new(Sessions::new(self.session_key)),
+            journal: self.journal,
+            disabled_methods: self.disabled_methods,
+            prompts: self.prompts.into_iter().map(|p| (p.name.clone(), p)).collect(),
+            completion_provider: self.completion_provider,
+            content_annotations: self.content_annotations,
+            call_logs: self.call_logs,
+            ready_signals: self.ready_signals,
+            notification_rx: std::sync::Mutex::new(Some(notification_rx)),
+            memory: self.memory,
+            progress_throttle,
+            paginator: self.paginator,
+            custom_methods: self.custom_methods,
+            background_tools: self.background_tools,
+            max_concurrent_requests: self.max_concurrent_requests,
+            max_queued_requests: self.max_queued_requests,
+            accept_jsonrpc_1: self.accept_jsonrpc_1,
+            concurrency: self.concurrency,
+            config: self.config,
+            flags: self.flags,
+            subprocess_env: Arc::new(self.subprocess_env),
+            deadline_policy: self.deadline_policy,
+            session_store: self.session_store,
+            session_refresh: self.session_refresh,
+            session_loads: std::sync::Mutex::new(HashMap::new()),
+            reinitialize_policy: self.reinitialize_policy,
+            content_digests: self.content_digests,
+            timeouts: self.timeouts,
+            relaxed_lifecycle: self.relaxed_lifecycle,
+            strict: self.strict,
+            #[cfg(feature = "zstd")]
+            compress_contents: self.compress_contents,
+            #[cfg(feature = "jsonschema")]
+            argument_validator: self.argument_validator,
+            #[cfg(feature = "jsonschema")]
+            output_validator: self.output_validator,
+            shutdown: ShutdownControl::new(),
+            replica_id: replica_id(),
+            following_updates: AtomicBool::new(false),
+            store_scope: OnceLock::new(),
+            read_only,
+            allowed_tools,
+            active_requests: ActiveRequests::default(),
+            client_requests: Arc::new(ClientRequests::new(self.client_request_timeout)),
+            notification_tx,
+        }
+    }
+}
+
+/// Cancellation tokens of the requests in flight, by session and request id
+#[derive(Debug, Default)]
+struct ActiveRequests(std::sync::Mutex<HashMap<(String, String), CancellationToken>>);
+
+impl ActiveRequests {
+    /// Track `token` until the returned guard is dropped
+    fn track(&self, session_id: &str, request_id: String, token: CancellationToken) -> ActiveRequest<'_> {
+        let key = (session_id.to_string(), request_id);
+        self.0.lock().unwrap().insert(key.clone(), token.clone());
+        ActiveRequest { requests: self, key, token }
+    }
+
+    fn remove(&self, session_id: &str, request_id: &str) -> Option<CancellationToken> {
+        self.0.lock().unwrap().remove(&(session_id.to_string(), request_id.to_string()))
+    }
+
+    fn drain(&self) -> Vec<((String, String), CancellationToken)> {
+        self.0.lock().unwrap().drain().collect()
+    }
+
+    /// Stop tracking the requests of `session_id` other than `except`
+    fn drain_session(&self, session_id: &str, except: Option<&str>) -> Vec<(String, CancellationToken)> {
+        let mut active = self.0.lock().unwrap();
+        let keys: Vec<_> = active.keys()
+            .filter(|(session, request_id)| session == session_id && Some(request_id.as_str()) != except)
+            .cloned()
+            .collect();
+        keys.into_iter().filter_map(|key| active.remove(&key).map(|token| (key.1, token))).collect()
+    }
+
+    fn len(&self) -> usize {
+        self.0.lock().unwrap().len()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Stops tracking a request however its future ends
+struct ActiveRequest<'a> {
+    requests: &'a ActiveRequests,
+    key: (String, String),
+    token: CancellationToken,
+}
+
+impl Drop for ActiveRequest<'_> {
+    fn drop(&mut self) {
+        let mut active = self.requests.0.lock().unwrap();
+        // A later request that reused the id keeps its own entry
+        if active.get(&self.key).is_some_and(|token| token.same(&self.token)) {
+            active.remove(&self.key);
+        }
+    }
+}
+
+/// A tool result, with `_meta.nextCursor` when more pages remain
+fn paged_result(response: ToolResponse, next: Option<String>) -> Result<Value, MCPError> {
+    let mut value = serde_json::to_value(response)?;
+    if let Some(cursor) = next {
+        value["_meta"] = json!({ "nextCursor": cursor });
+    }
+    Ok(value)
+}
+
+/// Whether a listed tool may be served: read-only ones only in read-only
+/// mode, otherwise anything not destructive
+fn tool_permitted(tool: &Value, read_only: bool) -> bool {
+    if read_only {
+        guards::is_read_only(tool)
+    } else {
+        !guards::is_destructive(tool)
+    }
+}
+
+/// Key of a request id in the active request table: strings as-is, numbers
+/// in decimal, so `requestId: 7` and `requestId: "7"` both cancel `id: 7`
+fn request_key(id: &Value) -> String {
+    id.as_str().map(str::to_string).unwrap_or_else(|| id.to_string())
+}
+
+pub struct SystemMCPServer<H: ToolHandler> {
+    handler: H,
+    capabilities: ServerCapabilities,
+    // tools/list, prompts/list and resources/list entries given to the builder
+    tool_list: Vec<Value>,
+    tool_registry: Option<ToolRegistry>,
+    prompt_list: Vec<Value>,
+    resource_list: Vec<Value>,
+    // Legacy method name -> canonical method name
+    method_aliases: HashMap<String, String>,
+    // Number of requests received under each alias
+    alias_usage: HashMap<String, AtomicU64>,
+    // Deduplicated text content served under cas://
+    content_store: Option<ContentStore>,
+    gc: Option<ResourceGc>,
+    result_pages: Option<ResultPages>,
+    result_limit: Option<ResultLimit>,
+    heartbeat: Option<Arc<Heartbeat>>,
+    keepalive: Option<Keepalive>,
+    tool_docs: bool,
+    metrics_resource: bool,
+    tool_list_cache: Option<ToolListCache>,
+    changes: Option<ChangeLog>,
+    metrics: ToolMetrics,
+    uri_resolver: Option<UriResolver>,
+    tool_versions: ToolVersions,
+    deprecated_tools: HashMap<String, Deprecation>,
+    output_schemas: HashMap<String, Value>,
+    warn_deprecated_calls: bool,
+    middleware: MiddlewareStack,
+    tool_call_guards: Vec<Arc<dyn ToolCallGuard>>,
+    sessions: Arc<Sessions>,
+    journal: Option<Journal>,
+    disabled_methods: HashSet<String>,
+    // Prompts registered on the builder, for argument validation and completion
+    prompts: HashMap<String, Prompt>,
+    completion_provider: Option<Arc<dyn CompletionProvider>>,
+    content_annotations: ContentAnnotations,
+    call_logs: Option<Arc<CallLogs>>,
+    ready_signals: Vec<ReadySignal>,
+    memory: Option<Arc<MemoryAccountant>>,
+    progress_throttle: Option<Arc<ProgressThrottle>>,
+    paginator: Option<Paginator>,
+    custom_methods: CustomMethods,
+    background_tools: HashSet<String>,
+    max_concurrent_requests: usize,
+    max_queued_requests: usize,
+    concurrency: ConcurrencyLimits,
+    config: Option<Arc<LiveConfig>>,
+    accept_jsonrpc_1: bool,
+    flags: FeatureFlags,
+    subprocess_env: Arc<SubprocessEnv>,
+    deadline_policy: DeadlinePolicy,
+    session_store: Option<Arc<dyn SessionStore>>,
+    session_refresh: Duration,
+    // When each session was last loaded from or saved to the store
+    session_loads: std::sync::Mutex<HashMap<String, Instant>>,
+    reinitialize_policy: ReinitializePolicy,
+    content_digests: bool,
+    timeouts: Timeouts,
+    relaxed_lifecycle: bool,
+    strict: bool,
+    #[cfg(feature = "zstd")]
+    compress_contents: Option<usize>,
+    #[cfg(feature = "jsonschema")]
+    argument_validator: Option<ArgumentValidator>,
+    #[cfg(feature = "jsonschema")]
+    output_validator: Option<OutputValidator>,
+    shutdown: ShutdownControl,
+    // Tells this server's resource updates apart from other replicas'
+    replica_id: String,
+    following_updates: AtomicBool,
+    // Prefix of this instance's keys in the session store
+    store_scope: OnceLock<String>,
+    read_only: bool,
+    // With deny_destructive_tools or read_only, the only tools that may be called
+    allowed_tools: Option<HashSet<String>>,
+    // Track in-progress requests for cancellation
+    active_requests: ActiveRequests,
+    // Requests to the client awaiting its response
+    client_requests: Arc<ClientRequests>,
+    // Notification channel for progress updates
+    notification_tx: mpsc::UnboundedSender<ServerNotification>,
+    notification_rx: std::sync::Mutex<Option<NotificationReceiver>>,
+}
+
+impl<H: ToolHandler> SystemMCPServer<H> {
+    pub fn builder() -> ServerBuilder {
+        ServerBuilder::new()
+    }
+
+    pub fn take_notification_receiver(&mut self) -> Option<NotificationReceiver> {
+        self.notification_rx.get_mut().unwrap().take()
+    }
+
+    /// Like [`take_notification_receiver`](Self::take_notification_receiver)
+    /// for a shared server
+    pub(crate) fn claim_notification_receiver(&self) -> Option<NotificationReceiver> {
+        self.notification_rx.lock().unwrap().take()
+    }
+
+    pub fn max_concurrent_requests(&self) -> usize {
+        self.max_concurrent_requests
+    }
+
+    pub fn max_queued_requests(&self) -> usize {
+        self.max_queued_requests
+    }
+
+    /// Stop taking requests, let in-flight ones finish and return once
+    /// they have. With a runner serving this server, the runner drains
+    /// within its shutdown deadline and flushes queued notifications, and
+    /// this returns after it stopped; without one, requests get
+    /// [`DEFAULT_SHUTDOWN_DEADLINE`] before being cancelled. New requests
+    /// are answered with [`MCPError::ShuttingDown`] meanwhile.
+    pub async fn shutdown(&self) {
+        if self.shutdown.request() {
+            eprintln!("[SHUTDOWN] Shutdown requested");
+        }
+        if self.shutdown.has_runners() {
+            self.shutdown.runners_finished().await;
+            return;
+        }
+        let deadline = tokio::time::Instant::now() + DEFAULT_SHUTDOWN_DEADLINE;
+        while !self.active_requests.is_empty() && tokio::time::Instant::now() < deadline {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        self.cancel_all("server shutting down").await;
+    }
+
+    /// Whether [`Self::shutdown`] was called
+    pub fn is_shutting_down(&self) -> bool {
+        self.shutdown.is_requested()
+    }
+
+    pub(crate) fn shutdown_control(&self) -> &ShutdownControl {
+        &self.shutdown
+    }
+
+    /// The server's feature flags; changes show up in the next `initialize`
+    /// and in tool listings
+    pub fn flags(&self) -> &FeatureFlags {
+        &self.flags
+    }
+
+    /// Capabilities as advertised now, including feature flag state
+    fn current_capabilities(&self) -> ServerCapabilities {
+        let mut capabilities = self.capabilities.clone();
+        if !self.flags.is_empty() {
+            capabilities.experimental.get_or_insert_with(Default::default)
+                .insert("featureFlags".into(), self.flags.to_json());
+        }
+        #[cfg(feature = "zstd")]
+        if let Some(min_size) = self.compress_contents {
+            capabilities.experimental.get_or_insert_with(Default::default)
+                .insert(compression::CAPABILITY.into(), json!({ "minSize": min_size }));
+        }
+        capabilities
+    }
+
+    /// Current progress debounce interval, when a policy is configured
+    pub fn progress_interval(&self) -> Option<Duration> {
+        self.progress_throttle.as_ref().map(|throttle| throttle.interval())
+    }
+
+    /// Memory accounting and shedding counters, when a ceiling is configured
+    pub fn memory_stats(&self) -> Option<MemoryStats> {
+        self.memory.as_ref().map(|memory| memory.stats())
+    }
+
+    /// Number of requests received under each configured method alias
+    pub fn alias_usage(&self) -> HashMap<String, u64> {
+        self.alias_usage.iter()
+            .map(|(alias, count)| (alias.clone(), count.load(Ordering::Relaxed)))
+            .collect()
+    }
+
+    /// Snapshot of a session's state
+    pub fn session(&self, id: &str) -> Option<SessionState> {
+        self.sessions.get(id)
+    }
+
+    /// The application handler, e.g. to inspect a mock after a test
+    pub fn handler(&self) -> &H {
+        &self.handler
+    }
+
+    /// Replace a session's state, e.g. when a resumed client reconnects to
+    /// another replica
+    pub fn restore_session(&self, id: &str, state: SessionState) {
+        self.sessions.update(id, |session| *session = state);
+    }
+
+    /// Record who a session authenticated as
+    pub fn set_principal(&self, id: &str, principal: impl Into<String>) {
+        let principal = principal.into();
+        self.sessions.update(id, |session| session.principal = Some(principal));
+    }
+
+    pub fn session_ids(&self) -> Vec<String> {
+        self.sessions.ids()
+    }
+
+    /// Session a request belongs to
+    pub(crate) fn session_of(&self, req: &MCPRequest) -> String {
+        self.sessions.session_id(req)
+    }
+
+    /// Forget a session and what was cached for it, e.g. once its
+    /// connection is gone
+    pub fn end_session(&self, id: &str) {
+        self.sessions.remove(id);
+        self.session_loads.lock().unwrap().remove(id);
+        self.client_requests.forget_roots(id);
+        if let Some(resolver) = &self.uri_resolver {
+            resolver.forget(id);
+        }
+    }
+
+    pub(crate) fn keepalive(&self) -> Option<Keepalive> {
+        self.keepalive
+    }
+
+    /// Send `ping` to the client of the current transport; any answer
+    /// within `wait` counts
+    pub(crate) async fn ping_client(&self, wait: Duration) -> Result<(), MCPError> {
+        let sender = self.progress_sender();
+        let ping = self.client_requests.request(&sender, "ping", json!({}));
+        match tokio::time::timeout(wait, ping).await {
+            Ok(Ok(_) | Err(MCPError::PeerError(_))) => Ok(()),
+            Ok(Err(e)) => Err(e),
+            Err(_) => Err(MCPError::RequestTimeout(format!("no answer to ping within {:?}", wait))),
+        }
+    }
+
+    /// Sessions subscribed to updates of `uri`
+    pub fn subscribers(&self, uri: &str) -> Vec<String> {
+        self.sessions.subscribers(uri)
+    }
+
+    /// Queue `notifications/resources/updated` if any session subscribed to
+    /// `uri`; returns whether a notification was sent. With a session store
+    /// the update is also published to the other replicas.
+    pub fn notify_resource_updated(&self, uri: &str) -> bool {
+        if let Some(store) = &self.session_store
+            && let Ok(runtime) = tokio::runtime::Handle::try_current()
+        {
+            let (store, update) = (store.clone(), ResourceUpdate { replica: self.replica_id.clone(), uri: uri.to_string() });
+            runtime.spawn(async move {
+                if let Err(e) = store.publish(&update).await {
+                    eprintln!("[SESSION] Failed to publish update of {}: {}", update.uri, e);
+                }
+            });
+        }
+        if self.sessions.subscribers(uri).is_empty() {
+            return false;
+        }
+        self.notification_tx.send(ServerNotification::ResourceUpdated { uri: uri.to_string() }).is_ok()
+    }
+
+    /// Deliver resource updates other replicas publish to the session
+    /// store; runners call this on start. Does nothing without a store or
+    /// when already following.
+    pub async fn follow_resource_updates(&self) -> Result<(), MCPError> {
+        let Some(store) = &self.session_store else { return Ok(()) };
+        if self.following_updates.swap(true, Ordering::Relaxed) {
+            return Ok(());
+        }
+        let mut updates = store.updates().await?;
+        let (sessions, notifications, replica) = (self.sessions.clone(), self.notification_tx.clone(), self.replica_id.clone());
+        tokio::spawn(async move {
+            while let Some(update) = updates.recv().await {
+                if update.replica != replica && !sessions.subscribers(&update.uri).is_empty()
+                    && notifications.send(ServerNotification::ResourceUpdated { uri: update.uri }).is_err()
+                {
+                    break;
+                }
+            }
+        });
+        Ok(())
+    }
+
+    /// Start bumping the heartbeat resource; runners call this on start.
+    /// `None` without a heartbeat or when one is already ticking.
+    pub fn start_heartbeat(&self) -> Option<HeartbeatTicker> {
+        let heartbeat = self.heartbeat.clone().filter(|heartbeat| heartbeat.start())?;
+        let (sessions, notifications) = (self.sessions.clone(), self.notification_tx.clone());
+        let mut shutdown = self.shutdown.watch();
+        let task = tokio::spawn({
+            let heartbeat = heartbeat.clone();
+            async move {
+                let mut ticks = tokio::time::interval(heartbeat.interval());
+                ticks.tick().await;
+                loop {
+                    tokio::select! {
+                        _ = ticks.tick() => {}
+                        _ = shutdown.wait_for(|requested| *requested) => break,
+                    }
+                    heartbeat.beat();
+                    if !sessions.subscribers(HEARTBEAT_URI).is_empty()
+                        && notifications.send(ServerNotification::ResourceUpdated { uri: HEARTBEAT_URI.into() }).is_err()
+                    {
+                        break;
+                    }
+                }
+            }
+        });
+        Some(HeartbeatTicker { task, heartbeat })
+    }
+
+    /// Queue `notifications/tools/list_changed`, e.g. after reloading the
+    /// handler's runtime tools
+    pub fn notify_tools_changed(&self) -> bool {
+        if let Some(cache) = &self.tool_list_cache {
+            cache.invalidate();
+        }
+        self.notification_tx.send(ServerNotification::ToolListChanged).is_ok()
+    }
+
+    fn resolve_method<'a>(&'a self, method: &'a str) -> &'a str {
+        match self.method_aliases.get(method) {
+            Some(canonical) => {
+                if let Some(count) = self.alias_usage.get(method)
+                    && count.fetch_add(1, Ordering::Relaxed) == 0
+                {
+                    eprintln!("[ALIAS] Client used legacy method {}, routing to {}", method, canonical);
+                }
+                canonical
+            }
+            None => method,
+        }
+    }
+
+    /// The request's JSON-RPC version, which its response is sent in
+    fn validate_and_detect_version(&self, req: &MCPRequest) -> Result<JsonRpcVersion, MCPError> {
+        match req.version() {
+            Some(JsonRpcVersion::V1_0) if !self.accept_jsonrpc_1 => {
+                Err(MCPError::InvalidJsonRpcVersion(req.jsonrpc_version().unwrap_or("missing").to_string()))
+            }
+            Some(version) => Ok(version),
+            None => Err(MCPError::InvalidJsonRpcVersion(req.jsonrpc_version().unwrap_or_default().to_string())),
+        }
+    }
+
+    /// Run the startup self-check and emit the configured ready signals.
+    /// Call once the transport is accepting requests.
+    pub fn signal_ready(&self) -> Result<(), MCPError> {
+        ready::self_check(&self.capabilities, &self.tool_list)?;
+        let info = ready::ready_info(&serde_json::to_value(self.server_info())?, PROTOCOL_VERSION);
+        for signal in &self.ready_signals {
+            signal.emit(&info)?;
+        }
+        Ok(())
+    }
+
+    fn server_info(&self) -> ServerInfo {
+        ServerInfo {
+            name: "secure-system-mcp".into(),
+            version: env!("CARGO_PKG_VERSION").into(),
+        }
+    }
+
+    /// Machine-readable description of the whole server surface: tools with
+    /// their schemas and annotations, prompts, resources and capabilities.
+    /// Intended for catalogs, documentation and client code generation.
+    pub fn export_manifest(&self) -> Value {
+        json!({
+            "manifestVersion": 1,
+            "serverInfo": self.server_info(),
+            "protocolVersion": PROTOCOL_VERSION,
+            "tools": self.tool_list,
+            "prompts": self.prompt_list,
+            "resources": self.resource_list,
+            "capabilities": self.current_capabilities(),
+            "methodAliases": self.method_aliases,
+        })
+    }
+
+    /// A list result, paged when pagination is configured
+    fn list(&self, items: &[Value], key: &str, req: &MCPRequest) -> Result<Value, MCPError> {
+        let mut list = serde_json::Map::new();
+        list.insert(key.into(), Value::Array(items.to_vec()));
+        match &self.paginator {
+            Some(paginator) => paginator.page(&list, key, req.params.as_ref()),
+            None => Ok(Value::Object(list)),
+        }
+    }
+
+    async fn list_tools(&self, req: &MCPRequest, ctx: &RequestContext) -> Result<Value, MCPError> {
+        // Read before listing, so a change made meanwhile is not cached as current
+        let Some((cache, generation)) = self.tool_list_cache.as_ref().zip(self.tools_generation()) else {
+            return self.list(&self.tools(ctx).await?, "tools", req);
+        };
+        if let Some(page) = cache.get(generation, ctx.session_id(), req.params.as_ref()) {
+            return Ok(page);
+        }
+        let page = self.list(&self.tools(ctx).await?, "tools", req)?;
+        cache.insert(generation, ctx.session_id(), req.params.as_ref(), &page);
+        Ok(page)
+    }
+
+    /// Advances whenever the listed tools may have changed; every source
+    /// only counts up, so the sum never repeats. `None` when the handler
+    /// cannot tell.
+    fn tools_generation(&self) -> Option<u64> {
+        let changes = self.tool_list_cache.as_ref().map_or(0, ToolListCache::changes);
+        let registry = self.tool_registry.as_ref().map_or(0, ToolRegistry::generation);
+        let config = self.config.as_ref().map_or(0, |config| config.generation());
+        let handler = self.handler.tools_generation()?;
+        Some(changes.wrapping_add(registry).wrapping_add(config).wrapping_add(self.flags.generation()).wrapping_add(handler))
+    }
+
+    /// The builder's tools followed by the handler's runtime tools
+    async fn tools(&self, ctx: &RequestContext) -> Result<Vec<Value>, MCPError> {
+        let mut tools = self.tool_list.clone();
+        tools.extend(self.runtime_tools(ctx).await?);
+        tools.retain(|tool| tool["name"].as_str().is_none_or(|name| self.tool_enabled(name)));
+        for tool in &mut tools {
+            if let Some(deprecation) = tool["name"].as_str().and_then(|name| self.deprecated_tools.get(name)) {
+                deprecation.annotate(tool);
+            }
+            if let Some(schema) = tool["name"].as_str().and_then(|name| self.output_schemas.get(name)) {
+                tool["outputSchema"] = schema.clone();
+            }
+        }
+        Ok(tools)
+    }
+
+    /// Compare the lists of the [`ServerBuilder::change_log`] source with
+    /// the previous snapshot, log what changed and send one `list_changed`
+    /// notification per list that differs. Call it after the source reloads;
+    /// see [`crate::changes`].
+    pub fn reload_registries(&self) -> Vec<ListDiff> {
+        let Some(changes) = &self.changes else { return Vec::new() };
+        let diffs = changes.reload();
+        if let Some(cache) = &self.tool_list_cache
+            && diffs.iter().any(|diff| diff.kind == ListKind::Tools)
+        {
+            cache.invalidate();
+        }
+        for diff in &diffs {
+            let _ = self.notification_tx.send(diff.kind.notification());
+        }
+        diffs
+    }
+
+    /// Whether neither a feature flag nor the live config hides `name`
+    fn tool_enabled(&self, name: &str) -> bool {
+        self.flags.tool_enabled(name) && self.config.as_ref().is_none_or(|config| config.tool_enabled(name))
+    }
+
+    /// Whether a log message at `level` reaches the session
+    fn log_enabled(&self, session_id: &str, level: &str) -> bool {
+        self.log_minimum(session_id).is_none_or(|minimum| config::level_passes(level, &minimum))
+    }
+
+    /// Minimum log level of the session: its own `logging/setLevel` choice,
+    /// else the configured default
+    fn log_minimum(&self, session_id: &str) -> Option<String> {
+        self.sessions.get(session_id)
+            .and_then(|session| session.log_level)
+            .or_else(|| self.config.as_ref()?.log_level())
+    }
+
+    /// The listed tool called `name`, if any
+    async fn find_tool(&self, name: &str, ctx: &RequestContext) -> Result<Option<Value>, MCPError> {
+        if !self.tool_enabled(name) {
+            return Ok(None);
+        }
+        let named = |tool: &Value| tool["name"] == name;
+        let listed = self.tool_list.iter().find(|tool| named(tool));
+        match listed {
+            Some(tool) => Ok(Some(tool.clone())),
+            None => Ok(self.runtime_tools(ctx).await?.into_iter().find(named)),
+        }
+    }
+
+    /// The handler's and the registry's runtime tools, without destructive
+    /// (or, read-only, writing) ones when those are denied
+    async fn runtime_tools(&self, ctx: &RequestContext) -> Result<Vec<Value>, MCPError> {
+        let mut tools = self.handler.list_tools(ctx).await?;
+        if let Some(registry) = &self.tool_registry {
+            tools.extend(registry.tools());
+        }
+        let tools = tools.into_iter().map(serde_json::to_value).collect::<Result<Vec<_>, _>>()?;
+        Ok(match self.allowed_tools {
+            Some(_) => tools.into_iter().filter(|tool| tool_permitted(tool, self.read_only)).collect(),
+            None => tools,
+        })
+    }
+
+    /// Parse and handle one message exactly as received from a transport,
+    /// so middleware can see the original bytes
+    pub async fn handle_raw(&self, raw: &[u8]) -> Option<MCPResponse> {
+        match IncomingRequest::parse(raw) {
+            Ok(incoming) => self.handle_incoming(incoming).await,
+            Err(err) => {
+                eprintln!("Failed to parse request: {}", err);
+                let response = IncomingRequest::rejection(&err);
+                if let Some(journal) = &self.journal {
+                    journal.record(raw, Some(&response));
+                }
+                Some(response)
+            }
+        }
+    }
+
+    pub async fn handle(&self, req: MCPRequest) -> Option<MCPResponse> {
+        self.handle_incoming(IncomingRequest::from_request(req)).await
+    }
+
+    /// Like [`handle_incoming`](Self::handle_incoming), also returning the
+    /// memory reserved for the response; drop it once the response is written
+    pub async fn handle_holding_output(&self, incoming: IncomingRequest) -> (Option<MCPResponse>, Option<memory::Reservation>) {
+        memory::holding_output(self.handle_incoming(incoming)).await
+    }
+
+    pub async fn handle_incoming(&self, incoming: IncomingRequest) -> Option<MCPResponse> {
+        // Answers to the server's own requests skip middleware and the journal
+        if incoming.request.method == outbound::RESPONSE_METHOD {
+            if let Some(response) = &incoming.request.params {
+                self.client_requests.complete(runner::current_origin(), response);
+            }
+            return None;
+        }
+        let Some(journal) = &self.journal else {
+            return self.run_middleware_and_dispatch(incoming).await;
+        };
+
+        let raw = match incoming.raw() {
+            Some(raw) => raw.to_vec(),
+            None => serde_json::to_vec(&incoming.request).unwrap_or_default(),
+        };
+        let response = self.run_middleware_and_dispatch(incoming).await;
+        journal.record(&raw, response.as_ref());
+        response
+    }
+
+    async fn run_middleware_and_dispatch(&self, mut incoming: IncomingRequest) -> Option<MCPResponse> {
+        if self.strict
+            && let Err(err) = StrictParsing::check(&incoming)
+        {
+            eprintln!("[STRICT] Rejected {}: {}", incoming.request.method, err);
+            let mut request = incoming.request;
+            // An id that is itself the problem is not echoed back
+            if request.id.as_ref().is_some_and(|id| !guards::is_valid_id(id)) {
+                request.id = Some(Value::Null);
+            }
+            return Endpoint::reject(self, &request, err);
+        }
+        Next::new(&self.middleware, self).run(&mut incoming).await
+    }
+
+    async fn dispatch(&self, req: &MCPRequest) -> Option<MCPResponse> {
+        // Validate and detect JSON-RPC version
+        let version = match self.validate_and_detect_version(req) {
+            Ok(version) => version,
+            Err(err) => {
+                return Some(self.create_error_response(JsonRpcVersion::V2_0, req.id.clone(), err));
+            }
+        };
+
+        let method = self.resolve_method(&req.method);
+
+        if self.disabled_methods.contains(method) {
+            if req.is_notification() {
+                return None;
+            }
+            return Some(self.create_error_response(version, req.id.clone(), MCPError::MethodNotFound(method.into())));
+        }
+
+        if self.shutdown.is_requested() && !req.is_notification() {
+            return Some(self.create_error_response(version, req.id.clone(), MCPError::ShuttingDown));
+        }
+
+        let session_id = self.sessions.session_id(req);
+        self.refresh_session(&session_id, method).await;
+
+        // Handle notifications (no response)
+        if req.is_notification() {
+            return match method {
+                "notifications/initialized" => {
+                    // Only completes a handshake that `initialize` started
+                    let ready = self.sessions.update_existing(&session_id, |session| {
+                        session.initialized && !std::mem::replace(&mut session.client_initialized, true)
+                    });
+                    if ready == Some(true) {
+                        self.save_session(&session_id).await;
+                        self.handler.on_initialized(&session_id).await;
+                    }
+                    None
+                }
+                "notifications/cancelled" => {
+                    self.handle_cancellation(req).await;
+                    None
+                }
+                crate::roots::ROOTS_CHANGED_NOTIFICATION => {
+                    self.client_requests.forget_roots(&session_id);
+                    None
+                }
+                "notifications/ping" => {
+                    eprintln!("[PING] Received ping from client");
+                    None
+                }
+                _ => None,
+            }
+        }
+
+        if let Err(err) = self.check_lifecycle(method, &session_id) {
+            return Some(self.create_error_response(version, req.id.clone(), err));
+        }
+
+        let tool = (method == "tools/call").then(|| req.params.as_ref()?.get("name")?.as_str()).flatten();
+        // Every request can be cancelled, not just tool calls, and also while
+        // it waits for a slot
+        let cancellation = CancellationToken::new();
+        let _active = req.id.as_ref()
+            .map(|id| self.active_requests.track(&session_id, request_key(id), cancellation.clone()));
+        // Limits apply to the version the call runs; a bad version is
+        // reported by the call itself
+        let resolved = tool.and_then(|tool| {
+            self.tool_versions.resolve(tool, req.params.as_ref().and_then(|p| p.get("_meta"))).ok().flatten()
+        });
+        let permits = async {
+            // A custom method's tool calls already run inside its global slot
+            match resolved.as_deref().or(tool) {
+                Some(tool) if STEP.try_with(|_| ()).is_ok() => self.concurrency.acquire_tool(tool).await,
+                tool => self.concurrency.acquire(method, tool).await,
+            }
+        };
+        let permits = tokio::select! {
+            permits = permits => permits,
+            _ = cancellation.cancelled() => {
+                let request_id = req.id.as_ref().map(request_key).unwrap_or_default();
+                eprintln!("[CANCEL] {} {} was cancelled while waiting for a slot", method, request_id);
+                Err(MCPError::RequestCancelled(request_id))
+            }
+        };
+        let _permits = match permits {
+            Ok(permits) => permits,
+            Err(err) => return Some(self.create_error_response(version, req.id.clone(), err)),
+        };
+        let _in_flight = match self.config.as_ref().map(|config| config.admit(method)).transpose() {
+            Ok(in_flight) => in_flight,
+            Err(err) => return Some(self.create_error_response(version, req.id.clone(), err)),
+        };
+        let limit = self.config.as_ref()
+            .and_then(|config| config.timeout_for(tool))
+            .or_else(|| self.timeouts.for_request(method, req));
+        let ctx = self.request_context(req).with_cancellation(cancellation.clone());
+        // The client stops waiting at its own timeout even if the server would not
+        let deadline = limit.into_iter().chain(subprocess::client_timeout(ctx.meta())).min();
+        let ctx = ctx.with_deadline(deadline.map(|limit| tokio::time::Instant::now() + limit), self.deadline_policy);
+        let call = async {
+            match method {
+                "initialize" => self.handle_initialize(req, &session_id).await,
+                "ping" => Ok(json!({})),
+                "tools/list" => self.list_tools(req, &ctx).await,
+                "tools/call" => self.handle_tool_call_with_cancellation(req, &ctx, limit).await,
+                "prompts/list" => self.list(&self.prompt_list, "prompts", req),
+                "prompts/get" => self.handle_prompt_get(req, &ctx).await,
+                "completion/complete" => self.handle_completion(req, &ctx).await,
+                "resources/list" => self.list(&self.resource_list, "resources", req),
+                "resources/read" => self.handle_resource_read(req, &ctx).await,
+                "resources/subscribe" | "resources/unsubscribe" => {
+                    self.handle_subscription(req, &session_id, method == "resources/subscribe")
+                }
+                "logging/setLevel" => self.handle_set_log_level(req, &session_id),
+                CLEANUP_METHOD if self.gc.as_ref().is_some_and(ResourceGc::serves_cleanup) => self.handle_cleanup(req),
+                other => match self.custom_methods.get(other) {
+                    Some(handler) => handler.handle(&StepCaller::new(self, &ctx), req.params.as_ref()).await,
+                    None => self.handler.experimental_method(other, req.params.as_ref(), &ctx).await,
+                },
+            }
+        };
+        // tools/call enforces its own limit and reports its own cancellation
+        let call = async {
+            match limit {
+                Some(limit) if method != "tools/call" => tokio::time::timeout(limit, call).await.unwrap_or_else(|_| {
+                    eprintln!("[TIMEOUT] {} timed out after {:?}", method, limit);
+                    Err(MCPError::RequestTimeout(format!("{} after {:?}", method, limit)))
+                }),
+                _ => call.await,
+            }
+        };
+        let mut result = tokio::select! {
+            result = call => result,
+            _ = cancellation.cancelled(), if method != "tools/call" => {
+                let request_id = ctx.request_id().to_string();
+                eprintln!("[CANCEL] {} {} was cancelled", method, request_id);
+                Err(MCPError::RequestCancelled(request_id))
+            }
+        };
+
+        if self.content_digests
+            && matches!(method, "resources/read" | "tools/call")
+            && let Ok(value) = &mut result
+        {
+            for contents in integrity::contents_mut(value) {
+                integrity::attach(contents);
+            }
+        }
+        // After digests, which cover the uncompressed bytes
+        #[cfg(feature = "zstd")]
+        if let Some(min_size) = self.compress_contents
+            && matches!(method, "resources/read" | "tools/call")
+            && self.sessions.get(&session_id).is_some_and(|session| session.accepts_zstd)
+            && let Ok(value) = &mut result
+        {
+            for contents in integrity::contents_mut(value) {
+                compression::compress(contents, min_size);
+            }
+        }
+
+        let changes_session = matches!(method, "initialize" | "resources/subscribe" | "resources/unsubscribe" | "logging/setLevel");
+        if changes_session && result.is_ok() {
+            self.save_session(&session_id).await;
+        }
+
+        match result {
+            Ok(res) => Some(self.create_success_response(version, req.id.clone(), res)),
+            Err(err) => Some(self.create_error_response(version, req.id.clone(), err)),
+        }
+    }
+
+    /// Pick up changes other replicas made to `session_id` once the copy in
+    /// memory is older than the refresh interval; `initialize` always loads,
+    /// to tell a re-initialization
+    async fn refresh_session(&self, session_id: &str, method: &str) {
+        let Some(store) = &self.session_store else { return };
+        let fresh = method != "initialize"
+            && self.session_loads.lock().unwrap().get(session_id).is_some_and(|loaded| loaded.elapsed() < self.session_refresh);
+        if fresh {
+            return;
+        }
+        match store.load(&self.store_key(session_id)).await {
+            Ok(state) => {
+                if let Some(state) = state {
+                    self.sessions.update(session_id, |session| *session = state);
+                }
+                self.session_loads.lock().unwrap().insert(session_id.to_string(), Instant::now());
+            }
+            Err(e) => eprintln!("[SESSION] Failed to load session {}: {}", session_id, e),
+        }
+    }
+
+    /// Share `session_id`'s state through the session store, if any
+    async fn save_session(&self, session_id: &str) {
+        let Some(store) = &self.session_store else { return };
+        let Some(state) = self.sessions.get(session_id) else { return };
+        match store.save(&self.store_key(session_id), &state).await {
+            Ok(()) => {
+                self.session_loads.lock().unwrap().insert(session_id.to_string(), Instant::now());
+            }
+            Err(e) => eprintln!("[SESSION] Failed to save session {}: {}", session_id, e),
+        }
+    }
+
+    fn store_key(&self, session_id: &str) -> String {
+        match self.store_scope.get() {
+            Some(scope) => format!("{}/{}", scope, session_id),
+            None => session_id.to_string(),
+        }
+    }
+
+    /// Keep this instance's sessions under `scope` in the session store, for
+    /// transports that give each client a server of its own; only the first
+    /// call has an effect
+    pub fn scope_session_store(&self, scope: impl Into<String>) {
+        let _ = self.store_scope.set(scope.into());
+    }
+
+    /// Forget a session the client ended, here and in the session store
+    pub async fn close_session(&self, session_id: &str) {
+        self.sessions.remove(session_id);
+        self.session_loads.lock().unwrap().remove(session_id);
+        if let Some(store) = &self.session_store
+            && let Err(e) = store.remove(&self.store_key(session_id)).await
+        {
+            eprintln!("[SESSION] Failed to remove session {}: {}", session_id, e);
+        }
+    }
+
+    /// Only `initialize` and `ping` are served before the client finished
+    /// the handshake with `notifications/initialized`
+    fn check_lifecycle(&self, method: &str, session_id: &str) -> Result<(), MCPError> {
+        if self.relaxed_lifecycle || matches!(method, "initialize" | "ping") {
+            return Ok(());
+        }
+        match self.sessions.get(session_id) {
+            Some(session) if session.client_initialized => Ok(()),
+            Some(session) if session.initialized => {
+                Err(MCPError::NotInitialized("waiting for notifications/initialized".into()))
+            }
+            _ => Err(MCPError::NotInitialized("send initialize first".into())),
+        }
+    }
+
+    async fn handle_initialize(&self, req: &MCPRequest, session_id: &str) -> Result<Value, MCPError> {
+        if self.sessions.get(session_id).is_some_and(|session| session.initialized) {
+            match self.reinitialize_policy {
+                ReinitializePolicy::Reject => return Err(MCPError::AlreadyInitialized),
+                ReinitializePolicy::Reset => {
+                    eprintln!("[SESSION] Session {} re-initialized; resetting state", session_id);
+                    let initialize_id = req.id.as_ref().map(request_key);
+                    for (request_id, cancellation) in self.active_requests.drain_session(session_id, initialize_id.as_deref()) {
+                        cancellation.cancel();
+                        eprintln!("[CANCEL] Request {} cancelled: session re-initialized", request_id);
+                        self.handler.on_request_cancelled(&request_id, Some("session re-initialized")).await;
+                    }
+                    self.handler.on_reinitialize(session_id).await;
+                }
+            }
+        }
+        let requested = req.params.as_ref().and_then(|p| p.get("protocolVersion")).and_then(Value::as_str);
+        let version = negotiate_protocol_version(requested);
+        #[cfg(feature = "zstd")]
+        let accepts_zstd = self.compress_contents.is_some()
+            && req.params.as_ref().and_then(|p| p.get("capabilities")).is_some_and(compression::supported);
+        #[cfg(not(feature = "zstd"))]
+        let accepts_zstd = false;
+        // The principal comes from the transport, not from negotiation
+        self.sessions.update(session_id, |session| *session = SessionState {
+            initialized: true,
+            protocol_version: Some(version.into()),
+            client: Some(ClientInfo::from_initialize(req.params.as_ref())),
+            accepts_zstd,
+            principal: session.principal.take(),
+            ..SessionState::default()
+        });
+        // A new client may expose different roots
+        self.client_requests.forget_roots(session_id);
+        if let Some(resolver) = &self.uri_resolver {
+            resolver.forget(session_id);
+        }
+
+        // Let developers of clients asking for another revision know why
+        // they got this one
+        let mut meta = requested.filter(|requested| *requested != version).map(|requested| {
+            if self.log_enabled(session_id, "notice") {
+                self.send_upgrade_notice(requested, version);
+            }
+            json!({ "supportedVersions": SUPPORTED_PROTOCOL_VERSIONS })
+        });
+        let instructions = self.read_only.then(|| {
+            meta.get_or_insert_with(|| json!({}))["readOnly"] = Value::Bool(true);
+            READ_ONLY_INSTRUCTIONS.to_string()
+        });
+        serde_json::to_value(InitializeResponse {
+            protocol_version: version.into(),
+            capabilities: self.current_capabilities(),
+            server_info: self.server_info(),
+            instructions,
+            meta,
+        }).map_err(MCPError::from)
+    }
+
+    fn send_upgrade_notice(&self, requested: &str, version: &str) {
+        let notice = ServerNotification::Log {
+            level: "notice".into(),
+            logger: Some("protocol".into()),
+            data: json!({
+                "message": format!(
+                    "Client requested protocol {}, which this server does not support; answering with {}",
+                    requested, version,
+                ),
+                "requestedVersion": requested,
+                "negotiatedVersion": version,
+                "supportedVersions": SUPPORTED_PROTOCOL_VERSIONS,
+            }),
+        };
+        self.send_to_origin(notice);
+    }
+
+    /// Warn the session about a deprecated tool the first time it calls it
+    fn warn_deprecated(&self, session_id: &str, tool: &str, deprecation: &Deprecation) {
+        let first = self.sessions.update(session_id, |session| session.deprecation_warnings.insert(tool.into()));
+        if first && self.log_enabled(session_id, "warning") {
+            self.send_to_origin(ServerNotification::Log {
+                level: "warning".into(),
+                logger: Some("deprecation".into()),
+                data: deprecation.warning(tool),
+            });
+        }
+    }
+
+    /// Send a notification to the transport the current request came in on
+    fn send_to_origin(&self, notification: ServerNotification) {
+        let notification = match runner::current_origin() {
+            Some(origin) => ServerNotification::Routed { origin, notification: Box::new(notification) },
+            None => notification,
+        };
+        let _ = self.notification_tx.send(notification);
+    }
+
+    fn create_success_response(&self, version: JsonRpcVersion, id: Option<Value>, result: Value) -> MCPResponse {
+        MCPResponse::versioned_success(version, id, result)
+    }
+
+    fn create_error_response(&self, version: JsonRpcVersion, id: Option<Value>, error: MCPError) -> MCPResponse {
+        MCPResponse::versioned_error(version, id, error.to_json_rpc_error())
+    }
+
+
+    /// Number of requests currently in flight
+    pub fn in_flight(&self) -> usize {
+        self.active_requests.len()
+    }
+
+    /// Cancel every in-flight request, e.g. when shutting down. Returns the
+    /// number of requests cancelled.
+    pub async fn cancel_all(&self, reason: &str) -> usize {
+        let cancelled = self.active_requests.drain();
+        let count = cancelled.len();
+        for ((_, request_id), cancellation) in cancelled {
+            cancellation.cancel();
+            eprintln!("[CANCEL] Request {} cancelled: {}", request_id, reason);
+            self.handler.on_request_cancelled(&request_id, Some(reason)).await;
+        }
+        count
+    }
+
+    async fn handle_cancellation(&self, req: &MCPRequest) {
+        if let Some(params) = &req.params
+            && let Some(request_id) = params.get("requestId").map(request_key)
+        {
+            let reason = params.get("reason").and_then(Value::as_str);
+
+            // Signal cancellation to the session's active request
+            let cancellation = self.active_requests.remove(&self.sessions.session_id(req), &request_id);
+            if let Some(cancellation) = cancellation {
+                cancellation.cancel();
+                eprintln!("[CANCEL] Request {} cancelled: {:?}", request_id, reason);
+
+                // Notify handler
+                self.handler.on_request_cancelled(&request_id, reason).await;
+            }
+        }
+    }
+
+    async fn handle_tool_call_with_cancellation(&self, req: &MCPRequest, ctx: &RequestContext, limit: Option<Duration>) -> Result<Value, MCPError> {
+        let request_id = ctx.request_id().to_string();
+
+        // Create progress sender for this request
+        let mut progress_sender = self.progress_sender()
+            .with_session(ctx.session_id())
+            .with_priority(self.priority(req))
+            .with_log_level(self.log_minimum(ctx.session_id()));
+        let log_uri = self.call_logs.as_ref().and(req.id.as_ref()).map(call_log::log_uri);
+        if let (Some(logs), Some(uri)) = (&self.call_logs, &log_uri) {
+            logs.start(uri);
+            progress_sender = progress_sender.with_call_log(logs.clone(), uri.clone());
+        }
+        let ctx = ctx.clone().with_progress(progress_sender);
+
+        // Execute with cancellation support
+        let started = Instant::now();
+        let mut metered = None;
+        let result = tokio::select! {
+            result = self.handle_tool_call(req, &ctx, &mut metered) => {
+                result
+            }
+            _ = ctx.cancellation().cancelled() => {
+                eprintln!("[CANCEL] Tool call {} was cancelled", request_id);
+                Err(MCPError::RequestCancelled(request_id.clone()))
+            }
+            _ = sleep_for(limit) => {
+                eprintln!("[TIMEOUT] Tool call {} timed out after {:?}", request_id, limit.unwrap_or_default());
+                Err(MCPError::RequestTimeout(format!("tools/call {} after {:?}", request_id, limit.unwrap_or_default())))
+            }
+        };
+
+        // A timeout or cancellation is a failed call too; unknown names
+        // would let clients grow the table without bound
+        if let Some(name) = metered
+            && !matches!(result, Err(MCPError::UnknownTool(_)))
+        {
+            let failed = result.as_ref().map_or(true, |value| value["isError"] == true);
+            self.metrics.record(&name, started.elapsed(), failed);
+        }
+
+        // Clean up
+        if let Some(throttle) = &self.progress_throttle {
+            throttle.finish(ctx.session_id(), &request_id);
+        }
+        if let (Some(logs), Some(uri)) = (&self.call_logs, &log_uri) {
+            logs.finish(uri);
+        }
+        if self.gc.as_ref().is_some_and(ResourceGc::due) {
+            self.collect_garbage();
+        }
+
+        result
+    }
+
+    /// Run one collection over the transient resource stores
+    fn collect_garbage(&self) -> GcReport {
+        let Some(gc) = &self.gc else { return GcReport::default() };
+        let mut report = GcReport::default();
+        if let Some(store) = &self.content_store {
+            report = report.merge(store.collect(gc.policy()));
+        }
+        if let Some(limit) = &self.result_limit {
+            report = report.merge(limit.collect(gc.policy()));
+        }
+        if let Some(pages) = &self.result_pages {
+            report = report.merge(pages.collect(gc.policy()));
+        }
+        if let Some(logs) = &self.call_logs {
+            report = report.merge(logs.purge_expired());
+        }
+        gc.record(report);
+        report
+    }
+
+    /// Call counts, error counts and latency percentiles of each tool called
+    /// so far
+    pub fn metrics(&self) -> BTreeMap<String, ToolStats> {
+        self.metrics.snapshot()
+    }
+
+    /// Totals of the resource collector, if configured
+    pub fn gc_metrics(&self) -> Option<GcMetrics> {
+        self.gc.as_ref().map(ResourceGc::metrics)
+    }
+
+    fn handle_cleanup(&self, req: &MCPRequest) -> Result<Value, MCPError> {
+        if let Some(gc) = &self.gc {
+            gc.authorize(req.params.as_ref())?;
+        }
+        let report = self.collect_garbage();
+        let store = self.content_store.as_ref();
+        Ok(json!({
+            "removed": report.removed,
+            "freedBytes": report.freed_bytes,
+            "cas": { "entries": store.map_or(0, ContentStore::len), "bytes": store.map_or(0, ContentStore::bytes) },
+            "callLogs": { "entries": self.call_logs.as_ref().map_or(0, |logs| logs.len()) },
+            "resultPages": { "entries": self.result_pages.as_ref().map_or(0, ResultPages::pending) },
+            "metrics": self.gc_metrics(),
+        }))
+    }
+
+    /// Answer a `tools/call`, setting `metered` to the tool's name once the
+    /// call is one [`SystemMCPServer::metrics`] counts
+    async fn handle_tool_call(&self, req: &MCPRequest, ctx: &RequestContext, metered: &mut Option<String>) -> Result<Value, MCPError> {
+        match (req.params.as_ref(), req.params.as_ref().and_then(|p| p.get("name")).and_then(Value::as_str)) {
+            (Some(params), Some(name)) => {
+                let args = params.get("arguments").unwrap_or(&Value::Null);
+                // A malformed selection must fail before the tool has side effects
+                let selectors = match params.get("_meta") {
+                    Some(meta) => parse_selectors(meta)?,
+                    None => None,
+                };
+                if name == NEXT_PAGE_TOOL
+                    && let Some(pages) = &self.result_pages
+                {
+                    let (tool_response, next) = pages.next_page(ctx.session_id(), args)?;
+                    return paged_result(tool_response, next);
+                }
+                let versioned = self.tool_versions.resolve(name, params.get("_meta"))?;
+                let name = versioned.as_deref().unwrap_or(name);
+                if !self.tool_enabled(name) {
+                    return Err(MCPError::UnknownTool(name.into()));
+                }
+                if self.warn_deprecated_calls
+                    && let Some(deprecation) = self.deprecated_tools.get(name).or_else(|| self.tool_versions.deprecation(name))
+                {
+                    self.warn_deprecated(ctx.session_id(), name, deprecation);
+                }
+                if let Some(allowed) = &self.allowed_tools
+                    && !allowed.contains(name)
+                    && !self.runtime_tools(ctx).await?.iter().any(|tool| tool["name"] == name)
+                {
+                    let reason = if self.read_only { "the server is read-only" } else { "destructive tools are disabled" };
+                    return Err(MCPError::Forbidden(format!("tool {} is not read-only and {}", name, reason)));
+                }
+                #[cfg(feature = "jsonschema")]
+                if let Some(validator) = &self.argument_validator
+                    && let Some(tool) = self.find_tool(name, ctx).await?
+                {
+                    // Arguments that break the schema are a failed call of a known tool
+                    validator.validate(name, &tool["inputSchema"], self.tools_generation(), args)
+                        .inspect_err(|_| *metered = Some(name.to_string()))?;
+                }
+                for guard in &self.tool_call_guards {
+                    guard.check(name, args, ctx).await?;
+                }
+
+                *metered = Some(name.to_string());
+                self.handler.on_tool_called(name).await;
+                let result = match self.tool_registry.as_ref().filter(|registry| registry.contains(name)) {
+                    Some(registry) => registry.call(name, args, ctx).await,
+                    None => self.handler.call_tool(name, args, ctx).await,
+                };
+                // A result breaking its schema is a failed call
+                #[cfg(feature = "jsonschema")]
+                let result = match (&self.output_validator, self.output_schemas.get(name)) {
+                    (Some(validator), Some(schema)) => {
+                        result.and_then(|response| validator.validate(name, schema, &response).map(|()| response))
+                    }
+                    _ => result,
+                };
+                let success = result.is_ok();
+                self.handler.on_tool_completed(name, success).await;
+
+                let mut tool_response = result?;
+                let next = match &self.result_pages {
+                    Some(pages) => pages.paginate(ctx.session_id(), &mut tool_response)?,
+                    None => None,
+                };
+                if let Some(store) = &self.content_store {
+                    store.dedupe(ctx.session_id(), &mut tool_response.content);
+                }
+                self.content_annotations.apply(name, &mut tool_response.content);
+                if let Some(resolver) = &self.uri_resolver {
+                    resolver.apply(ctx.session_id(), &self.uri_base(resolver, ctx), &mut tool_response.content);
+                }
+                if let Some(limit) = &self.result_limit {
+                    limit.apply(ctx.session_id(), &mut tool_response)?;
+                }
+                if let Some(reservation) = self.reserve_output(&tool_response)? {
+                    memory::hold_output(reservation);
+                }
+                if let Some(selectors) = &selectors
+                    && let Some(structured) = tool_response.structured_content.as_ref()
+                {
+                    tool_response.structured_content = Some(apply_selection(structured, selectors));
+                }
+                paged_result(tool_response, next)
+            }
+            (None, _) => Err(MCPError::MissingParameters),
+            (_, None) => Err(MCPError::MissingToolName),
+        }
+    }
+
+    fn uri_base(&self, resolver: &UriResolver, ctx: &RequestContext) -> String {
+        resolver.base(self.client_requests.cached_roots(ctx.session_id()).as_deref())
+    }
+
+    /// Priority lane of a `tools/call`
+    fn priority(&self, req: &MCPRequest) -> Priority {
+        let params = req.params.as_ref();
+        let tool = params.and_then(|p| p.get("name")).and_then(Value::as_str);
+        // Clients may only demote their own calls
+        let hinted = Priority::from_meta(params.and_then(|p| p.get("_meta")));
+        if tool.is_some_and(|tool| self.background_tools.contains(tool)) || hinted == Some(Priority::Background) {
+            Priority::Background
+        } else {
+            Priority::Interactive
+        }
+    }
+
+    /// Context of a request, reporting progress through the server's channel
+    fn request_context(&self, req: &MCPRequest) -> RequestContext {
+        let request_id = req.id.as_ref().map(request_key).unwrap_or_else(|| "unknown".to_string());
+        let meta = req.params.as_ref().and_then(|p| p.get("_meta")).cloned();
+        let session_id = self.sessions.session_id(req);
+        let session = self.sessions.get(&session_id).unwrap_or_default();
+        let log_level = session.log_level.or_else(|| self.config.as_ref()?.log_level());
+        let progress = self.progress_sender().with_session(session_id.clone()).with_log_level(log_level);
+        RequestContext::new(request_id, meta, self.flags.clone())
+            .with_session_id(session_id)
+            .with_session(session.client, session.protocol_version)
+            .with_progress(progress)
+            .with_subprocess_env(self.subprocess_env.clone())
+            .with_client_requests(self.client_requests.clone())
+            .with_memory(self.memory.clone())
+    }
+
+    fn progress_sender(&self) -> ProgressSender {
+        let mut progress_sender = ProgressSender::new(self.notification_tx.clone()).with_origin(runner::current_origin());
+        if let Some(memory) = &self.memory {
+            progress_sender = progress_sender.with_memory(memory.clone());
+        }
+        if let Some(throttle) = &self.progress_throttle {
+            progress_sender = progress_sender.with_throttle(throttle.clone());
+        }
+        progress_sender
+    }
+
+    /// Account a result against the memory ceiling, evicting caches before
+    /// rejecting it
+    fn reserve_output(&self, response: &ToolResponse) -> Result<Option<memory::Reservation>, MCPError> {
+        let Some(memory) = &self.memory else { return Ok(None) };
+        let size = memory::result_size(response);
+        if let Some(reservation) = memory.reserve(MemoryCategory::Output, size) {
+            return Ok(Some(reservation));
+        }
+        if let Some(store) = &self.content_store {
+            store.clear();
+        }
+        match memory.reserve(MemoryCategory::Output, size) {
+            Some(reservation) => Ok(Some(reservation)),
+            None => {
+                memory.record_rejection();
+                eprintln!("[MEMORY] Rejected {} byte result at {:.0}% pressure", size, memory.pressure() * 100.0);
+                Err(MCPError::OutputTooLarge)
+            }
+        }
+    }
+
+    async fn handle_prompt_get(&self, req: &MCPRequest, ctx: &RequestContext) -> Result<Value, MCPError> {
+        let params = req.params.as_ref().ok_or(MCPError::MissingParameters)?;
+        let name = params.get("name").and_then(Value::as_str).ok_or(MCPError::MissingParameters)?;
+        let args = params.get("arguments").unwrap_or(&Value::Null);
+
+        if let Some(prompt) = self.prompts.get(name) {
+            completion::validate_prompt_arguments(prompt, args)?;
+        }
+
+        let response = self.handler.get_prompt(name, args, ctx).await?;
+        for message in &response.messages {
+            message.validate().map_err(|e| {
+                MCPError::InternalError(format!("prompt {} produced an invalid message: {}", name, e))
+            })?;
+        }
+        serde_json::to_value(response).map_err(MCPError::from)
+    }
+
+    async fn handle_completion(&self, req: &MCPRequest, ctx: &RequestContext) -> Result<Value, MCPError> {
+        let params = req.params.as_ref().ok_or(MCPError::MissingParameters)?;
+        let (reference, argument) = completion::parse_request(params)?;
+
+        let from_enum = match &reference {
+            CompletionReference::Prompt { name } => self.prompts.get(name)
+                .and_then(|p| p.arguments.as_ref()?.iter().find(|a| a.name == argument.name))
+                .and_then(|a| completion::enum_completion(a, &argument.value)),
+            CompletionReference::Resource { .. } => None,
+        };
+
+        let completion = match (from_enum, &self.completion_provider) {
+            (Some(completion), _) => completion,
+            (None, Some(provider)) => provider.complete(&reference, &argument, ctx).await?,
+            (None, None) => Completion::default(),
+        };
+        serde_json::to_value(CompleteResult { completion }).map_err(MCPError::from)
+    }
+
+    fn handle_subscription(&self, req: &MCPRequest, session_id: &str, subscribe: bool) -> Result<Value, MCPError> {
+        if self.capabilities.resources.as_ref().is_none_or(|resources| resources.subscribe != Some(true)) {
+            return Err(MCPError::MethodNotFound(format!("{} (resources.subscribe is not advertised)", req.method)));
+        }
+        let params = req.params.as_ref().ok_or(MCPError::MissingParameters)?;
+        let uri = params.get("uri").and_then(Value::as_str).ok_or(MCPError::MissingParameters)?;
+
+        self.sessions.update(session_id, |session| {
+            if subscribe {
+                session.subscriptions.insert(uri.to_string());
+            } else {
+                session.subscriptions.remove(uri);
+            }
+        });
+        Ok(json!({}))
+    }
+
+    fn handle_set_log_level(&self, req: &MCPRequest, session_id: &str) -> Result<Value, MCPError> {
+        let params = req.params.as_ref().ok_or(MCPError::MissingParameters)?;
+        let level = params.get("level").and_then(Value::as_str).ok_or(MCPError::MissingParameters)?;
+
+        self.sessions.update(session_id, |session| session.log_level = Some(level.to_string()));
+        Ok(json!({}))
+    }
+
+    async fn handle_resource_read(&self, req: &MCPRequest, ctx: &RequestContext) -> Result<Value, MCPError> {
+        let params = req.params.as_ref().ok_or(MCPError::MissingParameters)?;
+        let uri = params.get("uri").and_then(Value::as_str).ok_or(MCPError::MissingParameters)?;
+        let resolved = self.uri_resolver.as_ref().map(|resolver| resolver.resolve(ctx.session_id(), &self.uri_base(resolver, ctx), uri));
+        let uri = resolved.as_deref().unwrap_or(uri);
+
+        if uri.starts_with(CAS_SCHEME) {
+            let content = self.content_store.as_ref()
+                .and_then(|store| store.read(ctx.session_id(), uri))
+                .ok_or_else(|| MCPError::ResourceNotFound(uri.into()))?;
+            return serde_json::to_value(content).map_err(MCPError::from);
+        }
+        if uri == HEARTBEAT_URI
+            && let Some(heartbeat) = &self.heartbeat
+        {
+            return serde_json::to_value(heartbeat.read()).map_err(MCPError::from);
+        }
+        if uri == METRICS_URI && self.metrics_resource {
+            return serde_json::to_value(self.metrics.read()).map_err(MCPError::from);
+        }
+        if uri == CHANGES_URI
+            && let Some(changes) = &self.changes
+        {
+            return serde_json::to_value(changes.read()).map_err(MCPError::from);
+        }
+        if self.tool_docs
+            && let Some(name) = tool_docs::tool_name(uri)
+        {
+            let tool = self.find_tool(name, ctx).await?.ok_or_else(|| MCPError::ResourceNotFound(uri.into()))?;
+            return serde_json::to_value(tool_docs::read(&tool)).map_err(MCPError::from);
+        }
+        if uri.starts_with(SPILL_SCHEME) {
+            let content = self.result_limit.as_ref()
+                .and_then(|limit| limit.read(ctx.session_id(), uri))
+                .ok_or_else(|| MCPError::ResourceNotFound(uri.into()))?;
+            return serde_json::to_value(content).map_err(MCPError::from);
+        }
+        if uri.starts_with(CALL_LOG_SCHEME) {
+            let content = self.call_logs.as_ref()
+                .and_then(|logs| logs.read(uri))
+                .ok_or_else(|| MCPError::ResourceNotFound(uri.into()))?;
+            return serde_json::to_value(content).map_err(MCPError::from);
+        }
+
+        let content = self.handler.read_resource(uri, ctx).await?;
+        serde_json::to_value(content).map_err(MCPError::from)
+    }
+}
+
+// Runners handle requests on tasks of their own, which share the server
+impl<H: ToolHandler + 'static> SystemMCPServer<H> {
+    /// Run loop over a transport, with shutdown options
+    pub fn runner(self: &Arc<Self>) -> ServerRunner<H> {
+        ServerRunner::new(self.clone())
+    }
+
+    /// Serve newline-delimited JSON on stdin/stdout until EOF or a
+    /// termination signal
+    pub async fn run_stdio(self: &Arc<Self>) -> Result<(), MCPError> {
+        self.runner().run_stdio().await
+    }
+
+    /// Serve one connection until it closes or a termination signal arrives
+    pub async fn run_with_transport<T: Transport>(self: &Arc<Self>, transport: T) -> Result<(), MCPError> {
+        self.runner().run_with_transport(transport).await
+    }
+}
+
+tokio::task_local! {
+    // Set while a custom method's tool call is dispatched
+    static STEP: ();
+}
+
+/// Tool calls of a custom method: each is a `tools/call` request of its own,
+/// in the method's session, with an id under the method's, through the
+/// middleware and guards, and cancelled along with the method
+struct StepCaller<'a, H: ToolHandler> {
+    server: &'a SystemMCPServer<H>,
+    ctx: &'a RequestContext,
+    steps: AtomicU64,
+}
+
+impl<'a, H: ToolHandler> StepCaller<'a, H> {
+    fn new(server: &'a SystemMCPServer<H>, ctx: &'a RequestContext) -> Self {
+        Self { server, ctx, steps: AtomicU64::new(0) }
+    }
+}
+
+#[async_trait]
+impl<H: ToolHandler> ToolCaller for StepCaller<'_, H> {
+    async fn call_tool(&self, name: &str, arguments: Value) -> Result<Value, MCPError> {
+        let id = format!("{}/{}", self.ctx.request_id(), self.steps.fetch_add(1, Ordering::Relaxed) + 1);
+        let mut params = json!({ "name": name, "arguments": arguments });
+        if let Some(meta) = self.ctx.meta() {
+            params["_meta"] = meta.clone();
+        }
+        let req = MCPRequest::new(Some(Value::String(id.clone())), "tools/call", Some(params));
+        let call = STEP.scope((), self.server.run_middleware_and_dispatch(IncomingRequest::from_request(req)));
+        // Dropping the call cancels it
+        let response = tokio::select! {
+            response = call => response,
+            _ = self.ctx.cancellation().cancelled() => return Err(MCPError::RequestCancelled(id)),
+        };
+        let response = response.ok_or_else(|| MCPError::InternalError(format!("no response to {}", id)))?;
+        match response.error {
+            Some(error) => Err(MCPError::PeerError(error)),
+            None => Ok(response.result.unwrap_or_default()),
+        }
+    }
+
+    fn supports_rollback(&self, name: &str) -> bool {
+        self.server.handler.supports_rollback(name)
+    }
+
+    async fn rollback_tool(&self, name: &str, arguments: &Value) -> Result<(), MCPError> {
+        self.server.handler.rollback_tool(name, arguments, self.ctx).await
+    }
+}
+
+#[async_trait]
+impl<H: ToolHandler> Endpoint for SystemMCPServer<H> {
+    async fn dispatch(&self, request: &MCPRequest) -> Option<MCPResponse> {
+        SystemMCPServer::dispatch(self, request).await
+    }
+
+    fn reject(&self, request: &MCPRequest, error: MCPError) -> Option<MCPResponse> {
+        if request.is_notification() {
+            eprintln!("[MIDDLEWARE] Dropped notification {}: {}", request.method, error);
+            return None;
+        }
+        let version = self.validate_and_detect_version(request).unwrap_or(JsonRpcVersion::V2_0);
+        Some(self.create_error_response(version, request.id.clone(), error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures;
+    use crate::tools::{ToolAnnotations, ToolInputSchema};
+
+    struct Sleepy;
+
+    #[async_trait]
+    impl ToolHandler for Sleepy {
+        async fn call_tool(&self, name: &str, _args: &Value, _ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+            let delay = if name == "slow" { 200 } else { 0 };
+            tokio::time::sleep(Duration::from_millis(delay)).await;
+            Ok(ToolResponse::new(name.into(), false))
+        }
+
+        async fn list_tools(&self, _ctx: &RequestContext) -> Result<Vec<Tool>, MCPError> {
+            tokio::time::sleep(Duration::from_millis(200)).await;
+            Ok(Vec::new())
+        }
+
+        async fn read_resource(&self, uri: &str, _ctx: &RequestContext) -> Result<ResourceContent, MCPError> {
+            Ok(ResourceContent { uri: uri.into(), mime_type: "text/plain".into(), text: "log line\n".repeat(500), blob: None })
+        }
+
+        async fn experimental_method(&self, method: &str, params: Option<&Value>, ctx: &RequestContext) -> Result<Value, MCPError> {
+            match method {
+                "acme/echo" => Ok(json!({ "params": params, "requestId": ctx.request_id() })),
+                _ => Err(MCPError::MethodNotFound(method.into())),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_jsonrpc_version_per_request() {
+        let v1 = || {
+            let mut ping = fixtures::request("ping").build();
+            ping.jsonrpc = None;
+            ping
+        };
+        let server = SystemMCPServer::<Sleepy>::builder().relaxed_lifecycle().accept_jsonrpc_1(true).build(Sleepy);
+        let old = serde_json::to_value(server.handle(v1()).await.unwrap()).unwrap();
+        assert!(old.get("jsonrpc").is_none() && old["result"] == json!({}));
+        let new = serde_json::to_value(server.handle(fixtures::request("ping").build()).await.unwrap()).unwrap();
+        assert_eq!(new["jsonrpc"], "2.0");
+
+        let strict = SystemMCPServer::<Sleepy>::builder().relaxed_lifecycle().accept_jsonrpc_1(false).build(Sleepy);
+        assert_eq!(strict.handle(v1()).await.unwrap().error.unwrap().code, -32600);
+    }
+
+    #[tokio::test]
+    async fn test_strict_mode() {
+        let server = SystemMCPServer::<Sleepy>::builder().relaxed_lifecycle().strict(true).build(Sleepy);
+        let error = |raw: &'static str| {
+            let server = &server;
+            async move { server.handle_raw(raw.as_bytes()).await.unwrap() }
+        };
+
+        let extra = error(r#"{"jsonrpc":"2.0","id":1,"method":"ping","trace":true}"#).await.error.unwrap();
+        assert_eq!((extra.code, extra.data.unwrap()["pointer"].clone()), (-32600, json!("/trace")));
+        let missing = error(r#"{"id":1,"method":"ping"}"#).await.error.unwrap();
+        assert_eq!((missing.code, missing.data.unwrap()["pointer"].clone()), (-32600, json!("/jsonrpc")));
+        let float_id = error(r#"{"jsonrpc":"2.0","id":1.5,"method":"ping"}"#).await;
+        assert_eq!((float_id.id, float_id.error.unwrap().data.unwrap()["received"].clone()), (Some(Value::Null), json!("number")));
+
+        let shape = error(r#"{"jsonrpc":"2.0","id":2,"method":"tools/call","params":{"name":7}}"#).await.error.unwrap();
+        assert_eq!(shape.code, -32602);
+        assert_eq!(shape.data.unwrap(), json!({ "kind": "params", "pointer": "/name", "expected": "string", "received": "integer" }));
+        assert!(error(r#"{"jsonrpc":"2.0","id":"a","method":"ping"}"#).await.error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_method_aliases() {
+        let schema = ToolInputSchema { schema_type: "object".into(), properties: Default::default(), required: vec![] };
+        let server = SystemMCPServer::<Sleepy>::builder()
+            .relaxed_lifecycle()
+            .with_tools(vec![Tool::new("fast", "Look", schema)])
+            .alias("tools/invoke", "tools/call")
+            .alias("tools/enumerate", "tools/list")
+            .build(Sleepy);
+        let invoke = || fixtures::request("tools/invoke").tool("fast").build();
+
+        assert_eq!(server.handle(invoke()).await.unwrap().result.unwrap()["content"][0]["text"], "fast");
+        server.handle(invoke()).await;
+        assert!(server.handle(fixtures::call_tool("fast").build()).await.unwrap().is_success());
+        let usage = server.alias_usage();
+        assert_eq!((usage["tools/invoke"], usage["tools/enumerate"]), (2, 0));
+        assert_eq!(server.export_manifest()["methodAliases"]["tools/invoke"], "tools/call");
+    }
+
+    #[tokio::test]
+    async fn test_content_annotations() {
+        use crate::testing::mock::{MockToolHandler, Reply};
+        use crate::tools::{Role, ToolResponse};
+
+        let mut annotated = ToolResponse::new("plain".into(), false);
+        annotated.content.push(ContentBlock::text("own").with_annotations(Annotations::for_audience([Role::Assistant])));
+        let handler = MockToolHandler::new()
+            .tool("search", Reply::value(annotated))
+            .tool("debug", Reply::text("trace"));
+        let server = SystemMCPServer::<MockToolHandler>::builder()
+            .relaxed_lifecycle()
+            .default_annotations(Annotations::for_audience([Role::User]))
+            .tool_annotations("debug", Annotations { audience: Some(vec![Role::Assistant]), priority: Some(0.2) })
+            .build(handler);
+        let call = |name: &str| {
+            let request = fixtures::call_tool(name).build();
+            let server = &server;
+            async move { server.handle(request).await.unwrap().result.unwrap()["content"].clone() }
+        };
+
+        let search = call("search").await;
+        assert_eq!(search[0]["annotations"], json!({ "audience": ["user"] }));
+        // Blocks that carry their own annotations keep them
+        assert_eq!(search[1]["annotations"], json!({ "audience": ["assistant"] }));
+        assert_eq!(call("debug").await[0]["annotations"], json!({ "audience": ["assistant"], "priority": 0.2 }));
+    }
+
+    #[tokio::test]
+    async fn test_disabled_methods() {
+        let schema = ToolInputSchema { schema_type: "object".into(), properties: Default::default(), required: vec![] };
+        let server = SystemMCPServer::<Sleepy>::builder()
+            .relaxed_lifecycle()
+            .with_tools(vec![Tool::new("fast", "Look", schema)])
+            .with_resources(vec![Resource::new("file:///a", "a")])
+            .with_subscribe(true)
+            .enable_logging()
+            .disable_methods(["tools/call", "resources/subscribe", "logging/setLevel"])
+            .build(Sleepy);
+
+        let init = server.handle(fixtures::initialize().build()).await.unwrap().result.unwrap();
+        assert!(init["capabilities"].get("tools").is_none());
+        assert!(init["capabilities"].get("logging").is_none());
+        assert_eq!(init["capabilities"]["resources"], json!({}));
+        for request in [fixtures::call_tool("fast"), fixtures::request("logging/setLevel").param("level", "debug")] {
+            assert_eq!(server.handle(request.build()).await.unwrap().error.unwrap().code, -32601);
+        }
+        assert!(server.handle(fixtures::request("resources/list").build()).await.unwrap().is_success());
+        // Notifications for disabled methods are dropped silently
+        assert!(server.handle(fixtures::call_tool("fast").no_id().build()).await.is_none());
+    }
+
+    #[test]
+    fn test_manifest_lists_only_what_is_served() {
+        let server = SystemMCPServer::<Sleepy>::builder()
+            .with_resources(vec![Resource::new("file:///a", "a")])
+            .build(Sleepy);
+        let manifest = server.export_manifest();
+        assert_eq!(manifest["protocolVersion"], PROTOCOL_VERSION);
+        assert_eq!(manifest["resources"][0]["uri"], "file:///a");
+        assert!(manifest.get("resourceTemplates").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_declared_capabilities() {
+        let server = SystemMCPServer::<Sleepy>::builder()
+            .relaxed_lifecycle()
+            .enable_logging()
+            .with_resources(vec![Resource::new("file:///a", "a")])
+            .with_subscribe(true)
+            .with_list_changed(true)
+            .build(Sleepy);
+        let init = server.handle(fixtures::initialize().build()).await.unwrap().result.unwrap();
+        assert_eq!(init["capabilities"]["logging"], json!({}));
+        assert_eq!(init["capabilities"]["resources"], json!({ "subscribe": true }));
+        assert_eq!(init["capabilities"]["tools"], json!({ "listChanged": true }));
+        let subscribe = || fixtures::request("resources/subscribe").param("uri", "file:///a").build();
+        assert!(server.handle(subscribe()).await.unwrap().is_success());
+        let unsubscribable = SystemMCPServer::<Sleepy>::builder().relaxed_lifecycle().build(Sleepy);
+        assert_eq!(unsubscribable.handle(subscribe()).await.unwrap().error.unwrap().code, -32601);
+
+        let unserved = |builder: ServerBuilder| builder.try_build(Sleepy).err().unwrap().to_string();
+        assert!(unserved(ServerBuilder::new().enable_completions()).contains("no completion provider"));
+        let resources = || ServerBuilder::new().with_resources(vec![Resource::new("file:///a", "a")]);
+        assert!(unserved(resources().with_subscribe(true).read_only(true)).contains("resources/subscribe is disabled"));
+        assert!(unserved(ServerBuilder::new().enable_logging().disable_methods(["logging/setLevel"])).contains("logging"));
+        assert!(unserved(ServerBuilder::new().enable_prompts()).contains("no prompts are registered"));
+        assert!(unserved(ServerBuilder::new().enable_resources()).contains("no resources are registered"));
+
+        // build() withdraws what it cannot serve instead of failing
+        let server = SystemMCPServer::<Sleepy>::builder().relaxed_lifecycle().enable_prompts().enable_completions().build(Sleepy);
+        let init = server.handle(fixtures::initialize().build()).await.unwrap().result.unwrap();
+        assert!(init["capabilities"].get("prompts").is_none() && init["capabilities"].get("completions").is_none());
+    }
+
+    #[tokio::test]
+    async fn test_unknown_methods_reach_handler() {
+        let server = SystemMCPServer::<Sleepy>::builder().relaxed_lifecycle().build(Sleepy);
+        let echo = fixtures::request("acme/echo").id(4).param("x", 1).build();
+        let result = server.handle(echo).await.unwrap().result.unwrap();
+        assert_eq!(result, json!({ "params": { "x": 1 }, "requestId": "4" }));
+        let error = server.handle(fixtures::request("acme/other").build()).await.unwrap().error.unwrap();
+        assert_eq!(error.code, -32601);
+    }
+
+    #[tokio::test]
+    async fn test_timeouts() {
+        let server = SystemMCPServer::<Sleepy>::builder()
+            .relaxed_lifecycle()
+            .request_timeout(Duration::from_secs(5))
+            .method_timeout("tools/list", Duration::from_millis(20))
+            .tool_timeout("slow", Duration::from_millis(20))
+            .build(Sleepy);
+
+        let error = server.handle(fixtures::call_tool("slow").build()).await.unwrap().error.unwrap();
+        assert_eq!(error.code, -32004);
+        assert!(server.active_requests.is_empty());
+        assert!(server.handle(fixtures::call_tool("fast").build()).await.unwrap().is_success());
+
+        let error = server.handle(fixtures::request("tools/list").build()).await.unwrap().error.unwrap();
+        assert_eq!(error.code, -32004);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_any_request() {
+        let server = Arc::new(SystemMCPServer::<Sleepy>::builder().relaxed_lifecycle().build(Sleepy));
+        let listing = tokio::spawn({
+            let server = server.clone();
+            async move { server.handle(fixtures::request("tools/list").id("list-1").build()).await }
+        });
+        while server.active_requests.is_empty() {
+            tokio::task::yield_now().await;
+        }
+        assert!(server.handle(fixtures::cancelled("list-1", Some("user"))).await.is_none());
+
+        let error = listing.await.unwrap().unwrap().error.unwrap();
+        assert_eq!(error.code, -32800);
+        assert!(server.active_requests.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_cancellation_is_per_session() {
+        let server = Arc::new(SystemMCPServer::<Sleepy>::builder().relaxed_lifecycle().demultiplex_sessions("sessionId").build(Sleepy));
+        let slow = |session: &str| {
+            let server = server.clone();
+            let call = fixtures::call_tool("slow").id(1).meta("sessionId", session).build();
+            tokio::spawn(async move { server.handle(call).await.unwrap() })
+        };
+        let (a, b) = (slow("a"), slow("b"));
+        while server.active_requests.len() < 2 {
+            tokio::task::yield_now().await;
+        }
+        let cancel = fixtures::notification("notifications/cancelled").param("requestId", 1).meta("sessionId", "a").build();
+        assert!(server.handle(cancel).await.is_none());
+        assert_eq!(a.await.unwrap().error.unwrap().code, -32800);
+        assert!(b.await.unwrap().is_success());
+
+        // A request abandoned mid-flight stops being tracked
+        let abandoned = tokio::time::timeout(Duration::from_millis(20), server.handle(fixtures::call_tool("slow").build()));
+        assert!(abandoned.await.is_err());
+        assert!(server.active_requests.is_empty());
+
+        // Only the token a request registered is removed when it ends
+        let (old, new) = (CancellationToken::new(), CancellationToken::new());
+        let first = server.active_requests.track("a", "7".into(), old);
+        let _second = server.active_requests.track("a", "7".into(), new.clone());
+        drop(first);
+        assert!(server.active_requests.remove("a", "7").is_some_and(|token| token.same(&new)));
+    }
+
+    #[tokio::test]
+    async fn test_read_only_mode() {
+        let schema = || ToolInputSchema { schema_type: "object".into(), properties: Default::default(), required: vec![] };
+        let server = SystemMCPServer::<Sleepy>::builder()
+            .relaxed_lifecycle()
+            .with_tools(vec![
+                Tool::new("fast", "Look", schema()).with_annotations(ToolAnnotations { read_only_hint: Some(true), ..Default::default() }),
+                Tool::new("slow", "Write", schema()),
+            ])
+            .heartbeat(Duration::from_secs(60))
+            .read_only(true)
+            .build(Sleepy);
+
+        let init = server.handle(fixtures::initialize().build()).await.unwrap().result.unwrap();
+        assert_eq!(init["_meta"]["readOnly"], true);
+        assert!(init["instructions"].as_str().unwrap().contains("read-only"));
+        assert!(init["capabilities"]["resources"].get("subscribe").is_none());
+        assert_eq!(init["capabilities"]["tools"], json!({}));
+
+        let tools = server.handle(fixtures::request("tools/list").build()).await.unwrap().result.unwrap();
+        assert_eq!(tools["tools"].as_array().unwrap().len(), 1);
+        assert!(server.handle(fixtures::call_tool("fast").build()).await.unwrap().is_success());
+        assert_eq!(server.handle(fixtures::call_tool("slow").build()).await.unwrap().error.unwrap().code, -32003);
+        let subscribe = fixtures::request("resources/subscribe").param("uri", HEARTBEAT_URI).build();
+        assert_eq!(server.handle(subscribe).await.unwrap().error.unwrap().code, -32601);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[tokio::test]
+    async fn test_compressed_contents() {
+        let read = || fixtures::request("resources/read").param("uri", "file:///log").build();
+        let server = SystemMCPServer::<Sleepy>::builder()
+            .relaxed_lifecycle()
+            .content_digests()
+            .compress_contents(1024)
+            .build(Sleepy);
+
+        server.handle(fixtures::initialize().build()).await;
+        assert!(server.handle(read()).await.unwrap().result.unwrap().get("text").is_some());
+
+        let init = fixtures::initialize().param("capabilities", json!({ "experimental": { "zstd": {} } })).build();
+        let capabilities = server.handle(init).await.unwrap().result.unwrap()["capabilities"].clone();
+        assert_eq!(capabilities["experimental"]["zstd"]["minSize"], 1024);
+        let mut contents = server.handle(read()).await.unwrap().result.unwrap();
+        assert_eq!(contents["_meta"]["encoding"], compression::ENCODING);
+        assert_eq!(compression::decompress_result(&mut contents).unwrap(), 1);
+        assert_eq!(integrity::verify_result(&contents).unwrap(), 1);
+    }
+}
@@ -0,0 +1,281 @@
+//! Per-session protocol state.
+//!
+//! A plain stdio server has a single session. Gateways that tunnel several
+//! logical clients through one pipe tag each request with a session id under
+//! `params._meta.<key>`; with [`ServerBuilder::demultiplex_sessions`] the
+//! server keeps separate initialization, log level and subscriptions for each.
+//!
+//! [`ServerBuilder::demultiplex_sessions`]: crate::server::ServerBuilder::demultiplex_sessions
+
+use crate::context::ClientInfo;
+use crate::error::MCPError;
+use crate::request::MCPRequest;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::RwLock;
+
+/// Session id used when demultiplexing is off or a request carries no key
+pub const DEFAULT_SESSION: &str = "default";
+
+/// 128 random bits in hex, for ids a client must not be able to guess
+pub fn random_id() -> Result<String, MCPError> {
+    let mut bytes = [0u8; 16];
+    getrandom::fill(&mut bytes).map_err(|e| MCPError::InternalError(format!("no randomness for id: {}", e)))?;
+    Ok(bytes.iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// State kept for one logical client
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionState {
+    /// `initialize` was answered
+    pub initialized: bool,
+    /// The client confirmed with `notifications/initialized`
+    #[serde(default)]
+    pub client_initialized: bool,
+    /// Protocol revision agreed during `initialize`
+    pub protocol_version: Option<String>,
+    /// Client name, version and capabilities from `initialize`
+    #[serde(default)]
+    pub client: Option<ClientInfo>,
+    pub log_level: Option<String>,
+    pub subscriptions: HashSet<String>,
+    /// The client takes zstd-compressed resource contents
+    #[serde(default)]
+    pub accepts_zstd: bool,
+    /// Who the client authenticated as, if an auth layer recorded it
+    pub principal: Option<String>,
+    /// Deprecated tools the client was already warned about
+    #[serde(default)]
+    pub deprecation_warnings: HashSet<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct Sessions {
+    key: Option<String>,
+    sessions: RwLock<HashMap<String, SessionState>>,
+}
+
+impl Sessions {
+    /// Partition requests by `params._meta[key]`, or keep one session when `None`
+    pub fn new(key: Option<String>) -> Self {
+        Sessions {
+            key,
+            sessions: RwLock::new(HashMap::new()),
+        }
+    }
+
+    /// Session a request belongs to
+    pub fn session_id(&self, req: &MCPRequest) -> String {
+        self.key.as_deref()
+            .and_then(|key| req.params.as_ref()?.get("_meta")?.get(key))
+            .and_then(Value::as_str)
+            .unwrap_or(DEFAULT_SESSION)
+            .to_string()
+    }
+
+    pub fn get(&self, id: &str) -> Option<SessionState> {
+        self.sessions.read().unwrap().get(id).cloned()
+    }
+
+    /// Apply `f` to a session, creating it on first use
+    pub fn update<R>(&self, id: &str, f: impl FnOnce(&mut SessionState) -> R) -> R {
+        let mut sessions = self.sessions.write().unwrap();
+        f(sessions.entry(id.to_string()).or_default())
+    }
+
+    /// Apply `f` to a session that exists
+    pub fn update_existing<R>(&self, id: &str, f: impl FnOnce(&mut SessionState) -> R) -> Option<R> {
+        self.sessions.write().unwrap().get_mut(id).map(f)
+    }
+
+    pub fn remove(&self, id: &str) -> Option<SessionState> {
+        self.sessions.write().unwrap().remove(id)
+    }
+
+    pub fn ids(&self) -> Vec<String> {
+        self.sessions.read().unwrap().keys().cloned().collect()
+    }
+
+    /// Sessions subscribed to `uri`
+    pub fn subscribers(&self, uri: &str) -> Vec<String> {
+        self.sessions.read().unwrap().iter()
+            .filter(|(_, state)| state.subscriptions.contains(uri))
+            .map(|(id, _)| id.clone())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::MCPError;
+    use crate::context::RequestContext;
+    use crate::server::{ReinitializePolicy, SystemMCPServer, ToolHandler, PROTOCOL_VERSION};
+    use crate::testing::fixtures;
+    use crate::testing::mock::{MockToolHandler, Reply};
+    use crate::tools::{Resource, ToolResponse};
+    use serde_json::json;
+
+    fn request(params: Value) -> MCPRequest {
+        MCPRequest::new(Some(json!(1)), "resources/subscribe", Some(params))
+    }
+
+    #[test]
+    fn test_demultiplexing() {
+        let sessions = Sessions::new(Some("sessionId".into()));
+        let a = sessions.session_id(&request(json!({ "_meta": { "sessionId": "a" } })));
+        let b = sessions.session_id(&request(json!({ "_meta": { "sessionId": "b" } })));
+        let none = sessions.session_id(&request(json!({})));
+        assert_eq!((a.as_str(), b.as_str(), none.as_str()), ("a", "b", DEFAULT_SESSION));
+
+        sessions.update(&a, |s| { s.subscriptions.insert("file:///x".into()); });
+        sessions.update(&b, |s| s.log_level = Some("debug".into()));
+        assert_eq!(sessions.subscribers("file:///x"), vec!["a".to_string()]);
+        assert_eq!(sessions.get("b").unwrap().log_level.as_deref(), Some("debug"));
+        assert!(sessions.get("a").unwrap().log_level.is_none());
+    }
+
+    #[test]
+    fn test_single_session_without_key() {
+        let sessions = Sessions::new(None);
+        let id = sessions.session_id(&request(json!({ "_meta": { "sessionId": "a" } })));
+        assert_eq!(id, DEFAULT_SESSION);
+    }
+
+    struct Reinits(std::sync::Arc<std::sync::atomic::AtomicUsize>);
+
+    #[async_trait::async_trait]
+    impl ToolHandler for Reinits {
+        async fn call_tool(&self, _name: &str, _args: &Value, _ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+            Ok(ToolResponse::new(String::new(), false))
+        }
+
+        async fn on_reinitialize(&self, _session_id: &str) {
+            self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_reinitialize_policy() {
+        let init = || MCPRequest::new(Some(json!(1)), "initialize", Some(json!({})));
+
+        let count = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let subscribable = || SystemMCPServer::<Reinits>::builder().with_resources(vec![Resource::new("file:///a", "a")]).with_subscribe(true);
+        let server = subscribable().build(Reinits(count.clone()));
+        server.handle(init()).await;
+        server.handle(fixtures::notification("notifications/initialized").build()).await;
+        server.handle(request(json!({ "uri": "file:///a" }))).await;
+        assert!(server.handle(init()).await.unwrap().error.is_none());
+        assert!(server.session(DEFAULT_SESSION).unwrap().subscriptions.is_empty());
+        assert_eq!(count.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        let server = subscribable()
+            .reinitialize_policy(ReinitializePolicy::Reject)
+            .build(Reinits(count.clone()));
+        server.handle(init()).await;
+        server.handle(fixtures::notification("notifications/initialized").build()).await;
+        server.handle(request(json!({ "uri": "file:///a" }))).await;
+        let error = server.handle(init()).await.unwrap().error.unwrap();
+        assert_eq!(error.code, -32600);
+        assert!(server.session(DEFAULT_SESSION).unwrap().subscriptions.contains("file:///a"));
+        assert_eq!(count.load(std::sync::atomic::Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_reset_cancels_requests_in_flight() {
+        let handler = MockToolHandler::new().tool("slow", Reply::hang());
+        let server = std::sync::Arc::new(SystemMCPServer::<MockToolHandler>::builder().build(handler));
+        server.handle(fixtures::initialize().build()).await;
+        server.handle(fixtures::notification("notifications/initialized").build()).await;
+        let call = tokio::spawn({
+            let server = server.clone();
+            async move { server.handle(fixtures::call_tool("slow").id("call-1").build()).await.unwrap() }
+        });
+        while server.in_flight() == 0 {
+            tokio::task::yield_now().await;
+        }
+
+        assert!(server.handle(fixtures::initialize().id("init-2").build()).await.unwrap().error.is_none());
+        assert_eq!(call.await.unwrap().error.unwrap().code, -32800);
+        assert_eq!(server.handler().cancelled(), ["call-1"]);
+        assert_eq!(server.in_flight(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_requests_wait_for_handshake() {
+        let server = SystemMCPServer::<Reinits>::builder().build(Reinits(Default::default()));
+        let list = || fixtures::request("tools/list").build();
+
+        assert_eq!(server.handle(list()).await.unwrap().error.unwrap().code, -32002);
+        assert!(server.handle(fixtures::request("ping").build()).await.unwrap().error.is_none());
+        server.handle(fixtures::initialize().build()).await;
+        assert_eq!(server.handle(list()).await.unwrap().error.unwrap().code, -32002);
+        server.handle(fixtures::notification("notifications/initialized").build()).await;
+        assert!(server.handle(list()).await.unwrap().error.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_initialized_hook_runs_once_after_initialize() {
+        let server = SystemMCPServer::<MockToolHandler>::builder().build(MockToolHandler::new());
+        let initialized = || fixtures::notification("notifications/initialized").build();
+
+        // Out of order: ignored, and no session is created for it
+        server.handle(initialized()).await;
+        assert!(server.handler().initialized().is_empty());
+        assert!(server.session(DEFAULT_SESSION).is_none());
+        assert_eq!(server.handle(fixtures::request("tools/list").build()).await.unwrap().error.unwrap().code, -32002);
+
+        server.handle(fixtures::initialize().build()).await;
+        server.handle(initialized()).await;
+        server.handle(initialized()).await;
+        assert_eq!(server.handler().initialized(), [DEFAULT_SESSION]);
+    }
+
+    #[tokio::test]
+    async fn test_unsupported_protocol_gets_upgrade_notice() {
+        let mut server = SystemMCPServer::<Reinits>::builder().build(Reinits(Default::default()));
+        let mut notifications = server.take_notification_receiver().unwrap();
+
+        let latest = server.handle(fixtures::initialize().build()).await.unwrap().result.unwrap();
+        assert_eq!(latest["protocolVersion"], PROTOCOL_VERSION);
+        assert!(latest.get("_meta").is_none());
+        assert!(notifications.try_recv().is_none());
+
+        let older = fixtures::initialize().param("protocolVersion", "2024-11-05").build();
+        let result = server.handle(older).await.unwrap().result.unwrap();
+        assert_eq!(result["protocolVersion"], PROTOCOL_VERSION);
+        assert_eq!(result["_meta"]["supportedVersions"], json!([PROTOCOL_VERSION]));
+        assert_eq!(server.session(DEFAULT_SESSION).unwrap().protocol_version.as_deref(), Some(PROTOCOL_VERSION));
+        let notice = notifications.try_recv().unwrap().to_json_rpc();
+        assert_eq!(notice["method"], "notifications/message");
+        assert_eq!(notice["params"]["data"]["requestedVersion"], "2024-11-05");
+        assert_eq!(notice["params"]["data"]["negotiatedVersion"], PROTOCOL_VERSION);
+    }
+
+    struct WhoAmI;
+
+    #[async_trait::async_trait]
+    impl ToolHandler for WhoAmI {
+        async fn call_tool(&self, _name: &str, _args: &Value, ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+            let context = ctx;
+            let client = context.client().map(|c| c.name.clone()).unwrap_or_default();
+            let images = context.client_supports("experimental.images");
+            Ok(ToolResponse::new(format!("{} {} {}", client, context.protocol_version().unwrap_or("-"), images), false))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_client_info_reaches_tools() {
+        let server = SystemMCPServer::<WhoAmI>::builder().relaxed_lifecycle().build(WhoAmI);
+        let call = || fixtures::call_tool("whoami").build();
+        let text = |response: crate::response::MCPResponse| response.result.unwrap()["content"][0]["text"].clone();
+        assert_eq!(text(server.handle(call()).await.unwrap()), " - false");
+
+        let init = fixtures::initialize().param("capabilities", json!({ "experimental": { "images": {} } })).build();
+        server.handle(init).await;
+        assert_eq!(text(server.handle(call()).await.unwrap()), format!("fixture-client {} true", PROTOCOL_VERSION));
+        assert_eq!(server.session(DEFAULT_SESSION).unwrap().client.unwrap().version, "0.0.0");
+    }
+}
@@ -0,0 +1,222 @@
+//! Session persistence shared between replicas.
+//!
+//! With [`ServerBuilder::session_store`] a server loads a session from the
+//! store when it first sees it and keeps it in memory, loading it again
+//! once it is older than [`ServerBuilder::session_refresh`] (and on every
+//! `initialize`), so it sees what other replicas changed without a round
+//! trip per message, and the store can expire only sessions that went
+//! quiet. It writes sessions back
+//! whenever `initialize`, `resources/(un)subscribe` or `logging/setLevel`
+//! changes them, removes them when the client ends them, and fans
+//! `notifications/resources/updated` out to every replica through the
+//! store's pub/sub, so a client subscribed on one replica hears about
+//! changes made on another. [`MemorySessionStore`] shares state within a
+//! process; `RedisSessionStore` (feature `redis`) across hosts.
+//!
+//! [`ServerBuilder::session_store`]: crate::server::ServerBuilder::session_store
+//! [`ServerBuilder::session_refresh`]: crate::server::ServerBuilder::session_refresh
+
+use crate::error::MCPError;
+use crate::session::SessionState;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// How long a loaded session is served from memory before it is loaded again
+pub const DEFAULT_SESSION_REFRESH: Duration = Duration::from_secs(5);
+
+/// A resource change announced by one replica
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResourceUpdate {
+    /// Replica that published the update
+    pub replica: String,
+    pub uri: String,
+}
+
+#[async_trait]
+pub trait SessionStore: Send + Sync {
+    /// A session's state; stores that expire sessions restart its clock
+    async fn load(&self, session_id: &str) -> Result<Option<SessionState>, MCPError>;
+
+    async fn save(&self, session_id: &str, state: &SessionState) -> Result<(), MCPError>;
+
+    async fn remove(&self, session_id: &str) -> Result<(), MCPError>;
+
+    /// Announce a resource change to every replica
+    async fn publish(&self, update: &ResourceUpdate) -> Result<(), MCPError>;
+
+    /// Updates published from now on by any replica, this one included; a
+    /// store that loses its connection reconnects rather than ending them
+    async fn updates(&self) -> Result<mpsc::UnboundedReceiver<ResourceUpdate>, MCPError>;
+}
+
+/// Store shared by servers in one process, e.g. for tests
+#[derive(Debug, Default)]
+pub struct MemorySessionStore {
+    sessions: Mutex<HashMap<String, SessionState>>,
+    listeners: Mutex<Vec<mpsc::UnboundedSender<ResourceUpdate>>>,
+}
+
+impl MemorySessionStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl SessionStore for MemorySessionStore {
+    async fn load(&self, session_id: &str) -> Result<Option<SessionState>, MCPError> {
+        Ok(self.sessions.lock().unwrap().get(session_id).cloned())
+    }
+
+    async fn save(&self, session_id: &str, state: &SessionState) -> Result<(), MCPError> {
+        self.sessions.lock().unwrap().insert(session_id.to_string(), state.clone());
+        Ok(())
+    }
+
+    async fn remove(&self, session_id: &str) -> Result<(), MCPError> {
+        self.sessions.lock().unwrap().remove(session_id);
+        Ok(())
+    }
+
+    async fn publish(&self, update: &ResourceUpdate) -> Result<(), MCPError> {
+        self.listeners.lock().unwrap().retain(|listener| listener.send(update.clone()).is_ok());
+        Ok(())
+    }
+
+    async fn updates(&self) -> Result<mpsc::UnboundedReceiver<ResourceUpdate>, MCPError> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.listeners.lock().unwrap().push(sender);
+        Ok(receiver)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::RequestContext;
+    use crate::request::MCPRequest;
+    use crate::server::{ReinitializePolicy, SystemMCPServer, ToolHandler};
+    use crate::session::DEFAULT_SESSION;
+    use crate::tools::{Resource, ToolResponse};
+    use serde_json::{json, Value};
+    use std::sync::Arc;
+
+    struct Noop;
+
+    #[async_trait]
+    impl ToolHandler for Noop {
+        async fn call_tool(&self, _name: &str, _args: &Value, _ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+            Ok(ToolResponse::new(String::new(), false))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_replicas_share_sessions_and_updates() {
+        let store = Arc::new(MemorySessionStore::new());
+        let replica = || SystemMCPServer::<Noop>::builder()
+            .session_store(store.clone())
+            // Load on every request, so changes show up at once
+            .session_refresh(Duration::ZERO)
+            .with_resources(vec![Resource::new("file:///a", "a")])
+            .with_subscribe(true)
+            .build(Noop);
+        let (mut first, second) = (replica(), replica());
+        let mut notifications = first.take_notification_receiver().unwrap();
+        first.follow_resource_updates().await.unwrap();
+
+        let request = |method: &str, params: Value| MCPRequest::new(Some(json!(1)), method, Some(params));
+        first.handle(request("initialize", json!({}))).await;
+        first.handle(MCPRequest::new(None, "notifications/initialized", None)).await;
+        first.handle(request("resources/subscribe", json!({ "uri": "file:///a" }))).await;
+
+        // The second replica picks the session up from the store
+        assert!(second.session(DEFAULT_SESSION).is_none());
+        second.handle(request("tools/list", json!({}))).await;
+        assert!(second.session(DEFAULT_SESSION).unwrap().subscriptions.contains("file:///a"));
+
+        // ...and its updates reach the replica holding the subscriber
+        second.notify_resource_updated("file:///a");
+        let notification = tokio::time::timeout(std::time::Duration::from_secs(1), notifications.recv()).await.unwrap().unwrap();
+        assert_eq!(notification.to_json_rpc()["params"]["uri"], "file:///a");
+
+        // Later changes on one replica are seen by the other
+        first.handle(request("resources/subscribe", json!({ "uri": "file:///b" }))).await;
+        second.handle(request("tools/list", json!({}))).await;
+        assert!(second.session(DEFAULT_SESSION).unwrap().subscriptions.contains("file:///b"));
+
+        second.close_session(DEFAULT_SESSION).await;
+        assert!(second.session(DEFAULT_SESSION).is_none());
+        assert_eq!(store.load(DEFAULT_SESSION).await.unwrap(), None);
+    }
+
+    /// Counts loads of the store it wraps
+    #[derive(Default)]
+    struct Counting(MemorySessionStore, std::sync::atomic::AtomicUsize);
+
+    #[async_trait]
+    impl SessionStore for Counting {
+        async fn load(&self, session_id: &str) -> Result<Option<SessionState>, MCPError> {
+            self.1.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            self.0.load(session_id).await
+        }
+
+        async fn save(&self, session_id: &str, state: &SessionState) -> Result<(), MCPError> {
+            self.0.save(session_id, state).await
+        }
+
+        async fn remove(&self, session_id: &str) -> Result<(), MCPError> {
+            self.0.remove(session_id).await
+        }
+
+        async fn publish(&self, update: &ResourceUpdate) -> Result<(), MCPError> {
+            self.0.publish(update).await
+        }
+
+        async fn updates(&self) -> Result<mpsc::UnboundedReceiver<ResourceUpdate>, MCPError> {
+            self.0.updates().await
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sessions_are_loaded_once_per_refresh() {
+        let store = Arc::new(Counting::default());
+        let loads = || store.1.load(std::sync::atomic::Ordering::Relaxed);
+        let server = SystemMCPServer::<Noop>::builder()
+            .relaxed_lifecycle()
+            .session_store(store.clone())
+            .session_refresh(Duration::from_millis(50))
+            .build(Noop);
+        let ping = || MCPRequest::new(Some(json!(1)), "ping", None);
+
+        for _ in 0..5 {
+            server.handle(ping()).await;
+        }
+        assert_eq!(loads(), 1);
+        // `initialize` always looks for a session other replicas started
+        server.handle(MCPRequest::new(Some(json!(2)), "initialize", Some(json!({})))).await;
+        assert_eq!(loads(), 2);
+
+        tokio::time::sleep(Duration::from_millis(60)).await;
+        server.handle(ping()).await;
+        server.handle(ping()).await;
+        assert_eq!(loads(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_reinitialization_is_seen_across_replicas() {
+        let store = Arc::new(MemorySessionStore::new());
+        let replica = || SystemMCPServer::<Noop>::builder()
+            .session_store(store.clone())
+            .reinitialize_policy(ReinitializePolicy::Reject)
+            .build(Noop);
+        let (first, second) = (replica(), replica());
+        let initialize = || MCPRequest::new(Some(json!(1)), "initialize", Some(json!({})));
+
+        assert!(first.handle(initialize()).await.unwrap().error.is_none());
+        assert_eq!(second.handle(initialize()).await.unwrap().error.unwrap().code, -32600);
+    }
+}
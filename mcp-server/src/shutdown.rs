@@ -0,0 +1,131 @@
+//! Graceful shutdown on SIGTERM/SIGINT or on request.
+//!
+//! [`terminate_signal`] resolves on the first termination signal, and
+//! [`SystemMCPServer::shutdown`] asks for the same from code. A runner then
+//! stops reading, gives in-flight requests until a deadline to finish,
+//! cancels the rest with [`SystemMCPServer::cancel_all`] and writes out the
+//! notifications still queued before returning.
+//!
+//! [`SystemMCPServer::shutdown`]: crate::server::SystemMCPServer::shutdown
+//! [`SystemMCPServer::cancel_all`]: crate::server::SystemMCPServer::cancel_all
+
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Default time in-flight requests get to finish after a termination signal
+pub const DEFAULT_SHUTDOWN_DEADLINE: Duration = Duration::from_secs(5);
+
+/// Wait for SIGTERM or SIGINT (Ctrl-C elsewhere) and return its name
+#[cfg(unix)]
+pub async fn terminate_signal() -> &'static str {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    let (Ok(mut term), Ok(mut int)) = (signal(SignalKind::terminate()), signal(SignalKind::interrupt())) else {
+        eprintln!("[SHUTDOWN] Failed to install signal handlers");
+        return std::future::pending().await;
+    };
+    tokio::select! {
+        _ = term.recv() => "SIGTERM",
+        _ = int.recv() => "SIGINT",
+    }
+}
+
+#[cfg(not(unix))]
+pub async fn terminate_signal() -> &'static str {
+    if tokio::signal::ctrl_c().await.is_err() {
+        eprintln!("[SHUTDOWN] Failed to install signal handlers");
+        return std::future::pending().await;
+    }
+    "CTRL_C"
+}
+
+/// Shutdown requests and running runners of one server
+#[derive(Debug)]
+pub(crate) struct ShutdownControl {
+    requested: watch::Sender<bool>,
+    runners: watch::Sender<usize>,
+}
+
+impl ShutdownControl {
+    pub(crate) fn new() -> Self {
+        ShutdownControl { requested: watch::Sender::new(false), runners: watch::Sender::new(0) }
+    }
+
+    /// Returns false if shutdown was already requested
+    pub(crate) fn request(&self) -> bool {
+        !self.requested.send_replace(true)
+    }
+
+    pub(crate) fn is_requested(&self) -> bool {
+        *self.requested.borrow()
+    }
+
+    /// Resolves once shutdown is requested
+    pub(crate) async fn requested(&self) {
+        let _ = self.requested.subscribe().wait_for(|requested| *requested).await;
+    }
+
+    /// Receiver for tasks that outlive a borrow of the server
+    pub(crate) fn watch(&self) -> watch::Receiver<bool> {
+        self.requested.subscribe()
+    }
+
+    /// Count a runner as running until the guard is dropped
+    pub(crate) fn runner(&self) -> RunnerGuard<'_> {
+        self.runners.send_modify(|n| *n += 1);
+        RunnerGuard(self)
+    }
+
+    pub(crate) fn has_runners(&self) -> bool {
+        *self.runners.borrow() > 0
+    }
+
+    pub(crate) async fn runners_finished(&self) {
+        let _ = self.runners.subscribe().wait_for(|n| *n == 0).await;
+    }
+}
+
+pub(crate) struct RunnerGuard<'a>(&'a ShutdownControl);
+
+impl Drop for RunnerGuard<'_> {
+    fn drop(&mut self) {
+        self.0.runners.send_modify(|n| *n -= 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_terminate_signal() {
+        let signal = terminate_signal();
+        tokio::pin!(signal);
+        // Polling once installs the handlers, so the signal below does not
+        // take the test process down
+        assert!(tokio::time::timeout(Duration::from_millis(10), &mut signal).await.is_err());
+        // SAFETY: raise(3) has no memory-safety preconditions
+        unsafe {
+            libc::raise(libc::SIGTERM);
+        }
+        assert_eq!(signal.await, "SIGTERM");
+    }
+
+    #[tokio::test]
+    async fn test_runners_and_requests() {
+        let control = ShutdownControl::new();
+        let runner = control.runner();
+        assert!(control.has_runners());
+        assert!(control.request());
+        assert!(!control.request());
+        control.requested().await;
+
+        let finished = control.runners_finished();
+        tokio::pin!(finished);
+        assert!(tokio::time::timeout(Duration::from_millis(10), &mut finished).await.is_err());
+        drop(runner);
+        finished.await;
+        assert!(control.is_requested() && !control.has_runners());
+    }
+}
@@ -0,0 +1,230 @@
+//! Environment defaults for subprocesses spawned by tools.
+//!
+//! Left alone, a tool's child inherits whatever environment the supervisor
+//! started the server with, so the same command can print differently
+//! encoded output or pick up different binaries on another host.
+//! [`SubprocessEnv`], set with [`ServerBuilder::subprocess_env`], pins the
+//! locale, cleans up `PATH`, overrides `HOME` and can pass through only
+//! allowlisted variables. Handlers get it applied by [`RequestContext::command`];
+//! declarative command tools use it too.
+//!
+//! A tool's own `timeout` argument knows nothing about how long the request
+//! may take: the server's time limit or the client's `_meta.timeoutMs` may
+//! end it first, leaving the process running after nobody waits for it.
+//! [`RequestContext::subprocess_timeout`] clamps such a timeout to the
+//! request deadline minus the [`DeadlinePolicy`] margin, so the tool has time
+//! to reap the process and answer.
+//!
+//! [`ServerBuilder::subprocess_env`]: crate::server::ServerBuilder::subprocess_env
+//! [`RequestContext::command`]: crate::context::RequestContext::command
+//! [`RequestContext::subprocess_timeout`]: crate::context::RequestContext::subprocess_timeout
+
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::ffi::OsString;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::process::Command;
+use tokio::time::Instant;
+
+#[derive(Debug, Clone, Default)]
+pub struct SubprocessEnv {
+    locale: Option<String>,
+    path: Option<Vec<PathBuf>>,
+    sanitize_path: bool,
+    home: Option<PathBuf>,
+    allowlist: Option<BTreeSet<String>>,
+}
+
+impl SubprocessEnv {
+    /// Inherit everything, like a plain [`Command`]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set `LANG` and `LC_ALL`, e.g. to `C.UTF-8`
+    pub fn locale(mut self, locale: impl Into<String>) -> Self {
+        self.locale = Some(locale.into());
+        self
+    }
+
+    /// Replace `PATH` with `dirs`
+    pub fn path<I, P>(mut self, dirs: I) -> Self
+    where
+        I: IntoIterator<Item = P>,
+        P: Into<PathBuf>,
+    {
+        self.path = Some(dirs.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Drop empty, relative and repeated `PATH` entries
+    pub fn sanitize_path(mut self) -> Self {
+        self.sanitize_path = true;
+        self
+    }
+
+    pub fn home(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.home = Some(dir.into());
+        self
+    }
+
+    /// Pass through only these variables (plus `PATH`) instead of the whole
+    /// environment; may be called repeatedly
+    pub fn allow_env<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.allowlist.get_or_insert_with(BTreeSet::new).extend(names.into_iter().map(Into::into));
+        self
+    }
+
+    /// Apply the defaults to `command`; variables set on it afterwards still win
+    pub fn apply(&self, command: &mut Command) {
+        if let Some(allowed) = &self.allowlist {
+            command.env_clear();
+            command.envs(std::env::vars_os().filter(|(key, _)| {
+                key == "PATH" || key.to_str().is_some_and(|key| allowed.contains(key))
+            }));
+        }
+        if let Some(path) = self.path_value() {
+            command.env("PATH", path);
+        }
+        if let Some(home) = &self.home {
+            command.env("HOME", home);
+        }
+        if let Some(locale) = &self.locale {
+            command.env("LANG", locale).env("LC_ALL", locale);
+        }
+    }
+
+    /// `PATH` for the child, if it differs from the inherited one
+    fn path_value(&self) -> Option<OsString> {
+        let dirs: Vec<PathBuf> = match &self.path {
+            Some(dirs) => dirs.clone(),
+            None if self.sanitize_path => std::env::split_paths(&std::env::var_os("PATH")?).collect(),
+            None => return None,
+        };
+        let mut seen = BTreeSet::new();
+        let dirs = dirs.into_iter()
+            .filter(|dir| !self.sanitize_path || dir.is_absolute())
+            .filter(|dir| seen.insert(dir.clone()));
+        std::env::join_paths(dirs).ok()
+    }
+}
+
+/// How far before the request deadline subprocesses must be done
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeadlinePolicy {
+    margin: Duration,
+    minimum: Duration,
+}
+
+impl Default for DeadlinePolicy {
+    fn default() -> Self {
+        DeadlinePolicy { margin: Duration::from_secs(1), minimum: Duration::from_millis(100) }
+    }
+}
+
+impl DeadlinePolicy {
+    /// A one second margin, never clamping below 100ms
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Time left between the process being killed and the deadline
+    pub fn margin(mut self, margin: Duration) -> Self {
+        self.margin = margin;
+        self
+    }
+
+    /// Shortest timeout clamping produces, however close the deadline
+    pub fn minimum(mut self, minimum: Duration) -> Self {
+        self.minimum = minimum;
+        self
+    }
+
+    /// `requested`, or less if it would run past `deadline` minus the margin
+    pub fn clamp(&self, requested: Duration, deadline: Option<Instant>) -> Duration {
+        let Some(deadline) = deadline else { return requested };
+        let available = deadline.saturating_duration_since(Instant::now()).saturating_sub(self.margin);
+        requested.min(available.max(self.minimum))
+    }
+}
+
+/// The client's `_meta.timeoutMs`, if it set one
+pub fn client_timeout(meta: Option<&Value>) -> Option<Duration> {
+    meta?.get("timeoutMs")?.as_u64().map(Duration::from_millis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::RequestContext;
+    use crate::error::MCPError;
+    use crate::server::{SystemMCPServer, ToolHandler};
+    use crate::testing::fixtures::{self, RequestBuilder};
+    use crate::tools::ToolResponse;
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_defaults_reach_the_child() {
+        let env = SubprocessEnv::new()
+            .locale("C.UTF-8")
+            .path(["/usr/bin", "bin", "/bin", "/usr/bin"])
+            .sanitize_path()
+            .home("/tmp")
+            .allow_env(["TERM"]);
+        let mut command = Command::new("sh");
+        command.args(["-c", "echo \"$LANG|$LC_ALL|$HOME|$PATH|${CARGO_PKG_NAME-unset}\""]);
+        env.apply(&mut command);
+        let output = command.output().await.unwrap();
+        assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "C.UTF-8|C.UTF-8|/tmp|/usr/bin:/bin|unset");
+    }
+
+    #[test]
+    fn test_clamp_to_deadline() {
+        let policy = DeadlinePolicy::new();
+        let deadline = Instant::now() + Duration::from_secs(10);
+        let clamped = policy.clamp(Duration::from_secs(30), Some(deadline));
+        assert!(clamped <= Duration::from_secs(9) && clamped > Duration::from_secs(8));
+        assert_eq!(policy.clamp(Duration::from_secs(5), Some(deadline)), Duration::from_secs(5));
+        assert_eq!(policy.clamp(Duration::from_secs(30), None), Duration::from_secs(30));
+        assert_eq!(policy.clamp(Duration::from_secs(30), Some(Instant::now())), Duration::from_millis(100));
+
+        assert_eq!(client_timeout(Some(&serde_json::json!({ "timeoutMs": 2500 }))), Some(Duration::from_millis(2500)));
+        assert_eq!(client_timeout(None), None);
+    }
+
+    struct Timeouts;
+
+    #[async_trait::async_trait]
+    impl ToolHandler for Timeouts {
+        async fn call_tool(&self, _name: &str, args: &Value, ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+            let requested = Duration::from_secs(args["timeout"].as_u64().unwrap());
+            Ok(ToolResponse::new(ctx.subprocess_timeout(requested).as_secs_f64().round().to_string(), false))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_server_clamps_to_client_timeout() {
+        let server = SystemMCPServer::<Timeouts>::builder()
+            .relaxed_lifecycle()
+            .tool_timeout("run", Duration::from_secs(60))
+            .deadline_policy(DeadlinePolicy::new().margin(Duration::from_secs(2)))
+            .build(Timeouts);
+        let timeout = |call: RequestBuilder| {
+            let server = &server;
+            async move { server.handle(call.build()).await.unwrap().result.unwrap()["content"][0]["text"].clone() }
+        };
+        assert_eq!(timeout(fixtures::call_tool("run").arg("timeout", 300)).await, "58");
+        assert_eq!(timeout(fixtures::call_tool("run").arg("timeout", 300).meta("timeoutMs", 10_000)).await, "8");
+        assert_eq!(timeout(fixtures::call_tool("run").arg("timeout", 5).meta("timeoutMs", 10_000)).await, "5");
+    }
+
+    #[test]
+    fn test_untouched_by_default() {
+        assert!(SubprocessEnv::new().path_value().is_none());
+    }
+}
@@ -0,0 +1,227 @@
+//! Builders and canned values for protocol messages.
+//!
+//! ```
+//! use mcp_server::testing::fixtures::request;
+//!
+//! let req = request("tools/call").id(1).tool("bash").arg("command", "ls").build();
+//! assert_eq!(req.params.unwrap()["arguments"]["command"], "ls");
+//! ```
+
+use crate::error::{JsonRpcError, MCPError};
+use crate::notifications::ServerNotification;
+use crate::request::MCPRequest;
+use crate::response::MCPResponse;
+use crate::server::PROTOCOL_VERSION;
+use crate::tools::ToolResponse;
+use serde_json::{json, Map, Value};
+
+/// Builds an [`MCPRequest`] field by field
+#[derive(Debug, Clone)]
+pub struct RequestBuilder {
+    id: Option<Value>,
+    method: String,
+    params: Option<Value>,
+    // First param that could not be set
+    error: Option<String>,
+}
+
+/// A request for `method` with id 1 and no params
+pub fn request(method: impl Into<String>) -> RequestBuilder {
+    RequestBuilder { id: Some(json!(1)), method: method.into(), params: None, error: None }
+}
+
+/// A notification for `method`: no id, no params
+pub fn notification(method: impl Into<String>) -> RequestBuilder {
+    RequestBuilder { id: None, method: method.into(), params: None, error: None }
+}
+
+impl RequestBuilder {
+    pub fn id(mut self, id: impl Into<Value>) -> Self {
+        self.id = Some(id.into());
+        self
+    }
+
+    /// Send as a notification
+    pub fn no_id(mut self) -> Self {
+        self.id = None;
+        self
+    }
+
+    /// Set a top-level param
+    pub fn param(mut self, key: &str, value: impl Into<Value>) -> Self {
+        if let Some(params) = self.params_mut(key) {
+            params.insert(key.into(), value.into());
+        }
+        self
+    }
+
+    /// Replace all params
+    pub fn params(mut self, params: Value) -> Self {
+        self.params = Some(params);
+        self
+    }
+
+    /// `params.name`, as used by `tools/call` and `prompts/get`
+    pub fn tool(self, name: &str) -> Self {
+        self.param("name", name)
+    }
+
+    /// One entry of `params.arguments`
+    pub fn arg(mut self, key: &str, value: impl Into<Value>) -> Self {
+        if let Some(arguments) = self.nested("arguments", key) {
+            arguments.insert(key.into(), value.into());
+        }
+        self
+    }
+
+    /// One entry of `params._meta`
+    pub fn meta(mut self, key: &str, value: impl Into<Value>) -> Self {
+        if let Some(meta) = self.nested("_meta", key) {
+            meta.insert(key.into(), value.into());
+        }
+        self
+    }
+
+    /// `params._meta.progressToken`
+    pub fn progress_token(self, token: impl Into<Value>) -> Self {
+        self.meta("progressToken", token)
+    }
+
+    /// The request, failing if a param was added to params that are not an
+    /// object, e.g. after [`params`](Self::params) set an array
+    pub fn try_build(self) -> Result<MCPRequest, MCPError> {
+        match self.error {
+            Some(error) => Err(MCPError::InvalidParams(error)),
+            None => Ok(MCPRequest::new(self.id, self.method, self.params)),
+        }
+    }
+
+    /// The request; panics where [`try_build`](Self::try_build) fails
+    pub fn build(self) -> MCPRequest {
+        self.try_build().unwrap_or_else(|e| panic!("{}", e))
+    }
+
+    /// The request as it would appear on the wire
+    pub fn to_json(self) -> Value {
+        serde_json::to_value(self.build()).expect("requests serialize")
+    }
+
+    /// The params to set `key` in, `None` (recording the error) when they
+    /// are not an object
+    fn params_mut(&mut self, key: &str) -> Option<&mut Map<String, Value>> {
+        match self.params.get_or_insert_with(|| json!({})) {
+            Value::Object(params) => Some(params),
+            params => {
+                self.error.get_or_insert(format!("cannot set {} in params {}", key, params));
+                None
+            }
+        }
+    }
+
+    /// `params.<field>` to set `key` in, like [`params_mut`](Self::params_mut)
+    fn nested(&mut self, field: &str, key: &str) -> Option<&mut Map<String, Value>> {
+        let params = self.params_mut(field)?;
+        if let Some(nested) = params.get(field).filter(|nested| !nested.is_object()) {
+            let error = format!("cannot set {} in {} {}", key, field, nested);
+            self.error.get_or_insert(error);
+            return None;
+        }
+        self.params_mut(field)?.entry(field).or_insert_with(|| json!({})).as_object_mut()
+    }
+}
+
+/// `initialize` with the server's protocol version and a test client
+pub fn initialize() -> RequestBuilder {
+    request("initialize")
+        .param("protocolVersion", PROTOCOL_VERSION)
+        .param("capabilities", json!({}))
+        .param("clientInfo", json!({ "name": "fixture-client", "version": "0.0.0" }))
+}
+
+/// `tools/call` for `name` with no arguments yet
+pub fn call_tool(name: &str) -> RequestBuilder {
+    request("tools/call").tool(name).param("arguments", json!({}))
+}
+
+/// `notifications/cancelled` for `request_id`
+pub fn cancelled(request_id: impl Into<Value>, reason: Option<&str>) -> MCPRequest {
+    let mut builder = notification("notifications/cancelled").param("requestId", request_id);
+    if let Some(reason) = reason {
+        builder = builder.param("reason", reason);
+    }
+    builder.build()
+}
+
+/// A success response carrying `result`
+pub fn success(id: impl Into<Value>, result: Value) -> MCPResponse {
+    MCPResponse::success(Some(id.into()), result)
+}
+
+/// An error response with `code` and `message`
+pub fn error(id: impl Into<Value>, code: i32, message: &str) -> MCPResponse {
+    MCPResponse::error(Some(id.into()), JsonRpcError { code, message: message.into(), data: None })
+}
+
+/// A `tools/call` response with a single text block
+pub fn tool_result(id: impl Into<Value>, text: &str, is_error: bool) -> MCPResponse {
+    let result = serde_json::to_value(ToolResponse::new(text.into(), is_error)).expect("tool responses serialize");
+    success(id, result)
+}
+
+/// A progress notification for `request_id`
+pub fn progress(request_id: &str, progress: f64, message: Option<&str>) -> ServerNotification {
+    ServerNotification::Progress {
+        request_id: request_id.into(),
+        progress,
+        message: message.map(String::from),
+        meta: None,
+    }
+}
+
+/// A resource update notification for `uri`
+pub fn resource_updated(uri: &str) -> ServerNotification {
+    ServerNotification::ResourceUpdated { uri: uri.into() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_builder() {
+        let req = request("tools/call").id("a").tool("bash").arg("command", "ls").progress_token(7).to_json();
+        assert_eq!(req, json!({
+            "jsonrpc": "2.0",
+            "id": "a",
+            "method": "tools/call",
+            "params": {
+                "name": "bash",
+                "arguments": { "command": "ls" },
+                "_meta": { "progressToken": 7 },
+            },
+        }));
+
+        let note = cancelled(3, Some("timeout"));
+        assert!(note.is_notification());
+        assert_eq!(note.params.unwrap(), json!({ "requestId": 3, "reason": "timeout" }));
+        assert_eq!(request("batch").params(json!([1, 2])).build().params.unwrap(), json!([1, 2]));
+    }
+
+    #[test]
+    fn test_params_that_are_not_objects() {
+        let Err(MCPError::InvalidParams(message)) = request("batch").params(json!([1, 2])).param("cursor", "a").try_build() else {
+            panic!("param replaced the array");
+        };
+        assert_eq!(message, "cannot set cursor in params [1,2]");
+        assert!(request("x").params(json!({ "arguments": 7 })).arg("path", "/").try_build().is_err());
+        assert!(request("x").params(json!({ "name": "a" })).arg("path", "/").try_build().is_ok());
+    }
+
+    #[test]
+    fn test_canned_responses() {
+        let response = tool_result(1, "hi", false);
+        assert_eq!(response.result.unwrap()["content"][0]["text"], "hi");
+        assert_eq!(error(2, -32601, "nope").error.unwrap().code, -32601);
+        assert_eq!(resource_updated("file:///a").to_json_rpc()["params"]["uri"], "file:///a");
+    }
+}
@@ -0,0 +1,298 @@
+//! A [`ToolHandler`] whose answers are scripted per call.
+//!
+//! Tests of the router, cancellation or middleware need a handler that fails,
+//! stalls or panics on cue, not a real one. [`MockToolHandler`] answers each
+//! tool, prompt and resource from a queue of [`Reply`]s: calls take them in
+//! order and the last one repeats. Every call is recorded for assertions.
+//!
+//! ```
+//! use mcp_server::testing::mock::{MockToolHandler, Reply};
+//! use mcp_server::error::MCPError;
+//! use std::time::Duration;
+//!
+//! let mock = MockToolHandler::new()
+//!     .tool("fetch", Reply::error(|| MCPError::CommandTimeout))
+//!     .tool("fetch", Reply::text("ok").after(Duration::from_millis(10)));
+//! ```
+
+use crate::context::RequestContext;
+use crate::error::MCPError;
+use crate::server::ToolHandler;
+use crate::tools::{PromptResponse, ResourceContent, Tool, ToolResponse};
+use async_trait::async_trait;
+use serde_json::Value;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How a mocked method answers one call
+#[derive(Clone)]
+pub struct Reply<T> {
+    outcome: Outcome<T>,
+    delay: Option<Duration>,
+}
+
+#[derive(Clone)]
+enum Outcome<T> {
+    Value(T),
+    // MCPError is not Clone, and the last reply repeats
+    Error(Arc<dyn Fn() -> MCPError + Send + Sync>),
+    Panic(String),
+    Hang,
+}
+
+impl<T> Reply<T> {
+    pub fn value(value: T) -> Self {
+        Reply { outcome: Outcome::Value(value), delay: None }
+    }
+
+    /// Fail with the error `error` builds
+    pub fn error(error: impl Fn() -> MCPError + Send + Sync + 'static) -> Self {
+        Reply { outcome: Outcome::Error(Arc::new(error)), delay: None }
+    }
+
+    pub fn panic(message: impl Into<String>) -> Self {
+        Reply { outcome: Outcome::Panic(message.into()), delay: None }
+    }
+
+    /// Never answer; only cancellation or a timeout ends the call
+    pub fn hang() -> Self {
+        Reply { outcome: Outcome::Hang, delay: None }
+    }
+
+    /// Wait `delay` before answering
+    pub fn after(mut self, delay: Duration) -> Self {
+        self.delay = Some(delay);
+        self
+    }
+
+    async fn play(self) -> Result<T, MCPError> {
+        if let Some(delay) = self.delay {
+            tokio::time::sleep(delay).await;
+        }
+        match self.outcome {
+            Outcome::Value(value) => Ok(value),
+            Outcome::Error(error) => Err(error()),
+            Outcome::Panic(message) => panic!("{}", message),
+            Outcome::Hang => std::future::pending().await,
+        }
+    }
+}
+
+impl Reply<ToolResponse> {
+    /// A successful tool result with one text block
+    pub fn text(text: impl Into<String>) -> Self {
+        Reply::value(ToolResponse::new(text.into(), false))
+    }
+}
+
+/// One recorded call
+#[derive(Debug, Clone, PartialEq)]
+pub struct Call {
+    /// `tools/call`, `tools/list`, `prompts/get` or `resources/read`
+    pub method: &'static str,
+    /// Tool or prompt name, or resource URI; empty for lists
+    pub name: String,
+    pub args: Value,
+    pub request_id: String,
+}
+
+/// Replies queued per name; the last one repeats
+struct Script<T>(Mutex<HashMap<String, VecDeque<Reply<T>>>>);
+
+impl<T: Clone> Script<T> {
+    fn push(&self, name: &str, reply: Reply<T>) {
+        self.0.lock().unwrap().entry(name.into()).or_default().push_back(reply);
+    }
+
+    fn len(&self, name: &str) -> usize {
+        self.0.lock().unwrap().get(name).map_or(0, VecDeque::len)
+    }
+
+    fn next(&self, name: &str) -> Option<Reply<T>> {
+        let mut scripts = self.0.lock().unwrap();
+        let queue = scripts.get_mut(name)?;
+        if queue.len() > 1 { queue.pop_front() } else { queue.front().cloned() }
+    }
+}
+
+impl<T> Default for Script<T> {
+    fn default() -> Self {
+        Script(Mutex::default())
+    }
+}
+
+/// Scripted [`ToolHandler`]; unscripted tools, prompts and resources are unknown
+#[derive(Default)]
+pub struct MockToolHandler {
+    tools: Script<ToolResponse>,
+    tool_lists: Script<Vec<Tool>>,
+    prompts: Script<PromptResponse>,
+    resources: Script<ResourceContent>,
+    calls: Mutex<Vec<Call>>,
+    cancelled: Mutex<Vec<String>>,
+    initialized: Mutex<Vec<String>>,
+}
+
+impl MockToolHandler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queue the answer to the next unanswered call of tool `name`
+    pub fn tool(self, name: &str, reply: Reply<ToolResponse>) -> Self {
+        self.tools.push(name, reply);
+        self
+    }
+
+    /// Queue an answer to `tools/list` (runtime tools only)
+    pub fn list_tools(self, reply: Reply<Vec<Tool>>) -> Self {
+        self.tool_lists.push("", reply);
+        self
+    }
+
+    pub fn prompt(self, name: &str, reply: Reply<PromptResponse>) -> Self {
+        self.prompts.push(name, reply);
+        self
+    }
+
+    pub fn resource(self, uri: &str, reply: Reply<ResourceContent>) -> Self {
+        self.resources.push(uri, reply);
+        self
+    }
+
+    /// Every call so far, in order
+    pub fn calls(&self) -> Vec<Call> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// Arguments of each call of tool `name`
+    pub fn calls_to(&self, name: &str) -> Vec<Value> {
+        self.calls.lock().unwrap().iter()
+            .filter(|call| call.method == "tools/call" && call.name == name)
+            .map(|call| call.args.clone())
+            .collect()
+    }
+
+    /// Ids of requests the server reported as cancelled
+    pub fn cancelled(&self) -> Vec<String> {
+        self.cancelled.lock().unwrap().clone()
+    }
+
+    /// Sessions that completed the handshake
+    pub fn initialized(&self) -> Vec<String> {
+        self.initialized.lock().unwrap().clone()
+    }
+
+    #[track_caller]
+    pub fn assert_called(&self, name: &str, times: usize) {
+        let calls = self.calls_to(name).len();
+        assert_eq!(calls, times, "tool {} was called {} times, expected {}", name, calls, times);
+    }
+
+    #[track_caller]
+    pub fn assert_called_with(&self, name: &str, args: &Value) {
+        let calls = self.calls_to(name);
+        assert!(calls.contains(args), "tool {} was never called with {}; calls: {:?}", name, args, calls);
+    }
+
+    fn record(&self, method: &'static str, name: &str, args: &Value, ctx: &RequestContext) {
+        self.calls.lock().unwrap().push(Call {
+            method,
+            name: name.into(),
+            args: args.clone(),
+            request_id: ctx.request_id().into(),
+        });
+    }
+}
+
+#[async_trait]
+impl ToolHandler for MockToolHandler {
+    async fn call_tool(&self, name: &str, args: &Value, ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+        self.record("tools/call", name, args, ctx);
+        let reply = self.tools.next(name).ok_or_else(|| MCPError::UnknownTool(name.into()))?;
+        reply.play().await
+    }
+
+    async fn list_tools(&self, ctx: &RequestContext) -> Result<Vec<Tool>, MCPError> {
+        self.record("tools/list", "", &Value::Null, ctx);
+        match self.tool_lists.next("") {
+            Some(reply) => reply.play().await,
+            None => Ok(vec![]),
+        }
+    }
+
+    /// Listings only change while more than one is queued
+    fn tools_generation(&self) -> Option<u64> {
+        (self.tool_lists.len("") <= 1).then_some(0)
+    }
+
+    async fn get_prompt(&self, name: &str, args: &Value, ctx: &RequestContext) -> Result<PromptResponse, MCPError> {
+        self.record("prompts/get", name, args, ctx);
+        let reply = self.prompts.next(name).ok_or_else(|| MCPError::UnknownPrompt(name.into()))?;
+        reply.play().await
+    }
+
+    async fn read_resource(&self, uri: &str, ctx: &RequestContext) -> Result<ResourceContent, MCPError> {
+        self.record("resources/read", uri, &Value::Null, ctx);
+        let reply = self.resources.next(uri).ok_or_else(|| MCPError::ResourceNotFound(uri.into()))?;
+        reply.play().await
+    }
+
+    async fn on_request_cancelled(&self, request_id: &str, _reason: Option<&str>) {
+        self.cancelled.lock().unwrap().push(request_id.into());
+    }
+
+    async fn on_initialized(&self, session_id: &str) {
+        self.initialized.lock().unwrap().push(session_id.into());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::SystemMCPServer;
+    use crate::testing::fixtures;
+    use serde_json::json;
+
+    #[tokio::test]
+    async fn test_scripted_replies() {
+        let mock = MockToolHandler::new()
+            .tool("fetch", Reply::error(|| MCPError::CommandTimeout))
+            .tool("fetch", Reply::text("ok"));
+        let ctx = RequestContext::default();
+        assert!(matches!(mock.call_tool("fetch", &json!({ "n": 1 }), &ctx).await, Err(MCPError::CommandTimeout)));
+        for n in 2..4 {
+            assert!(mock.call_tool("fetch", &json!({ "n": n }), &ctx).await.is_ok());
+        }
+        assert!(matches!(mock.call_tool("other", &json!({}), &ctx).await, Err(MCPError::UnknownTool(_))));
+        mock.assert_called("fetch", 3);
+        mock.assert_called_with("fetch", &json!({ "n": 2 }));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_hanging_call() {
+        let server = Arc::new(SystemMCPServer::<MockToolHandler>::builder()
+            .relaxed_lifecycle()
+            .build(MockToolHandler::new().tool("slow", Reply::hang())));
+        let call = tokio::spawn({
+            let server = server.clone();
+            async move { server.handle(fixtures::call_tool("slow").id("c-1").build()).await }
+        });
+        while server.handler().calls().is_empty() {
+            tokio::task::yield_now().await;
+        }
+        server.handle(fixtures::cancelled("c-1", None)).await;
+
+        let response = call.await.unwrap().unwrap();
+        assert_eq!(response.error.unwrap().code, -32800);
+        assert_eq!(server.handler().cancelled(), ["c-1"]);
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "scripted")]
+    async fn test_panic() {
+        let mock = MockToolHandler::new().tool("boom", Reply::panic("scripted"));
+        let _ = mock.call_tool("boom", &json!({}), &RequestContext::default()).await;
+    }
+}
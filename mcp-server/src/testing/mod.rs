@@ -0,0 +1,4 @@
+//! Helpers for testing handlers and servers without hand-written JSON.
+
+pub mod fixtures;
+pub mod mock;
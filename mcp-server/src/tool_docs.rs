@@ -0,0 +1,185 @@
+//! Markdown documentation resources for tools.
+//!
+//! With [`ServerBuilder::tool_docs`] every tool gets a resource
+//! `tool://{name}/docs` rendered from its `tools/list` entry: title and
+//! description, an argument table from the input schema, behavior hints
+//! from the annotations and examples from `_meta.examples` (a list of
+//! `{ "description": ..., "arguments": {...} }`). Tools given to the builder
+//! are listed in `resources/list`; runtime tools' docs can be read all the same.
+//!
+//! [`ServerBuilder::tool_docs`]: crate::server::ServerBuilder::tool_docs
+
+use crate::tools::{Resource, ResourceContent};
+use serde_json::Value;
+use std::fmt::Write;
+
+/// URI scheme of tool docs
+pub const TOOL_DOCS_SCHEME: &str = "tool://";
+
+/// Docs URI of tool `name`
+pub fn docs_uri(name: &str) -> String {
+    format!("{}{}/docs", TOOL_DOCS_SCHEME, name)
+}
+
+/// Tool named by a docs URI
+pub fn tool_name(uri: &str) -> Option<&str> {
+    uri.strip_prefix(TOOL_DOCS_SCHEME)?.strip_suffix("/docs").filter(|name| !name.is_empty())
+}
+
+/// Listing entry for the docs of a `tools/list` entry
+pub fn resource(tool: &Value) -> Option<Resource> {
+    let name = tool.get("name")?.as_str()?;
+    Some(Resource::new(docs_uri(name), format!("{} docs", name))
+        .with_description(format!("Documentation of the {} tool", name))
+        .with_mime_type("text/markdown"))
+}
+
+/// Docs of a `tools/list` entry as resource contents
+pub fn read(tool: &Value) -> ResourceContent {
+    let name = tool.get("name").and_then(Value::as_str).unwrap_or_default();
+    ResourceContent {
+        uri: docs_uri(name),
+        mime_type: "text/markdown".into(),
+        text: render(tool),
+        blob: None,
+    }
+}
+
+/// Render a `tools/list` entry as markdown
+pub fn render(tool: &Value) -> String {
+    let text = |value: Option<&Value>| value.and_then(Value::as_str).unwrap_or_default().to_string();
+    let name = text(tool.get("name"));
+    let annotations = tool.get("annotations");
+    let title = annotations.and_then(|a| a.get("title")).and_then(Value::as_str).unwrap_or(&name);
+
+    let mut doc = format!("# {}\n\n", title);
+    if title != name {
+        let _ = writeln!(doc, "Tool name: `{}`\n", name);
+    }
+    let description = text(tool.get("description"));
+    if !description.is_empty() {
+        let _ = writeln!(doc, "{}\n", description);
+    }
+
+    doc.push_str("## Arguments\n\n");
+    let schema = tool.get("inputSchema");
+    let required: Vec<&str> = schema.and_then(|s| s.get("required")).and_then(Value::as_array)
+        .map(|names| names.iter().filter_map(Value::as_str).collect())
+        .unwrap_or_default();
+    match schema.and_then(|s| s.get("properties")).and_then(Value::as_object).filter(|p| !p.is_empty()) {
+        Some(properties) => {
+            doc.push_str("| Name | Type | Required | Description |\n|---|---|---|---|\n");
+            for (argument, property) in properties {
+                let mut description = text(property.get("description"));
+                if let Some(default) = property.get("default") {
+                    let _ = write!(description, " (default: `{}`)", default);
+                }
+                let _ = writeln!(
+                    doc, "| `{}` | {} | {} | {} |",
+                    argument,
+                    text(property.get("type")),
+                    if required.contains(&argument.as_str()) { "yes" } else { "no" },
+                    description.trim().replace('|', "\\|").replace('\n', " "),
+                );
+            }
+            doc.push('\n');
+        }
+        None => doc.push_str("None.\n\n"),
+    }
+
+    // Spec defaults: not read-only, destructive, not idempotent, open world
+    let hint = |name: &str| annotations.and_then(|a| a.get(name)).and_then(Value::as_bool);
+    let read_only = hint("readOnlyHint") == Some(true);
+    doc.push_str("## Behavior\n\n");
+    let _ = writeln!(doc, "- Read-only: {}", yes_no(read_only));
+    if !read_only {
+        let _ = writeln!(doc, "- Destructive: {}", yes_no(hint("destructiveHint") != Some(false)));
+        let _ = writeln!(doc, "- Idempotent: {}", yes_no(hint("idempotentHint") == Some(true)));
+    }
+    let _ = writeln!(doc, "- Interacts with external systems: {}", yes_no(hint("openWorldHint") != Some(false)));
+
+    let examples = tool.get("_meta").and_then(|m| m.get("examples")).and_then(Value::as_array);
+    if let Some(examples) = examples.filter(|examples| !examples.is_empty()) {
+        doc.push_str("\n## Examples\n");
+        for example in examples {
+            let description = text(example.get("description"));
+            if !description.is_empty() {
+                let _ = write!(doc, "\n{}\n", description);
+            }
+            let arguments = example.get("arguments").unwrap_or(example);
+            let pretty = serde_json::to_string_pretty(arguments).unwrap_or_default();
+            let _ = write!(doc, "\n```json\n{}\n```\n", pretty);
+        }
+    }
+    doc
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::context::RequestContext;
+    use crate::error::MCPError;
+    use crate::server::{SystemMCPServer, ToolHandler};
+    use crate::testing::fixtures;
+    use crate::tools::{Tool, ToolInputSchema, ToolResponse};
+    use serde_json::json;
+
+    struct Noop;
+
+    #[async_trait::async_trait]
+    impl ToolHandler for Noop {
+        async fn call_tool(&self, _name: &str, _args: &Value, _ctx: &RequestContext) -> Result<ToolResponse, MCPError> {
+            Ok(ToolResponse::new(String::new(), false))
+        }
+    }
+
+    #[test]
+    fn test_render() {
+        let tool = json!({
+            "name": "grep",
+            "description": "Search files",
+            "inputSchema": {
+                "type": "object",
+                "properties": {
+                    "pattern": { "type": "string", "description": "Regex | literal" },
+                    "limit": { "type": "number", "description": "Max hits", "default": 100 },
+                },
+                "required": ["pattern"],
+            },
+            "annotations": { "title": "Search", "readOnlyHint": true },
+            "_meta": { "examples": [{ "description": "Find TODOs", "arguments": { "pattern": "TODO" } }] },
+        });
+        let doc = render(&tool);
+        assert!(doc.starts_with("# Search\n\nTool name: `grep`\n\nSearch files\n"));
+        assert!(doc.contains("| `pattern` | string | yes | Regex \\| literal |"));
+        assert!(doc.contains("| `limit` | number | no | Max hits (default: `100`) |"));
+        assert!(doc.contains("- Read-only: yes\n- Interacts with external systems: yes"));
+        assert!(doc.contains("Find TODOs\n\n```json\n{\n  \"pattern\": \"TODO\"\n}\n```"));
+
+        assert_eq!(tool_name(&docs_uri("grep")), Some("grep"));
+        assert_eq!(tool_name("tool:///docs"), None);
+    }
+
+    #[tokio::test]
+    async fn test_docs_resources() {
+        let schema = ToolInputSchema { schema_type: "object".into(), properties: Default::default(), required: vec![] };
+        let server = SystemMCPServer::<Noop>::builder()
+            .relaxed_lifecycle()
+            .with_tools(vec![Tool::new("bash", "Run a command", schema)])
+            .tool_docs()
+            .build(Noop);
+        let listed = server.handle(fixtures::request("resources/list").build()).await.unwrap().result.unwrap();
+        assert_eq!(listed["resources"][0]["uri"], "tool://bash/docs");
+
+        let read = fixtures::request("resources/read").param("uri", "tool://bash/docs").build();
+        let docs = server.handle(read).await.unwrap().result.unwrap();
+        assert_eq!(docs["mimeType"], "text/markdown");
+        assert!(docs["text"].as_str().unwrap().starts_with("# bash\n\nRun a command\n\n## Arguments\n\nNone."));
+        let missing = fixtures::request("resources/read").param("uri", "tool://rm/docs").build();
+        assert!(server.handle(missing).await.unwrap().error.is_some());
+    }
+}
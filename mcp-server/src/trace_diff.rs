@@ -0,0 +1,265 @@
+//! Compare the wire behaviour of two server builds.
+//!
+//! A transcript (a [`journal`](crate::journal) file or plain JSON lines of
+//! requests) is played against two server commands over stdio. Every
+//! response and the notifications emitted before it are captured per
+//! request, and the differences are reported as a [`TraceDiff`]. Used to
+//! check that refactors do not change what clients see. Responses are
+//! matched by id: one that arrives after its request timed out is dropped
+//! rather than counted against the next request.
+
+use crate::error::MCPError;
+use crate::journal::JournalEntry;
+use serde::Serialize;
+use serde_json::Value;
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, Lines};
+use tokio::process::{Child, ChildStdin, ChildStdout, Command};
+
+/// How long to wait for one response before recording a timeout
+pub const DEFAULT_RESPONSE_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Everything a server sent in reaction to one request
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Observed {
+    pub response: Option<Value>,
+    pub notifications: Vec<Value>,
+    #[serde(rename = "timedOut", skip_serializing_if = "std::ops::Not::not")]
+    pub timed_out: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RequestDiff {
+    pub seq: usize,
+    pub request: Value,
+    pub left: Observed,
+    pub right: Observed,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TraceDiff {
+    pub requests: usize,
+    pub diffs: Vec<RequestDiff>,
+}
+
+impl TraceDiff {
+    pub fn is_identical(&self) -> bool {
+        self.diffs.is_empty()
+    }
+}
+
+/// Load requests from a journal or from one JSON request per line
+pub fn read_transcript(path: impl AsRef<Path>) -> Result<Vec<Value>, MCPError> {
+    let text = std::fs::read_to_string(path)?;
+    let mut requests = Vec::new();
+    for line in text.lines().filter(|line| !line.trim().is_empty()) {
+        let value: Value = serde_json::from_str(line)?;
+        match serde_json::from_value::<JournalEntry>(value.clone()) {
+            Ok(entry) => requests.push(serde_json::from_str(&entry.request)?),
+            Err(_) => requests.push(value),
+        }
+    }
+    Ok(requests)
+}
+
+/// Split a server command line into words the way a POSIX shell does:
+/// whitespace separates words except inside single or double quotes, and a
+/// backslash escapes the next character (in double quotes only `"`, `\\`,
+/// `$` and `` ` ``)
+pub fn split_command(command: &str) -> Result<Vec<String>, MCPError> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut chars = command.chars();
+    let unterminated = || MCPError::InvalidParams(format!("unterminated quote in {:?}", command));
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => words.extend(word.take()),
+            '\'' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next().ok_or_else(unterminated)? {
+                        '\'' => break,
+                        c => word.push(c),
+                    }
+                }
+            }
+            '"' => {
+                let word = word.get_or_insert_with(String::new);
+                loop {
+                    match chars.next().ok_or_else(unterminated)? {
+                        '"' => break,
+                        '\\' => match chars.next().ok_or_else(unterminated)? {
+                            c @ ('"' | '\\' | '$' | '`') => word.push(c),
+                            c => word.extend(['\\', c]),
+                        },
+                        c => word.push(c),
+                    }
+                }
+            }
+            '\\' => word.get_or_insert_with(String::new).extend(chars.next()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// A server process driven over stdio
+struct Peer {
+    child: Child,
+    stdin: ChildStdin,
+    stdout: Lines<BufReader<ChildStdout>>,
+}
+
+impl Peer {
+    fn spawn(command: &[String]) -> Result<Self, MCPError> {
+        let (program, args) = command.split_first()
+            .ok_or_else(|| MCPError::InvalidParams("empty server command".into()))?;
+        let mut child = Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()?;
+        let stdin = child.stdin.take().ok_or_else(|| MCPError::InternalError("no stdin".into()))?;
+        let stdout = child.stdout.take().ok_or_else(|| MCPError::InternalError("no stdout".into()))?;
+        Ok(Peer { child, stdin, stdout: BufReader::new(stdout).lines() })
+    }
+
+    async fn exchange(&mut self, request: &Value, timeout: Duration) -> Result<Observed, MCPError> {
+        let mut line = serde_json::to_vec(request)?;
+        line.push(b'\n');
+        self.stdin.write_all(&line).await?;
+        self.stdin.flush().await?;
+
+        let mut observed = Observed::default();
+        let Some(id) = request.get("id") else {
+            return Ok(observed);
+        };
+
+        let collect = async {
+            while let Some(line) = self.stdout.next_line().await? {
+                let Ok(message) = serde_json::from_str::<Value>(&line) else { continue };
+                let is_response = message.get("method").is_none();
+                match message.get("id") {
+                    Some(answered) if is_response && answered == id => {
+                        observed.response = Some(message);
+                        break;
+                    }
+                    // The late answer to a request that already timed out
+                    Some(_) if is_response => continue,
+                    _ => observed.notifications.push(message),
+                }
+            }
+            Ok::<_, MCPError>(())
+        };
+        match tokio::time::timeout(timeout, collect).await {
+            Ok(result) => result?,
+            Err(_) => observed.timed_out = true,
+        }
+        Ok(observed)
+    }
+}
+
+/// Blank out volatile fields (JSON pointers) before comparing
+fn normalize(observed: &mut Observed, ignore: &[String]) {
+    let messages = observed.response.iter_mut().chain(observed.notifications.iter_mut());
+    for message in messages {
+        for pointer in ignore {
+            if let Some(value) = message.pointer_mut(pointer) {
+                *value = Value::Null;
+            }
+        }
+    }
+}
+
+/// Play `requests` against both commands and diff what they send back.
+/// `ignore` lists JSON pointers (e.g. `/result/structuredContent/elapsedMs`)
+/// that are nulled in every message before comparison.
+pub async fn diff_servers(
+    left: &[String],
+    right: &[String],
+    requests: &[Value],
+    ignore: &[String],
+    timeout: Duration,
+) -> Result<TraceDiff, MCPError> {
+    let mut left_peer = Peer::spawn(left)?;
+    let mut right_peer = Peer::spawn(right)?;
+    let mut report = TraceDiff::default();
+
+    for (seq, request) in requests.iter().enumerate() {
+        let (l, r) = tokio::join!(left_peer.exchange(request, timeout), right_peer.exchange(request, timeout));
+        let (mut l, mut r) = (l?, r?);
+        normalize(&mut l, ignore);
+        normalize(&mut r, ignore);
+
+        report.requests += 1;
+        if l != r {
+            report.diffs.push(RequestDiff { seq, request: request.clone(), left: l, right: r });
+        }
+    }
+
+    for peer in [&mut left_peer, &mut right_peer] {
+        let _ = peer.child.start_kill();
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_normalize() {
+        let mut observed = Observed {
+            response: Some(json!({ "id": 1, "result": { "elapsedMs": 12, "text": "ok" } })),
+            notifications: vec![],
+            timed_out: false,
+        };
+        normalize(&mut observed, &["/result/elapsedMs".to_string()]);
+        assert_eq!(observed.response.unwrap()["result"], json!({ "elapsedMs": null, "text": "ok" }));
+    }
+
+    #[test]
+    fn test_split_command() {
+        let words = split_command(r#"cargo run --bin 'my server' -- --name "a \"b\"" c\ d"#).unwrap();
+        assert_eq!(words, ["cargo", "run", "--bin", "my server", "--", "--name", r#"a "b""#, "c d"]);
+        assert_eq!(split_command(r#"echo "" ''"#).unwrap(), ["echo", "", ""]);
+        assert!(split_command("echo 'open").is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_late_responses_are_not_attributed_to_the_next_request() {
+        // Answers request 1 only once request 2 has arrived
+        let script = r#"read a; read b; echo '{"jsonrpc":"2.0","id":1,"result":{}}'; echo '{"jsonrpc":"2.0","method":"notifications/message"}'; echo '{"jsonrpc":"2.0","id":2,"result":{}}'"#;
+        let slow = ["sh", "-c", script].map(String::from);
+        let mut peer = Peer::spawn(&slow).unwrap();
+        let first = peer.exchange(&json!({ "jsonrpc": "2.0", "id": 1, "method": "ping" }), Duration::from_millis(200)).await.unwrap();
+        assert!(first.timed_out);
+        let second = peer.exchange(&json!({ "jsonrpc": "2.0", "id": 2, "method": "ping" }), Duration::from_secs(5)).await.unwrap();
+        assert_eq!(second.response, Some(json!({ "jsonrpc": "2.0", "id": 2, "result": {} })));
+        assert_eq!(second.notifications.len(), 1);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_diff_servers() {
+        // Each request comes back as a response with its own id and the method as the result
+        let sed = |script: &str| ["sed", "-u", "-e", "s/\"method\"/\"result\"/", "-e", script].map(String::from).to_vec();
+        let (cat, shout) = (sed("s/x/x/"), sed("s/ping/PING/"));
+        let requests = vec![
+            json!({ "jsonrpc": "2.0", "id": 1, "method": "tools/list" }),
+            json!({ "jsonrpc": "2.0", "id": 2, "method": "ping" }),
+        ];
+
+        let report = diff_servers(&cat, &shout, &requests, &[], Duration::from_secs(5)).await.unwrap();
+        assert_eq!(report.requests, 2);
+        assert_eq!(report.diffs.len(), 1);
+        assert_eq!(report.diffs[0].seq, 1);
+    }
+}
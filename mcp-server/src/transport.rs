@@ -0,0 +1,755 @@
+//! Transports carry JSON-RPC messages between a client and the server.
+//!
+//! A [`Transport`] yields parsed requests (keeping their raw bytes for
+//! middleware) and writes responses and notifications back. [`StdioTransport`]
+//! speaks newline-delimited JSON or LSP-style `Content-Length` framing (see
+//! [`Framing`]) over any async reader/writer pair, stdin and stdout by default.
+//! [`in_process`] pairs a server-side transport with an [`InProcessClient`]
+//! over channels, for embedding a server or testing without processes.
+//! A [`TransportSet`] lets one runner serve several transports at once.
+//!
+//! A message may take up to [`DEFAULT_MAX_MESSAGE_SIZE`] bytes (see
+//! [`StdioTransport::with_max_message_size`]); the size is checked before
+//! anything is allocated for the message, and a larger one closes the
+//! connection.
+//!
+//! Byte-stream transports retry reads and writes that fail with a transient
+//! error (`WouldBlock`, `Interrupted`) with exponential backoff, per
+//! [`IoRetryPolicy`]. End of input is never retried, and once an operation
+//! exhausts its retries the error is returned and the session ends.
+
+use crate::error::MCPError;
+use crate::middleware::IncomingRequest;
+use crate::notifications::{NotificationReceiver, ServerNotification};
+use crate::response::MCPResponse;
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::mpsc;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncWrite, AsyncWriteExt, BufReader, Stdin, Stdout};
+
+#[async_trait]
+pub trait Transport: Send {
+    /// Next request from the peer, or `Ok(None)` once it has disconnected.
+    /// A message that is not valid JSON-RPC yields `MCPError::JsonError`;
+    /// the transport stays usable.
+    async fn recv(&mut self) -> Result<Option<IncomingRequest>, MCPError>;
+
+    async fn send(&mut self, response: MCPResponse) -> Result<(), MCPError>;
+
+    async fn send_notification(&mut self, notification: ServerNotification) -> Result<(), MCPError>;
+}
+
+/// Transports served together by one runner; see
+/// [`ServerRunner::run_transports`]. Transports may be added while the
+/// runner is running, e.g. as a listener accepts connections.
+///
+/// [`ServerRunner::run_transports`]: crate::runner::ServerRunner::run_transports
+pub struct TransportSet {
+    handle: TransportHandle,
+    receiver: mpsc::UnboundedReceiver<Box<dyn Transport>>,
+}
+
+/// Adds transports to a running [`TransportSet`]
+#[derive(Clone)]
+pub struct TransportHandle {
+    sender: mpsc::UnboundedSender<Box<dyn Transport>>,
+}
+
+impl Default for TransportSet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TransportSet {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        TransportSet { handle: TransportHandle { sender }, receiver }
+    }
+
+    pub fn add(&self, transport: impl Transport + 'static) {
+        self.handle.add(transport);
+    }
+
+    /// A handle for adding transports later; the runner keeps waiting for
+    /// new transports while any handle is alive
+    pub fn handle(&self) -> TransportHandle {
+        self.handle.clone()
+    }
+
+    pub(crate) fn into_receiver(self) -> mpsc::UnboundedReceiver<Box<dyn Transport>> {
+        self.receiver
+    }
+}
+
+impl TransportHandle {
+    /// Returns false once the runner has stopped
+    pub fn add(&self, transport: impl Transport + 'static) -> bool {
+        self.sender.send(Box::new(transport)).is_ok()
+    }
+}
+
+/// Add every connection accepted on a Unix socket to `transports`, as
+/// newline-delimited JSON
+#[cfg(unix)]
+pub async fn accept_unix(listener: tokio::net::UnixListener, transports: TransportHandle) -> Result<(), MCPError> {
+    loop {
+        let (stream, _) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                eprintln!("[TRANSPORT] Accept failed: {}", e);
+                tokio::time::sleep(ACCEPT_BACKOFF).await;
+                continue;
+            }
+        };
+        let (reader, writer) = stream.into_split();
+        if !transports.add(StdioTransport::from_parts(BufReader::new(reader), writer)) {
+            return Ok(());
+        }
+    }
+}
+
+/// Send a queued notification, feeding the write latency back into the
+/// session's progress throttle
+pub async fn forward_notification<T: Transport + ?Sized>(
+    transport: &mut T,
+    notifications: &NotificationReceiver,
+    notification: ServerNotification,
+) -> Result<(), MCPError> {
+    let started = Instant::now();
+    transport.send_notification(notification).await?;
+    notifications.record_delivery(started.elapsed());
+    Ok(())
+}
+
+/// How messages are delimited on a byte stream
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Framing {
+    /// One JSON message per line
+    #[default]
+    NewlineDelimited,
+    /// `Content-Length: N` header, blank line, then N bytes of JSON (as in LSP)
+    ContentLength,
+}
+
+impl std::str::FromStr for Framing {
+    type Err = MCPError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "newline" | "ndjson" => Ok(Framing::NewlineDelimited),
+            "content-length" | "lsp" => Ok(Framing::ContentLength),
+            other => Err(MCPError::InvalidParams(format!("unknown framing '{}'", other))),
+        }
+    }
+}
+
+fn framing_error(message: String) -> MCPError {
+    MCPError::IoError(std::io::Error::new(std::io::ErrorKind::InvalidData, message))
+}
+
+/// Bytes a message (or, newline-delimited, a line) may take unless
+/// [`StdioTransport::with_max_message_size`] says otherwise
+pub const DEFAULT_MAX_MESSAGE_SIZE: usize = 16 << 20;
+
+/// Pause after a failed `accept`, which usually means descriptors ran out
+#[cfg(any(unix, feature = "http", feature = "websocket"))]
+pub(crate) const ACCEPT_BACKOFF: Duration = Duration::from_millis(100);
+
+/// An oversized message ends the connection: skipping it would mean reading
+/// it anyway, and nothing of it can be answered
+pub(crate) fn too_large(limit: usize) -> MCPError {
+    eprintln!("[TRANSPORT] Message exceeds the {} byte limit, closing the connection", limit);
+    framing_error(format!("message exceeds the limit of {} bytes", limit))
+}
+
+/// Backoff for I/O errors that do not mean the peer is gone
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoRetryPolicy {
+    /// Consecutive retries without progress before the error is returned
+    pub max_retries: u32,
+    /// Delay before the first retry; doubles with each further one
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for IoRetryPolicy {
+    fn default() -> Self {
+        IoRetryPolicy { max_retries: 5, initial_backoff: Duration::from_millis(10), max_backoff: Duration::from_millis(500) }
+    }
+}
+
+impl IoRetryPolicy {
+    /// Fail on the first error
+    pub fn none() -> Self {
+        IoRetryPolicy { max_retries: 0, ..Self::default() }
+    }
+}
+
+/// Retry counters of one transport
+#[derive(Debug, Default)]
+pub struct IoRetryStats {
+    retried: AtomicU64,
+    exhausted: AtomicU64,
+}
+
+impl IoRetryStats {
+    /// Operations attempted again after a transient error
+    pub fn retried(&self) -> u64 {
+        self.retried.load(Ordering::Relaxed)
+    }
+
+    /// Operations that failed after using up their retries
+    pub fn exhausted(&self) -> u64 {
+        self.exhausted.load(Ordering::Relaxed)
+    }
+}
+
+/// Whether an operation that failed with `err` may be attempted again
+pub fn is_transient(err: &std::io::Error) -> bool {
+    matches!(err.kind(), std::io::ErrorKind::WouldBlock | std::io::ErrorKind::Interrupted)
+}
+
+/// Retry state of one read or write
+struct Backoff<'a> {
+    policy: &'a IoRetryPolicy,
+    stats: &'a IoRetryStats,
+    attempt: u32,
+}
+
+impl<'a> Backoff<'a> {
+    fn new(policy: &'a IoRetryPolicy, stats: &'a IoRetryStats) -> Self {
+        Backoff { policy, stats, attempt: 0 }
+    }
+
+    /// Data moved since the last error; the retry budget starts over
+    fn progressed(&mut self) {
+        self.attempt = 0;
+    }
+
+    /// Wait before retrying, or give `err` back if it is fatal or retries ran out
+    async fn retry(&mut self, err: std::io::Error) -> Result<(), std::io::Error> {
+        if !is_transient(&err) {
+            return Err(err);
+        }
+        if self.attempt >= self.policy.max_retries {
+            self.stats.exhausted.fetch_add(1, Ordering::Relaxed);
+            eprintln!("[TRANSPORT] Giving up after {} retries: {}", self.attempt, err);
+            return Err(err);
+        }
+        let delay = self.policy.initial_backoff.saturating_mul(1 << self.attempt.min(16)).min(self.policy.max_backoff);
+        self.attempt += 1;
+        self.stats.retried.fetch_add(1, Ordering::Relaxed);
+        tokio::time::sleep(delay).await;
+        Ok(())
+    }
+}
+
+/// A `Content-Length` framed message being read
+#[derive(Debug, Default)]
+struct PartialFrame {
+    // Whether a header of the message has been read
+    started: bool,
+    length: Option<usize>,
+    // Present once the headers have ended
+    body: Option<Vec<u8>>,
+}
+
+/// JSON-RPC over a reader/writer pair. Receiving is cancel safe: a message
+/// partly read when `recv` is dropped (say, in a `select!` another branch
+/// won) is completed by the next call.
+#[derive(Debug)]
+pub struct StdioTransport<R = BufReader<Stdin>, W = Stdout> {
+    reader: R,
+    writer: W,
+    framing: Framing,
+    // Start of a line whose end has not been read yet
+    pending: Vec<u8>,
+    frame: PartialFrame,
+    max_message_size: usize,
+    retry: IoRetryPolicy,
+    retry_stats: Arc<IoRetryStats>,
+}
+
+impl StdioTransport {
+    /// Read from stdin and write to stdout
+    pub fn new() -> Self {
+        Self::from_parts(BufReader::new(tokio::io::stdin()), tokio::io::stdout())
+    }
+}
+
+impl Default for StdioTransport {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<R, W> StdioTransport<R, W>
+where
+    R: AsyncBufRead + Unpin + Send,
+    W: AsyncWrite + Unpin + Send,
+{
+    pub fn from_parts(reader: R, writer: W) -> Self {
+        StdioTransport {
+            reader,
+            writer,
+            framing: Framing::NewlineDelimited,
+            pending: Vec::new(),
+            frame: PartialFrame::default(),
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            retry: IoRetryPolicy::default(),
+            retry_stats: Arc::default(),
+        }
+    }
+
+    pub fn with_framing(mut self, framing: Framing) -> Self {
+        self.framing = framing;
+        self
+    }
+
+    /// Close the connection on messages of more than `bytes`
+    pub fn with_max_message_size(mut self, bytes: usize) -> Self {
+        self.max_message_size = bytes;
+        self
+    }
+
+    pub fn with_retry(mut self, policy: IoRetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Counters of retried and abandoned reads and writes
+    pub fn retry_stats(&self) -> Arc<IoRetryStats> {
+        self.retry_stats.clone()
+    }
+
+    /// Serialize `message` straight into the one buffer that is written, so
+    /// a large result is not copied again to frame it
+    async fn write_message(&mut self, message: &impl Serialize) -> Result<(), MCPError> {
+        let mut backoff = Backoff::new(&self.retry, &self.retry_stats);
+        let mut body = Vec::new();
+        serde_json::to_writer(&mut body, message)?;
+        match self.framing {
+            Framing::NewlineDelimited => body.push(b'\n'),
+            Framing::ContentLength => {
+                let header = format!("Content-Length: {}\r\n\r\n", body.len());
+                Self::write_retrying(&mut self.writer, header.as_bytes(), &mut backoff).await?;
+            }
+        }
+        Self::write_retrying(&mut self.writer, &body, &mut backoff).await?;
+        loop {
+            match self.writer.flush().await {
+                Ok(()) => return Ok(()),
+                Err(e) => backoff.retry(e).await?,
+            }
+        }
+    }
+
+    async fn write_retrying(writer: &mut W, bytes: &[u8], backoff: &mut Backoff<'_>) -> Result<(), MCPError> {
+        // Track progress ourselves: a failed write_all may have written part of the buffer
+        let mut written = 0;
+        while written < bytes.len() {
+            match writer.write(&bytes[written..]).await {
+                Ok(0) => return Err(std::io::Error::from(std::io::ErrorKind::WriteZero).into()),
+                Ok(n) => {
+                    written += n;
+                    backoff.progressed();
+                }
+                Err(e) => backoff.retry(e).await?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Next line, without its line ending, or `None` at end of input. Bytes
+    /// read so far are kept in `self.pending`, so a call that is cancelled
+    /// (or fails with a retried error) loses nothing.
+    async fn next_line(&mut self) -> Result<Option<Vec<u8>>, MCPError> {
+        let mut backoff = Backoff::new(&self.retry, &self.retry_stats);
+        loop {
+            // fill_buf is cancel safe, and the copy below is not interrupted
+            let available = match self.reader.fill_buf().await {
+                // A last line without a newline ends at end of input
+                Ok([]) if self.pending.is_empty() => return Ok(None),
+                Ok([]) => return Ok(Some(std::mem::take(&mut self.pending))),
+                Ok(available) => available,
+                Err(e) => {
+                    backoff.retry(e).await?;
+                    continue;
+                }
+            };
+            let (used, complete) = match available.iter().position(|&b| b == b'\n') {
+                Some(newline) => (newline + 1, true),
+                None => (available.len(), false),
+            };
+            if self.pending.len() + used > self.max_message_size {
+                self.pending.clear();
+                return Err(too_large(self.max_message_size));
+            }
+            self.pending.extend_from_slice(&available[..used]);
+            self.reader.consume(used);
+            backoff.progressed();
+            if complete {
+                return Ok(Some(std::mem::take(&mut self.pending)));
+            }
+        }
+    }
+
+    /// Next non-blank line, trimmed, or `None` at end of input
+    async fn read_line(&mut self) -> Result<Option<Vec<u8>>, MCPError> {
+        loop {
+            let Some(line) = self.next_line().await? else { return Ok(None) };
+            if !line.trim_ascii().is_empty() {
+                return Ok(Some(line.trim_ascii().to_vec()));
+            }
+        }
+    }
+
+    /// Body of the next `Content-Length` framed message. Framing errors are
+    /// I/O errors: the stream cannot be resynchronised after one. Headers and
+    /// body read so far stay in `self.frame` when a call is cancelled.
+    async fn read_framed(&mut self) -> Result<Option<Vec<u8>>, MCPError> {
+        let result = self.fill_frame().await;
+        if matches!(result, Err(_) | Ok(Some(_))) {
+            self.frame = PartialFrame::default();
+        }
+        result
+    }
+
+    async fn fill_frame(&mut self) -> Result<Option<Vec<u8>>, MCPError> {
+        while self.frame.body.is_none() {
+            let Some(line) = self.next_line().await? else {
+                if self.frame.started {
+                    return Err(framing_error("end of input inside headers".into()));
+                }
+                return Ok(None);
+            };
+            let header = String::from_utf8_lossy(line.trim_ascii()).into_owned();
+            if header.is_empty() {
+                // Blank lines between messages are skipped
+                if self.frame.started {
+                    let length = self.frame.length.ok_or_else(|| framing_error("missing Content-Length header".into()))?;
+                    // Checked before allocating: the length is the peer's word
+                    if length > self.max_message_size {
+                        return Err(too_large(self.max_message_size));
+                    }
+                    self.frame.body = Some(Vec::with_capacity(length));
+                }
+                continue;
+            }
+            let (name, value) = header.split_once(':')
+                .ok_or_else(|| framing_error(format!("malformed header '{}'", header)))?;
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                let value = value.trim().parse::<usize>()
+                    .map_err(|_| framing_error(format!("invalid Content-Length '{}'", value.trim())))?;
+                self.frame.length = Some(value);
+            }
+            self.frame.started = true;
+        }
+
+        let length = self.frame.length.unwrap_or_default();
+        let mut backoff = Backoff::new(&self.retry, &self.retry_stats);
+        while let Some(body) = &mut self.frame.body
+            && body.len() < length
+        {
+            // fill_buf is cancel safe, and the copy below is not interrupted
+            let available = match self.reader.fill_buf().await {
+                Ok([]) => return Err(framing_error("end of input inside body".into())),
+                Ok(available) => available,
+                Err(e) => {
+                    backoff.retry(e).await?;
+                    continue;
+                }
+            };
+            let n = available.len().min(length - body.len());
+            body.extend_from_slice(&available[..n]);
+            self.reader.consume(n);
+            backoff.progressed();
+        }
+        Ok(self.frame.body.take())
+    }
+}
+
+#[async_trait]
+impl<R, W> Transport for StdioTransport<R, W>
+where
+    R: AsyncBufRead + Unpin + Send,
+    W: AsyncWrite + Unpin + Send,
+{
+    async fn recv(&mut self) -> Result<Option<IncomingRequest>, MCPError> {
+        let message = match self.framing {
+            Framing::NewlineDelimited => self.read_line().await?,
+            Framing::ContentLength => self.read_framed().await?,
+        };
+        message.map(|bytes| IncomingRequest::parse(&bytes)).transpose()
+    }
+
+    async fn send(&mut self, response: MCPResponse) -> Result<(), MCPError> {
+        self.write_message(&response).await
+    }
+
+    async fn send_notification(&mut self, notification: ServerNotification) -> Result<(), MCPError> {
+        self.write_message(&notification.to_json_rpc()).await
+    }
+}
+
+/// Server half of an in-process connection
+#[derive(Debug)]
+pub struct InProcessTransport {
+    incoming: mpsc::UnboundedReceiver<Vec<u8>>,
+    outgoing: mpsc::UnboundedSender<Value>,
+}
+
+/// Client half of an in-process connection
+#[derive(Debug)]
+pub struct InProcessClient {
+    outgoing: mpsc::UnboundedSender<Vec<u8>>,
+    incoming: mpsc::UnboundedReceiver<Value>,
+    // Notifications that arrived while waiting for a response
+    notifications: VecDeque<Value>,
+    next_id: u64,
+}
+
+/// A connected client/transport pair backed by unbounded channels
+pub fn in_process() -> (InProcessClient, InProcessTransport) {
+    let (client_tx, server_rx) = mpsc::unbounded_channel();
+    let (server_tx, client_rx) = mpsc::unbounded_channel();
+    let client = InProcessClient {
+        outgoing: client_tx,
+        incoming: client_rx,
+        notifications: VecDeque::new(),
+        next_id: 1,
+    };
+    (client, InProcessTransport { incoming: server_rx, outgoing: server_tx })
+}
+
+fn disconnected() -> MCPError {
+    MCPError::IoError(std::io::Error::new(std::io::ErrorKind::BrokenPipe, "in-process peer disconnected"))
+}
+
+impl InProcessTransport {
+    fn deliver(&self, message: &impl Serialize) -> Result<(), MCPError> {
+        self.outgoing.send(serde_json::to_value(message)?).map_err(|_| disconnected())
+    }
+}
+
+#[async_trait]
+impl Transport for InProcessTransport {
+    async fn recv(&mut self) -> Result<Option<IncomingRequest>, MCPError> {
+        match self.incoming.recv().await {
+            Some(bytes) => IncomingRequest::parse(&bytes).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    async fn send(&mut self, response: MCPResponse) -> Result<(), MCPError> {
+        self.deliver(&response)
+    }
+
+    async fn send_notification(&mut self, notification: ServerNotification) -> Result<(), MCPError> {
+        self.deliver(&notification.to_json_rpc())
+    }
+}
+
+impl InProcessClient {
+    /// Send a raw message, exactly as a transport would receive it
+    pub fn send_raw(&self, message: impl Into<Vec<u8>>) -> Result<(), MCPError> {
+        self.outgoing.send(message.into()).map_err(|_| disconnected())
+    }
+
+    /// Send a JSON-RPC notification (no response expected)
+    pub fn notify(&self, method: &str, params: Value) -> Result<(), MCPError> {
+        self.send_raw(json!({ "jsonrpc": "2.0", "method": method, "params": params }).to_string())
+    }
+
+    /// Send a request and wait for its response, queueing any notifications
+    /// that arrive first
+    pub async fn request(&mut self, method: &str, params: Value) -> Result<Value, MCPError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.send_raw(json!({ "jsonrpc": "2.0", "id": id, "method": method, "params": params }).to_string())?;
+        loop {
+            let message = self.incoming.recv().await.ok_or_else(disconnected)?;
+            if message.get("id").and_then(Value::as_u64) == Some(id) {
+                return Ok(message);
+            }
+            self.notifications.push_back(message);
+        }
+    }
+
+    /// Next message from the server that was not a response to [`request`](Self::request)
+    pub async fn next_notification(&mut self) -> Option<Value> {
+        match self.notifications.pop_front() {
+            Some(message) => Some(message),
+            None => self.incoming.recv().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_newline_framing() {
+        let input: &[u8] = b"{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"ping\"}\n\n not json\n";
+        let mut output = Vec::new();
+        let mut transport = StdioTransport::from_parts(input, &mut output);
+
+        let request = transport.recv().await.unwrap().unwrap();
+        assert_eq!(request.request.method, "ping");
+        assert!(matches!(transport.recv().await, Err(MCPError::JsonError(_))));
+        assert!(transport.recv().await.unwrap().is_none());
+
+        transport.send(MCPResponse::parse_error()).await.unwrap();
+        transport.send_notification(ServerNotification::Progress {
+            request_id: "1".into(),
+            progress: 0.5,
+            message: None,
+            meta: None,
+        }).await.unwrap();
+        drop(transport);
+
+        let lines: Vec<&str> = std::str::from_utf8(&output).unwrap().lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].contains("notifications/progress"));
+    }
+
+    #[tokio::test]
+    async fn test_content_length_framing() {
+        let body = r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#;
+        let input = format!("Content-Length: {}\r\nContent-Type: application/json\r\n\r\n{}Content-Length: 3\r\n\r\n{{x}}", body.len(), body);
+        let mut output = Vec::new();
+        let mut transport = StdioTransport::from_parts(input.as_bytes(), &mut output).with_framing(Framing::ContentLength);
+
+        assert_eq!(transport.recv().await.unwrap().unwrap().request.method, "ping");
+        assert!(matches!(transport.recv().await, Err(MCPError::JsonError(_))));
+        assert!(transport.recv().await.unwrap().is_none());
+
+        transport.send(MCPResponse::parse_error()).await.unwrap();
+        drop(transport);
+
+        let output = String::from_utf8(output).unwrap();
+        let (header, json) = output.split_once("\r\n\r\n").unwrap();
+        assert_eq!(header, format!("Content-Length: {}", json.len()));
+        assert!("lsp".parse::<Framing>().is_ok() && "xml".parse::<Framing>().is_err());
+    }
+
+    /// Fails with `kind` `failures` times, then serves `data` three bytes at a time
+    struct Flaky {
+        data: Vec<u8>,
+        failures: usize,
+        kind: std::io::ErrorKind,
+    }
+
+    impl tokio::io::AsyncRead for Flaky {
+        fn poll_read(mut self: std::pin::Pin<&mut Self>, _cx: &mut std::task::Context<'_>, buf: &mut tokio::io::ReadBuf<'_>) -> std::task::Poll<std::io::Result<()>> {
+            if self.failures > 0 {
+                self.failures -= 1;
+                return std::task::Poll::Ready(Err(self.kind.into()));
+            }
+            let n = self.data.len().min(3).min(buf.remaining());
+            buf.put_slice(&self.data[..n]);
+            self.data.drain(..n);
+            // Fail again after every chunk so partial lines must survive retries
+            self.failures = usize::from(n > 0);
+            std::task::Poll::Ready(Ok(()))
+        }
+    }
+
+    fn flaky(data: &str, failures: usize, kind: std::io::ErrorKind) -> BufReader<Flaky> {
+        BufReader::with_capacity(3, Flaky { data: data.as_bytes().to_vec(), failures, kind })
+    }
+
+    #[tokio::test]
+    async fn test_transient_errors_are_retried() {
+        let policy = IoRetryPolicy { initial_backoff: Duration::from_millis(1), ..IoRetryPolicy::default() };
+        let input = "{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"ping\"}\n";
+        let mut transport = StdioTransport::from_parts(flaky(input, 2, std::io::ErrorKind::WouldBlock), Vec::new()).with_retry(policy);
+        assert_eq!(transport.recv().await.unwrap().unwrap().request.method, "ping");
+        assert!(transport.recv().await.unwrap().is_none());
+        assert!(transport.retry_stats().retried() > 2);
+        assert_eq!(transport.retry_stats().exhausted(), 0);
+
+        let mut stuck = StdioTransport::from_parts(flaky(input, 100, std::io::ErrorKind::Interrupted), Vec::new()).with_retry(policy);
+        assert!(matches!(stuck.recv().await, Err(MCPError::IoError(_))));
+        assert_eq!((stuck.retry_stats().retried(), stuck.retry_stats().exhausted()), (5, 1));
+
+        let mut fatal = StdioTransport::from_parts(flaky(input, 1, std::io::ErrorKind::ConnectionReset), Vec::new()).with_retry(policy);
+        assert!(fatal.recv().await.is_err());
+        assert_eq!(fatal.retry_stats().retried(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_messages_close_the_connection() {
+        // Rejected from the header alone, before a byte of body arrives
+        let input = "Content-Length: 99999999999\r\n\r\n";
+        let mut framed = StdioTransport::from_parts(input.as_bytes(), Vec::new()).with_framing(Framing::ContentLength);
+        let Err(MCPError::IoError(e)) = framed.recv().await else { panic!("oversized frame accepted") };
+        assert_eq!(e.kind(), std::io::ErrorKind::InvalidData);
+
+        let input = format!("{{\"jsonrpc\":\"2.0\",\"id\":1,\"method\":\"ping\",\"params\":{{\"x\":\"{}\"}}}}\n", "x".repeat(100));
+        let mut lines = StdioTransport::from_parts(input.as_bytes(), Vec::new()).with_max_message_size(64);
+        assert!(matches!(lines.recv().await, Err(MCPError::IoError(_))));
+        let mut roomy = StdioTransport::from_parts(input.as_bytes(), Vec::new()).with_max_message_size(256);
+        assert_eq!(roomy.recv().await.unwrap().unwrap().request.method, "ping");
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_recv_keeps_partial_message() {
+        let body = r#"{"jsonrpc":"2.0","id":1,"method":"ping"}"#;
+        for framing in [Framing::NewlineDelimited, Framing::ContentLength] {
+            let message = match framing {
+                Framing::NewlineDelimited => format!("{}\n", body),
+                Framing::ContentLength => format!("Content-Length: {}\r\n\r\n{}", body.len(), body),
+            };
+            let (mut peer, stream) = tokio::io::duplex(64);
+            let mut transport = StdioTransport::from_parts(BufReader::new(stream), Vec::new()).with_framing(framing);
+
+            // Cut mid-headers and mid-body, cancelling recv after each part
+            let (first, rest) = message.split_at(10);
+            let (second, third) = rest.split_at(rest.len() - 8);
+            for part in [first, second] {
+                peer.write_all(part.as_bytes()).await.unwrap();
+                let cancelled = tokio::time::timeout(Duration::from_millis(20), transport.recv()).await;
+                assert!(cancelled.is_err(), "{:?}", framing);
+            }
+            peer.write_all(third.as_bytes()).await.unwrap();
+            assert_eq!(transport.recv().await.unwrap().unwrap().request.method, "ping");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_in_process_pair() {
+        use crate::server::SystemMCPServer;
+        use crate::testing::mock::{MockToolHandler, Reply};
+
+        let (mut client, mut transport) = in_process();
+        tokio::spawn(async move {
+            let handler = MockToolHandler::new().tool("echo", Reply::text("pong"));
+            let server = SystemMCPServer::<MockToolHandler>::builder().relaxed_lifecycle().build(handler);
+            loop {
+                let response = match transport.recv().await {
+                    Ok(Some(incoming)) => server.handle_incoming(incoming).await,
+                    Ok(None) => break,
+                    Err(_) => Some(MCPResponse::parse_error()),
+                };
+                if let Some(response) = response {
+                    transport.send(response).await.unwrap();
+                }
+            }
+        });
+
+        let reply = client.request("tools/call", json!({ "name": "echo", "arguments": { "x": 1 } })).await.unwrap();
+        assert_eq!(reply["result"]["content"][0]["text"], "pong");
+        client.send_raw("not json").unwrap();
+        let reply = client.request("tools/list", json!({})).await.unwrap();
+        assert_eq!(reply["id"], 2);
+        assert_eq!(client.next_notification().await.unwrap()["error"]["code"], -32700);
+    }
+}
@@ -0,0 +1,221 @@
+//! Resolution of relative resource URIs in tool results.
+//!
+//! Tools often know a file only by its path relative to where they work, and
+//! a `./output.txt` link reaching the client is a link nobody can follow.
+//! With [`ServerBuilder::relative_uris`] the server resolves relative
+//! resource links and embedded resources against a base: the session's first
+//! client root if [`UriResolver::against_roots`] is set and the roots were
+//! listed, otherwise the configured base. Dot segments are normalized; `..`
+//! stops at the root of the path.
+//!
+//! Each session remembers what its recent relative URIs resolved to, so a
+//! client echoing one back in `resources/read` gets the same resource even if
+//! the base changed since.
+//!
+//! [`ServerBuilder::relative_uris`]: crate::server::ServerBuilder::relative_uris
+
+use crate::roots::Root;
+use crate::tools::ContentBlock;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Mutex;
+
+/// Relative URIs remembered per session
+const REMEMBERED: usize = 64;
+
+#[derive(Debug)]
+pub struct UriResolver {
+    base: String,
+    against_roots: bool,
+    // Session id -> (relative, resolved), oldest first
+    recent: Mutex<HashMap<String, VecDeque<(String, String)>>>,
+}
+
+impl UriResolver {
+    /// Resolve against directory `base`, e.g. `file:///srv/work/`
+    pub fn new(base: impl Into<String>) -> Self {
+        UriResolver { base: directory(base.into()), against_roots: false, recent: Mutex::default() }
+    }
+
+    /// Prefer the session's first root once the client listed its roots
+    pub fn against_roots(mut self) -> Self {
+        self.against_roots = true;
+        self
+    }
+
+    /// Base for a session with the given cached roots
+    pub fn base(&self, roots: Option<&[Root]>) -> String {
+        match roots.and_then(|roots| roots.first()).filter(|_| self.against_roots) {
+            Some(root) => directory(root.uri.clone()),
+            None => self.base.clone(),
+        }
+    }
+
+    /// Resolve the relative URIs of `content` against `base`
+    pub fn apply(&self, session_id: &str, base: &str, content: &mut [ContentBlock]) {
+        for block in content {
+            let uri = match block {
+                ContentBlock::ResourceLink(link) => &mut link.uri,
+                ContentBlock::Resource(embedded) => &mut embedded.resource.uri,
+                _ => continue,
+            };
+            if is_relative(uri) {
+                let resolved = join(base, uri);
+                self.remember(session_id, uri, &resolved);
+                *uri = resolved;
+            }
+        }
+    }
+
+    /// The URI a relative one resolved to for this session, or resolve it now
+    pub fn resolve(&self, session_id: &str, base: &str, uri: &str) -> String {
+        if !is_relative(uri) {
+            return uri.to_string();
+        }
+        let recent = self.recent.lock().unwrap();
+        let remembered = recent.get(session_id)
+            .and_then(|entries| entries.iter().rev().find(|(relative, _)| relative == uri));
+        match remembered {
+            Some((_, resolved)) => resolved.clone(),
+            None => join(base, uri),
+        }
+    }
+
+    pub fn forget(&self, session_id: &str) {
+        self.recent.lock().unwrap().remove(session_id);
+    }
+
+    fn remember(&self, session_id: &str, relative: &str, resolved: &str) {
+        let mut recent = self.recent.lock().unwrap();
+        let entries = recent.entry(session_id.to_string()).or_default();
+        entries.retain(|(known, _)| known != relative);
+        if entries.len() == REMEMBERED {
+            entries.pop_front();
+        }
+        entries.push_back((relative.to_string(), resolved.to_string()));
+    }
+}
+
+/// `uri` with a trailing slash, so it names a directory when joined onto
+fn directory(mut uri: String) -> String {
+    if !uri.ends_with('/') {
+        uri.push('/');
+    }
+    uri
+}
+
+/// Whether `uri` is a relative reference: no scheme and no authority
+pub fn is_relative(uri: &str) -> bool {
+    let scheme = uri.split_once(':').map(|(scheme, _)| scheme).filter(|scheme| {
+        scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+            && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+    });
+    !uri.is_empty() && scheme.is_none() && !uri.starts_with("//")
+}
+
+/// Resolve relative reference `reference` against `base` (RFC 3986 §5.2,
+/// without the query and fragment rules base URIs here never need)
+pub fn join(base: &str, reference: &str) -> String {
+    // scheme://authority stays; only the path is merged
+    let path_start = match base.find("://") {
+        Some(at) => base[at + 3..].find('/').map_or(base.len(), |slash| at + 3 + slash),
+        None => base.find(':').map_or(0, |colon| colon + 1),
+    };
+    let (origin, base_path) = base.split_at(path_start);
+    let split = reference.find(['?', '#']).unwrap_or(reference.len());
+    let (path, suffix) = reference.split_at(split);
+
+    let merged = if path.starts_with('/') {
+        path.to_string()
+    } else {
+        format!("{}{}", &base_path[..base_path.rfind('/').map_or(0, |slash| slash + 1)], path)
+    };
+    format!("{}{}{}", origin, remove_dot_segments(&merged), suffix)
+}
+
+fn remove_dot_segments(path: &str) -> String {
+    let mut segments: Vec<&str> = Vec::new();
+    let parts: Vec<&str> = path.split('/').collect();
+    for (i, segment) in parts.iter().enumerate() {
+        let last = i == parts.len() - 1;
+        match *segment {
+            "." | ".." if last => {
+                if *segment == ".." && segments.len() > 1 {
+                    segments.pop();
+                }
+                segments.push("");
+            }
+            "." => {}
+            // Keep the leading empty segment: `..` stops at the root
+            ".." => {
+                if segments.len() > 1 {
+                    segments.pop();
+                }
+            }
+            segment => segments.push(segment),
+        }
+    }
+    segments.join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::SystemMCPServer;
+    use crate::testing::fixtures;
+    use crate::testing::mock::{MockToolHandler, Reply};
+    use crate::tools::{ResourceContent, ToolResponse};
+
+    #[test]
+    fn test_join() {
+        let base = "file:///srv/work/";
+        assert_eq!(join(base, "./output.txt"), "file:///srv/work/output.txt");
+        assert_eq!(join(base, "out/../logs/./a.log?tail=10"), "file:///srv/work/logs/a.log?tail=10");
+        assert_eq!(join(base, "../../../etc/passwd"), "file:///etc/passwd");
+        assert_eq!(join(base, "/tmp/x"), "file:///tmp/x");
+        assert_eq!(join(base, "sub/.."), "file:///srv/work/");
+        assert_eq!(join("https://example.com/a/b", "c"), "https://example.com/a/c");
+
+        assert!(is_relative("./a") && is_relative("a/b.txt") && is_relative("/abs"));
+        assert!(!is_relative("file:///a") && !is_relative("cas://x") && !is_relative("//host/a"));
+    }
+
+    #[test]
+    fn test_apply_and_remember() {
+        let resolver = UriResolver::new("file:///srv/work").against_roots();
+        let root = Root { uri: "file:///home/me/project".into(), name: None };
+        assert_eq!(resolver.base(None), "file:///srv/work/");
+        let base = resolver.base(Some(&[root]));
+        assert_eq!(base, "file:///home/me/project/");
+
+        let mut content = vec![ContentBlock::resource_link("./out.txt", "out"), ContentBlock::resource_link("cas://abc", "c")];
+        resolver.apply("s1", &base, &mut content);
+        let uris: Vec<_> = content.iter().map(|block| match block {
+            ContentBlock::ResourceLink(link) => link.uri.as_str(),
+            _ => unreachable!(),
+        }).collect();
+        assert_eq!(uris, ["file:///home/me/project/out.txt", "cas://abc"]);
+
+        // Remembered per session, even though the base moved on
+        assert_eq!(resolver.resolve("s1", "file:///srv/work/", "./out.txt"), "file:///home/me/project/out.txt");
+        assert_eq!(resolver.resolve("s2", "file:///srv/work/", "./out.txt"), "file:///srv/work/out.txt");
+        resolver.forget("s1");
+        assert_eq!(resolver.resolve("s1", "file:///srv/work/", "./out.txt"), "file:///srv/work/out.txt");
+    }
+
+    #[tokio::test]
+    async fn test_server_resolves_links() {
+        let resolved = "file:///srv/work/out.txt";
+        let mock = MockToolHandler::new()
+            .tool("build", Reply::value(ToolResponse::from_content(vec![ContentBlock::resource_link("./out.txt", "out")], false)))
+            .resource(resolved, Reply::value(ResourceContent { uri: resolved.into(), mime_type: "text/plain".into(), text: "built".into(), blob: None }));
+        let server = SystemMCPServer::<MockToolHandler>::builder()
+            .relaxed_lifecycle()
+            .relative_uris(UriResolver::new("file:///srv/work"))
+            .build(mock);
+
+        let result = server.handle(fixtures::call_tool("build").build()).await.unwrap().result.unwrap();
+        assert_eq!(result["content"][0]["uri"], resolved);
+        let read = fixtures::request("resources/read").param("uri", "./out.txt").build();
+        assert_eq!(server.handle(read).await.unwrap().result.unwrap()["text"], "built");
+    }
+}
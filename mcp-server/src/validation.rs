@@ -0,0 +1,288 @@
+//! Tool arguments checked against the tool's input schema.
+//!
+//! Every tool declares the shape of its arguments in `inputSchema`, yet
+//! without validation each handler repeats the same null and type checks
+//! before it can trust them. With [`ServerBuilder::validate_arguments`] the
+//! server validates `arguments` against the listed tool's schema before the
+//! handler runs, and answers calls that do not match with -32602 whose
+//! `data.violations` lists every mismatch with a JSON pointer to the value.
+//! Missing arguments count as `{}`. Schemas are compiled on first use and
+//! kept until the listed tools change, which the server tells from the same
+//! generation that drives its `tools/list` cache, so unregistered tools do
+//! not linger. For a handler that reports no `tools_generation` each call
+//! compares the schema with the one compiled. A schema that does not compile
+//! fails every call of its tool with -32603, and
+//! [`ServerBuilder::try_build`] refuses builder tools and output schemas
+//! whose schemas do not compile.
+//!
+//! Output schemas catch the opposite drift, a handler whose results no longer
+//! match what its tool promises. With [`ServerBuilder::validate_output`] the
+//! `structuredContent` of each successful result is checked against the
+//! schema given to [`ServerBuilder::output_schema`] before the handler's
+//! completion hook runs; a result without `structuredContent` breaks it too.
+//! [`OutputValidation`] chooses between logging mismatches and answering
+//! with -32603 listing them, the latter meant for development and tests.
+//!
+//! [`ServerBuilder::validate_arguments`]: crate::server::ServerBuilder::validate_arguments
+//! [`ServerBuilder::validate_output`]: crate::server::ServerBuilder::validate_output
+//! [`ServerBuilder::output_schema`]: crate::server::ServerBuilder::output_schema
+//! [`ServerBuilder::try_build`]: crate::server::ServerBuilder::try_build
+
+use crate::error::{MCPError, SchemaViolation};
+use crate::tools::ToolResponse;
+use jsonschema::Validator;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// Tools whose schemas are kept when the handler reports no generation
+pub const MAX_COMPILED_SCHEMAS: usize = 256;
+
+/// A tool's schema and what it compiled to
+type Compiled = (Value, Arc<Validator>);
+
+#[derive(Debug, Default)]
+pub struct ArgumentValidator {
+    schemas: Schemas,
+}
+
+impl ArgumentValidator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Check `args` against `schema`, the input schema of `tool`, while the
+    /// listed tools are at `generation`; `None` when that is unknown
+    pub fn validate(&self, tool: &str, schema: &Value, generation: Option<u64>, args: &Value) -> Result<(), MCPError> {
+        let empty = json!({});
+        let args = if args.is_null() { &empty } else { args };
+        let violations = self.schemas.violations(tool, "Input", schema, generation, args)?;
+        if violations.is_empty() { Ok(()) } else { Err(MCPError::InvalidArguments(violations)) }
+    }
+}
+
+/// What happens to results whose structured content breaks the tool's
+/// output schema
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputValidation {
+    /// Log the violations and send the result anyway
+    Warn,
+    /// Answer the call with -32603 listing the violations
+    Error,
+}
+
+#[derive(Debug)]
+pub struct OutputValidator {
+    policy: OutputValidation,
+    schemas: Schemas,
+}
+
+impl OutputValidator {
+    pub fn new(policy: OutputValidation) -> Self {
+        OutputValidator { policy, schemas: Schemas::default() }
+    }
+
+    /// Check the structured content of `response` against `schema`, the
+    /// output schema of `tool`; error results are not checked
+    pub fn validate(&self, tool: &str, schema: &Value, response: &ToolResponse) -> Result<(), MCPError> {
+        if response.is_error {
+            return Ok(());
+        }
+        let violations = match &response.structured_content {
+            // Output schemas are given to the builder and never change
+            Some(structured) => self.schemas.violations(tool, "Output", schema, Some(0), structured)?,
+            None => vec![SchemaViolation {
+                pointer: String::new(),
+                keyword: "required".into(),
+                message: "the result has no structuredContent".into(),
+            }],
+        };
+        if violations.is_empty() {
+            return Ok(());
+        }
+        match self.policy {
+            OutputValidation::Warn => {
+                eprintln!("[VALIDATE] Result of {} does not match its output schema: {}", tool, MCPError::InvalidOutput(violations));
+                Ok(())
+            }
+            OutputValidation::Error => Err(MCPError::InvalidOutput(violations)),
+        }
+    }
+}
+
+/// Check that `schema` compiles; `kind` and `tool` name it in the error
+pub(crate) fn check_schema(tool: &str, kind: &str, schema: &Value) -> Result<(), MCPError> {
+    compile(tool, kind, schema).map(drop)
+}
+
+fn compile(tool: &str, kind: &str, schema: &Value) -> Result<Validator, MCPError> {
+    jsonschema::validator_for(schema)
+        .map_err(|e| MCPError::InternalError(format!("{} schema of {} does not compile: {}", kind, tool, e)))
+}
+
+/// Compiled schemas by tool name
+#[derive(Default)]
+struct Schemas {
+    compiled: Mutex<SchemaCache>,
+}
+
+#[derive(Default)]
+struct SchemaCache {
+    // Tools generation the schemas were compiled at
+    generation: Option<u64>,
+    schemas: HashMap<String, Compiled>,
+}
+
+impl std::fmt::Debug for Schemas {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Schemas").field("tools", &self.compiled.lock().unwrap().schemas.len()).finish()
+    }
+}
+
+impl Schemas {
+    /// How `value` breaks `schema`; `kind` names the schema in errors
+    fn violations(&self, tool: &str, kind: &str, schema: &Value, generation: Option<u64>, value: &Value) -> Result<Vec<SchemaViolation>, MCPError> {
+        let validator = self.compile(tool, kind, schema, generation)?;
+        Ok(validator.iter_errors(value)
+            .map(|error| SchemaViolation {
+                pointer: error.instance_path().as_str().into(),
+                keyword: error.kind().keyword().into(),
+                message: error.to_string(),
+            })
+            .collect())
+    }
+
+    fn compile(&self, tool: &str, kind: &str, schema: &Value, generation: Option<u64>) -> Result<Arc<Validator>, MCPError> {
+        let mut cache = self.compiled.lock().unwrap();
+        if cache.generation != generation || generation.is_none() && cache.schemas.len() >= MAX_COMPILED_SCHEMAS {
+            *cache = SchemaCache { generation, schemas: HashMap::new() };
+        }
+        // Without a generation only the schema itself tells a change
+        if let Some((cached, validator)) = cache.schemas.get(tool)
+            && (generation.is_some() || cached == schema)
+        {
+            return Ok(validator.clone());
+        }
+        let validator = Arc::new(compile(tool, kind, schema)?);
+        cache.schemas.insert(tool.into(), (schema.clone(), validator.clone()));
+        Ok(validator)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::{NoTools, ToolRegistry};
+    use crate::server::SystemMCPServer;
+    use crate::testing::fixtures;
+    use crate::tools::{Tool, ToolInputSchema, ToolResponse};
+
+    #[test]
+    fn test_violations() {
+        let validator = ArgumentValidator::new();
+        let schema = json!({
+            "type": "object",
+            "properties": { "path": { "type": "string" }, "depth": { "type": "integer" } },
+            "required": ["path"],
+        });
+        assert!(validator.validate("walk", &schema, Some(1), &json!({ "path": "/tmp" })).is_ok());
+
+        let Err(MCPError::InvalidArguments(violations)) = validator.validate("walk", &schema, Some(1), &json!({ "depth": "deep" })) else {
+            panic!("arguments passed");
+        };
+        let mut found: Vec<_> = violations.iter().map(|v| (v.pointer.as_str(), v.keyword.as_str())).collect();
+        found.sort();
+        assert_eq!(found, [("", "required"), ("/depth", "type")]);
+        assert!(validator.validate("walk", &schema, Some(1), &Value::Null).is_err());
+        // A schema that does not compile fails every call
+        for _ in 0..2 {
+            assert!(matches!(validator.validate("broken", &json!({ "type": 7 }), Some(1), &json!({})), Err(MCPError::InternalError(_))));
+        }
+
+        // The same generation trusts the compiled schema; a new one starts over
+        let loose = json!({ "type": "object" });
+        assert!(validator.validate("walk", &loose, Some(1), &json!({})).is_err());
+        assert!(validator.validate("walk", &loose, Some(2), &json!({})).is_ok());
+        assert_eq!(validator.schemas.compiled.lock().unwrap().schemas.len(), 1);
+        // Without one, a changed schema is recompiled
+        assert!(validator.validate("walk", &schema, None, &json!({})).is_err());
+        assert!(validator.validate("walk", &loose, None, &json!({})).is_ok());
+    }
+
+    #[test]
+    fn test_broken_schemas_fail_the_build() {
+        let broken = || SystemMCPServer::<NoTools>::builder()
+            .output_schema("count", json!({ "type": 7 }))
+            .validate_output(OutputValidation::Warn);
+        let Err(MCPError::InternalError(message)) = broken().try_build(NoTools) else { panic!("built") };
+        assert!(message.contains("Output schema of count"));
+        broken().build(NoTools);
+    }
+
+    #[tokio::test]
+    async fn test_calls_are_validated() {
+        let registry = ToolRegistry::new();
+        let schema = ToolInputSchema { schema_type: "object".into(), properties: Default::default(), required: vec!["path".into()] };
+        registry.register(Tool::new("stat", "file metadata", schema), |args, _ctx| async move {
+            Ok(ToolResponse::new(args["path"].as_str().unwrap().to_string(), false))
+        });
+        let server = SystemMCPServer::<NoTools>::builder()
+            .relaxed_lifecycle()
+            .tool_registry(registry)
+            .validate_arguments()
+            .build(NoTools);
+
+        let ok = server.handle(fixtures::call_tool("stat").arg("path", json!("/etc")).build()).await.unwrap();
+        assert_eq!(ok.result.unwrap()["content"][0]["text"], "/etc");
+        let error = server.handle(fixtures::call_tool("stat").build()).await.unwrap().error.unwrap();
+        assert_eq!(error.code, -32602);
+        let data = error.data.unwrap();
+        assert_eq!(data["kind"], "arguments");
+        assert_eq!(data["violations"][0]["keyword"], "required");
+        assert!(data["violations"][0]["message"].as_str().unwrap().contains("path"));
+        let stats = server.metrics()["stat"];
+        assert_eq!((stats.calls, stats.errors), (2, 1));
+    }
+
+    #[tokio::test]
+    async fn test_output_is_validated() {
+        let registry = ToolRegistry::new();
+        let schema = ToolInputSchema { schema_type: "object".into(), properties: Default::default(), required: vec![] };
+        registry.register(Tool::new("count", "count things", schema), |args, _ctx| async move {
+            let response = ToolResponse::new("counted".into(), false);
+            match args.get("count") {
+                Some(count) => Ok(response.with_structured_content(json!({ "count": count }))),
+                None => Ok(response),
+            }
+        });
+        let server = |policy| SystemMCPServer::<NoTools>::builder()
+            .relaxed_lifecycle()
+            .tool_registry(registry.clone())
+            .output_schema("count", json!({
+                "type": "object",
+                "properties": { "count": { "type": "integer" } },
+                "required": ["count"],
+            }))
+            .validate_output(policy)
+            .build(NoTools);
+
+        let strict = server(OutputValidation::Error);
+        let listed = strict.handle(fixtures::request("tools/list").build()).await.unwrap().result.unwrap();
+        assert_eq!(listed["tools"][0]["outputSchema"]["required"], json!(["count"]));
+        let ok = strict.handle(fixtures::call_tool("count").arg("count", json!(3)).build()).await.unwrap();
+        assert_eq!(ok.result.unwrap()["structuredContent"]["count"], 3);
+        let error = strict.handle(fixtures::call_tool("count").arg("count", json!("three")).build()).await.unwrap().error.unwrap();
+        assert_eq!(error.code, -32603);
+        let data = error.data.unwrap();
+        assert_eq!(data["kind"], "output");
+        assert_eq!((data["violations"][0]["pointer"].clone(), data["violations"][0]["keyword"].clone()), (json!("/count"), json!("type")));
+        let missing = strict.handle(fixtures::call_tool("count").build()).await.unwrap().error.unwrap();
+        assert_eq!(missing.data.unwrap()["violations"][0]["message"], "the result has no structuredContent");
+        let stats = strict.metrics()["count"];
+        assert_eq!((stats.calls, stats.errors), (3, 2));
+
+        let lenient = server(OutputValidation::Warn);
+        let sent = lenient.handle(fixtures::call_tool("count").arg("count", json!("three")).build()).await.unwrap();
+        assert_eq!(sent.result.unwrap()["structuredContent"]["count"], "three");
+    }
+}
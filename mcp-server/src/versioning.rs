@@ -0,0 +1,283 @@
+//! Multiple versions of the same tool (`bash@1`, `bash@2`).
+//!
+//! `tools/list` advertises the default version under the plain name plus
+//! every version under `name@version`. A call selects a version with the
+//! suffixed name or `_meta.toolVersion`, falling back to the default. The
+//! handler always receives the qualified `name@version`.
+//!
+//! Any listed tool can also be marked deprecated, with
+//! [`ServerBuilder::deprecate_tool`] or, for one version of a versioned tool,
+//! [`ServerBuilder::deprecate_tool_version`]. Its `tools/list` entry then
+//! carries `_meta.deprecated`, the note and the name of its replacement, and
+//! with [`ServerBuilder::warn_deprecated_calls`] each session gets a
+//! `warning` log notification the first time it calls the tool.
+//!
+//! [`ServerBuilder::deprecate_tool`]: crate::server::ServerBuilder::deprecate_tool
+//! [`ServerBuilder::deprecate_tool_version`]: crate::server::ServerBuilder::deprecate_tool_version
+//! [`ServerBuilder::warn_deprecated_calls`]: crate::server::ServerBuilder::warn_deprecated_calls
+
+use crate::error::MCPError;
+use crate::tools::Tool;
+use serde_json::{json, Value};
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Debug, Clone)]
+struct ToolVersion {
+    tool: Tool,
+    deprecation: Option<Deprecation>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct VersionSet {
+    default: Option<u32>,
+    versions: BTreeMap<u32, ToolVersion>,
+}
+
+impl VersionSet {
+    fn default_version(&self) -> Option<u32> {
+        self.default.or_else(|| self.versions.keys().next_back().copied())
+    }
+}
+
+/// Registered tool versions, keyed by base tool name
+#[derive(Debug, Clone, Default)]
+pub struct ToolVersions {
+    tools: HashMap<String, VersionSet>,
+}
+
+/// Why a tool should no longer be used and what to use instead
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Deprecation {
+    note: String,
+    replaced_by: Option<String>,
+}
+
+impl Deprecation {
+    pub fn new(note: impl Into<String>) -> Self {
+        Deprecation { note: note.into(), replaced_by: None }
+    }
+
+    /// Name of the tool to call instead
+    pub fn replaced_by(mut self, tool: impl Into<String>) -> Self {
+        self.replaced_by = Some(tool.into());
+        self
+    }
+
+    /// Add the deprecation to a listed tool's `_meta`
+    pub fn annotate(&self, tool: &mut Value) {
+        if !tool["_meta"].is_object() {
+            tool["_meta"] = json!({});
+        }
+        self.mark(&mut tool["_meta"]);
+    }
+
+    fn mark(&self, meta: &mut Value) {
+        meta["deprecated"] = json!(true);
+        meta["deprecationNote"] = json!(self.note);
+        if let Some(replacement) = &self.replaced_by {
+            meta["replacedBy"] = json!(replacement);
+        }
+    }
+
+    /// `data` of the warning sent when `tool` is called
+    pub fn warning(&self, tool: &str) -> Value {
+        let message = match &self.replaced_by {
+            Some(replacement) => format!("Tool {} is deprecated: {}; use {} instead", tool, self.note, replacement),
+            None => format!("Tool {} is deprecated: {}", tool, self.note),
+        };
+        json!({ "message": message, "tool": tool, "replacedBy": self.replaced_by })
+    }
+}
+
+pub fn qualified_name(name: &str, version: u32) -> String {
+    format!("{}@{}", name, version)
+}
+
+impl ToolVersions {
+    pub fn is_empty(&self) -> bool {
+        self.tools.is_empty()
+    }
+
+    /// Register `tool` (named by its base name) as `version`
+    pub fn register(&mut self, version: u32, tool: Tool) {
+        let set = self.tools.entry(tool.name.clone()).or_default();
+        set.versions.insert(version, ToolVersion { tool, deprecation: None });
+    }
+
+    /// Version used when a call does not pick one; defaults to the highest
+    pub fn set_default(&mut self, name: &str, version: u32) {
+        self.tools.entry(name.to_string()).or_default().default = Some(version);
+    }
+
+    pub fn deprecate(&mut self, name: &str, version: u32, deprecation: Deprecation) {
+        if let Some(entry) = self.tools.get_mut(name).and_then(|set| set.versions.get_mut(&version)) {
+            entry.deprecation = Some(deprecation);
+        }
+    }
+
+    /// Deprecation of a version, by the qualified name [`Self::resolve`] returns
+    pub fn deprecation(&self, qualified: &str) -> Option<&Deprecation> {
+        let (base, version) = qualified.rsplit_once('@')?;
+        let entry = self.tools.get(base)?.versions.get(&version.parse().ok()?)?;
+        entry.deprecation.as_ref()
+    }
+
+    /// `tools/list` entries for every versioned tool
+    pub fn list(&self) -> Vec<Tool> {
+        let mut names: Vec<&String> = self.tools.keys().collect();
+        names.sort();
+
+        let mut listed = Vec::new();
+        for name in names {
+            let set = &self.tools[name];
+            let all: Vec<u32> = set.versions.keys().copied().collect();
+            let default = set.default_version();
+
+            for (version, entry) in &set.versions {
+                let mut meta = json!({ "version": version, "versions": all });
+                if let Some(deprecation) = &entry.deprecation {
+                    deprecation.mark(&mut meta);
+                }
+
+                if Some(*version) == default {
+                    let mut tool = entry.tool.clone();
+                    tool.meta = Some(meta.clone());
+                    listed.push(tool);
+                }
+
+                let mut tool = entry.tool.clone();
+                tool.name = qualified_name(name, *version);
+                tool.meta = Some(meta);
+                listed.push(tool);
+            }
+        }
+        listed
+    }
+
+    /// Resolve a call to the qualified name of a registered version, or
+    /// `None` when `name` is not a versioned tool
+    pub fn resolve(&self, name: &str, meta: Option<&Value>) -> Result<Option<String>, MCPError> {
+        let (base, suffix) = match name.rsplit_once('@')
+            .and_then(|(base, version)| Some((base, version.parse::<u32>().ok()?)))
+        {
+            Some((base, version)) => (base, Some(version)),
+            None => (name, None),
+        };
+
+        let Some(set) = self.tools.get(base) else {
+            return Ok(None);
+        };
+
+        let requested = match meta.and_then(|m| m.get("toolVersion")) {
+            Some(v) => Some(v.as_u64().and_then(|v| u32::try_from(v).ok())
+                .ok_or_else(|| MCPError::InvalidParams("_meta.toolVersion must be a version number".into()))?),
+            None => None,
+        };
+
+        let version = suffix.or(requested).or_else(|| set.default_version())
+            .ok_or_else(|| MCPError::UnknownTool(name.into()))?;
+        if !set.versions.contains_key(&version) {
+            return Err(MCPError::UnknownTool(qualified_name(base, version)));
+        }
+        Ok(Some(qualified_name(base, version)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tools::ToolInputSchema;
+
+    fn tool(name: &str) -> Tool {
+        Tool::new(name, "test", ToolInputSchema {
+            schema_type: "object".into(),
+            properties: Default::default(),
+            required: vec![],
+        })
+    }
+
+    #[test]
+    fn test_resolution() {
+        let mut versions = ToolVersions::default();
+        versions.register(1, tool("bash"));
+        versions.register(2, tool("bash"));
+        versions.deprecate("bash", 1, Deprecation::new("superseded").replaced_by("bash@2"));
+
+        assert_eq!(versions.deprecation("bash@1"), Some(&Deprecation::new("superseded").replaced_by("bash@2")));
+        assert_eq!(versions.deprecation("bash@2"), None);
+        assert_eq!(versions.resolve("bash", None).unwrap().as_deref(), Some("bash@2"));
+        assert_eq!(versions.resolve("bash@1", None).unwrap().as_deref(), Some("bash@1"));
+        let meta = json!({ "toolVersion": 1 });
+        assert_eq!(versions.resolve("bash", Some(&meta)).unwrap().as_deref(), Some("bash@1"));
+        assert!(versions.resolve("bash@3", None).is_err());
+        assert_eq!(versions.resolve("other", None).unwrap(), None);
+
+        versions.set_default("bash", 1);
+        assert_eq!(versions.resolve("bash", None).unwrap().as_deref(), Some("bash@1"));
+    }
+
+    #[test]
+    fn test_listing() {
+        let mut versions = ToolVersions::default();
+        versions.register(1, tool("bash"));
+        versions.register(2, tool("bash"));
+        versions.deprecate("bash", 1, Deprecation::new("superseded").replaced_by("bash@2"));
+
+        let names: Vec<String> = versions.list().into_iter().map(|t| t.name).collect();
+        assert_eq!(names, vec!["bash@1", "bash", "bash@2"]);
+        assert_eq!(versions.list()[0].meta.as_ref().unwrap()["replacedBy"], json!("bash@2"));
+    }
+
+    #[tokio::test]
+    async fn test_deprecated_tools() {
+        use crate::notifications::ServerNotification;
+        use crate::server::SystemMCPServer;
+        use crate::testing::fixtures;
+        use crate::testing::mock::{MockToolHandler, Reply};
+
+        let mut server = SystemMCPServer::<MockToolHandler>::builder()
+            .relaxed_lifecycle()
+            .with_tools(vec![tool("cat"), tool("read")])
+            .deprecate_tool("cat", Deprecation::new("renamed").replaced_by("read"))
+            .warn_deprecated_calls()
+            .build(MockToolHandler::new().tool("cat", Reply::text("contents")));
+        let mut notifications = server.take_notification_receiver().unwrap();
+
+        let listed = server.handle(fixtures::request("tools/list").build()).await.unwrap().result.unwrap();
+        assert_eq!(listed["tools"][0]["_meta"], json!({ "deprecated": true, "deprecationNote": "renamed", "replacedBy": "read" }));
+        assert!(listed["tools"][1].get("_meta").is_none());
+
+        for _ in 0..2 {
+            assert!(server.handle(fixtures::call_tool("cat").build()).await.unwrap().result.is_some());
+        }
+        let Some(ServerNotification::Log { level, data, .. }) = notifications.try_recv() else { panic!("no warning") };
+        assert_eq!((level.as_str(), data["replacedBy"].clone()), ("warning", json!("read")));
+        // Once per session
+        assert!(notifications.try_recv().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_deprecated_versions_warn_once() {
+        use crate::notifications::ServerNotification;
+        use crate::server::SystemMCPServer;
+        use crate::testing::fixtures;
+        use crate::testing::mock::{MockToolHandler, Reply};
+
+        let mut server = SystemMCPServer::<MockToolHandler>::builder()
+            .relaxed_lifecycle()
+            .with_tool_version(1, tool("bash"))
+            .with_tool_version(2, tool("bash"))
+            .deprecate_tool_version("bash", 1, Deprecation::new("superseded").replaced_by("bash@2"))
+            .warn_deprecated_calls()
+            .build(MockToolHandler::new().tool("bash@1", Reply::text("old")).tool("bash@2", Reply::text("new")));
+        let mut notifications = server.take_notification_receiver().unwrap();
+
+        for _ in 0..3 {
+            server.handle(fixtures::call_tool("bash@1").build()).await.unwrap();
+            server.handle(fixtures::call_tool("bash").build()).await.unwrap();
+        }
+        let Some(ServerNotification::Log { data, .. }) = notifications.try_recv() else { panic!("no warning") };
+        assert_eq!((data["tool"].clone(), data["replacedBy"].clone()), (json!("bash@1"), json!("bash@2")));
+        assert!(notifications.try_recv().is_none());
+    }
+}
@@ -0,0 +1,144 @@
+//! WebSocket transport (feature `websocket`).
+//!
+//! Each text or binary frame carries one JSON-RPC message. [`serve`] accepts
+//! connections and gives every connection its own server instance, so
+//! sessions, subscriptions and notifications never leak between clients.
+
+use crate::error::MCPError;
+use crate::middleware::IncomingRequest;
+use crate::notifications::ServerNotification;
+use crate::response::MCPResponse;
+use crate::server::{SystemMCPServer, ToolHandler};
+use crate::transport::{Transport, ACCEPT_BACKOFF};
+use async_trait::async_trait;
+use futures_util::{SinkExt, StreamExt};
+use serde::Serialize;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, ToSocketAddrs};
+use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+use tokio_tungstenite::WebSocketStream;
+
+fn ws_error(e: WsError) -> MCPError {
+    match e {
+        WsError::Io(e) => MCPError::IoError(e),
+        other => MCPError::StreamError(other.to_string()),
+    }
+}
+
+#[derive(Debug)]
+pub struct WebSocketTransport<S> {
+    stream: WebSocketStream<S>,
+}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> WebSocketTransport<S> {
+    /// Wrap an already upgraded WebSocket stream
+    pub fn new(stream: WebSocketStream<S>) -> Self {
+        WebSocketTransport { stream }
+    }
+
+    /// Perform the server side of the handshake on a raw connection
+    pub async fn accept(io: S) -> Result<Self, MCPError> {
+        Ok(Self::new(tokio_tungstenite::accept_async(io).await.map_err(ws_error)?))
+    }
+
+    async fn send_json(&mut self, message: &impl Serialize) -> Result<(), MCPError> {
+        let text = serde_json::to_string(message)?;
+        self.stream.send(Message::text(text)).await.map_err(ws_error)
+    }
+}
+
+#[async_trait]
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> Transport for WebSocketTransport<S> {
+    async fn recv(&mut self) -> Result<Option<IncomingRequest>, MCPError> {
+        while let Some(message) = self.stream.next().await {
+            match message {
+                Ok(Message::Text(text)) => return IncomingRequest::parse(text.as_bytes()).map(Some),
+                Ok(Message::Binary(bytes)) => return IncomingRequest::parse(&bytes).map(Some),
+                Ok(Message::Close(_)) => return Ok(None),
+                // Pings are answered by tungstenite itself
+                Ok(_) => continue,
+                Err(WsError::ConnectionClosed | WsError::AlreadyClosed) => return Ok(None),
+                Err(e) => return Err(ws_error(e)),
+            }
+        }
+        Ok(None)
+    }
+
+    async fn send(&mut self, response: MCPResponse) -> Result<(), MCPError> {
+        self.send_json(&response).await
+    }
+
+    async fn send_notification(&mut self, notification: ServerNotification) -> Result<(), MCPError> {
+        self.send_json(&notification.to_json_rpc()).await
+    }
+}
+
+/// Serve one connection until the client disconnects, forwarding the
+/// server's notifications between responses
+pub async fn serve_connection<H, T>(server: SystemMCPServer<H>, transport: T) -> Result<(), MCPError>
+where
+    H: ToolHandler + 'static,
+    T: Transport,
+{
+    Arc::new(server).runner().without_signals().run_with_transport(transport).await
+}
+
+/// Accept WebSocket clients on `addr`, building a fresh server per
+/// connection with `make_server`
+pub async fn serve<H, F>(addr: impl ToSocketAddrs, make_server: F) -> Result<(), MCPError>
+where
+    H: ToolHandler + 'static,
+    F: Fn() -> SystemMCPServer<H>,
+{
+    let listener = TcpListener::bind(addr).await?;
+    loop {
+        let (socket, peer) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(e) => {
+                eprintln!("[WEBSOCKET] Accept failed: {}", e);
+                tokio::time::sleep(ACCEPT_BACKOFF).await;
+                continue;
+            }
+        };
+        let server = make_server();
+        tokio::spawn(async move {
+            let result = match WebSocketTransport::accept(socket).await {
+                Ok(transport) => serve_connection(server, transport).await,
+                Err(e) => Err(e),
+            };
+            if let Err(e) = result {
+                eprintln!("[WEBSOCKET] Connection {} ended with error: {}", peer, e);
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::fixtures;
+    use crate::testing::mock::{MockToolHandler, Reply};
+    use serde_json::Value;
+
+    #[tokio::test]
+    async fn test_round_trip() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let transport = WebSocketTransport::accept(socket).await.unwrap();
+            let handler = MockToolHandler::new().tool("echo", Reply::text("pong"));
+            serve_connection(SystemMCPServer::<MockToolHandler>::builder().relaxed_lifecycle().build(handler), transport).await.unwrap();
+        });
+
+        let (mut client, _) = tokio_tungstenite::connect_async(format!("ws://{}", addr)).await.unwrap();
+        let request = fixtures::call_tool("echo").id(1).arg("x", 1).to_json();
+        client.send(Message::text(request.to_string())).await.unwrap();
+
+        let Some(Ok(Message::Text(reply))) = client.next().await else { panic!("no reply") };
+        let reply: Value = serde_json::from_str(reply.as_str()).unwrap();
+        assert_eq!(reply["id"], 1);
+        assert_eq!(reply["result"]["content"][0]["text"], "pong");
+    }
+}
@@ -0,0 +1,200 @@
+//! Base64 payloads that are encoded while serializing.
+//!
+//! [`Base64Data`] keeps binary content as raw bytes and encodes it straight
+//! into the serializer's output through `collect_str`, so a multi-megabyte
+//! blob is never held twice (raw and as a base64 `String`). Already-encoded
+//! strings are passed through untouched. The saving applies when serializing
+//! to a writer; converting to `serde_json::Value` still builds the string.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::fmt;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Input bytes encoded per `write_str` call; a multiple of 3 so chunks need no padding
+const CHUNK: usize = 3 * 256;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Repr {
+    Raw(Vec<u8>),
+    Encoded(String),
+}
+
+/// Binary content serialized as standard base64
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Base64Data(Repr);
+
+impl Base64Data {
+    /// Wrap a string that is already base64-encoded
+    pub fn encoded(data: impl Into<String>) -> Self {
+        Base64Data(Repr::Encoded(data.into()))
+    }
+
+    /// Raw bytes, if this was built from bytes
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match &self.0 {
+            Repr::Raw(bytes) => Some(bytes),
+            Repr::Encoded(_) => None,
+        }
+    }
+
+    /// Length of the base64 text
+    pub fn encoded_len(&self) -> usize {
+        match &self.0 {
+            Repr::Raw(bytes) => bytes.len().div_ceil(3) * 4,
+            Repr::Encoded(text) => text.len(),
+        }
+    }
+
+    /// Encode into an owned string
+    pub fn to_base64(&self) -> String {
+        use alloc::string::ToString;
+        self.to_string()
+    }
+
+    /// Raw bytes, decoding if this was built from a base64 string
+    pub fn to_bytes(&self) -> Option<Vec<u8>> {
+        match &self.0 {
+            Repr::Raw(bytes) => Some(bytes.clone()),
+            Repr::Encoded(text) => decode(text),
+        }
+    }
+}
+
+/// Decode standard base64, padded or not; `None` on any other character,
+/// on padding that does not exactly complete the last group, and on set
+/// bits past the last byte, so each input has one encoding
+pub fn decode(text: &str) -> Option<Vec<u8>> {
+    fn value(c: u8) -> Option<u32> {
+        ALPHABET.iter().position(|&a| a == c).map(|v| v as u32)
+    }
+
+    let padded = text.as_bytes();
+    let text = padded.strip_suffix(b"==").or_else(|| padded.strip_suffix(b"=")).unwrap_or(padded);
+    let padding = padded.len() - text.len();
+    if padding > 0 && text.len() % 4 != 4 - padding {
+        return None;
+    }
+    let mut out = Vec::with_capacity(text.len() / 4 * 3 + 2);
+    for group in text.chunks(4) {
+        if group.len() == 1 {
+            return None;
+        }
+        let mut n = 0;
+        for (i, &c) in group.iter().enumerate() {
+            n |= value(c)? << (18 - 6 * i);
+        }
+        if n & (0xFF_FFFF >> (8 * (group.len() - 1))) != 0 {
+            return None;
+        }
+        let bytes = [(n >> 16) as u8, (n >> 8) as u8, n as u8];
+        out.extend_from_slice(&bytes[..group.len() - 1]);
+    }
+    Some(out)
+}
+
+impl From<Vec<u8>> for Base64Data {
+    fn from(bytes: Vec<u8>) -> Self {
+        Base64Data(Repr::Raw(bytes))
+    }
+}
+
+impl From<&[u8]> for Base64Data {
+    fn from(bytes: &[u8]) -> Self {
+        Base64Data(Repr::Raw(bytes.to_vec()))
+    }
+}
+
+impl From<String> for Base64Data {
+    fn from(encoded: String) -> Self {
+        Base64Data::encoded(encoded)
+    }
+}
+
+fn encode_chunk(input: &[u8], out: &mut [u8]) -> usize {
+    let mut len = 0;
+    for group in input.chunks(3) {
+        let b = [group[0], *group.get(1).unwrap_or(&0), *group.get(2).unwrap_or(&0)];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        out[len] = ALPHABET[(n >> 18) as usize & 63];
+        out[len + 1] = ALPHABET[(n >> 12) as usize & 63];
+        out[len + 2] = if group.len() > 1 { ALPHABET[(n >> 6) as usize & 63] } else { b'=' };
+        out[len + 3] = if group.len() > 2 { ALPHABET[n as usize & 63] } else { b'=' };
+        len += 4;
+    }
+    len
+}
+
+impl fmt::Display for Base64Data {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes = match &self.0 {
+            Repr::Raw(bytes) => bytes,
+            Repr::Encoded(text) => return f.write_str(text),
+        };
+        let mut buffer = [0u8; CHUNK / 3 * 4];
+        for chunk in bytes.chunks(CHUNK) {
+            let len = encode_chunk(chunk, &mut buffer);
+            // The alphabet is ASCII
+            f.write_str(core::str::from_utf8(&buffer[..len]).map_err(|_| fmt::Error)?)?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Base64Data {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match &self.0 {
+            Repr::Encoded(text) => serializer.serialize_str(text),
+            Repr::Raw(_) => serializer.collect_str(self),
+        }
+    }
+}
+
+/// Kept encoded; [`Base64Data::to_bytes`] decodes on demand
+impl<'de> Deserialize<'de> for Base64Data {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Base64Data::encoded)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use alloc::vec;
+
+    #[test]
+    fn test_encoding() {
+        for (input, expected) in [("", ""), ("f", "Zg=="), ("fo", "Zm8="), ("foo", "Zm9v"), ("foobar", "Zm9vYmFy")] {
+            let data = Base64Data::from(input.as_bytes());
+            assert_eq!(data.to_base64(), expected);
+            assert_eq!(data.encoded_len(), expected.len());
+        }
+    }
+
+    #[test]
+    fn test_decoding() {
+        for input in ["", "f", "fo", "foo", "foobar"] {
+            assert_eq!(decode(&Base64Data::from(input.as_bytes()).to_base64()).unwrap(), input.as_bytes());
+        }
+        assert_eq!(decode("Zm8").unwrap(), b"fo");
+        assert!(decode("Z").is_none());
+        assert!(decode("Zm9v!").is_none());
+        assert_eq!(decode("Zm8=").unwrap(), b"fo");
+        for loose in ["Zm8==", "Zm9=", "Zm9v=", "Zm9vYg===", "Zm=9v"] {
+            assert!(decode(loose).is_none(), "{}", loose);
+        }
+    }
+
+    #[test]
+    fn test_streams_across_chunks() {
+        let bytes: Vec<u8> = (0..=255u8).cycle().take(CHUNK * 3 + 2).collect();
+        let mut expected = vec![0u8; bytes.len().div_ceil(3) * 4];
+        encode_chunk(&bytes, &mut expected);
+
+        let json = serde_json::to_string(&Base64Data::from(bytes)).unwrap();
+        assert_eq!(json.trim_matches('"').as_bytes(), &expected[..]);
+        assert_eq!(serde_json::to_string(&Base64Data::encoded("AAEC")).unwrap(), "\"AAEC\"");
+    }
+}
@@ -0,0 +1,157 @@
+//! Zstd-compressed resource contents.
+//!
+//! A client that lists `zstd` under `capabilities.experimental` in its
+//! `initialize` request may receive contents whose `blob` is the base64 of a
+//! zstd frame, marked with `_meta.encoding: "zstd+base64"`. Compressed text
+//! also carries `_meta.compressedField: "text"` so it can be restored as text.
+//! Digests from [`integrity`](crate::integrity) cover the uncompressed bytes.
+//! Decompression stops at [`MAX_DECOMPRESSED_SIZE`], so a small frame cannot
+//! expand into unbounded memory.
+
+use crate::base64::{self, Base64Data};
+use crate::error::MCPError;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use ruzstd::encoding::{compress_to_vec, CompressionLevel};
+use ruzstd::io::Read;
+use serde_json::{json, Value};
+
+/// `_meta.encoding` value of compressed contents
+pub const ENCODING: &str = "zstd+base64";
+
+/// Key under `capabilities.experimental` that advertises support
+pub const CAPABILITY: &str = "zstd";
+
+/// Largest contents [`decompress`] restores
+pub const MAX_DECOMPRESSED_SIZE: usize = 64 * 1024 * 1024;
+
+/// Whether `capabilities` (from either side of `initialize`) advertise zstd
+pub fn supported(capabilities: &Value) -> bool {
+    capabilities.get("experimental").and_then(|e| e.get(CAPABILITY)).is_some()
+}
+
+/// Compress a contents object whose text or blob is at least `min_size`
+/// bytes; returns whether it did. Contents that would not shrink are left alone.
+pub fn compress(contents: &mut Value, min_size: usize) -> bool {
+    let (field, bytes) = if let Some(text) = contents.get("text").and_then(Value::as_str) {
+        ("text", text.as_bytes().to_vec())
+    } else if let Some(bytes) = contents.get("blob").and_then(Value::as_str).and_then(base64::decode) {
+        ("blob", bytes)
+    } else {
+        return false;
+    };
+    if bytes.len() < min_size {
+        return false;
+    }
+    let compressed = compress_to_vec(bytes.as_slice(), CompressionLevel::Fastest);
+    if compressed.len() >= bytes.len() {
+        return false;
+    }
+    let Some(object) = contents.as_object_mut() else { return false };
+    let Some(meta) = object.entry("_meta").or_insert_with(|| json!({})).as_object_mut() else { return false };
+    meta.insert("encoding".into(), ENCODING.into());
+    if field == "text" {
+        meta.insert("compressedField".into(), "text".into());
+    }
+    object.remove(field);
+    object.insert("blob".into(), Value::String(Base64Data::from(compressed).to_base64()));
+    true
+}
+
+/// Undo [`compress`] in place; `Ok(false)` when `contents` is not compressed
+pub fn decompress(contents: &mut Value) -> Result<bool, MCPError> {
+    let Some(meta) = contents.get("_meta") else { return Ok(false) };
+    match meta.get("encoding").and_then(Value::as_str) {
+        Some(ENCODING) => {}
+        Some(other) => return Err(MCPError::ContentEncoding(format!("unsupported encoding {}", other))),
+        None => return Ok(false),
+    }
+    let as_text = meta.get("compressedField").and_then(Value::as_str) == Some("text");
+    let uri = contents.get("uri").and_then(Value::as_str).unwrap_or_default().to_string();
+    let error = |reason: &str| MCPError::ContentEncoding(format!("{}: {}", uri, reason));
+
+    let compressed = contents.get("blob").and_then(Value::as_str).and_then(base64::decode)
+        .ok_or_else(|| error("blob is not valid base64"))?;
+    let bytes = decode(&compressed, MAX_DECOMPRESSED_SIZE).map_err(|e| error(&e))?;
+    let value = if as_text {
+        Value::String(String::from_utf8(bytes).map_err(|_| error("text is not valid UTF-8"))?)
+    } else {
+        Value::String(Base64Data::from(bytes).to_base64())
+    };
+
+    let Some(object) = contents.as_object_mut() else { return Ok(false) };
+    object.remove("blob");
+    object.insert(if as_text { "text" } else { "blob" }.into(), value);
+    if let Some(meta) = object.get_mut("_meta").and_then(Value::as_object_mut) {
+        meta.remove("encoding");
+        meta.remove("compressedField");
+        if meta.is_empty() {
+            object.remove("_meta");
+        }
+    }
+    Ok(true)
+}
+
+/// Decompress every contents object in a `resources/read` or `tools/call`
+/// result; returns how many were compressed
+pub fn decompress_result(result: &mut Value) -> Result<usize, MCPError> {
+    crate::contents::contents_mut(result).into_iter()
+        .try_fold(0, |count, contents| Ok(count + usize::from(decompress(contents)?)))
+}
+
+fn decode(mut compressed: &[u8], limit: usize) -> Result<Vec<u8>, String> {
+    let mut decoder = ruzstd::decoding::StreamingDecoder::new(&mut compressed).map_err(|e| e.to_string())?;
+    let mut bytes = Vec::new();
+    let mut chunk = [0u8; 16 * 1024];
+    loop {
+        let read = decoder.read(&mut chunk).map_err(|e| e.to_string())?;
+        if read == 0 {
+            return Ok(bytes);
+        }
+        if bytes.len() + read > limit {
+            return Err(format!("decompresses to more than {} bytes", limit));
+        }
+        bytes.extend_from_slice(&chunk[..read]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let text = "line of a large log file\n".repeat(200);
+        let mut contents = json!({ "uri": "file:///log", "mimeType": "text/plain", "text": text });
+        assert!(compress(&mut contents, 1024));
+        assert_eq!(contents["_meta"]["encoding"], ENCODING);
+        assert!(contents["blob"].as_str().unwrap().len() < text.len() / 4);
+
+        let mut result = json!({ "contents": [contents] });
+        assert_eq!(decompress_result(&mut result).unwrap(), 1);
+        assert_eq!(result["contents"][0], json!({ "uri": "file:///log", "mimeType": "text/plain", "text": text }));
+
+        let blob = Base64Data::from(alloc::vec![7u8; 4096]).to_base64();
+        let mut contents = json!({ "uri": "file:///bin", "blob": blob, "_meta": { "sha256": "x" } });
+        assert!(compress(&mut contents, 1024));
+        assert!(decompress(&mut contents).unwrap());
+        assert_eq!(contents, json!({ "uri": "file:///bin", "blob": blob, "_meta": { "sha256": "x" } }));
+    }
+
+    #[test]
+    fn test_small_or_unknown_contents() {
+        let mut small = json!({ "uri": "file:///a", "text": "abc" });
+        assert!(!compress(&mut small, 1024));
+        assert!(!decompress(&mut small).unwrap());
+
+        let mut gzip = json!({ "uri": "file:///a", "blob": "", "_meta": { "encoding": "gzip+base64" } });
+        assert!(matches!(decompress(&mut gzip), Err(MCPError::ContentEncoding(_))));
+        assert!(supported(&json!({ "experimental": { "zstd": {} } })));
+
+        let bomb = compress_to_vec([0u8; 4096].as_slice(), CompressionLevel::Fastest);
+        assert_eq!(decode(&bomb, 4096).unwrap().len(), 4096);
+        assert!(decode(&bomb, 4095).is_err());
+        assert!(!supported(&json!({})));
+    }
+}
@@ -0,0 +1,24 @@
+//! Locating resource contents objects inside results.
+
+use alloc::vec::Vec;
+use serde_json::Value;
+
+/// Resource contents objects in a `resources/read` or `tools/call` result
+pub fn contents_mut(result: &mut Value) -> Vec<&mut Value> {
+    if result.get("uri").is_some() {
+        return alloc::vec![result];
+    }
+    let Some(object) = result.as_object_mut() else { return Vec::new() };
+    let mut found = Vec::new();
+    for (key, value) in object.iter_mut() {
+        let Value::Array(items) = value else { continue };
+        match key.as_str() {
+            "contents" => found.extend(items.iter_mut()),
+            "content" => found.extend(items.iter_mut()
+                .filter(|block| block.get("type").and_then(Value::as_str) == Some("resource"))
+                .filter_map(|block| block.get_mut("resource"))),
+            _ => {}
+        }
+    }
+    found
+}
@@ -0,0 +1,43 @@
+//! `elicitation/create`: the server asking the user, through the client, for
+//! a decision or a few values.
+
+use alloc::string::String;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Method of an elicitation request
+pub const ELICIT_METHOD: &str = "elicitation/create";
+
+/// Params of `elicitation/create`
+#[derive(Debug, Serialize, Clone)]
+pub struct ElicitParams {
+    /// What the user is asked
+    pub message: String,
+    /// Flat object schema of the values asked for
+    #[serde(rename = "requestedSchema")]
+    pub requested_schema: Value,
+}
+
+impl ElicitParams {
+    /// Ask `message`, expecting values matching `requested_schema`
+    pub fn new(message: impl Into<String>, requested_schema: Value) -> Self {
+        ElicitParams { message: message.into(), requested_schema }
+    }
+}
+
+/// How the user answered
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ElicitAction {
+    Accept,
+    Decline,
+    Cancel,
+}
+
+/// The user's answer; `content` is only present when they accepted
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ElicitResult {
+    pub action: ElicitAction,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<Value>,
+}
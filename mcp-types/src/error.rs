@@ -0,0 +1,234 @@
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::error::Error as _;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum MCPError {
+    #[error("Invalid JSON-RPC version: {0}")]
+    InvalidJsonRpcVersion(String),
+    #[error("Method not found: {0}")]
+    MethodNotFound(String),
+    #[error("Missing parameters")]
+    MissingParameters,
+    #[error("Invalid parameters: {0}")]
+    InvalidParams(String),
+    #[error("Missing tool name")]
+    MissingToolName,
+    #[error("Unknown tool: {0}")]
+    UnknownTool(String),
+    #[error("Unknown prompt: {0}")]
+    UnknownPrompt(String),
+    #[error("Unknown resource: {0}")]
+    UnknownResource(String),
+    #[error("Resource not found: {0}")]
+    ResourceNotFound(String),
+    #[error("Command timeout")]
+    CommandTimeout,
+    #[error("Output too large")]
+    OutputTooLarge,
+    #[error("Stream error: {0}")]
+    StreamError(String),
+    #[error("Request was cancelled: {0}")]
+    RequestCancelled(String),
+    #[error("Unexpected response id: {0}")]
+    UnexpectedResponse(String),
+    #[error("Internal error: {0}")]
+    InternalError(String),
+    #[error("Unauthorized: {0}")]
+    Unauthorized(String),
+    #[error("Forbidden: {0}")]
+    Forbidden(String),
+    #[error("Rate limit exceeded")]
+    RateLimited,
+    #[error("Session already initialized")]
+    AlreadyInitialized,
+    #[error("Integrity check failed: {0}")]
+    IntegrityError(String),
+    #[error("Request timed out: {0}")]
+    RequestTimeout(String),
+    #[error("Server is shutting down")]
+    ShuttingDown,
+    #[error("Server busy: {0}")]
+    ServerBusy(String),
+    #[error("Session not initialized: {0}")]
+    NotInitialized(String),
+    #[error("Content encoding error: {0}")]
+    ContentEncoding(String),
+    /// The peer answered a request of ours with a JSON-RPC error
+    #[error("Peer returned error {}: {}", .0.code, .0.message)]
+    PeerError(JsonRpcError),
+    /// A typed parameter failed to deserialize; `pointer` locates it
+    #[error("Invalid parameter at {pointer:?}: {message}")]
+    InvalidParamAt {
+        pointer: String,
+        message: String,
+        expected: Option<String>,
+        received: Option<String>,
+    },
+    /// The request envelope breaks JSON-RPC 2.0; `pointer` locates the member
+    #[error("Invalid request at {pointer:?}: {message}")]
+    InvalidRequestAt {
+        pointer: String,
+        message: String,
+        expected: Option<String>,
+        received: Option<String>,
+    },
+    /// Tool arguments that do not match the tool's input schema
+    #[error("Invalid arguments: {}", summary(.0))]
+    InvalidArguments(Vec<SchemaViolation>),
+    /// A tool result whose structured content breaks the tool's output schema
+    #[error("Invalid tool output: {}", summary(.0))]
+    InvalidOutput(Vec<SchemaViolation>),
+    #[cfg(feature = "std")]
+    #[error("IO error: {0}")]
+    IoError(#[from] std::io::Error),
+    #[error("JSON error: {0}")]
+    JsonError(#[from] serde_json::Error),
+}
+
+/// One way tool arguments break their input schema
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaViolation {
+    /// JSON pointer to the offending value within the arguments
+    pub pointer: String,
+    /// The schema keyword that failed, such as `type` or `required`
+    pub keyword: String,
+    pub message: String,
+}
+
+fn summary(violations: &[SchemaViolation]) -> String {
+    violations.iter()
+        .map(|violation| format!("{:?}: {}", violation.pointer, violation.message))
+        .collect::<Vec<_>>()
+        .join("; ")
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonRpcError {
+    pub code: i32,
+    pub message: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl MCPError {
+    pub fn to_json_rpc_error(&self) -> JsonRpcError {
+        let (code, message) = match self {
+            MCPError::InvalidJsonRpcVersion(_) | MCPError::AlreadyInitialized => (-32600, self.to_string()),
+            MCPError::InvalidRequestAt { .. } => (-32600, self.to_string()),
+            MCPError::MethodNotFound(_) => (-32601, self.to_string()),
+            MCPError::MissingParameters | MCPError::InvalidParams(_) | MCPError::MissingToolName => (-32602, self.to_string()),
+            MCPError::InvalidParamAt { .. } | MCPError::InvalidArguments(_) => (-32602, self.to_string()),
+            MCPError::UnknownPrompt(_) | MCPError::UnknownResource(_) | MCPError::ResourceNotFound(_) => (-32602, self.to_string()),
+            MCPError::RequestCancelled(_) => (-32800, self.to_string()), // Custom cancellation code
+            MCPError::Unauthorized(_) => (-32001, self.to_string()),
+            MCPError::Forbidden(_) => (-32003, self.to_string()),
+            MCPError::RateLimited => (-32029, self.to_string()),
+            MCPError::RequestTimeout(_) => (-32004, self.to_string()),
+            MCPError::ShuttingDown => (-32000, self.to_string()),
+            MCPError::ServerBusy(_) => (-32005, self.to_string()),
+            MCPError::NotInitialized(_) => (-32002, self.to_string()),
+            _ => (-32603, self.to_string()),
+        };
+        JsonRpcError { code, message, data: self.error_data() }
+    }
+
+    /// Structured `data` for errors wrapping an underlying io/json error
+    fn error_data(&self) -> Option<Value> {
+        let mut data = match self {
+            #[cfg(feature = "std")]
+            MCPError::IoError(e) => {
+                let mut data = json!({
+                    "kind": "io",
+                    "ioErrorKind": format!("{:?}", e.kind()),
+                });
+                if let Some(errno) = e.raw_os_error() {
+                    data["errno"] = json!(errno);
+                }
+                data
+            }
+            MCPError::JsonError(e) => {
+                let category = match e.classify() {
+                    serde_json::error::Category::Io => "io",
+                    serde_json::error::Category::Syntax => "syntax",
+                    serde_json::error::Category::Data => "data",
+                    serde_json::error::Category::Eof => "eof",
+                };
+                json!({
+                    "kind": "json",
+                    "category": category,
+                    "line": e.line(),
+                    "column": e.column(),
+                })
+            }
+            MCPError::InvalidParamAt { pointer, expected, received, .. } => {
+                return Some(json!({
+                    "kind": "params",
+                    "pointer": pointer,
+                    "expected": expected,
+                    "received": received,
+                }));
+            }
+            MCPError::InvalidRequestAt { pointer, expected, received, .. } => {
+                return Some(json!({
+                    "kind": "request",
+                    "pointer": pointer,
+                    "expected": expected,
+                    "received": received,
+                }));
+            }
+            MCPError::InvalidArguments(violations) => {
+                return Some(json!({ "kind": "arguments", "violations": violations }));
+            }
+            MCPError::InvalidOutput(violations) => {
+                return Some(json!({ "kind": "output", "violations": violations }));
+            }
+            _ => return None,
+        };
+        data["sourceChain"] = Value::from(self.source_chain());
+        Some(data)
+    }
+
+    /// Messages of all underlying errors, outermost first
+    pub fn source_chain(&self) -> Vec<String> {
+        let mut chain = Vec::new();
+        let mut source = self.source();
+        while let Some(err) = source {
+            chain.push(err.to_string());
+            source = err.source();
+        }
+        chain
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_io_error_data() {
+        let err = MCPError::from(std::io::Error::from_raw_os_error(2));
+        let data = err.to_json_rpc_error().data.unwrap();
+        assert_eq!(data["kind"], "io");
+        assert_eq!(data["ioErrorKind"], "NotFound");
+        assert_eq!(data["errno"], 2);
+        assert_eq!(data["sourceChain"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_json_error_data() {
+        let err = MCPError::from(serde_json::from_str::<Value>("{").unwrap_err());
+        let data = err.to_json_rpc_error().data.unwrap();
+        assert_eq!(data["kind"], "json");
+        assert_eq!(data["category"], "eof");
+    }
+
+    #[test]
+    fn test_plain_error_has_no_data() {
+        assert!(MCPError::MissingParameters.to_json_rpc_error().data.is_none());
+    }
+}
@@ -0,0 +1,103 @@
+//! SHA-256 digests for resource contents.
+//!
+//! A digest covers the content itself: the UTF-8 bytes of `text`, or the
+//! decoded bytes of `blob`. It travels as `_meta.sha256` (lowercase hex) on
+//! the contents object, so a client can check a large artifact end to end.
+
+use crate::base64;
+pub use crate::contents::contents_mut;
+use crate::error::MCPError;
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+/// Lowercase hex SHA-256 of `data`
+pub fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data).iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Bytes a digest covers, or `None` if `contents` has no text or valid blob
+fn content_bytes(contents: &Value) -> Option<Vec<u8>> {
+    if let Some(text) = contents.get("text").and_then(Value::as_str) {
+        return Some(text.as_bytes().to_vec());
+    }
+    base64::decode(contents.get("blob")?.as_str()?)
+}
+
+/// Add `_meta.sha256` to a resource contents object; returns whether it did
+pub fn attach(contents: &mut Value) -> bool {
+    let Some(bytes) = content_bytes(contents) else { return false };
+    let Some(object) = contents.as_object_mut() else { return false };
+    let meta = object.entry("_meta").or_insert_with(|| json!({}));
+    match meta.as_object_mut() {
+        Some(meta) => {
+            meta.insert("sha256".into(), Value::String(sha256_hex(&bytes)));
+            true
+        }
+        None => false,
+    }
+}
+
+/// Check `_meta.sha256` on a resource contents object. `Ok(false)` when it
+/// carries no digest, an error when the digest does not match.
+pub fn verify(contents: &Value) -> Result<bool, MCPError> {
+    let Some(expected) = contents.get("_meta").and_then(|m| m.get("sha256")).and_then(Value::as_str) else {
+        return Ok(false);
+    };
+    let uri = contents.get("uri").and_then(Value::as_str).unwrap_or_default();
+    let bytes = content_bytes(contents)
+        .ok_or_else(|| MCPError::IntegrityError(format!("{}: no content to check", uri)))?;
+    let actual = sha256_hex(&bytes);
+    if !actual.eq_ignore_ascii_case(expected) {
+        return Err(MCPError::IntegrityError(format!("{}: expected sha256 {}, got {}", uri, expected, actual)));
+    }
+    Ok(true)
+}
+
+/// Verify every digest in a `resources/read` or `tools/call` result;
+/// returns how many were checked
+pub fn verify_result(result: &Value) -> Result<usize, MCPError> {
+    let embedded = result.get("content").and_then(Value::as_array).into_iter().flatten()
+        .filter(|block| block.get("type").and_then(Value::as_str) == Some("resource"))
+        .filter_map(|block| block.get("resource"));
+    let listed = result.get("contents").and_then(Value::as_array).into_iter().flatten();
+    let top = result.get("uri").is_some().then_some(result);
+    top.into_iter().chain(listed).chain(embedded)
+        .try_fold(0, |verified, contents| Ok(verified + usize::from(verify(contents)?)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attach_and_verify() {
+        let mut text = json!({ "uri": "file:///a", "mimeType": "text/plain", "text": "abc" });
+        assert!(attach(&mut text));
+        assert_eq!(text["_meta"]["sha256"], "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad");
+        assert!(verify(&text).unwrap());
+
+        let mut blob = json!({ "uri": "file:///b", "mimeType": "application/octet-stream", "blob": "YWJj" });
+        attach(&mut blob);
+        assert_eq!(blob["_meta"]["sha256"], text["_meta"]["sha256"]);
+
+        blob["blob"] = json!("YWJk");
+        assert!(matches!(verify(&blob), Err(MCPError::IntegrityError(_))));
+        assert!(!verify(&json!({ "uri": "file:///c", "text": "" })).unwrap());
+    }
+
+    #[test]
+    fn test_embedded_resources() {
+        let mut result = json!({ "content": [
+            { "type": "text", "text": "see attached" },
+            { "type": "resource", "resource": { "uri": "file:///a", "text": "abc" } },
+        ] });
+        for contents in contents_mut(&mut result) {
+            attach(contents);
+        }
+        assert!(result["content"][0].get("_meta").is_none());
+        assert_eq!(verify_result(&result).unwrap(), 1);
+    }
+}
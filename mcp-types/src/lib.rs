@@ -0,0 +1,45 @@
+//! MCP protocol types: JSON-RPC envelopes, errors and tool/prompt/resource definitions.
+//!
+//! This crate only depends on serde, so it can be used by clients and by
+//! targets that cannot pull in an async runtime. Disabling the default `std`
+//! feature builds it as `no_std` with `alloc`.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+pub mod base64;
+#[cfg(feature = "zstd")]
+pub mod compression;
+pub mod contents;
+pub mod elicitation;
+pub mod error;
+#[cfg(feature = "integrity")]
+pub mod integrity;
+pub mod media;
+pub mod protocol;
+pub mod request;
+pub mod response;
+pub mod roots;
+pub mod sampling;
+#[cfg(feature = "schemars")]
+pub mod schema;
+pub mod tools;
+
+pub use base64::Base64Data;
+pub use elicitation::{ElicitAction, ElicitParams, ElicitResult};
+pub use error::{MCPError, SchemaViolation};
+pub use request::MCPRequest;
+pub use response::{JsonRpcVersion, MCPResponse};
+pub use roots::{ListRootsResult, Root};
+pub use sampling::{CreateMessageParams, CreateMessageResult};
+pub use tools::{
+    Annotations, AudioContent, CancellationNotification, CancellationNotificationMessage,
+    CancellationParams, CompleteResult, Completion, CompletionArgument, CompletionReference,
+    CompletionsCapability, ContentBlock, EmbeddedResource, ImageContent, InitializeResponse,
+    LoggingCapability, ProgressNotification, ProgressNotificationMessage, ProgressParams, Prompt,
+    PromptArgument, PromptContent, PromptMessage, PromptResponse, PromptsCapability, Resource,
+    ResourceContent, ResourceLink, ResourcesCapability, Role, ServerCapabilities, ServerInfo,
+    StreamChunk, TextContent, Tool, ToolAnnotations, ToolContent, ToolInputSchema, ToolProperty,
+    ToolResponse, ToolsCapability,
+};
@@ -0,0 +1,108 @@
+//! Image and audio content built from raw bytes or files.
+//!
+//! [`ImageContent::from_bytes`] and [`AudioContent::from_bytes`] wrap bytes
+//! whose MIME type the caller knows; the base64 encoding happens while the
+//! result is serialized (see [`crate::base64`]). With the `infer` feature,
+//! `sniff` tells the type from the bytes' magic numbers, and with `std` as
+//! well `from_path` reads a file and sniffs it, rejecting files that are not
+//! images (or audio) of a known format.
+
+use crate::base64::Base64Data;
+#[cfg(feature = "infer")]
+use crate::error::MCPError;
+use crate::tools::{AudioContent, ImageContent};
+#[cfg(feature = "infer")]
+use alloc::format;
+use alloc::string::String;
+use alloc::vec::Vec;
+
+impl ImageContent {
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>, mime_type: impl Into<String>) -> Self {
+        ImageContent { data: Base64Data::from(bytes.into()), mime_type: mime_type.into(), annotations: None }
+    }
+
+    /// Image content with the MIME type read from the bytes' signature
+    #[cfg(feature = "infer")]
+    pub fn sniff(bytes: impl Into<Vec<u8>>) -> Result<Self, MCPError> {
+        let bytes = bytes.into();
+        let mime_type = sniff(&bytes, infer::MatcherType::Image, "an image")?;
+        Ok(Self::from_bytes(bytes, mime_type))
+    }
+
+    /// Read and sniff an image file
+    #[cfg(all(feature = "infer", feature = "std"))]
+    pub fn from_path(path: impl AsRef<std::path::Path>) -> Result<Self, MCPError> {
+        let path = path.as_ref();
+        Self::sniff(std::fs::read(path)?).map_err(|e| in_file(path, e))
+    }
+}
+
+impl AudioContent {
+    pub fn from_bytes(bytes: impl Into<Vec<u8>>, mime_type: impl Into<String>) -> Self {
+        AudioContent { data: Base64Data::from(bytes.into()), mime_type: mime_type.into(), annotations: None }
+    }
+
+    /// Audio content with the MIME type read from the bytes' signature
+    #[cfg(feature = "infer")]
+    pub fn sniff(bytes: impl Into<Vec<u8>>) -> Result<Self, MCPError> {
+        let bytes = bytes.into();
+        let mime_type = sniff(&bytes, infer::MatcherType::Audio, "audio")?;
+        Ok(Self::from_bytes(bytes, mime_type))
+    }
+
+    /// Read and sniff an audio file
+    #[cfg(all(feature = "infer", feature = "std"))]
+    pub fn from_path(path: impl AsRef<std::path::Path>) -> Result<Self, MCPError> {
+        let path = path.as_ref();
+        Self::sniff(std::fs::read(path)?).map_err(|e| in_file(path, e))
+    }
+}
+
+/// MIME type of `bytes` if they are of the `expected` kind
+#[cfg(feature = "infer")]
+fn sniff(bytes: &[u8], expected: infer::MatcherType, kind: &str) -> Result<&'static str, MCPError> {
+    match infer::get(bytes) {
+        Some(found) if found.matcher_type() == expected => Ok(found.mime_type()),
+        Some(found) => Err(MCPError::ContentEncoding(format!("expected {}, found {}", kind, found.mime_type()))),
+        None => Err(MCPError::ContentEncoding(format!("expected {}, found an unknown format", kind))),
+    }
+}
+
+#[cfg(all(feature = "infer", feature = "std"))]
+fn in_file(path: &std::path::Path, error: MCPError) -> MCPError {
+    match error {
+        MCPError::ContentEncoding(message) => MCPError::ContentEncoding(format!("{}: {}", path.display(), message)),
+        other => other,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_bytes() {
+        let image = ImageContent::from_bytes(&b"\x89PNG"[..], "image/png");
+        let json = serde_json::to_value(&image).unwrap();
+        assert_eq!(json, serde_json::json!({ "data": "iVBORw==", "mimeType": "image/png" }));
+        assert_eq!(AudioContent::from_bytes(alloc::vec![1, 2, 3], "audio/wav").data.to_base64(), "AQID");
+    }
+
+    #[cfg(all(feature = "infer", feature = "std"))]
+    #[test]
+    fn test_from_path() {
+        let dir = std::env::temp_dir().join(format!("mcp-media-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let png = dir.join("pixel.bin");
+        std::fs::write(&png, b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR").unwrap();
+        let wav = dir.join("beep.wav");
+        std::fs::write(&wav, b"RIFF\x24\0\0\0WAVEfmt ").unwrap();
+
+        assert_eq!(ImageContent::from_path(&png).unwrap().mime_type, "image/png");
+        assert_eq!(AudioContent::from_path(&wav).unwrap().mime_type, "audio/x-wav");
+        let error = ImageContent::from_path(&wav).unwrap_err().to_string();
+        assert!(error.contains("beep.wav") && error.contains("audio/x-wav"), "{}", error);
+        assert!(matches!(AudioContent::from_path(dir.join("missing")), Err(MCPError::IoError(_))));
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
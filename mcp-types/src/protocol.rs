@@ -0,0 +1,21 @@
+//! Protocol revisions and their negotiation, shared by servers and clients.
+
+/// Newest MCP protocol revision spoken here
+pub const PROTOCOL_VERSION: &str = "2025-06-18";
+
+/// Protocol revisions accepted during negotiation, newest first. Earlier
+/// revisions require JSON-RPC batches, which are not supported.
+pub const SUPPORTED_PROTOCOL_VERSIONS: &[&str] = &[PROTOCOL_VERSION];
+
+/// The revision to answer a client requesting `requested` with: the same
+/// one when supported, otherwise the newest
+pub fn negotiate_protocol_version(requested: Option<&str>) -> &'static str {
+    SUPPORTED_PROTOCOL_VERSIONS.iter()
+        .find(|version| Some(**version) == requested)
+        .unwrap_or(&PROTOCOL_VERSION)
+}
+
+/// Whether `version` is one of [`SUPPORTED_PROTOCOL_VERSIONS`]
+pub fn is_supported(version: &str) -> bool {
+    SUPPORTED_PROTOCOL_VERSIONS.contains(&version)
+}
@@ -0,0 +1,57 @@
+use alloc::string::String;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use crate::response::JsonRpcVersion;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MCPRequest {
+    /// JSON-RPC version string; absent in JSON-RPC 1.0. Servers decide at
+    /// runtime which versions they accept.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jsonrpc: Option<String>,
+    
+    /// Request ID; absent for notifications
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<Value>,
+    
+    pub method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub params: Option<Value>,
+}
+
+impl MCPRequest {
+    /// Create a JSON-RPC 2.0 request, or a notification when `id` is `None`
+    pub fn new(id: Option<Value>, method: impl Into<String>, params: Option<Value>) -> Self {
+        MCPRequest {
+            jsonrpc: Some("2.0".into()),
+            id,
+            method: method.into(),
+            params,
+        }
+    }
+
+    /// The `jsonrpc` member as sent
+    pub fn jsonrpc_version(&self) -> Option<&str> {
+        self.jsonrpc.as_deref()
+    }
+
+    /// The JSON-RPC version the request uses, `None` if it names an unknown one
+    pub fn version(&self) -> Option<JsonRpcVersion> {
+        JsonRpcVersion::detect(self.jsonrpc_version())
+    }
+    
+    /// Check if this is a JSON-RPC 2.0 request
+    pub fn is_v2(&self) -> bool {
+        self.jsonrpc_version() == Some("2.0")
+    }
+    
+    /// Check if this is a JSON-RPC 1.0 request (no version field or version "1.0")
+    pub fn is_v1(&self) -> bool {
+        matches!(self.jsonrpc_version(), None | Some("1.0"))
+    }
+    
+    /// Check if this is a notification (no id field)
+    pub fn is_notification(&self) -> bool {
+        self.id.is_none()
+    }
+}
@@ -1,76 +1,107 @@
-use serde::Serialize;
+use alloc::string::String;
+use core::fmt;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use serde_json::Value;
 use crate::error::JsonRpcError;
 
+/// JSON-RPC flavor of a message, detected per request at runtime
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum JsonRpcVersion {
+    /// No `jsonrpc` member (or `"1.0"`); errors carry `result: null`
+    V1_0,
+    #[default]
+    V2_0,
+}
+
+impl JsonRpcVersion {
+    /// The version named by a `jsonrpc` member, if it is a known one
+    pub fn detect(jsonrpc: Option<&str>) -> Option<Self> {
+        match jsonrpc {
+            None | Some("1.0") => Some(JsonRpcVersion::V1_0),
+            Some("2.0") => Some(JsonRpcVersion::V2_0),
+            Some(_) => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            JsonRpcVersion::V1_0 => "1.0",
+            JsonRpcVersion::V2_0 => "2.0",
+        }
+    }
+
+    fn is_v1(&self) -> bool {
+        *self == JsonRpcVersion::V1_0
+    }
+
+    /// A missing `jsonrpc` member means 1.0
+    fn missing() -> Self {
+        JsonRpcVersion::V1_0
+    }
+}
+
+impl fmt::Display for JsonRpcVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for JsonRpcVersion {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for JsonRpcVersion {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let version = String::deserialize(deserializer)?;
+        JsonRpcVersion::detect(Some(&version))
+            .ok_or_else(|| serde::de::Error::custom(alloc::format!("unknown JSON-RPC version {:?}", version)))
+    }
+}
+
 /// MCP Response structure supporting multiple JSON-RPC versions and schema variations
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct MCPResponse {
-    /// JSON-RPC version string - optional for 1.0, required for 2.0
-    #[cfg(feature = "jsonrpc-1")]
-    #[serde(skip_serializing_if = "Option::is_none")]
-    pub jsonrpc: Option<String>,
-    /// JSON-RPC version string, always "2.0" in strict mode
-    #[cfg(all(feature = "jsonrpc-2", not(feature = "jsonrpc-1")))]
-    pub jsonrpc: String,
+    /// JSON-RPC version; serialized as `"2.0"`, or omitted for 1.0
+    #[serde(default = "JsonRpcVersion::missing", skip_serializing_if = "JsonRpcVersion::is_v1")]
+    pub jsonrpc: JsonRpcVersion,
 
     /// Request ID (null for notifications)
     pub id: Option<Value>,
 
     /// Response result (success case)
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub result: Option<Value>,
 
     /// Response error (error case)
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub error: Option<JsonRpcError>,
 }
 
 impl MCPResponse {
     /// Helper for request too large error
     pub fn too_large() -> Self {
-        MCPResponse {
-            #[cfg(feature = "jsonrpc-1")]
-            jsonrpc: None,
-            #[cfg(all(feature = "jsonrpc-2", not(feature = "jsonrpc-1")))]
-            jsonrpc: "2.0".into(),
-            id: None,
-            #[cfg(feature = "jsonrpc-1")]
-            result: Some(Value::Null), // JSON-RPC 1.0 style
-            #[cfg(all(feature = "jsonrpc-2", not(feature = "jsonrpc-1")))]
-            result: None, // JSON-RPC 2.0 style
-            error: Some(JsonRpcError {
-                code: -32700,
-                message: "Request too large".into(),
-                data: None
-            }),
-        }
+        Self::v2_error(None, JsonRpcError {
+            code: -32700,
+            message: "Request too large".into(),
+            data: None
+        })
     }
 
     /// Helper for parse error
     pub fn parse_error() -> Self {
-        MCPResponse {
-            #[cfg(feature = "jsonrpc-1")]
-            jsonrpc: None,
-            #[cfg(all(feature = "jsonrpc-2", not(feature = "jsonrpc-1")))]
-            jsonrpc: "2.0".into(),
-            id: None,
-            #[cfg(feature = "jsonrpc-1")]
-            result: Some(Value::Null), // JSON-RPC 1.0 style
-            #[cfg(all(feature = "jsonrpc-2", not(feature = "jsonrpc-1")))]
-            result: None, // JSON-RPC 2.0 style
-            error: Some(JsonRpcError {
-                code: -32700,
-                message: "Parse error".into(),
-                data: None
-            }),
-        }
+        Self::v2_error(None, JsonRpcError {
+            code: -32700,
+            message: "Parse error".into(),
+            data: None
+        })
     }
 
     /// Create a JSON-RPC 1.0 success response
-    #[cfg(feature = "jsonrpc-1")]
     pub fn v1_success(id: Option<Value>, result: Value) -> Self {
         MCPResponse {
-            jsonrpc: None,
+            jsonrpc: JsonRpcVersion::V1_0,
             id,
             result: Some(result),
             error: None,
@@ -78,10 +109,9 @@ impl MCPResponse {
     }
 
     /// Create a JSON-RPC 1.0 error response
-    #[cfg(feature = "jsonrpc-1")]
     pub fn v1_error(id: Option<Value>, error: JsonRpcError) -> Self {
         MCPResponse {
-            jsonrpc: None,
+            jsonrpc: JsonRpcVersion::V1_0,
             id,
             result: Some(Value::Null), // 1.0 style: null result on error
             error: Some(error),
@@ -89,13 +119,9 @@ impl MCPResponse {
     }
 
     /// Create a JSON-RPC 2.0 success response
-    #[cfg(feature = "jsonrpc-2")]
     pub fn v2_success(id: Option<Value>, result: Value) -> Self {
         MCPResponse {
-            #[cfg(feature = "jsonrpc-1")]
-            jsonrpc: Some("2.0".into()),
-            #[cfg(all(feature = "jsonrpc-2", not(feature = "jsonrpc-1")))]
-            jsonrpc: "2.0".into(),
+            jsonrpc: JsonRpcVersion::V2_0,
             id,
             result: Some(result),
             error: None,
@@ -103,41 +129,39 @@ impl MCPResponse {
     }
 
     /// Create a JSON-RPC 2.0 error response
-    #[cfg(feature = "jsonrpc-2")]
     pub fn v2_error(id: Option<Value>, error: JsonRpcError) -> Self {
         MCPResponse {
-            #[cfg(feature = "jsonrpc-1")]
-            jsonrpc: Some("2.0".into()),
-            #[cfg(all(feature = "jsonrpc-2", not(feature = "jsonrpc-1")))]
-            jsonrpc: "2.0".into(),
+            jsonrpc: JsonRpcVersion::V2_0,
             id,
             result: None,
             error: Some(error),
         }
     }
 
-    /// Create a success response using the appropriate version
-    pub fn success(id: Option<Value>, result: Value) -> Self {
-        #[cfg(all(feature = "jsonrpc-2", not(feature = "jsonrpc-1")))]
-        {
-            Self::v2_success(id, result)
+    /// Create a success response in `version`
+    pub fn versioned_success(version: JsonRpcVersion, id: Option<Value>, result: Value) -> Self {
+        match version {
+            JsonRpcVersion::V1_0 => Self::v1_success(id, result),
+            JsonRpcVersion::V2_0 => Self::v2_success(id, result),
         }
-        #[cfg(feature = "jsonrpc-1")]
-        {
-            Self::v1_success(id, result)
+    }
+
+    /// Create an error response in `version`
+    pub fn versioned_error(version: JsonRpcVersion, id: Option<Value>, error: JsonRpcError) -> Self {
+        match version {
+            JsonRpcVersion::V1_0 => Self::v1_error(id, error),
+            JsonRpcVersion::V2_0 => Self::v2_error(id, error),
         }
     }
 
-    /// Create an error response using the appropriate version
+    /// Create a JSON-RPC 2.0 success response
+    pub fn success(id: Option<Value>, result: Value) -> Self {
+        Self::v2_success(id, result)
+    }
+
+    /// Create a JSON-RPC 2.0 error response
     pub fn error(id: Option<Value>, error: JsonRpcError) -> Self {
-        #[cfg(all(feature = "jsonrpc-2", not(feature = "jsonrpc-1")))]
-        {
-            Self::v2_error(id, error)
-        }
-        #[cfg(feature = "jsonrpc-1")]
-        {
-            Self::v1_error(id, error)
-        }
+        Self::v2_error(id, error)
     }
 
     /// Create notification response (should be None)
@@ -147,26 +171,12 @@ impl MCPResponse {
 
     /// Check if this is a JSON-RPC 2.0 response
     pub fn is_v2(&self) -> bool {
-        #[cfg(feature = "jsonrpc-1")]
-        {
-            self.jsonrpc.as_deref() == Some("2.0")
-        }
-        #[cfg(all(feature = "jsonrpc-2", not(feature = "jsonrpc-1")))]
-        {
-            true // Always 2.0 in strict mode
-        }
+        self.jsonrpc == JsonRpcVersion::V2_0
     }
 
     /// Check if this is a JSON-RPC 1.0 response
     pub fn is_v1(&self) -> bool {
-        #[cfg(feature = "jsonrpc-1")]
-        {
-            self.jsonrpc.is_none() || self.jsonrpc.as_deref() == Some("1.0")
-        }
-        #[cfg(all(feature = "jsonrpc-2", not(feature = "jsonrpc-1")))]
-        {
-            false // Never 1.0 in strict mode
-        }
+        self.jsonrpc == JsonRpcVersion::V1_0
     }
 
     /// Check if this response indicates success
@@ -205,7 +215,6 @@ mod tests {
         assert!(resp.error.is_some());
     }
 
-    #[cfg(feature = "jsonrpc-2")]
     #[test]
     fn test_v2_success_response() {
         let resp = MCPResponse::v2_success(Some(json!(1)), json!("test"));
@@ -215,7 +224,6 @@ mod tests {
         assert!(resp.error.is_none());
     }
 
-    #[cfg(feature = "jsonrpc-2")]
     #[test]
     fn test_v2_error_response() {
         let error = JsonRpcError {
@@ -229,7 +237,6 @@ mod tests {
         assert!(resp.error.is_some());
     }
 
-    #[cfg(feature = "jsonrpc-1")]
     #[test]
     fn test_v1_success_response() {
         let resp = MCPResponse::v1_success(Some(json!(1)), json!("test"));
@@ -239,7 +246,6 @@ mod tests {
         assert!(resp.error.is_none());
     }
 
-    #[cfg(feature = "jsonrpc-1")]
     #[test]
     fn test_v1_error_response() {
         let error = JsonRpcError {
@@ -254,6 +260,20 @@ mod tests {
         assert!(resp.error.is_some());
     }
 
+    #[test]
+    fn test_version_on_the_wire() {
+        let v1 = MCPResponse::versioned_error(JsonRpcVersion::V1_0, Some(json!(1)), JsonRpcError { code: -32601, message: "x".into(), data: None });
+        let wire = serde_json::to_value(&v1).unwrap();
+        assert!(wire.get("jsonrpc").is_none());
+        assert_eq!(wire["result"], Value::Null);
+        assert!(serde_json::from_value::<MCPResponse>(wire).unwrap().is_v1());
+
+        let v2 = serde_json::to_value(MCPResponse::versioned_success(JsonRpcVersion::V2_0, Some(json!(1)), json!({}))).unwrap();
+        assert_eq!(v2["jsonrpc"], "2.0");
+        assert!(serde_json::from_value::<MCPResponse>(v2).unwrap().is_v2());
+        assert!(serde_json::from_value::<MCPResponse>(json!({ "jsonrpc": "3.0", "id": 1 })).is_err());
+    }
+
     #[test]
     fn test_parse_error_helper() {
         let resp = MCPResponse::parse_error();
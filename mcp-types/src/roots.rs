@@ -0,0 +1,57 @@
+//! `roots/list`: the filesystem roots a client lets the server work in.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use serde::{Deserialize, Serialize};
+
+/// Method of a roots request
+pub const LIST_ROOTS_METHOD: &str = "roots/list";
+
+/// Sent by the client when its roots change
+pub const ROOTS_CHANGED_NOTIFICATION: &str = "notifications/roots/list_changed";
+
+/// A directory or file the client exposes, as a `file://` URI
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Root {
+    pub uri: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl Root {
+    /// Filesystem path of a `file://` root
+    pub fn path(&self) -> Option<&str> {
+        self.uri.strip_prefix("file://")
+    }
+
+    /// Whether absolute `path` is this root or lies below it. Paths are
+    /// compared as given; resolve `..` and symlinks first.
+    pub fn contains(&self, path: &str) -> bool {
+        let Some(root) = self.path().map(|root| root.trim_end_matches('/')) else { return false };
+        match path.strip_prefix(root) {
+            Some(rest) => rest.is_empty() || rest.starts_with('/'),
+            None => false,
+        }
+    }
+}
+
+/// Result of `roots/list`
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ListRootsResult {
+    pub roots: Vec<Root>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_contains() {
+        let root = Root { uri: "file:///home/me/project/".into(), name: None };
+        assert!(root.contains("/home/me/project"));
+        assert!(root.contains("/home/me/project/src/main.rs"));
+        assert!(!root.contains("/home/me/project-old/x"));
+        assert!(Root { uri: "file:///".into(), name: None }.contains("/etc"));
+        assert!(!Root { uri: "https://example.com".into(), name: None }.contains("/"));
+    }
+}
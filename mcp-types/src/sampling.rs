@@ -0,0 +1,102 @@
+//! `sampling/createMessage`: the server asking the client's model for a
+//! completion.
+
+use alloc::string::String;
+use alloc::vec::Vec;
+use crate::tools::{PromptMessage, Role};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Method of a sampling request
+pub const CREATE_MESSAGE_METHOD: &str = "sampling/createMessage";
+
+/// Params of `sampling/createMessage`
+#[derive(Debug, Serialize, Clone)]
+pub struct CreateMessageParams {
+    pub messages: Vec<PromptMessage>,
+    #[serde(rename = "maxTokens")]
+    pub max_tokens: u32,
+    #[serde(rename = "systemPrompt", skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub temperature: Option<f64>,
+    #[serde(rename = "stopSequences", skip_serializing_if = "Vec::is_empty")]
+    pub stop_sequences: Vec<String>,
+    /// Hints and priorities for the client's model choice
+    #[serde(rename = "modelPreferences", skip_serializing_if = "Option::is_none")]
+    pub model_preferences: Option<Value>,
+    /// `"none"`, `"thisServer"` or `"allServers"`
+    #[serde(rename = "includeContext", skip_serializing_if = "Option::is_none")]
+    pub include_context: Option<String>,
+}
+
+impl CreateMessageParams {
+    /// Sample up to `max_tokens` tokens in reply to `messages`
+    pub fn new(messages: Vec<PromptMessage>, max_tokens: u32) -> Self {
+        CreateMessageParams {
+            messages,
+            max_tokens,
+            system_prompt: None,
+            temperature: None,
+            stop_sequences: Vec::new(),
+            model_preferences: None,
+            include_context: None,
+        }
+    }
+
+    pub fn with_system_prompt(mut self, prompt: impl Into<String>) -> Self {
+        self.system_prompt = Some(prompt.into());
+        self
+    }
+
+    pub fn with_temperature(mut self, temperature: f64) -> Self {
+        self.temperature = Some(temperature);
+        self
+    }
+}
+
+/// The sampled message
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CreateMessageResult {
+    pub role: Role,
+    /// One content block, as sent by the client
+    pub content: Value,
+    /// Model that produced the message
+    pub model: String,
+    #[serde(rename = "stopReason", default, skip_serializing_if = "Option::is_none")]
+    pub stop_reason: Option<String>,
+}
+
+impl CreateMessageResult {
+    /// The message's text, if it is a text block
+    pub fn text(&self) -> Option<&str> {
+        match self.content.get("type")?.as_str()? {
+            "text" => self.content.get("text")?.as_str(),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_wire_format() {
+        let params = CreateMessageParams::new(alloc::vec![PromptMessage::user("Summarize")], 100)
+            .with_system_prompt("Be brief");
+        let wire = serde_json::to_value(&params).unwrap();
+        assert_eq!(wire["maxTokens"], 100);
+        assert_eq!(wire["messages"][0]["content"]["text"], "Summarize");
+        assert!(wire.get("temperature").is_none());
+
+        let result: CreateMessageResult = serde_json::from_value(json!({
+            "role": "assistant",
+            "content": { "type": "text", "text": "Done." },
+            "model": "m-1",
+            "stopReason": "endTurn",
+        })).unwrap();
+        assert_eq!((result.role, result.text()), (Role::Assistant, Some("Done.")));
+    }
+}
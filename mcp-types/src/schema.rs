@@ -0,0 +1,145 @@
+//! Tool input schemas derived from Rust types with schemars.
+//!
+//! Property maps written by hand drift from the struct the arguments end up
+//! in. [`ToolInputSchema::from_type`] builds the schema from the struct
+//! itself: each field becomes a property with its JSON type, its doc comment
+//! as description and its `#[serde(default)]` value, and fields that are
+//! neither `Option` nor defaulted are required. `ToolProperty` only carries a
+//! type, so nested structs and enums are listed under their JSON type
+//! without their inner structure.
+
+use crate::tools::{ToolInputSchema, ToolProperty, ToolPropertyItems};
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use schemars::JsonSchema;
+use serde_json::Value;
+
+impl ToolInputSchema {
+    /// The schema of `T`'s fields; `T` should derive `JsonSchema` and
+    /// `Deserialize`
+    pub fn from_type<T: JsonSchema>() -> Self {
+        let root = schemars::schema_for!(T);
+        let root = root.as_value();
+        let properties = root.get("properties").and_then(Value::as_object)
+            .map(|properties| {
+                properties.iter().map(|(name, schema)| (name.clone(), property(root, schema))).collect()
+            })
+            .unwrap_or_default();
+        let required = root.get("required").and_then(Value::as_array)
+            .map(|required| required.iter().filter_map(Value::as_str).map(String::from).collect())
+            .unwrap_or_default();
+        ToolInputSchema { schema_type: "object".into(), properties, required }
+    }
+}
+
+fn property(root: &Value, schema: &Value) -> ToolProperty {
+    let resolved = resolve(root, schema);
+    let property_type = json_type(root, schema);
+    let items = (property_type == "array")
+        .then(|| resolved.get("items"))
+        .flatten()
+        .map(|items| ToolPropertyItems { item_type: json_type(root, items) });
+    // A field's doc comment sits next to its `$ref`, a type's in the definition
+    let description = schema.get("description").or_else(|| resolved.get("description"))
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+    ToolProperty { property_type, description, items, default: schema.get("default").cloned() }
+}
+
+/// The definition a `$ref` points at, or `schema` itself
+fn resolve<'a>(root: &'a Value, schema: &'a Value) -> &'a Value {
+    let target = schema.get("$ref").and_then(Value::as_str)
+        .and_then(|reference| reference.strip_prefix('#'))
+        .and_then(|pointer| root.pointer(pointer));
+    match target {
+        Some(target) => resolve(root, target),
+        None => schema,
+    }
+}
+
+/// JSON type of values matching `schema`, ignoring `null` from `Option`
+fn json_type(root: &Value, schema: &Value) -> String {
+    let schema = resolve(root, schema);
+    match schema.get("type") {
+        Some(Value::String(kind)) => return kind.clone(),
+        Some(Value::Array(kinds)) => {
+            if let Some(kind) = kinds.iter().filter_map(Value::as_str).find(|kind| *kind != "null") {
+                return kind.into();
+            }
+        }
+        _ => {}
+    }
+    // `Option<Struct>` and enums are unions of subschemas
+    let branches: Vec<&Value> = ["anyOf", "oneOf"].iter()
+        .filter_map(|key| schema.get(*key)?.as_array())
+        .flatten()
+        .collect();
+    if let Some(kind) = branches.iter().map(|branch| json_type(root, branch)).find(|kind| kind != "null") {
+        return kind;
+    }
+    if let Some(values) = schema.get("enum").and_then(Value::as_array) {
+        return values.first().map_or("string", value_type).into();
+    }
+    schema.get("const").map_or("object", value_type).into()
+}
+
+fn value_type(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(n) if n.is_f64() => "number",
+        Value::Number(_) => "integer",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    /// How deep to look
+    #[derive(Deserialize, JsonSchema)]
+    #[serde(rename_all = "lowercase")]
+    #[allow(dead_code)]
+    enum Depth {
+        Shallow,
+        Deep,
+    }
+
+    #[derive(Deserialize, JsonSchema)]
+    #[allow(dead_code)]
+    struct SearchArgs {
+        /// Regular expression to search for
+        pattern: String,
+        /// Files to search, relative to the root
+        paths: Vec<String>,
+        /// Stop after this many matches
+        limit: Option<u32>,
+        #[serde(default = "default_context")]
+        context: u8,
+        depth: Depth,
+    }
+
+    fn default_context() -> u8 {
+        2
+    }
+
+    #[test]
+    fn test_from_type() {
+        let schema = ToolInputSchema::from_type::<SearchArgs>();
+        assert_eq!(schema.schema_type, "object");
+        assert_eq!(schema.required, ["pattern", "paths", "depth"]);
+
+        let pattern = &schema.properties["pattern"];
+        assert_eq!((pattern.property_type.as_str(), pattern.description.as_str()), ("string", "Regular expression to search for"));
+        assert_eq!(schema.properties["paths"].items.as_ref().unwrap().item_type, "string");
+        assert_eq!(schema.properties["limit"].property_type, "integer");
+        assert_eq!(schema.properties["context"].default, Some(Value::from(2)));
+        let depth = &schema.properties["depth"];
+        assert_eq!((depth.property_type.as_str(), depth.description.as_str()), ("string", "How deep to look"));
+    }
+}
@@ -0,0 +1,856 @@
+use alloc::collections::BTreeMap;
+use alloc::string::String;
+use alloc::vec;
+use alloc::vec::Vec;
+use crate::base64::Base64Data;
+use crate::error::MCPError;
+use alloc::format;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// Sender or intended reader of content
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    User,
+    Assistant,
+}
+
+impl Role {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Role::User => "user",
+            Role::Assistant => "assistant",
+        }
+    }
+}
+
+impl core::str::FromStr for Role {
+    type Err = MCPError;
+
+    fn from_str(role: &str) -> Result<Self, MCPError> {
+        match role {
+            "user" => Ok(Role::User),
+            "assistant" => Ok(Role::Assistant),
+            "system" => Err(MCPError::InvalidParams(
+                "role \"system\" is not allowed in prompt messages; put instructions in a user message".into(),
+            )),
+            other => Err(MCPError::InvalidParams(format!("unknown role {:?}, expected user or assistant", other))),
+        }
+    }
+}
+
+impl TryFrom<&str> for Role {
+    type Error = MCPError;
+
+    fn try_from(role: &str) -> Result<Self, MCPError> {
+        role.parse()
+    }
+}
+
+/// Hints about who content is for and how important it is
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Annotations {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audience: Option<Vec<Role>>,
+    /// 0.0 (optional) to 1.0 (required)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<f64>,
+}
+
+impl Annotations {
+    pub fn for_audience(audience: impl Into<Vec<Role>>) -> Self {
+        Annotations {
+            audience: Some(audience.into()),
+            priority: None,
+        }
+    }
+
+    pub fn with_priority(mut self, priority: f64) -> Self {
+        self.priority = Some(priority.clamp(0.0, 1.0));
+        self
+    }
+}
+
+/// Plain text content
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct TextContent {
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Annotations>,
+}
+
+/// Base64-encoded image content
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ImageContent {
+    pub data: Base64Data,
+    #[serde(rename = "mimeType")]
+    pub mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Annotations>,
+}
+
+/// Base64-encoded audio content
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct AudioContent {
+    pub data: Base64Data,
+    #[serde(rename = "mimeType")]
+    pub mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Annotations>,
+}
+
+/// Reference to a resource the client can fetch with `resources/read`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ResourceLink {
+    pub uri: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Annotations>,
+}
+
+/// Resource contents embedded directly in a result
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct EmbeddedResource {
+    pub resource: ResourceContent,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<Annotations>,
+}
+
+/// One block of tool output, tagged by `type` on the wire
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type")]
+pub enum ContentBlock {
+    #[serde(rename = "text")]
+    Text(TextContent),
+    #[serde(rename = "image")]
+    Image(ImageContent),
+    #[serde(rename = "audio")]
+    Audio(AudioContent),
+    #[serde(rename = "resource_link")]
+    ResourceLink(ResourceLink),
+    #[serde(rename = "resource")]
+    Resource(EmbeddedResource),
+}
+
+/// One chunk of tool output
+pub type ToolContent = ContentBlock;
+
+impl ContentBlock {
+    pub fn text(text: impl Into<String>) -> Self {
+        ContentBlock::Text(TextContent { text: text.into(), annotations: None })
+    }
+
+    pub fn resource_link(uri: impl Into<String>, name: impl Into<String>) -> Self {
+        ContentBlock::ResourceLink(ResourceLink {
+            uri: uri.into(),
+            name: name.into(),
+            description: None,
+            mime_type: None,
+            size: None,
+            annotations: None,
+        })
+    }
+
+    pub fn annotations(&self) -> Option<&Annotations> {
+        match self {
+            ContentBlock::Text(c) => c.annotations.as_ref(),
+            ContentBlock::Image(c) => c.annotations.as_ref(),
+            ContentBlock::Audio(c) => c.annotations.as_ref(),
+            ContentBlock::ResourceLink(c) => c.annotations.as_ref(),
+            ContentBlock::Resource(c) => c.annotations.as_ref(),
+        }
+    }
+
+    pub fn annotations_mut(&mut self) -> &mut Option<Annotations> {
+        match self {
+            ContentBlock::Text(c) => &mut c.annotations,
+            ContentBlock::Image(c) => &mut c.annotations,
+            ContentBlock::Audio(c) => &mut c.annotations,
+            ContentBlock::ResourceLink(c) => &mut c.annotations,
+            ContentBlock::Resource(c) => &mut c.annotations,
+        }
+    }
+
+    pub fn with_annotations(mut self, annotations: Annotations) -> Self {
+        *self.annotations_mut() = Some(annotations);
+        self
+    }
+
+    /// Text of a text block
+    pub fn as_text(&self) -> Option<&str> {
+        match self {
+            ContentBlock::Text(content) => Some(&content.text),
+            _ => None,
+        }
+    }
+}
+
+/// Full tool response
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ToolResponse {
+    pub content: Vec<ContentBlock>,
+    #[serde(rename = "isError", default)]
+    pub is_error: bool,
+    #[serde(rename = "structuredContent", skip_serializing_if = "Option::is_none")]
+    pub structured_content: Option<Value>,
+}
+
+impl ToolResponse {
+    pub fn new(text: String, is_error: bool) -> Self {
+        ToolResponse {
+            content: vec![ContentBlock::text(text)],
+            is_error,
+            structured_content: None,
+        }
+    }
+
+    pub fn from_content(content: Vec<ContentBlock>, is_error: bool) -> Self {
+        ToolResponse {
+            content,
+            is_error,
+            structured_content: None,
+        }
+    }
+
+    pub fn with_structured_content(mut self, structured_content: Value) -> Self {
+        self.structured_content = Some(structured_content);
+        self
+    }
+}
+
+/// Progress notification for long-running operations
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProgressNotification {
+    #[serde(rename = "requestId")]
+    pub request_id: String,
+    pub progress: f64, // 0.0 to 1.0
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+/// Cancellation notification
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CancellationNotification {
+    #[serde(rename = "requestId")]
+    pub request_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+/// JSON-RPC progress notification message (MCP protocol compliant)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProgressNotificationMessage {
+    pub jsonrpc: String,
+    pub method: String,
+    pub params: ProgressParams,
+}
+
+/// Parameters for progress notifications
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProgressParams {
+    #[serde(rename = "requestId")]
+    pub request_id: String,
+    pub progress: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>,
+}
+
+impl ProgressNotificationMessage {
+    /// Create a new progress notification message
+    pub fn new(request_id: String, progress: f64, message: Option<String>) -> Self {
+        Self {
+            jsonrpc: "2.0".into(),
+            method: "notifications/progress".into(),
+            params: ProgressParams {
+                request_id,
+                progress,
+                message,
+            },
+        }
+    }
+}
+
+/// JSON-RPC cancellation notification message (MCP protocol compliant)
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CancellationNotificationMessage {
+    pub jsonrpc: String,
+    pub method: String,
+    pub params: CancellationParams,
+}
+
+/// Parameters for cancellation notifications
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CancellationParams {
+    #[serde(rename = "requestId")]
+    pub request_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+}
+
+impl CancellationNotificationMessage {
+    /// Create a new cancellation notification message
+    pub fn new(request_id: String, reason: Option<String>) -> Self {
+        Self {
+            jsonrpc: "2.0".into(),
+            method: "notifications/cancelled".into(),
+            params: CancellationParams {
+                request_id,
+                reason,
+            },
+        }
+    }
+}
+
+/// Prompt definition with parameters
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Prompt {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<Vec<PromptArgument>>,
+}
+
+/// Prompt argument definition
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PromptArgument {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub required: bool,
+    /// JSON-schema fragment (`type`, `enum`, `pattern`) the value must satisfy
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub schema: Option<Value>,
+}
+
+/// Prompt response with messages
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PromptResponse {
+    #[serde(default)]
+    pub description: String,
+    pub messages: Vec<PromptMessage>,
+}
+
+/// Individual prompt message
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PromptMessage {
+    pub role: Role,
+    pub content: PromptContent,
+}
+
+/// Prompt message content
+pub type PromptContent = ContentBlock;
+
+impl PromptMessage {
+    /// Build a message, rejecting content the role may not carry
+    pub fn new(role: Role, content: ContentBlock) -> Result<Self, MCPError> {
+        let message = PromptMessage { role, content };
+        message.validate()?;
+        Ok(message)
+    }
+
+    pub fn user(text: impl Into<String>) -> Self {
+        PromptMessage { role: Role::User, content: ContentBlock::text(text) }
+    }
+
+    pub fn assistant(text: impl Into<String>) -> Self {
+        PromptMessage { role: Role::Assistant, content: ContentBlock::text(text) }
+    }
+
+    /// Resources are context supplied on the user's side, so assistant
+    /// messages may only carry text, image or audio
+    pub fn validate(&self) -> Result<(), MCPError> {
+        match (self.role, &self.content) {
+            (Role::Assistant, ContentBlock::ResourceLink(_) | ContentBlock::Resource(_)) => Err(MCPError::InvalidParams(
+                "assistant prompt messages cannot carry resources".into(),
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Resource definition
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Resource {
+    pub uri: String,
+    pub name: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(rename = "mimeType", skip_serializing_if = "Option::is_none")]
+    pub mime_type: Option<String>,
+}
+
+/// Resource content response; binary contents go in `blob` and are sent
+/// instead of `text`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(from = "ResourceContentWire")]
+pub struct ResourceContent {
+    pub uri: String,
+    pub mime_type: String,
+    pub text: String,
+    pub blob: Option<Base64Data>,
+}
+
+impl ResourceContent {
+    pub fn blob(uri: impl Into<String>, mime_type: impl Into<String>, blob: impl Into<Base64Data>) -> Self {
+        ResourceContent {
+            uri: uri.into(),
+            mime_type: mime_type.into(),
+            text: String::new(),
+            blob: Some(blob.into()),
+        }
+    }
+}
+
+impl Serialize for ResourceContent {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+
+        let mut state = serializer.serialize_struct("ResourceContent", 3)?;
+        state.serialize_field("uri", &self.uri)?;
+        state.serialize_field("mimeType", &self.mime_type)?;
+        match &self.blob {
+            Some(blob) => state.serialize_field("blob", blob)?,
+            None => state.serialize_field("text", &self.text)?,
+        }
+        state.end()
+    }
+}
+
+/// `ResourceContent` as received, with `text` or `blob`
+#[derive(Deserialize)]
+struct ResourceContentWire {
+    uri: String,
+    #[serde(rename = "mimeType", default)]
+    mime_type: String,
+    #[serde(default)]
+    text: String,
+    blob: Option<Base64Data>,
+}
+
+impl From<ResourceContentWire> for ResourceContent {
+    fn from(wire: ResourceContentWire) -> Self {
+        ResourceContent { uri: wire.uri, mime_type: wire.mime_type, text: wire.text, blob: wire.blob }
+    }
+}
+
+/// Streaming chunk for long operations
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct StreamChunk {
+    pub chunk_type: String, // "progress", "data", "complete", "error"
+    pub data: Value,
+}
+
+/// Server capabilities object; a `None` feature is not offered
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ServerCapabilities {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tools: Option<ToolsCapability>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompts: Option<PromptsCapability>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<ResourcesCapability>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub logging: Option<LoggingCapability>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completions: Option<CompletionsCapability>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub experimental: Option<serde_json::Map<String, Value>>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ToolsCapability {
+    /// The server sends `notifications/tools/list_changed`
+    #[serde(rename = "listChanged", default, skip_serializing_if = "Option::is_none")]
+    pub list_changed: Option<bool>,
+}
+
+impl ToolsCapability {
+    pub fn with_list_changed(mut self, list_changed: bool) -> Self {
+        self.list_changed = Some(list_changed);
+        self
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct PromptsCapability {
+    #[serde(rename = "listChanged", default, skip_serializing_if = "Option::is_none")]
+    pub list_changed: Option<bool>,
+}
+
+impl PromptsCapability {
+    pub fn with_list_changed(mut self, list_changed: bool) -> Self {
+        self.list_changed = Some(list_changed);
+        self
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ResourcesCapability {
+    /// Clients may `resources/subscribe` to updates
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub subscribe: Option<bool>,
+    #[serde(rename = "listChanged", default, skip_serializing_if = "Option::is_none")]
+    pub list_changed: Option<bool>,
+}
+
+impl ResourcesCapability {
+    pub fn with_subscribe(mut self, subscribe: bool) -> Self {
+        self.subscribe = Some(subscribe);
+        self
+    }
+
+    pub fn with_list_changed(mut self, list_changed: bool) -> Self {
+        self.list_changed = Some(list_changed);
+        self
+    }
+}
+
+/// The server accepts `logging/setLevel` and sends `notifications/message`
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct LoggingCapability {}
+
+/// The server answers `completion/complete`
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct CompletionsCapability {}
+
+/// Argument being completed in `completion/complete`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CompletionArgument {
+    pub name: String,
+    pub value: String,
+}
+
+/// What a `completion/complete` request refers to
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(tag = "type")]
+pub enum CompletionReference {
+    #[serde(rename = "ref/prompt")]
+    Prompt { name: String },
+    #[serde(rename = "ref/resource")]
+    Resource { uri: String },
+}
+
+/// Suggested values for a completion request
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Completion {
+    pub values: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<u64>,
+    #[serde(rename = "hasMore", skip_serializing_if = "Option::is_none")]
+    pub has_more: Option<bool>,
+}
+
+/// Result of `completion/complete`
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct CompleteResult {
+    pub completion: Completion,
+}
+
+/// Response to initialize()
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct InitializeResponse {
+    #[serde(rename = "protocolVersion")]
+    pub protocol_version: String,
+    pub capabilities: ServerCapabilities,
+    #[serde(rename = "serverInfo")]
+    pub server_info: ServerInfo,
+    /// How to use the server, for the client to pass on to the model
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub instructions: Option<String>,
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+}
+
+/// Static server info
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ServerInfo {
+    pub name: String,
+    pub version: String,
+}
+
+/// Schema for a single tool's inputs
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ToolInputSchema {
+    #[serde(rename = "type")]
+    pub schema_type: String,
+    #[serde(default)]
+    pub properties: BTreeMap<String, ToolProperty>,
+    #[serde(default)]
+    pub required: Vec<String>,
+}
+
+/// One property in a tool's input schema
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ToolProperty {
+    #[serde(rename = "type")]
+    pub property_type: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub items: Option<ToolPropertyItems>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub default: Option<Value>,
+}
+
+/// When `ToolProperty` is an array
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ToolPropertyItems {
+    #[serde(rename = "type")]
+    pub item_type: String,
+}
+
+/// One tool's metadata
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Tool {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(rename = "inputSchema")]
+    pub input_schema: ToolInputSchema,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub annotations: Option<ToolAnnotations>,
+    #[serde(rename = "_meta", skip_serializing_if = "Option::is_none")]
+    pub meta: Option<Value>,
+}
+
+/// Behavioral hints about a tool; clients must treat them as untrusted
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ToolAnnotations {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(rename = "readOnlyHint", skip_serializing_if = "Option::is_none")]
+    pub read_only_hint: Option<bool>,
+    #[serde(rename = "destructiveHint", skip_serializing_if = "Option::is_none")]
+    pub destructive_hint: Option<bool>,
+    #[serde(rename = "idempotentHint", skip_serializing_if = "Option::is_none")]
+    pub idempotent_hint: Option<bool>,
+    #[serde(rename = "openWorldHint", skip_serializing_if = "Option::is_none")]
+    pub open_world_hint: Option<bool>,
+}
+
+impl Tool {
+    pub fn new(name: impl Into<String>, description: impl Into<String>, input_schema: ToolInputSchema) -> Self {
+        Tool {
+            name: name.into(),
+            description: description.into(),
+            input_schema,
+            annotations: None,
+            meta: None,
+        }
+    }
+
+    pub fn with_annotations(mut self, annotations: ToolAnnotations) -> Self {
+        self.annotations = Some(annotations);
+        self
+    }
+}
+
+impl ToolProperty {
+    pub fn string(description: impl Into<String>) -> Self {
+        ToolProperty {
+            property_type: "string".into(),
+            description: description.into(),
+            items: None,
+            default: None,
+        }
+    }
+
+    pub fn array(description: impl Into<String>, item_type: impl Into<String>) -> Self {
+        ToolProperty {
+            property_type: "array".into(),
+            description: description.into(),
+            items: Some(ToolPropertyItems { item_type: item_type.into() }),
+            default: None,
+        }
+    }
+
+    pub fn boolean(description: impl Into<String>, default: bool) -> Self {
+        ToolProperty {
+            property_type: "boolean".into(),
+            description: description.into(),
+            items: None,
+            default: Some(Value::Bool(default)),
+        }
+    }
+}
+
+impl Prompt {
+    pub fn new(name: impl Into<String>, description: impl Into<String>) -> Self {
+        Prompt {
+            name: name.into(),
+            description: description.into(),
+            arguments: None,
+        }
+    }
+
+    pub fn with_arguments(mut self, args: Vec<PromptArgument>) -> Self {
+        self.arguments = Some(args);
+        self
+    }
+}
+
+impl PromptArgument {
+    pub fn new(name: impl Into<String>, description: impl Into<String>, required: bool) -> Self {
+        PromptArgument {
+            name: name.into(),
+            description: description.into(),
+            required,
+            schema: None,
+        }
+    }
+
+    pub fn with_schema(mut self, schema: Value) -> Self {
+        self.schema = Some(schema);
+        self
+    }
+
+    /// Allowed values declared by the schema's `enum`
+    pub fn enum_values(&self) -> Vec<&str> {
+        self.schema.as_ref()
+            .and_then(|s| s.get("enum"))
+            .and_then(Value::as_array)
+            .map(|values| values.iter().filter_map(Value::as_str).collect())
+            .unwrap_or_default()
+    }
+}
+
+impl Resource {
+    pub fn new(uri: impl Into<String>, name: impl Into<String>) -> Self {
+        Resource {
+            uri: uri.into(),
+            name: name.into(),
+            description: None,
+            mime_type: None,
+        }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn with_mime_type(mut self, mime_type: impl Into<String>) -> Self {
+        self.mime_type = Some(mime_type.into());
+        self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_prompt_message_roles() {
+        assert_eq!("assistant".parse::<Role>().unwrap(), Role::Assistant);
+        assert!(Role::try_from("system").is_err());
+
+        let link = ContentBlock::resource_link("file:///notes.md", "notes");
+        assert!(PromptMessage::new(Role::User, link.clone()).is_ok());
+        assert!(PromptMessage::new(Role::Assistant, link).is_err());
+
+        let json = serde_json::to_value(PromptMessage::user("hi")).unwrap();
+        assert_eq!(json, serde_json::json!({ "role": "user", "content": { "type": "text", "text": "hi" } }));
+    }
+
+    #[test]
+    fn test_capabilities_wire_format() {
+        let capabilities = ServerCapabilities {
+            tools: Some(ToolsCapability::default().with_list_changed(true)),
+            resources: Some(ResourcesCapability::default().with_subscribe(true)),
+            logging: Some(LoggingCapability {}),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(&capabilities).unwrap();
+        assert_eq!(json, serde_json::json!({
+            "tools": { "listChanged": true },
+            "resources": { "subscribe": true },
+            "logging": {},
+        }));
+        assert_eq!(serde_json::from_value::<ServerCapabilities>(json).unwrap(), capabilities);
+    }
+
+    /// `value` reads back from its own JSON into the same JSON
+    fn assert_round_trip<T: Serialize + serde::de::DeserializeOwned>(value: &T) {
+        let json = serde_json::to_value(value).unwrap();
+        let parsed: T = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), json);
+    }
+
+    #[test]
+    fn test_content_round_trip() {
+        let annotations = Annotations::for_audience([Role::User]).with_priority(0.5);
+        let blocks = [
+            ContentBlock::text("hello").with_annotations(annotations),
+            ContentBlock::Image(ImageContent { data: Base64Data::from(&b"png"[..]), mime_type: "image/png".into(), annotations: None }),
+            ContentBlock::Audio(AudioContent { data: Base64Data::encoded("AAEC"), mime_type: "audio/wav".into(), annotations: None }),
+            ContentBlock::resource_link("file:///notes.md", "notes"),
+            ContentBlock::Resource(EmbeddedResource {
+                resource: ResourceContent { uri: "file:///a.txt".into(), mime_type: "text/plain".into(), text: "a".into(), blob: None },
+                annotations: None,
+            }),
+            ContentBlock::Resource(EmbeddedResource { resource: ResourceContent::blob("file:///a.bin", "application/octet-stream", vec![1, 2]), annotations: None }),
+        ];
+        for block in &blocks {
+            assert_round_trip(block);
+        }
+
+        let parsed: Vec<ContentBlock> = serde_json::from_value(serde_json::to_value(&blocks).unwrap()).unwrap();
+        assert_eq!(parsed[0].annotations().unwrap().audience, Some(vec![Role::User]));
+        let ContentBlock::Resource(EmbeddedResource { resource, .. }) = &parsed[5] else { panic!("not a resource") };
+        assert_eq!(resource.blob.as_ref().unwrap().to_bytes().unwrap(), [1, 2]);
+        assert!(serde_json::from_value::<ContentBlock>(serde_json::json!({ "type": "video" })).is_err());
+    }
+
+    #[test]
+    fn test_protocol_round_trip() {
+        let schema = ToolInputSchema {
+            schema_type: "object".into(),
+            properties: BTreeMap::from([("all".into(), ToolProperty::boolean("Include hidden", false))]),
+            required: vec![],
+        };
+        let tool = Tool::new("ls", "List files", schema)
+            .with_annotations(ToolAnnotations { read_only_hint: Some(true), ..Default::default() });
+        assert_round_trip(&tool);
+        assert_round_trip(&ToolResponse::new("done".into(), false).with_structured_content(serde_json::json!({ "files": 2 })));
+        assert_round_trip(&ProgressNotificationMessage::new("7".into(), 0.5, Some("halfway".into())));
+        assert_round_trip(&CancellationNotificationMessage::new("7".into(), None));
+        assert_round_trip(&ProgressNotification { request_id: "7".into(), progress: 1.0, message: None });
+        assert_round_trip(&CancellationNotification { request_id: "7".into(), reason: Some("user".into()) });
+        let argument = PromptArgument { name: "lang".into(), description: "Language".into(), required: true, schema: None };
+        assert_round_trip(&Prompt::new("review", "Review code").with_arguments(vec![argument]));
+        assert_round_trip(&PromptResponse { description: "Review".into(), messages: vec![PromptMessage::user("look"), PromptMessage::assistant("ok")] });
+        assert_round_trip(&Resource { uri: "file:///a".into(), name: "a".into(), description: None, mime_type: Some("text/plain".into()) });
+        assert_round_trip(&StreamChunk { chunk_type: "data".into(), data: serde_json::json!([1]) });
+        assert_round_trip(&CompletionArgument { name: "lang".into(), value: "ru".into() });
+        assert_round_trip(&CompletionReference::Prompt { name: "review".into() });
+        assert_round_trip(&CompleteResult { completion: Completion { values: vec!["rust".into()], total: Some(1), has_more: Some(false) } });
+        assert_round_trip(&InitializeResponse {
+            protocol_version: "2025-06-18".into(),
+            capabilities: ServerCapabilities { tools: Some(ToolsCapability::default()), ..Default::default() },
+            server_info: ServerInfo { name: "test".into(), version: "1.0".into() },
+            instructions: Some("Be brief".into()),
+            meta: None,
+        });
+    }
+
+    #[test]
+    fn test_parses_minimal_server_output() {
+        // Optional members left out, as other servers send them
+        let result: ToolResponse = serde_json::from_value(serde_json::json!({ "content": [{ "type": "text", "text": "hi" }] })).unwrap();
+        assert!(!result.is_error);
+        assert_eq!(result.content[0].as_text(), Some("hi"));
+        let tool: Tool = serde_json::from_value(serde_json::json!({ "name": "ls", "inputSchema": { "type": "object" } })).unwrap();
+        assert!(tool.annotations.is_none() && tool.input_schema.properties.is_empty());
+        let prompt: Prompt = serde_json::from_value(serde_json::json!({ "name": "review" })).unwrap();
+        assert!(prompt.description.is_empty());
+    }
+}
@@ -1,16 +1,82 @@
 use async_trait::async_trait;
+use mcp_sdk::declarative::{DeclarativeTools, WithDeclarativeTools};
 use mcp_sdk::error::MCPError;
-use mcp_sdk::notifications::ProgressSender;
-use mcp_sdk::request::MCPRequest;
+use mcp_sdk::guards::AuditLog;
+use mcp_sdk::journal::{replay, Journal};
+use mcp_sdk::config::LiveConfig;
+use mcp_sdk::context::RequestContext;
+use mcp_sdk::priority::{BackgroundLimits, Priority};
+use mcp_sdk::ready::ReadySignal;
+use mcp_sdk::shutdown::DEFAULT_SHUTDOWN_DEADLINE;
+use mcp_sdk::subprocess::SubprocessEnv;
 use mcp_sdk::server::{SystemMCPServer, ToolHandler};
-use mcp_sdk::tools::{Tool, ToolInputSchema, ToolProperty, ToolResponse};
-use serde_json::Value;
-use std::collections::HashMap;
-use std::process::Stdio;
+use mcp_sdk::tools::{Annotations, Role, Tool, ToolAnnotations, ToolInputSchema, ToolProperty, ToolResponse};
+#[cfg(unix)]
+use mcp_sdk::transport::{accept_unix, TransportSet};
+use mcp_sdk::transport::StdioTransport;
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use std::process::{ExitStatus, Stdio};
+use std::sync::Arc;
 use tokio::io::{AsyncBufReadExt, BufReader};
-use tokio::process::Command;
+use tokio::time::{Duration, Instant};
+use tracing::Instrument;
 
-struct BashToolHandler;
+struct BashToolHandler {
+    // Applied to commands of background-lane calls
+    background: Option<BackgroundLimits>,
+}
+
+/// How stdout and stderr are presented in the tool result
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CaptureMode {
+    /// STDOUT and STDERR sections, each in arrival order
+    Separate,
+    /// Both streams merged in arrival order with timestamps and stream tags
+    Interleaved,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum OutputStream {
+    Stdout,
+    Stderr,
+}
+
+impl OutputStream {
+    fn as_str(self) -> &'static str {
+        match self {
+            OutputStream::Stdout => "stdout",
+            OutputStream::Stderr => "stderr",
+        }
+    }
+}
+
+/// One captured line of subprocess output
+#[derive(Debug, Clone)]
+struct OutputLine {
+    stream: OutputStream,
+    /// Milliseconds since the process was spawned
+    elapsed_ms: u128,
+    text: String,
+}
+
+impl OutputLine {
+    fn new(stream: OutputStream, started: Instant, text: String) -> Self {
+        OutputLine {
+            stream,
+            elapsed_ms: started.elapsed().as_millis(),
+            text,
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "stream": self.stream.as_str(),
+            "timestampMs": self.elapsed_ms as u64,
+            "text": self.text,
+        })
+    }
+}
 
 #[async_trait]
 impl ToolHandler for BashToolHandler {
@@ -18,30 +84,117 @@ impl ToolHandler for BashToolHandler {
         &self,
         name: &str,
         args: &Value,
-        progress_sender: ProgressSender,
+        ctx: &RequestContext,
     ) -> Result<ToolResponse, MCPError> {
         match name {
-            "bash" => self.execute_bash_command(args, progress_sender).await,
+            "bash" => self.execute_bash_command(args, ctx).await,
             _ => Err(MCPError::UnknownTool(name.to_string())),
         }
     }
 }
 
+/// A `tool_call` tracing span one call runs in, tagged with its correlation
+/// id; calls dropped before [`finish`](Self::finish) close as `aborted`
+struct CallSpan {
+    span: tracing::Span,
+    started: Instant,
+    outcome: &'static str,
+}
+
+impl CallSpan {
+    fn new(tool: &'static str, correlation_id: &str) -> Self {
+        let span = tracing::info_span!("tool_call", tool, correlation = ?correlation_id, outcome = tracing::field::Empty);
+        CallSpan { span, started: Instant::now(), outcome: "aborted" }
+    }
+
+    fn elapsed_ms(&self) -> u64 {
+        self.started.elapsed().as_millis() as u64
+    }
+
+    fn finish(&mut self, outcome: &'static str) {
+        self.outcome = outcome;
+    }
+}
+
+impl Drop for CallSpan {
+    fn drop(&mut self) {
+        self.span.record("outcome", self.outcome);
+    }
+}
+
+/// Kills a command's process group when dropped, so cancelled or timed-out
+/// calls do not orphan children of the shell
+struct ProcessGroup(Option<u32>);
+
+impl ProcessGroup {
+    fn new(pid: Option<u32>) -> Self {
+        ProcessGroup(pid)
+    }
+
+    fn kill(&self) {
+        #[cfg(unix)]
+        if let Some(pid) = self.0 {
+            // SAFETY: kill(2) has no memory-safety preconditions
+            unsafe {
+                libc::kill(-(pid as libc::pid_t), libc::SIGKILL);
+            }
+        }
+    }
+
+    /// The command finished normally; leave its group alone
+    fn disarm(&mut self) {
+        self.0 = None;
+    }
+}
+
+impl Drop for ProcessGroup {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
 impl BashToolHandler {
     async fn execute_bash_command(
         &self,
         args: &Value,
-        progress_sender: ProgressSender,
+        context: &RequestContext,
+    ) -> Result<ToolResponse, MCPError> {
+        let mut span = CallSpan::new("bash", context.correlation_id());
+        let instrumented = span.span.clone();
+        let result = self.run_bash_command(args, context, &mut span).instrument(instrumented).await;
+        if result.is_err() {
+            span.finish("error");
+        }
+        result
+    }
+
+    async fn run_bash_command(
+        &self,
+        args: &Value,
+        context: &RequestContext,
+        span: &mut CallSpan,
     ) -> Result<ToolResponse, MCPError> {
         let command = args
             .get("command")
             .and_then(|v| v.as_str())
             .ok_or(MCPError::MissingParameters)?;
 
-        let timeout_seconds = args.get("timeout").and_then(|v| v.as_u64()).unwrap_or(30);
+        let requested = Duration::from_secs(args.get("timeout").and_then(|v| v.as_u64()).unwrap_or(30));
+        // Reap the command before the client gives up on the request
+        let timeout = context.subprocess_timeout(requested);
 
         let working_dir = args.get("working_dir").and_then(|v| v.as_str());
 
+        let capture_mode = match args.get("capture_mode").and_then(|v| v.as_str()) {
+            None | Some("separate") => CaptureMode::Separate,
+            Some("interleaved") => CaptureMode::Interleaved,
+            Some(other) => {
+                return Err(MCPError::InvalidParams(format!("unknown capture_mode: {}", other)))
+            }
+        };
+
+        let progress_sender = context.progress();
+
         let _ = progress_sender
             .send_progress(
                 "request",
@@ -50,17 +203,33 @@ impl BashToolHandler {
             )
             .await;
 
-        let mut cmd = Command::new("bash");
+        let mut cmd = context.command("bash");
         cmd.arg("-c")
             .arg(command)
             .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            // Let scripts tag their own logs with the call that started them
+            .env("MCP_REQUEST_ID", context.request_id())
+            .env("MCP_CORRELATION_ID", context.correlation_id());
+        // Own process group so the whole command tree can be killed
+        #[cfg(unix)]
+        cmd.process_group(0);
 
         if let Some(dir) = working_dir {
             cmd.current_dir(dir);
         }
+        let background = self.background.as_ref().filter(|_| progress_sender.priority() == Priority::Background);
+        if let Some(limits) = background {
+            limits.apply(&mut cmd);
+        }
 
-        let mut child = cmd.spawn().map_err(|e| MCPError::IoError(e))?;
+        let mut child = cmd.spawn().map_err(MCPError::IoError)?;
+        tracing::debug!(pid = child.id(), "command started");
+        let mut group = ProcessGroup::new(child.id());
+        if let Some(limits) = background {
+            limits.attach(child.id());
+        }
 
         let _ = progress_sender
             .send_progress(
@@ -73,59 +242,72 @@ impl BashToolHandler {
         let stdout = child.stdout.take().unwrap();
         let stderr = child.stderr.take().unwrap();
 
-        let stdout_reader = BufReader::new(stdout);
-        let stderr_reader = BufReader::new(stderr);
-
-        let mut stdout_lines = stdout_reader.lines();
-        let mut stderr_lines = stderr_reader.lines();
+        let mut stdout_lines = BufReader::new(stdout).lines();
+        let mut stderr_lines = BufReader::new(stderr).lines();
 
-        let mut stdout_output = Vec::new();
-        let mut stderr_output = Vec::new();
+        let started = Instant::now();
+        let mut output: Vec<OutputLine> = Vec::new();
+        // Captured lines count against the server's memory ceiling
+        let mut held = context.reserve_output(0)?;
+        let mut charge = |line: &str| {
+            if held.as_mut().is_some_and(|held| !held.try_grow(line.len())) {
+                return Err(MCPError::OutputTooLarge);
+            }
+            Ok(())
+        };
 
-        let timeout =
-            tokio::time::timeout(std::time::Duration::from_secs(timeout_seconds), async {
-                loop {
-                    tokio::select! {
-                        stdout_line = stdout_lines.next_line() => {
-                            match stdout_line {
-                                Ok(Some(line)) => stdout_output.push(line),
-                                Ok(None) => break,
-                                Err(e) => return Err(MCPError::IoError(e)),
+        let reader = tokio::time::timeout(timeout, async {
+            let mut stdout_done = false;
+            let mut stderr_done = false;
+
+            while !(stdout_done && stderr_done) {
+                tokio::select! {
+                    stdout_line = stdout_lines.next_line(), if !stdout_done => {
+                        match stdout_line {
+                            Ok(Some(line)) => {
+                                charge(&line)?;
+                                progress_sender.log(line.clone());
+                                output.push(OutputLine::new(OutputStream::Stdout, started, line));
                             }
+                            Ok(None) => stdout_done = true,
+                            Err(e) => return Err(MCPError::IoError(e)),
                         }
-                        stderr_line = stderr_lines.next_line() => {
-                            match stderr_line {
-                                Ok(Some(line)) => stderr_output.push(line),
-                                Ok(None) => {},
-                                Err(e) => return Err(MCPError::IoError(e)),
+                    }
+                    stderr_line = stderr_lines.next_line(), if !stderr_done => {
+                        match stderr_line {
+                            Ok(Some(line)) => {
+                                charge(&line)?;
+                                progress_sender.log(format!("stderr: {}", line));
+                                output.push(OutputLine::new(OutputStream::Stderr, started, line));
                             }
+                            Ok(None) => stderr_done = true,
+                            Err(e) => return Err(MCPError::IoError(e)),
                         }
                     }
                 }
+            }
 
-                let _ = progress_sender
-                    .send_progress(
-                        "request",
-                        0.8,
-                        Some("Waiting for command completion".to_string()),
-                    )
-                    .await;
-
-                let exit_status = child.wait().await.map_err(|e| MCPError::IoError(e))?;
+            let _ = progress_sender
+                .send_progress(
+                    "request",
+                    0.8,
+                    Some("Waiting for command completion".to_string()),
+                )
+                .await;
 
-                Ok((exit_status, stdout_output, stderr_output))
-            });
+            child.wait().await.map_err(MCPError::IoError)
+        });
 
-        let (exit_status, stdout_output, stderr_output) = match timeout.await {
-            Ok(result) => result?,
+        let (exit_status, timed_out) = match reader.await {
+            Ok(result) => (result?, false),
             Err(_) => {
-                let _ = child.kill().await;
-                return Ok(ToolResponse::new(
-                    format!("Command timed out after {} seconds", timeout_seconds),
-                    true,
-                ));
+                group.kill();
+                let _ = child.start_kill();
+                (child.wait().await.map_err(MCPError::IoError)?, true)
             }
         };
+        group.disarm();
+        let exit = ExitInfo::new(exit_status, timed_out);
 
         let _ = progress_sender
             .send_progress("request", 1.0, Some("Command completed".to_string()))
@@ -134,37 +316,204 @@ impl BashToolHandler {
         let mut response_text = String::new();
 
         response_text.push_str(&format!("Command: {}\n", command));
-        response_text.push_str(&format!(
-            "Exit code: {}\n\n",
-            exit_status.code().unwrap_or(-1)
-        ));
+        if timed_out {
+            if timeout < requested {
+                response_text.push_str(&format!("Command timed out after {:?}, clamped to the request deadline\n", timeout));
+            } else {
+                response_text.push_str(&format!("Command timed out after {} seconds\n", requested.as_secs()));
+            }
+        }
+        response_text.push_str(&format!("{}\n\n", exit.describe()));
+
+        match capture_mode {
+            CaptureMode::Separate => {
+                let stdout_output: Vec<&str> = output.iter()
+                    .filter(|l| l.stream == OutputStream::Stdout)
+                    .map(|l| l.text.as_str())
+                    .collect();
+                let stderr_output: Vec<&str> = output.iter()
+                    .filter(|l| l.stream == OutputStream::Stderr)
+                    .map(|l| l.text.as_str())
+                    .collect();
+
+                if !stdout_output.is_empty() {
+                    response_text.push_str("STDOUT:\n");
+                    response_text.push_str(&stdout_output.join("\n"));
+                    response_text.push_str("\n\n");
+                }
 
-        if !stdout_output.is_empty() {
-            response_text.push_str("STDOUT:\n");
-            response_text.push_str(&stdout_output.join("\n"));
-            response_text.push_str("\n\n");
+                if !stderr_output.is_empty() {
+                    response_text.push_str("STDERR:\n");
+                    response_text.push_str(&stderr_output.join("\n"));
+                    response_text.push('\n');
+                }
+            }
+            CaptureMode::Interleaved => {
+                for line in &output {
+                    response_text.push_str(&format!(
+                        "[+{}.{:03}s {}] {}\n",
+                        line.elapsed_ms / 1000,
+                        line.elapsed_ms % 1000,
+                        line.stream.as_str(),
+                        line.text
+                    ));
+                }
+            }
         }
 
-        if !stderr_output.is_empty() {
-            response_text.push_str("STDERR:\n");
-            response_text.push_str(&stderr_output.join("\n"));
-            response_text.push_str("\n");
+        let mut structured = exit.to_json();
+        structured["correlationId"] = json!(context.correlation_id());
+        structured["durationMs"] = json!(span.elapsed_ms());
+        if capture_mode == CaptureMode::Interleaved {
+            structured["lines"] = output.iter().map(OutputLine::to_json).collect();
         }
 
-        let is_error = !exit_status.success();
-        Ok(ToolResponse::new(response_text, is_error))
+        let is_error = timed_out || !exit_status.success();
+        span.finish(exit.termination.as_str());
+        Ok(ToolResponse::new(response_text, is_error).with_structured_content(structured))
     }
 }
 
+/// Why the subprocess stopped running
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Termination {
+    /// Exited on its own with a status code
+    Exited,
+    /// Killed by us after exceeding the timeout
+    Timeout,
+    /// Terminated by a signal we did not send
+    Signal,
+}
+
+impl Termination {
+    fn as_str(self) -> &'static str {
+        match self {
+            Termination::Exited => "exited",
+            Termination::Timeout => "timeout",
+            Termination::Signal => "signal",
+        }
+    }
+}
+
+/// Exit details of a finished subprocess, including signal information on Unix
+#[derive(Debug, Clone)]
+struct ExitInfo {
+    code: Option<i32>,
+    signal: Option<i32>,
+    core_dumped: bool,
+    termination: Termination,
+}
+
+impl ExitInfo {
+    fn new(status: ExitStatus, timed_out: bool) -> Self {
+        #[cfg(unix)]
+        let (signal, core_dumped) = {
+            use std::os::unix::process::ExitStatusExt;
+            (status.signal(), status.core_dumped())
+        };
+        #[cfg(not(unix))]
+        let (signal, core_dumped) = (None, false);
+
+        let termination = if timed_out {
+            Termination::Timeout
+        } else if signal.is_some() {
+            Termination::Signal
+        } else {
+            Termination::Exited
+        };
+
+        ExitInfo { code: status.code(), signal, core_dumped, termination }
+    }
+
+    fn describe(&self) -> String {
+        match (self.code, self.signal) {
+            (Some(code), _) => format!("Exit code: {}", code),
+            (None, Some(signal)) => {
+                let mut text = format!("Terminated by signal {}", signal);
+                if let Some(name) = signal_name(signal) {
+                    text.push_str(&format!(" ({})", name));
+                }
+                if self.termination == Termination::Timeout {
+                    text.push_str(" after timeout");
+                }
+                if self.core_dumped {
+                    text.push_str(", core dumped");
+                }
+                text
+            }
+            (None, None) => "Exit code: unknown".to_string(),
+        }
+    }
+
+    fn to_json(&self) -> Value {
+        json!({
+            "exitCode": self.code,
+            "signal": self.signal,
+            "signalName": self.signal.and_then(signal_name),
+            "coreDumped": self.core_dumped,
+            "termination": self.termination.as_str(),
+        })
+    }
+}
+
+/// Name of common POSIX signals; numbers differ between platforms
+#[cfg(unix)]
+fn signal_name(signal: i32) -> Option<&'static str> {
+    let name = match signal {
+        libc::SIGHUP => "SIGHUP",
+        libc::SIGINT => "SIGINT",
+        libc::SIGQUIT => "SIGQUIT",
+        libc::SIGILL => "SIGILL",
+        libc::SIGABRT => "SIGABRT",
+        libc::SIGBUS => "SIGBUS",
+        libc::SIGFPE => "SIGFPE",
+        libc::SIGKILL => "SIGKILL",
+        libc::SIGSEGV => "SIGSEGV",
+        libc::SIGPIPE => "SIGPIPE",
+        libc::SIGALRM => "SIGALRM",
+        libc::SIGTERM => "SIGTERM",
+        _ => return None,
+    };
+    Some(name)
+}
+
+#[cfg(not(unix))]
+fn signal_name(_signal: i32) -> Option<&'static str> {
+    None
+}
+
+/// Bind `path`, replacing a socket left behind by an earlier run but never
+/// any other kind of file
+#[cfg(unix)]
+fn bind_unix_socket(path: &str) -> std::io::Result<tokio::net::UnixListener> {
+    use std::os::unix::fs::FileTypeExt;
+
+    match std::fs::symlink_metadata(path) {
+        Ok(meta) if meta.file_type().is_socket() => std::fs::remove_file(path)?,
+        Ok(_) => {
+            return Err(std::io::Error::new(std::io::ErrorKind::AlreadyExists, format!("{} exists and is not a socket", path)));
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    tokio::net::UnixListener::bind(path)
+}
+
 #[tokio::main]
 async fn main() {
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_ansi(false)
+        .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+        .init();
+
     let bash_tool = Tool {
         name: "bash".to_string(),
         description: "Execute bash commands with support for complex operations like rg, sed, awk, grep, find, etc.".to_string(),
         input_schema: ToolInputSchema {
             schema_type: "object".to_string(),
             properties: {
-                let mut props = HashMap::new();
+                let mut props = BTreeMap::new();
                 props.insert(
                     "command".to_string(),
                     ToolProperty::string("The bash command to execute")
@@ -178,6 +527,15 @@ async fn main() {
                         default: Some(Value::Number(30.into())),
                     }
                 );
+                props.insert(
+                    "capture_mode".to_string(),
+                    ToolProperty {
+                        property_type: "string".to_string(),
+                        description: "Output capture mode: \"separate\" (default) groups stdout and stderr, \"interleaved\" merges both streams in arrival order with timestamps".to_string(),
+                        items: None,
+                        default: Some(Value::String("separate".into())),
+                    }
+                );
                 props.insert(
                     "working_dir".to_string(),
                     ToolProperty {
@@ -191,49 +549,270 @@ async fn main() {
             },
             required: vec!["command".to_string()],
         },
+        annotations: Some(ToolAnnotations {
+            title: Some("Run bash command".to_string()),
+            read_only_hint: Some(false),
+            destructive_hint: Some(true),
+            idempotent_hint: Some(false),
+            open_world_hint: Some(true),
+        }),
+        meta: None,
     };
 
-    let server = SystemMCPServer::<BashToolHandler>::builder()
+    let args: Vec<String> = std::env::args().collect();
+    let flag_value = |flag: &str| {
+        args.iter().position(|arg| arg == flag).and_then(|i| args.get(i + 1)).cloned()
+    };
+
+    let mut builder = SystemMCPServer::<WithDeclarativeTools<BashToolHandler>>::builder()
         .with_tools(vec![bash_tool])
-        .build(BashToolHandler);
+        .alias("tools/invoke", "tools/call")
+        .layer(AuditLog)
+        .tool_annotations("bash", Annotations::for_audience([Role::Assistant]))
+        .call_logs(Duration::from_secs(300))
+        .ready_signal(ReadySignal::SdNotify);
+    if let Some(path) = flag_value("--journal") {
+        builder = builder.journal(Journal::create(&path).expect("failed to open journal"));
+    }
+    // Only read-only wrapper tools; bash itself is refused
+    if args.iter().any(|arg| arg == "--read-only") {
+        builder = builder.read_only(true);
+    }
+    if args.iter().any(|arg| arg == "--ready-stderr") {
+        builder = builder.ready_signal(ReadySignal::Stderr);
+    }
+    if let Some(path) = flag_value("--ready-file") {
+        builder = builder.ready_signal(ReadySignal::File(path.into()));
+    }
+    // Timeouts, limits and served tools, tunable without a restart
+    if let Some(path) = flag_value("--config") {
+        let config = Arc::new(LiveConfig::load(path).expect("failed to load config"));
+        config.watch(Duration::from_secs(2));
+        builder = builder.config(config);
+    }
+    // Reproducible environment for commands, whatever the supervisor passed
+    let mut subprocess_env = SubprocessEnv::new();
+    if let Some(locale) = flag_value("--locale") {
+        subprocess_env = subprocess_env.locale(locale);
+    }
+    if args.iter().any(|arg| arg == "--sanitize-path") {
+        subprocess_env = subprocess_env.sanitize_path();
+    }
+    if let Some(home) = flag_value("--home") {
+        subprocess_env = subprocess_env.home(home);
+    }
+    if let Some(names) = flag_value("--env-allow") {
+        subprocess_env = subprocess_env.allow_env(names.split(',').filter(|name| !name.is_empty()));
+    }
+    builder = builder.subprocess_env(subprocess_env);
+    let background_nice = flag_value("--background-nice").map(|nice| nice.parse().expect("--background-nice must be 0-19"));
+    let background_cgroup = flag_value("--background-cgroup");
+    let background = (background_nice.is_some() || background_cgroup.is_some()).then(|| BackgroundLimits {
+        nice: background_nice.unwrap_or(BackgroundLimits::default().nice),
+        cgroup: background_cgroup.map(Into::into),
+        ..BackgroundLimits::default()
+    });
+    // Wrapper tools from a JSON/YAML file, reloaded when it changes
+    let tools_file = flag_value("--tools-file");
+    let declarative = Arc::new(match &tools_file {
+        Some(path) => DeclarativeTools::load(path).expect("failed to load tools file"),
+        None => DeclarativeTools::default(),
+    });
+    let builder = if tools_file.is_some() { builder.change_log(declarative.clone()) } else { builder };
+    let server = Arc::new(builder.build(WithDeclarativeTools::new(BashToolHandler { background }, declarative.clone())));
+    if tools_file.is_some() {
+        let watched = server.clone();
+        declarative.watch(Duration::from_secs(2), move || {
+            watched.reload_registries();
+        });
+    }
+
+    if args.iter().any(|arg| arg == "--manifest") {
+        println!("{}", serde_json::to_string_pretty(&server.export_manifest()).unwrap());
+        return;
+    }
+
+    if let Some(path) = flag_value("--replay") {
+        let report = replay(&server, &path).await.expect("failed to replay journal");
+        println!("{}", serde_json::to_string_pretty(&report).unwrap());
+        std::process::exit(if report.is_identical() { 0 } else { 1 });
+    }
 
     eprintln!("Bash MCP Server starting...");
 
-    let mut stdin = tokio::io::stdin();
-    let mut stdout = tokio::io::stdout();
+    let shutdown_deadline = flag_value("--shutdown-timeout")
+        .and_then(|secs| secs.parse().ok())
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_SHUTDOWN_DEADLINE);
 
-    loop {
-        use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    let framing = flag_value("--framing")
+        .map(|framing| framing.parse().expect("--framing must be 'newline' or 'content-length'"))
+        .unwrap_or_default();
 
-        let mut reader = BufReader::new(&mut stdin);
-        let mut line = String::new();
+    if let Err(e) = server.signal_ready() {
+        eprintln!("Startup self-check failed: {}", e);
+        std::process::exit(1);
+    }
 
-        match reader.read_line(&mut line).await {
-            Ok(0) => break,
-            Ok(_) => {
-                line = line.trim().to_string();
-                if line.is_empty() {
-                    continue;
+    let transport = StdioTransport::new().with_framing(framing);
+    let runner = server.runner().shutdown_deadline(shutdown_deadline);
+    // Also accept clients on a Unix socket, e.g. an IDE plugin next to a CLI
+    let result = match flag_value("--unix-socket") {
+        #[cfg(unix)]
+        Some(path) => {
+            let listener = match bind_unix_socket(&path) {
+                Ok(listener) => listener,
+                Err(e) => {
+                    eprintln!("Failed to bind --unix-socket {}: {}", path, e);
+                    std::process::exit(1);
                 }
+            };
+            let transports = TransportSet::new();
+            transports.add(transport);
+            tokio::spawn(accept_unix(listener, transports.handle()));
+            runner.run_transports(transports).await
+        }
+        #[cfg(not(unix))]
+        Some(_) => {
+            eprintln!("--unix-socket is only supported on Unix");
+            std::process::exit(1);
+        }
+        None => runner.run_with_transport(transport).await,
+    };
+    if let Err(e) = result {
+        eprintln!("Server stopped: {}", e);
+        std::process::exit(1);
+    }
+}
 
-                match serde_json::from_str::<MCPRequest>(&line) {
-                    Ok(request) => {
-                        if let Some(response) = server.handle(request).await {
-                            let response_json = serde_json::to_string(&response).unwrap();
-                            stdout.write_all(response_json.as_bytes()).await.unwrap();
-                            stdout.write_all(b"\n").await.unwrap();
-                            stdout.flush().await.unwrap();
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!("Failed to parse request: {}", e);
-                    }
-                }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use mcp_sdk::testing::fixtures;
+
+    #[tokio::test]
+    async fn test_correlation_id_reaches_subprocess() {
+        let server = SystemMCPServer::<BashToolHandler>::builder()
+            .relaxed_lifecycle()
+            .layer(AuditLog)
+            .build(BashToolHandler { background: None });
+        let call = fixtures::call_tool("bash")
+            .arg("command", "echo $MCP_CORRELATION_ID $MCP_REQUEST_ID")
+            .meta("correlationId", "trace-42")
+            .build();
+        let result = server.handle(call).await.unwrap().result.unwrap();
+        assert!(result["content"][0]["text"].as_str().unwrap().contains("trace-42 1"));
+        assert_eq!(result["structuredContent"]["correlationId"], "trace-42");
+        assert!(result["structuredContent"]["durationMs"].is_u64());
+
+        let forged = fixtures::call_tool("bash")
+            .arg("command", "echo \"[$MCP_CORRELATION_ID]\"")
+            .meta("correlationId", "x\nMCP_EVIL=1")
+            .build();
+        let result = server.handle(forged).await.unwrap().result.unwrap();
+        assert!(result["content"][0]["text"].as_str().unwrap().contains("[1]"));
+    }
+
+    /// Tracing output written into a buffer
+    #[derive(Clone, Default)]
+    struct Captured(Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Captured {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_call_runs_in_its_span() {
+        let captured = Captured::default();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer({
+                let captured = captured.clone();
+                move || captured.clone()
+            })
+            .with_ansi(false)
+            .with_max_level(tracing::Level::DEBUG)
+            .with_span_events(tracing_subscriber::fmt::format::FmtSpan::CLOSE)
+            .finish();
+        let _default = tracing::subscriber::set_default(subscriber);
+
+        let server = SystemMCPServer::<BashToolHandler>::builder()
+            .relaxed_lifecycle()
+            .build(BashToolHandler { background: None });
+        let call = fixtures::call_tool("bash").arg("command", "true").meta("correlationId", "trace-7").build();
+        assert!(server.handle(call).await.unwrap().is_success());
+        let missing = fixtures::call_tool("bash").meta("correlationId", "trace-8").build();
+        server.handle(missing).await;
+
+        let output = String::from_utf8(captured.0.lock().unwrap().clone()).unwrap();
+        let started = output.lines().find(|line| line.contains("command started")).unwrap();
+        assert!(started.contains("tool_call{tool=\"bash\" correlation=\"trace-7\"}"), "{}", started);
+        assert!(output.lines().any(|line| line.contains("trace-7") && line.contains("outcome=\"exited\"")), "{}", output);
+        assert!(output.lines().any(|line| line.contains("trace-8") && line.contains("outcome=\"error\"")), "{}", output);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_unix_socket_replaces_only_sockets() {
+        let path = std::env::temp_dir().join(format!("mcp-socket-{}", std::process::id()));
+        let path = path.to_str().unwrap();
+        std::fs::write(path, "keep me").unwrap();
+        assert!(bind_unix_socket(path).is_err());
+        assert_eq!(std::fs::read_to_string(path).unwrap(), "keep me");
+
+        std::fs::remove_file(path).unwrap();
+        drop(bind_unix_socket(path).unwrap());
+        // The socket left behind is stale and gets replaced
+        drop(bind_unix_socket(path).unwrap());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_cancelled_call_kills_process_group() {
+        let server = SystemMCPServer::<BashToolHandler>::builder()
+            .relaxed_lifecycle()
+            .build(BashToolHandler { background: None });
+        let pid_file = std::env::temp_dir().join(format!("mcp-group-{}", std::process::id()));
+        let _ = std::fs::remove_file(&pid_file);
+        let call = fixtures::call_tool("bash")
+            .id(7)
+            .arg("command", format!("sleep 30 & echo $! > {}; wait", pid_file.display()))
+            .build();
+
+        let running = server.handle(call);
+        tokio::pin!(running);
+        let pid: i32 = loop {
+            tokio::select! {
+                _ = &mut running => panic!("command finished before it was cancelled"),
+                _ = tokio::time::sleep(Duration::from_millis(10)) => {}
             }
-            Err(e) => {
-                eprintln!("Failed to read line: {}", e);
+            if let Some(pid) = std::fs::read_to_string(&pid_file).ok().and_then(|pid| pid.trim().parse().ok()) {
+                break pid;
+            }
+        };
+        server.handle(fixtures::cancelled("7", Some("user abort"))).await;
+        assert_eq!(running.await.unwrap().error.unwrap().code, -32800);
+        std::fs::remove_file(&pid_file).unwrap();
+
+        // The backgrounded sleep went with the shell; at most a zombie is left
+        // for whoever adopted it
+        let gone = || {
+            let stat = std::fs::read_to_string(format!("/proc/{}/stat", pid)).unwrap_or_default();
+            stat.is_empty() || stat.contains(") Z ")
+        };
+        for _ in 0..100 {
+            if gone() {
                 break;
             }
+            tokio::time::sleep(Duration::from_millis(10)).await;
         }
+        assert!(gone());
     }
-}
\ No newline at end of file
+}